@@ -35,7 +35,7 @@ pub enum Error {
     UrlParse(#[from] url::ParseError),
     #[error("Unrecognized User Agent : {0}")]
     UnrecognizedUserAgent(String),
-    #[error("Invalid identifier format: '{0}' is neither a valid DOI nor PMID")]
+    #[error("Invalid identifier format: '{0}' is not a DOI, PMID, PMCID, arXiv, OpenAlex, MAG or CORE ID")]
     InvalidIdFormat(String),
     #[error("Too many identifiers: maximum 10 allowed, got {0}")]
     TooManyIds(usize),
@@ -19,6 +19,11 @@ use search::*;
 
 mod common;
 
+/// Identifier parsing shared with the server and the library, see its module documentation.
+#[allow(dead_code)]
+#[path = "../../lib/src/identifier.rs"]
+mod identifier;
+
 mod systematic_review;
 use systematic_review::*;
 
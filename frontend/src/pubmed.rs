@@ -7,29 +7,36 @@ use yew_router::prelude::*;
 
 use crate::search::denylist::upload_denylist_to_backend;
 
-/// Fetches up to 500 PMIDs for a PubMed keyword search via NCBI ESearch.
+/// A result of `/api/pubmed_search`, of which only the PMID is needed here.
+#[derive(serde::Deserialize)]
+struct PubmedSearchResult {
+    pmid: String,
+}
+
+/// Fetches up to 500 PMIDs for a PubMed keyword search via the backend, which queries
+/// NCBI ESearch with its own rate limit and cache.
 /// PMIDs are sent directly to the backend, which resolves them via Lens.org.
 pub async fn get_pubmed_pmids(query: &str) -> Result<Vec<String>, Error> {
-    let query_encoded = js_sys::encode_uri_component(query);
-    let url = format!(
-        "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/esearch.fcgi?db=pubmed&retmode=json&sort=relevance&retmax=500&term={}",
-        query_encoded
-    );
-    let text = gloo_net::http::Request::get(&url)
+    let body = serde_json::json!({
+        "query": query,
+        "max_results": 500,
+        "pmids_only": true,
+    });
+
+    let response = gloo_net::http::Request::post("/api/pubmed_search")
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_string(&body)?)?
         .send()
-        .await?
-        .text()
         .await?;
-    let json: serde_json::Value = serde_json::from_str(&text)?;
-    let pmids = json["esearchresult"]["idlist"]
-        .as_array()
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_str().map(String::from))
-                .collect()
-        })
-        .unwrap_or_default();
-    Ok(pmids)
+
+    let text = response.text().await?;
+
+    if !response.ok() {
+        return Err(Error::Api(text));
+    }
+
+    let results: Vec<PubmedSearchResult> = serde_json::from_str(&text)?;
+    Ok(results.into_iter().map(|result| result.pmid).collect())
 }
 
 enum PageState {
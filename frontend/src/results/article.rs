@@ -12,9 +12,81 @@ pub struct Article {
     pub pmid: Option<String>,
     pub citations: Option<i32>,
     pub score: Option<i32>,
+    #[serde(default)]
+    pub authors: Vec<ArticleAuthor>,
+    #[serde(default)]
+    pub date_published: Option<String>,
+    #[serde(default)]
+    pub volume: Option<String>,
+    #[serde(default)]
+    pub issue: Option<String>,
+    #[serde(default)]
+    pub pages: Option<String>,
+    #[serde(default)]
+    pub issn: Vec<String>,
+    #[serde(default)]
+    pub publisher: Option<String>,
+    #[serde(default)]
+    pub publication_type: Option<String>,
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub mesh_terms: Vec<String>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub retracted: bool,
+    #[serde(default)]
+    pub open_access: bool,
+    #[serde(default)]
+    pub open_access_colour: Option<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub full_text_urls: Vec<String>,
+}
+
+/// An author of an article, with the institutions listed for this article.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ArticleAuthor {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub initials: Option<String>,
+    #[serde(default)]
+    pub affiliations: Vec<String>,
+}
+
+impl ArticleAuthor {
+    /// Name in the `Last, First` form expected by reference managers.
+    pub fn citation_name(&self) -> Option<String> {
+        match (&self.last_name, &self.first_name) {
+            (Some(last), Some(first)) => Some(format!("{}, {}", last, first)),
+            (Some(last), None) => Some(last.clone()),
+            (None, Some(first)) => Some(first.clone()),
+            (None, None) => None,
+        }
+    }
 }
 
 impl Article {
+    /// Author names in the `Last, First` form, falling back to `first_author`
+    /// for results without the full author list.
+    pub fn citation_authors(&self) -> Vec<String> {
+        let authors: Vec<String> = self
+            .authors
+            .iter()
+            .filter_map(ArticleAuthor::citation_name)
+            .collect();
+
+        if authors.is_empty() {
+            self.first_author.clone().into_iter().collect()
+        } else {
+            authors
+        }
+    }
+
     /// Get a unique identifier for this article (DOI preferred, fallback to PMID).
     pub fn id(&self) -> Option<String> {
         self.doi.clone().or_else(|| self.pmid.clone())
@@ -83,7 +83,7 @@ pub fn to_ris(articles: &[Article]) -> Result<Vec<u8>, common::Error> {
 
     for article in articles.iter() {
         writeln!(ris, "TY  - JOUR")?;
-        if let Some(author) = &article.first_author {
+        for author in article.citation_authors() {
             writeln!(ris, "AU  - {}", author)?;
         }
         if let Some(title) = &article.title {
@@ -95,6 +95,36 @@ pub fn to_ris(articles: &[Article]) -> Result<Vec<u8>, common::Error> {
         if let Some(year) = article.year_published {
             writeln!(ris, "PY  - {}", year)?;
         }
+        if let Some(date) = &article.date_published {
+            writeln!(ris, "DA  - {}", date.replace('-', "/"))?;
+        }
+        if let Some(volume) = &article.volume {
+            writeln!(ris, "VL  - {}", volume)?;
+        }
+        if let Some(issue) = &article.issue {
+            writeln!(ris, "IS  - {}", issue)?;
+        }
+        if let Some(pages) = &article.pages {
+            let mut range = pages.splitn(2, '-');
+            if let Some(start) = range.next() {
+                writeln!(ris, "SP  - {}", start)?;
+            }
+            if let Some(end) = range.next() {
+                writeln!(ris, "EP  - {}", end)?;
+            }
+        }
+        for issn in &article.issn {
+            writeln!(ris, "SN  - {}", issn)?;
+        }
+        if let Some(publisher) = &article.publisher {
+            writeln!(ris, "PB  - {}", publisher)?;
+        }
+        for language in &article.languages {
+            writeln!(ris, "LA  - {}", language)?;
+        }
+        for keyword in article.keywords.iter().chain(article.mesh_terms.iter()) {
+            writeln!(ris, "KW  - {}", keyword)?;
+        }
         if let Some(summary) = &article.summary {
             writeln!(ris, "AB  - {}", summary)?;
         }
@@ -140,8 +170,9 @@ pub fn to_bibtex(articles: &[Article]) -> Result<Vec<u8>, common::Error> {
                 .collect::<String>()
         );
         writeln!(bibtex, "@article{{{},", citeid)?;
-        if let Some(author) = &article.first_author {
-            writeln!(bibtex, "  author = \"{}\",", author)?;
+        let authors = article.citation_authors();
+        if !authors.is_empty() {
+            writeln!(bibtex, "  author = \"{}\",", authors.join(" and "))?;
         }
         if let Some(title) = &article.title {
             writeln!(bibtex, "  title = \"{}\",", title)?;
@@ -152,6 +183,24 @@ pub fn to_bibtex(articles: &[Article]) -> Result<Vec<u8>, common::Error> {
         if let Some(year) = article.year_published {
             writeln!(bibtex, "  year = {},", year)?;
         }
+        if let Some(volume) = &article.volume {
+            writeln!(bibtex, "  volume = \"{}\",", volume)?;
+        }
+        if let Some(issue) = &article.issue {
+            writeln!(bibtex, "  number = \"{}\",", issue)?;
+        }
+        if let Some(pages) = &article.pages {
+            writeln!(bibtex, "  pages = \"{}\",", pages.replacen('-', "--", 1))?;
+        }
+        if let Some(issn) = article.issn.first() {
+            writeln!(bibtex, "  issn = \"{}\",", issn)?;
+        }
+        if let Some(publisher) = &article.publisher {
+            writeln!(bibtex, "  publisher = \"{}\",", publisher)?;
+        }
+        if !article.keywords.is_empty() {
+            writeln!(bibtex, "  keywords = \"{}\",", article.keywords.join(", "))?;
+        }
         if let Some(summary) = &article.summary {
             writeln!(bibtex, "  abstract = \"{}\",", summary)?;
         }
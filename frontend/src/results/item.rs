@@ -167,6 +167,14 @@ pub fn Item(props: &ItemProps) -> Html {
                         if props.is_seed {
                             <span class="badge bg-primary bg-opacity-10 text-primary border border-primary border-opacity-25 ms-2 align-middle" style="font-size: 0.7rem; font-weight: 500;">{ "Seed" }</span>
                         }
+                        if props.article.retracted {
+                            <span class="badge bg-danger ms-2 align-middle" style="font-size: 0.7rem; font-weight: 500;" title="This article has been retracted">{ "Retracted" }</span>
+                        }
+                        if props.article.open_access {
+                            <span class="badge bg-success bg-opacity-10 text-success border border-success border-opacity-25 ms-2 align-middle" style="font-size: 0.7rem; font-weight: 500;" title={props.article.license.clone().unwrap_or_default()}>
+                                { format!("Open access{}", props.article.open_access_colour.as_ref().map(|colour| format!(" ({})", colour)).unwrap_or_default()) }
+                            </span>
+                        }
                     </h5>
                     <div class="mb-2 text-muted" style="font-size: 0.95rem;">
                         {props.article.first_author.clone().unwrap_or_else(|| "Unknown Author".to_string())}
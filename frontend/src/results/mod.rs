@@ -225,12 +225,13 @@ pub fn Results(props: &ResultsProps) -> Html {
 
 /// Fetches BibliZap snowball results for a list of IDs from the backend API.
 /// Expert params default to `Limit(100)`, depth 2, and `Both` when `None`.
+/// Also returns whether the server built the results from incomplete citations.
 pub async fn run_snowball_with_ids(
     ids: &[String],
     depth: Option<u8>,
     output_max_size: Option<&OutputMaxSize>,
     search_for: Option<&SearchFor>,
-) -> Result<Vec<Article>, Error> {
+) -> Result<(Vec<Article>, bool), Error> {
     use gloo_utils::document;
     let url = document().document_uri();
     let url = match url {
@@ -263,12 +264,17 @@ pub async fn run_snowball_with_ids(
         return Err(Error::Api(result_text));
     }
 
+    let incomplete_citations = response
+        .headers()
+        .get("X-Biblizap-Incomplete-Citations")
+        .is_some_and(|value| value == "true");
+
     let value = serde_json::from_str::<serde_json::Value>(&result_text)?;
     let mut articles = serde_json::from_value::<Vec<Article>>(value)?;
 
     articles.sort_by_key(|article| std::cmp::Reverse(article.score.unwrap_or_default()));
 
-    Ok(articles)
+    Ok((articles, incomplete_citations))
 }
 
 fn location_to_query(
@@ -282,7 +288,8 @@ fn location_to_query(
 
 enum FetchStatus {
     Loading,
-    Success(Vec<Article>),
+    /// The articles, and whether they were found from incomplete citations
+    Success(Vec<Article>, bool),
     Error(Error),
 }
 
@@ -351,7 +358,9 @@ pub fn BibliZapResults() -> Html {
                 )
                 .await;
                 match result {
-                    Ok(articles) => fetch_status.set(FetchStatus::Success(articles)),
+                    Ok((articles, incomplete_citations)) => {
+                        fetch_status.set(FetchStatus::Success(articles, incomplete_citations))
+                    }
                     Err(e) => fetch_status.set(FetchStatus::Error(e)),
                 }
             });
@@ -450,16 +459,25 @@ pub fn BibliZapResults() -> Html {
             {match fetch_status.deref() {
                 FetchStatus::Loading => html! { <Spinner /> },
                 FetchStatus::Error(msg) => html! { <ErrorMessage msg={msg.to_string()} /> },
-                FetchStatus::Success(articles) => {
+                FetchStatus::Success(articles, incomplete_citations) => {
                     let seed_ids: HashSet<String> =
                         ids.iter().map(|s| s.to_lowercase()).collect();
                     html! {
+                        <>
+                        if *incomplete_citations {
+                            <div class="container-fluid">
+                                <div class="alert alert-warning" role="alert">
+                                    {"Some citations were taken from earlier searches and may be incomplete: articles citing your seeds may be missing or ranked too low."}
+                                </div>
+                            </div>
+                        }
                         <Results
                             articles={articles.clone()}
                             denylist={(*denylist).clone()}
                             on_rerun_snowball={on_rerun_snowball}
                             {seed_ids}
                         />
+                        </>
                     }
                 },
             }}
@@ -1,3 +1,4 @@
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
@@ -6,37 +7,64 @@ use denylist::*;
 
 use crate::common::*;
 use crate::common::{get_value, BibliZapResultsQuery, FormPosition};
+use crate::identifier::{IdType, Identifier};
 
-/// Validates if a string is a valid DOI.
-/// DOIs start with "10." followed by at least 4 digits, a "/", and a suffix.
-fn is_valid_doi(s: &str) -> bool {
-    s.starts_with("10.")
-        && s.len() > 7  // Minimum: "10.1234/x"
-        && s.contains('/')
-        && s.chars().skip(3).take_while(|c| c.is_ascii_digit()).count() >= 4
-}
-
-/// Validates if a string is a valid PMID.
-/// PMIDs are purely numeric identifiers.
-fn is_valid_pmid(s: &str) -> bool {
-    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
-}
-
-/// Validates if a string is either a valid DOI or PMID.
+/// Validates if a string is a supported seed identifier (any type but Lens IDs), with the
+/// same rules as the server.
 fn is_valid_id(s: &str) -> bool {
-    is_valid_doi(s) || is_valid_pmid(s)
+    Identifier::parse(s).is_some_and(|identifier| identifier.id_type() != IdType::LensId)
 }
 
-/// Checks if the input string contains keywords (i.e., not all tokens are DOIs/PMIDs).
+/// Checks if the input string contains keywords (i.e., not all tokens are identifiers).
 fn contains_keywords(input: &str) -> bool {
     let tokens: Vec<&str> = input.trim().split_whitespace().collect();
     if tokens.is_empty() {
         return false;
     }
-    // If ANY token is not a valid DOI or PMID, treat the whole input as keywords
+    // If ANY token is not a valid identifier, treat the whole input as keywords
     tokens.iter().any(|t| !is_valid_id(t))
 }
 
+/// Resolution of one seed identifier, as returned by `/api/resolve`.
+#[derive(Clone, PartialEq, Debug, serde::Deserialize)]
+struct SeedResolution {
+    input: String,
+    status: String,
+}
+
+impl SeedResolution {
+    fn is_resolved(&self) -> bool {
+        self.status == "resolved"
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status.as_str() {
+            "invalid_format" => "not a supported identifier",
+            "not_found" => "not found in Lens.org",
+            "ambiguous" => "matches several Lens.org records",
+            _ => "could not be resolved",
+        }
+    }
+}
+
+/// Checks the seed identifiers with the backend before running a snowball.
+async fn resolve_seeds(ids: &[String]) -> Result<Vec<SeedResolution>, Error> {
+    let body = serde_json::json!({ "input_id_list": ids });
+
+    let response = gloo_net::http::Request::post("/api/resolve")
+        .body(serde_json::to_string(&body)?)?
+        .send()
+        .await?;
+
+    let result_text = response.text().await?;
+
+    if !response.ok() {
+        return Err(Error::Api(result_text));
+    }
+
+    Ok(serde_json::from_str::<Vec<SeedResolution>>(&result_text)?)
+}
+
 /// Query params for `/pubmed-results?q=…`
 #[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
 pub struct PubMedResultsQuery {
@@ -123,7 +151,9 @@ fn session_set(key: &str, val: &str) {
 
 /// Component for the snowball search form.
 /// Allows users to input IDs or keywords, select depth, max results, and search direction.
-/// On submit, navigates to `/pubmed-results?q=…` or `/biblizap-results?ids=…`.
+/// On submit, navigates to `/pubmed-results?q=…` or, once the seeds are checked with
+/// `/api/resolve`, to `/biblizap-results?ids=…`. Seeds that cannot be resolved are listed
+/// with the reason instead.
 #[function_component(BiblizapSearchBar)]
 pub fn biblizap_search_bar(props: &SearchBarProps) -> Html {
     let navigator = use_navigator().unwrap();
@@ -199,6 +229,10 @@ pub fn biblizap_search_bar(props: &SearchBarProps) -> Html {
         }
     });
 
+    // Seeds rejected by `/api/resolve` on the last submit, and whether a check is running
+    let unresolved = use_state(Vec::<SeedResolution>::new);
+    let checking = use_state(|| false);
+
     let onchange = {
         let id_list_node = id_list_node.clone();
         let id_list = id_list.clone();
@@ -214,6 +248,8 @@ pub fn biblizap_search_bar(props: &SearchBarProps) -> Html {
         let id_list_node = id_list_node.clone();
         let advanced_params = advanced_params.clone();
         let navigator = navigator.clone();
+        let unresolved = unresolved.clone();
+        let checking = checking.clone();
 
         Callback::from(move |event: SubmitEvent| {
             event.prevent_default();
@@ -233,7 +269,7 @@ pub fn biblizap_search_bar(props: &SearchBarProps) -> Html {
                     position.next(),
                 );
             } else {
-                // DOI/PMID search → validate then navigate to BibliZap results page
+                // Identifier search → validate then navigate to BibliZap results page
                 let ids: Vec<String> = input_trimmed
                     .split_whitespace()
                     .map(|s| s.to_string())
@@ -255,27 +291,52 @@ pub fn biblizap_search_bar(props: &SearchBarProps) -> Html {
                 gloo_console::log!(format!("Form position: {:#?}", position));
                 gloo_console::log!(format!("Next form position: {:#?}", position.next()));
 
-                let _ = navigator.push_with_query_and_state(
-                    &Route::BibliZapResults,
-                    BibliZapResultsQuery {
-                        ids: ids_str,
-                        depth: Some(p.depth),
-                        output_max_size: Some(p.output_max_size),
-                        search_for: Some(p.search_for),
-                        denylists: if p.denylists.is_empty() {
-                            None
-                        } else {
-                            Some(
-                                p.denylists
-                                    .iter()
-                                    .map(hex::encode)
-                                    .collect::<Vec<_>>()
-                                    .join(" "),
-                            )
-                        },
+                let query = BibliZapResultsQuery {
+                    ids: ids_str,
+                    depth: Some(p.depth),
+                    output_max_size: Some(p.output_max_size),
+                    search_for: Some(p.search_for),
+                    denylists: if p.denylists.is_empty() {
+                        None
+                    } else {
+                        Some(
+                            p.denylists
+                                .iter()
+                                .map(hex::encode)
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                        )
                     },
-                    position.next(),
-                );
+                };
+
+                let navigator = navigator.clone();
+                let unresolved = unresolved.clone();
+                let checking = checking.clone();
+                checking.set(true);
+                spawn_local(async move {
+                    match resolve_seeds(&ids).await {
+                        Ok(resolutions) => {
+                            let rejected: Vec<SeedResolution> = resolutions
+                                .into_iter()
+                                .filter(|resolution| !resolution.is_resolved())
+                                .collect();
+                            if !rejected.is_empty() {
+                                checking.set(false);
+                                unresolved.set(rejected);
+                                return;
+                            }
+                        }
+                        // The snowball reports its own errors, so a failed check does not block it
+                        Err(error) => gloo_console::log!(format!("Seed check failed: {error}")),
+                    }
+                    checking.set(false);
+                    unresolved.set(Vec::new());
+                    let _ = navigator.push_with_query_and_state(
+                        &Route::BibliZapResults,
+                        query,
+                        position.next(),
+                    );
+                });
             }
         })
     };
@@ -292,12 +353,22 @@ pub fn biblizap_search_bar(props: &SearchBarProps) -> Html {
                         value={id_list.to_string()}
                     />
                     <SearchGear show_advanced={show_advanced.clone()} />
-                    <button type="submit" class="btn btn-outline-primary">
+                    <button type="submit" class="btn btn-outline-primary" disabled={*checking}>
                         <i class="bi bi-search"></i>
                         {" Search"}
                     </button>
                 </div>
-                <div id="idInputHelp" class="form-text">{"Enter DOIs or PMIDs to run BibliZap directly, or enter keywords to search PubMed first."}</div>
+                <div id="idInputHelp" class="form-text">{"Enter DOIs, PMIDs, PMCIDs, arXiv or OpenAlex IDs to run BibliZap directly, or enter keywords to search PubMed first."}</div>
+                if !unresolved.is_empty() {
+                    <div class="alert alert-warning mt-2" role="alert">
+                        {"Some identifiers could not be resolved, please check or remove them:"}
+                        <ul class="mb-0">
+                            { for unresolved.iter().map(|resolution| html! {
+                                <li><code>{ &resolution.input }</code>{ format!(": {}", resolution.reason()) }</li>
+                            }) }
+                        </ul>
+                    </div>
+                }
             </div>
             <SearchAdvancedPanel show_advanced={show_advanced.clone()} advanced_params={advanced_params.clone()} on_denylists_change={props.on_denylists_change.clone()} />
         </form>
@@ -1,6 +1,8 @@
 use serde::Deserialize;
 
+/// Which links to follow
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum SearchFor {
     References,
     Citations,
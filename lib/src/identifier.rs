@@ -0,0 +1,492 @@
+//! Parsing and normalisation of article identifiers.
+//!
+//! Seeds are pasted from reference managers, web pages and PDFs, so the same paper shows
+//! up as `10.1000/ABC`, `doi:10.1000/abc`, `https://doi.org/10.1000/abc.` or with a
+//! Unicode dash. [`Identifier::parse`] strips the `doi:`-like and URL prefixes, the
+//! invisible characters, Unicode dashes and surrounding punctuation, recognises the type of
+//! the identifier and gives it a canonical form. That form is used for `id_mappings` keys,
+//! both when they are written and when they are looked up, so one paper always gets one
+//! key.
+//!
+//! This module only depends on `std`: the frontend includes it with `#[path]` to validate
+//! seeds exactly like the server and the library do.
+
+use std::fmt;
+
+/// The kinds of article identifiers understood by the Lens.org scholarly search.
+///
+/// MAG and CORE IDs are plain numbers like PMIDs, so they must be written with a
+/// `mag:` or `core:` prefix to be told apart. The same prefixed form is their
+/// `id_mappings` key, so that a MAG ID never shadows the PMID with the same digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IdType {
+    LensId,
+    Pmid,
+    Doi,
+    /// PubMed Central ID, e.g. `PMC1234567`
+    Pmcid,
+    /// arXiv ID, e.g. `2101.00001`, `arXiv:2101.00001v2` or `hep-th/9901001`
+    Arxiv,
+    /// OpenAlex work ID, e.g. `W2741809807` or `https://openalex.org/W2741809807`
+    OpenAlex,
+    /// Microsoft Academic Graph ID, e.g. `mag:2741809807`
+    Mag,
+    /// CORE ID, e.g. `core:82573464`
+    Core,
+}
+
+impl IdType {
+    /// Every identifier type, in detection order.
+    pub const ALL: [IdType; 8] = [
+        IdType::LensId,
+        IdType::Pmid,
+        IdType::Doi,
+        IdType::Pmcid,
+        IdType::Arxiv,
+        IdType::OpenAlex,
+        IdType::Mag,
+        IdType::Core,
+    ];
+
+    /// Finds the type of a raw identifier, or `None` if it is not recognised.
+    pub fn detect(id: &str) -> Option<Self> {
+        Identifier::parse(id).map(|identifier| identifier.id_type())
+    }
+
+    /// The Lens query field for this type, which is also its `external_ids` type.
+    pub fn lens_field(self) -> &'static str {
+        match self {
+            IdType::LensId => "lens_id",
+            IdType::Pmid => "pmid",
+            IdType::Doi => "doi",
+            IdType::Pmcid => "pmcid",
+            IdType::Arxiv => "arxiv",
+            IdType::OpenAlex => "openalex",
+            IdType::Mag => "magid",
+            IdType::Core => "coreid",
+        }
+    }
+
+    /// The type matching an `external_ids` type returned by Lens.
+    pub fn from_lens_field(field: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|id_type| id_type.lens_field() == field)
+    }
+}
+
+/// Prefixes that announce the type of the identifier that follows, matched
+/// case-insensitively. URL schemes and `www.` are stripped before these are tried.
+const TYPED_PREFIXES: [(&str, IdType); 19] = [
+    ("doi.org/", IdType::Doi),
+    ("dx.doi.org/", IdType::Doi),
+    ("doi:", IdType::Doi),
+    ("pubmed.ncbi.nlm.nih.gov/", IdType::Pmid),
+    ("ncbi.nlm.nih.gov/pubmed/", IdType::Pmid),
+    ("pmid:", IdType::Pmid),
+    ("pmc.ncbi.nlm.nih.gov/articles/", IdType::Pmcid),
+    ("ncbi.nlm.nih.gov/pmc/articles/", IdType::Pmcid),
+    ("pmcid:", IdType::Pmcid),
+    ("arxiv.org/abs/", IdType::Arxiv),
+    ("arxiv.org/pdf/", IdType::Arxiv),
+    ("arxiv:", IdType::Arxiv),
+    ("api.openalex.org/works/", IdType::OpenAlex),
+    ("openalex.org/", IdType::OpenAlex),
+    ("openalex:", IdType::OpenAlex),
+    ("lens.org/lens/scholar/article/", IdType::LensId),
+    ("lens:", IdType::LensId),
+    ("mag:", IdType::Mag),
+    ("core:", IdType::Core),
+];
+
+/// A recognised identifier in canonical form.
+///
+/// | Type | Canonical form |
+/// |------|----------------|
+/// | Lens ID | `565-646-398-006-312`, check character `X` uppercase |
+/// | PMID | digits |
+/// | DOI | lowercase (DOIs are case-insensitive) |
+/// | PMCID | `PMC` and digits |
+/// | arXiv | without `arXiv:` prefix and version, old-style archive lowercase |
+/// | OpenAlex | `W` and digits |
+/// | MAG, CORE | digits, with a `mag:` or `core:` prefix in [`Identifier::key`] |
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Identifier {
+    id_type: IdType,
+    value: String,
+}
+
+impl Identifier {
+    /// Parses a raw identifier of any supported type, or returns `None` if it is not one.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let cleaned = clean(raw);
+        let (hint, rest) = strip_typed_prefix(&cleaned);
+        match hint {
+            Some(id_type) => Self::normalize(id_type, rest),
+            // MAG and CORE IDs are only told apart from PMIDs by their prefix
+            None => [
+                IdType::LensId,
+                IdType::Pmid,
+                IdType::Doi,
+                IdType::Pmcid,
+                IdType::Arxiv,
+                IdType::OpenAlex,
+            ]
+            .into_iter()
+            .find_map(|id_type| Self::normalize(id_type, rest)),
+        }
+    }
+
+    /// Parses an identifier known to be of a given type, e.g. an `external_ids` value
+    /// returned by Lens. Prefixes of that type are accepted but not required.
+    pub fn parse_as(id_type: IdType, raw: &str) -> Option<Self> {
+        let cleaned = clean(raw);
+        match strip_typed_prefix(&cleaned) {
+            (Some(hint), rest) if hint == id_type => Self::normalize(id_type, rest),
+            (Some(_), _) => None,
+            (None, rest) => Self::normalize(id_type, rest),
+        }
+    }
+
+    fn normalize(id_type: IdType, s: &str) -> Option<Self> {
+        let value = match id_type {
+            IdType::LensId => normalize_lens_id(s)?,
+            IdType::Pmid | IdType::Mag | IdType::Core => is_digits(s).then(|| s.to_string())?,
+            IdType::Doi => normalize_doi(s)?,
+            IdType::Pmcid => {
+                let digits = strip_prefix_ignore_case(s, "pmc")?;
+                is_digits(digits).then(|| format!("PMC{digits}"))?
+            }
+            IdType::Arxiv => normalize_arxiv(s)?,
+            IdType::OpenAlex => {
+                let digits = s.strip_prefix(['W', 'w'])?;
+                is_digits(digits).then(|| format!("W{digits}"))?
+            }
+        };
+        Some(Self { id_type, value })
+    }
+
+    pub fn id_type(&self) -> IdType {
+        self.id_type
+    }
+
+    /// The canonical value, as sent to Lens.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// The canonical key of the identifier, used for `id_mappings`. The same as
+    /// [`Identifier::value`], except for the `mag:` and `core:` prefixes.
+    pub fn key(&self) -> String {
+        match self.id_type {
+            IdType::Mag => format!("mag:{}", self.value),
+            IdType::Core => format!("core:{}", self.value),
+            _ => self.value.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.key())
+    }
+}
+
+/// Removes whitespace and invisible characters, replaces Unicode dashes with `-` and strips
+/// quotes, brackets and punctuation around the identifier.
+fn clean(raw: &str) -> String {
+    let mut s: String = raw
+        .chars()
+        .filter(|c| {
+            !c.is_whitespace() && !matches!(c, '\u{200B}'..='\u{200D}' | '\u{FEFF}' | '\u{00AD}')
+        })
+        .map(|c| match c {
+            '\u{2010}'..='\u{2015}' | '\u{2212}' | '\u{FE58}' | '\u{FE63}' | '\u{FF0D}' => '-',
+            c => c,
+        })
+        .collect();
+
+    loop {
+        let before = s.len();
+
+        let leading = s.chars().next().filter(|c| {
+            matches!(
+                c,
+                '"' | '\'' | '<' | '(' | '[' | '{' | '\u{201C}' | '\u{2018}'
+            )
+        });
+        if let Some(c) = leading {
+            s.remove(0);
+            // Drop the matching closing bracket, if any
+            let closing = match c {
+                '<' => Some('>'),
+                '(' => Some(')'),
+                '[' => Some(']'),
+                '{' => Some('}'),
+                _ => None,
+            };
+            if closing.is_some_and(|closing| s.ends_with(closing)) {
+                s.pop();
+            }
+        }
+
+        if let Some(c) = s.chars().last() {
+            let strip = match c {
+                '.' | ',' | ';' | ':' | '"' | '\'' | '>' | '\u{201D}' | '\u{2019}' => true,
+                // Closing brackets are part of some DOIs, e.g. `10.1002/(sici)1097-4636(199604)`
+                ')' => s.matches('(').count() < s.matches(')').count(),
+                ']' => s.matches('[').count() < s.matches(']').count(),
+                '}' => s.matches('{').count() < s.matches('}').count(),
+                _ => false,
+            };
+            if strip {
+                s.pop();
+            }
+        }
+
+        if s.len() == before {
+            return s;
+        }
+    }
+}
+
+/// Strips a URL scheme, `www.` and one typed prefix, returning the type it announces.
+fn strip_typed_prefix(s: &str) -> (Option<IdType>, &str) {
+    let mut rest = s;
+    for scheme in ["https://", "http://"] {
+        if let Some(stripped) = strip_prefix_ignore_case(rest, scheme) {
+            rest = stripped;
+            break;
+        }
+    }
+    if let Some(stripped) = strip_prefix_ignore_case(rest, "www.") {
+        rest = stripped;
+    }
+
+    for (prefix, id_type) in TYPED_PREFIXES {
+        if let Some(stripped) = strip_prefix_ignore_case(rest, prefix) {
+            // Trailing path segments of article pages, e.g. `/main` or `.pdf`
+            let stripped = stripped.trim_end_matches('/');
+            let stripped = match id_type {
+                IdType::LensId => stripped.strip_suffix("/main").unwrap_or(stripped),
+                IdType::Arxiv => strip_suffix_ignore_case(stripped, ".pdf").unwrap_or(stripped),
+                _ => stripped,
+            };
+            return (Some(id_type), stripped);
+        }
+    }
+
+    // Not an identifier URL: keep the scheme so that it cannot pass as anything else
+    (None, s)
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
+
+fn strip_suffix_ignore_case<'a>(s: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = s.len().checked_sub(suffix.len())?;
+    let tail = s.get(split..)?;
+    tail.eq_ignore_ascii_case(suffix).then(|| &s[..split])
+}
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// `ddd-ddd-ddd-ddd-ddC`, where the check character `C` is a digit or `X`.
+fn normalize_lens_id(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let valid = bytes.len() == 19
+        && bytes.iter().enumerate().all(|(i, &b)| match i {
+            3 | 7 | 11 | 15 => b == b'-',
+            18 => b.is_ascii_digit() || b == b'X' || b == b'x',
+            _ => b.is_ascii_digit(),
+        });
+    valid.then(|| s.to_ascii_uppercase())
+}
+
+/// `10.` followed by a registrant code of at least four digits, optionally with
+/// dot-separated sub-codes, a `/` and a non-empty suffix.
+fn normalize_doi(s: &str) -> Option<String> {
+    let (prefix, suffix) = s.split_once('/')?;
+    let registrant = prefix.strip_prefix("10.")?;
+    let mut codes = registrant.split('.');
+    let valid = codes
+        .next()
+        .is_some_and(|code| code.len() >= 4 && is_digits(code))
+        && codes.all(is_digits)
+        && !suffix.is_empty();
+    valid.then(|| s.to_lowercase())
+}
+
+/// New-style `yymm.nnnnn` or old-style `archive(.XX)/yymmnnn` IDs, with an optional version.
+fn normalize_arxiv(s: &str) -> Option<String> {
+    let unversioned = match s.rfind(['v', 'V']) {
+        Some(v) if v > 0 && is_digits(&s[v + 1..]) => &s[..v],
+        _ => s,
+    };
+
+    if let Some((archive, number)) = unversioned.split_once('/') {
+        let (name, subject) = match archive.split_once('.') {
+            Some((name, subject)) => (name, Some(subject)),
+            None => (archive, None),
+        };
+        let valid = !name.is_empty()
+            && name.bytes().all(|b| b.is_ascii_alphabetic() || b == b'-')
+            && subject.is_none_or(|subject| {
+                subject.len() == 2 && subject.bytes().all(|b| b.is_ascii_alphabetic())
+            })
+            && number.len() == 7
+            && is_digits(number);
+        // Subject classes are uppercase, e.g. `math.GT/0309136`
+        valid.then(|| match subject {
+            Some(subject) => format!(
+                "{}.{}/{number}",
+                name.to_ascii_lowercase(),
+                subject.to_ascii_uppercase()
+            ),
+            None => format!("{}/{number}", name.to_ascii_lowercase()),
+        })
+    } else {
+        let (yymm, number) = unversioned.split_once('.')?;
+        let valid = yymm.len() == 4
+            && is_digits(yymm)
+            && (4..=5).contains(&number.len())
+            && is_digits(number);
+        valid.then(|| unversioned.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(raw: &str) -> Option<String> {
+        Identifier::parse(raw).map(|identifier| identifier.key())
+    }
+
+    #[test]
+    fn parses_and_normalises_every_id_type() {
+        let cases = [
+            ("565-646-398-006-312", IdType::LensId, "565-646-398-006-312"),
+            ("830-182-661-063-60x", IdType::LensId, "830-182-661-063-60X"),
+            (
+                "https://www.lens.org/lens/scholar/article/830-182-661-063-60X/main",
+                IdType::LensId,
+                "830-182-661-063-60X",
+            ),
+            ("30000001", IdType::Pmid, "30000001"),
+            ("PMID: 30000001", IdType::Pmid, "30000001"),
+            (
+                "https://pubmed.ncbi.nlm.nih.gov/30000001/",
+                IdType::Pmid,
+                "30000001",
+            ),
+            ("10.1000/STUB.A", IdType::Doi, "10.1000/stub.a"),
+            ("doi:10.1000/stub.a", IdType::Doi, "10.1000/stub.a"),
+            (
+                "https://doi.org/10.1000/Stub.A",
+                IdType::Doi,
+                "10.1000/stub.a",
+            ),
+            (
+                "http://dx.doi.org/10.1000/stub.a",
+                IdType::Doi,
+                "10.1000/stub.a",
+            ),
+            ("10.1000.10/stub.a", IdType::Doi, "10.1000.10/stub.a"),
+            ("pmc7000001", IdType::Pmcid, "PMC7000001"),
+            (
+                "https://www.ncbi.nlm.nih.gov/pmc/articles/PMC7000001/",
+                IdType::Pmcid,
+                "PMC7000001",
+            ),
+            ("2101.00001", IdType::Arxiv, "2101.00001"),
+            ("arXiv:2101.00001v2", IdType::Arxiv, "2101.00001"),
+            (
+                "https://arxiv.org/pdf/2101.00001v1.pdf",
+                IdType::Arxiv,
+                "2101.00001",
+            ),
+            ("HEP-TH/9901001", IdType::Arxiv, "hep-th/9901001"),
+            ("math.gt/0309136", IdType::Arxiv, "math.GT/0309136"),
+            ("w2741809807", IdType::OpenAlex, "W2741809807"),
+            (
+                "https://openalex.org/W2741809807",
+                IdType::OpenAlex,
+                "W2741809807",
+            ),
+            ("MAG:2741809807", IdType::Mag, "2741809807"),
+            ("core:82573464", IdType::Core, "82573464"),
+        ];
+
+        for (raw, id_type, value) in cases {
+            let identifier = Identifier::parse(raw).unwrap_or_else(|| panic!("{raw}"));
+            assert_eq!(identifier.id_type(), id_type, "{raw}");
+            assert_eq!(identifier.value(), value, "{raw}");
+        }
+    }
+
+    #[test]
+    fn strips_dashes_invisible_characters_and_punctuation() {
+        let canonical = Some("10.1016/s0140-6736(20)30183-5".to_string());
+        for raw in [
+            "10.1016/S0140-6736(20)30183-5",
+            " 10.1016/S0140\u{2010}6736(20)30183\u{2013}5. ",
+            "(doi:10.1016/S0140-6736(20)30183-5)",
+            "\"https://doi.org/10.1016/S0140-6736(20)30183-5\",",
+            "10.1016/S0140-6736(20)\u{200B}30183-5;",
+            "<10.1016/S0140-6736(20)30183-5>",
+        ] {
+            assert_eq!(key(raw), canonical, "{raw:?}");
+        }
+
+        // Balanced closing brackets belong to the DOI
+        assert_eq!(
+            key("10.1002/(SICI)1097-4636(199604)"),
+            Some("10.1002/(sici)1097-4636(199604)".to_string())
+        );
+        assert_eq!(key("PMC7000001)."), Some("PMC7000001".to_string()));
+    }
+
+    #[test]
+    fn rejects_malformed_identifiers() {
+        for raw in [
+            "",
+            "not an id",
+            "10.12/x",
+            "10.1000",
+            "10.1000/",
+            "doi:30000001",
+            "PMC",
+            "W",
+            "mag:",
+            "mag:W123",
+            "123-456",
+            "arXiv:",
+            "https://example.org/10.1000/stub.a",
+            "565-646-398-006-31Y",
+        ] {
+            assert_eq!(Identifier::parse(raw), None, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn keys_keep_numeric_ids_apart() {
+        assert_eq!(key("123"), Some("123".to_string()));
+        assert_eq!(key("mag:123"), Some("mag:123".to_string()));
+        assert_eq!(key("core:123"), Some("core:123".to_string()));
+        assert_eq!(
+            Identifier::parse_as(IdType::Mag, "123").map(|identifier| identifier.key()),
+            Some("mag:123".to_string())
+        );
+        assert_eq!(Identifier::parse_as(IdType::Pmid, "doi:10.1000/x"), None);
+        assert_eq!(
+            Identifier::parse_as(IdType::Doi, "10.1000/STUB.A").map(|identifier| identifier.key()),
+            Some("10.1000/stub.a".to_string())
+        );
+    }
+}
@@ -2,7 +2,9 @@ use serde::de::{self, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Map;
 
+use super::id_types::IdType;
 use super::lensid::LensId;
+use crate::identifier::Identifier;
 
 /// Represents an article as returned by the Lens.org API.
 ///
@@ -29,13 +31,48 @@ pub struct Article {
     pub source: Option<Source>,
     /// The year of publication.
     pub year_published: Option<i32>,
+    /// The full publication date (e.g., "2020-03-15").
+    pub date_published: Option<String>,
+    /// The volume of the source the article was published in.
+    pub volume: Option<String>,
+    /// The issue of the source the article was published in.
+    pub issue: Option<String>,
+    /// The first page of the article.
+    pub start_page: Option<String>,
+    /// The last page of the article.
+    pub end_page: Option<String>,
+    /// The publication type (e.g., "journal article", "review").
+    pub publication_type: Option<String>,
+    /// The languages of the article, as ISO 639-1 codes.
+    pub languages: Option<Vec<String>>,
+    /// Author-provided keywords.
+    pub keywords: Option<Vec<String>>,
+    /// MeSH terms, for articles indexed in PubMed.
+    pub mesh_terms: Option<Vec<MeshTerm>>,
+    /// Whether the article has been retracted.
+    pub is_retracted: Option<bool>,
+    /// Whether the article can be read for free.
+    pub is_open_access: Option<bool>,
+    /// Open-access colour and licence.
+    pub open_access: Option<OpenAccess>,
+    /// Links to the article on the publisher's and repositories' websites.
+    pub source_urls: Option<Vec<SourceUrl>>,
 }
 
+/// Version of the [`ArticleData`] fields fetched from Lens.org, stored with each cache entry.
+///
+/// Entries of an older version lack fields added since, like the retraction status, which
+/// would silently read as `None`: the caches treat them as misses, and replace them once
+/// they are fetched again. Bump it whenever fields are added to `ArticleData`.
+pub const ARTICLE_DATA_VERSION: i64 = 1;
+
 /// Article metadata without the LensId.
 ///
 /// This contains all the article fields except the lens_id itself.
 /// Used in combination with ArticleWithData for completion results.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Fields added over time are missing from older cache entries, which are fetched again
+/// rather than read with these fields as `None` (see [`ARTICLE_DATA_VERSION`]).
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct ArticleData {
     /// The title of the article.
     pub title: Option<String>,
@@ -55,6 +92,32 @@ pub struct ArticleData {
     pub source: Option<Source>,
     /// The year of publication.
     pub year_published: Option<i32>,
+    /// The full publication date (e.g., "2020-03-15").
+    pub date_published: Option<String>,
+    /// The volume of the source the article was published in.
+    pub volume: Option<String>,
+    /// The issue of the source the article was published in.
+    pub issue: Option<String>,
+    /// The first page of the article.
+    pub start_page: Option<String>,
+    /// The last page of the article.
+    pub end_page: Option<String>,
+    /// The publication type (e.g., "journal article", "review").
+    pub publication_type: Option<String>,
+    /// The languages of the article, as ISO 639-1 codes.
+    pub languages: Option<Vec<String>>,
+    /// Author-provided keywords.
+    pub keywords: Option<Vec<String>>,
+    /// MeSH terms, for articles indexed in PubMed.
+    pub mesh_terms: Option<Vec<MeshTerm>>,
+    /// Whether the article has been retracted.
+    pub is_retracted: Option<bool>,
+    /// Whether the article can be read for free.
+    pub is_open_access: Option<bool>,
+    /// Open-access colour and licence.
+    pub open_access: Option<OpenAccess>,
+    /// Links to the article on the publisher's and repositories' websites.
+    pub source_urls: Option<Vec<SourceUrl>>,
 }
 
 /// Article data combined with its LensId.
@@ -69,15 +132,42 @@ pub struct ArticleWithData {
     pub article_data: ArticleData,
 }
 
+impl From<Article> for ArticleWithData {
+    fn from(article: Article) -> Self {
+        ArticleWithData {
+            lens_id: article.lens_id,
+            article_data: ArticleData {
+                title: article.title,
+                summary: article.summary,
+                scholarly_citations_count: article.scholarly_citations_count,
+                external_ids: article.external_ids,
+                authors: article.authors,
+                source: article.source,
+                year_published: article.year_published,
+                date_published: article.date_published,
+                volume: article.volume,
+                issue: article.issue,
+                start_page: article.start_page,
+                end_page: article.end_page,
+                publication_type: article.publication_type,
+                languages: article.languages,
+                keywords: article.keywords,
+                mesh_terms: article.mesh_terms,
+                is_retracted: article.is_retracted,
+                is_open_access: article.is_open_access,
+                open_access: article.open_access,
+                source_urls: article.source_urls,
+            },
+        }
+    }
+}
+
 impl ArticleWithData {
     pub fn all_id_mappings(&self) -> Vec<(String, LensId)> {
         let mut mappings = Vec::new();
         if let Some(external_ids) = &self.article_data.external_ids {
-            for doi in &external_ids.doi {
-                mappings.push((doi.clone(), self.lens_id.clone()));
-            }
-            for pmid in &external_ids.pmid {
-                mappings.push((pmid.clone(), self.lens_id.clone()));
+            for key in external_ids.mapping_keys() {
+                mappings.push((key, self.lens_id.clone()));
             }
         }
         mappings
@@ -94,6 +184,61 @@ pub struct ExternalIds {
     pub pmid: Vec<String>,
     /// List of DOIs (Digital Object Identifier).
     pub doi: Vec<String>,
+    /// List of PubMed Central IDs (PMCID).
+    #[serde(default)]
+    pub pmcid: Vec<String>,
+    /// List of arXiv IDs.
+    #[serde(default)]
+    pub arxiv: Vec<String>,
+    /// List of OpenAlex work IDs.
+    #[serde(default)]
+    pub openalex: Vec<String>,
+    /// List of Microsoft Academic Graph IDs.
+    #[serde(default)]
+    pub magid: Vec<String>,
+    /// List of CORE IDs.
+    #[serde(default)]
+    pub coreid: Vec<String>,
+}
+
+impl ExternalIds {
+    /// The IDs of one type.
+    pub fn ids(&self, id_type: IdType) -> &[String] {
+        match id_type {
+            IdType::LensId => &[],
+            IdType::Pmid => &self.pmid,
+            IdType::Doi => &self.doi,
+            IdType::Pmcid => &self.pmcid,
+            IdType::Arxiv => &self.arxiv,
+            IdType::OpenAlex => &self.openalex,
+            IdType::Mag => &self.magid,
+            IdType::Core => &self.coreid,
+        }
+    }
+
+    fn ids_mut(&mut self, id_type: IdType) -> Option<&mut Vec<String>> {
+        match id_type {
+            IdType::LensId => None,
+            IdType::Pmid => Some(&mut self.pmid),
+            IdType::Doi => Some(&mut self.doi),
+            IdType::Pmcid => Some(&mut self.pmcid),
+            IdType::Arxiv => Some(&mut self.arxiv),
+            IdType::OpenAlex => Some(&mut self.openalex),
+            IdType::Mag => Some(&mut self.magid),
+            IdType::Core => Some(&mut self.coreid),
+        }
+    }
+
+    /// The canonical `id_mappings` keys of every external ID. Values that are not valid
+    /// identifiers of their type are skipped.
+    pub fn mapping_keys(&self) -> impl Iterator<Item = String> + '_ {
+        IdType::ALL.into_iter().flat_map(move |id_type| {
+            self.ids(id_type)
+                .iter()
+                .filter_map(move |value| Identifier::parse_as(id_type, value))
+                .map(|identifier| identifier.key())
+        })
+    }
 }
 
 /// Represents an author in the Lens.org API response.
@@ -105,10 +250,19 @@ pub struct Author {
     pub initials: Option<String>,
     /// The last name of the author.
     pub last_name: Option<String>,
+    /// The institutions the author was affiliated with for this article.
+    pub affiliations: Option<Vec<Affiliation>>,
+}
+
+/// Represents an author affiliation in the Lens.org API response.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Affiliation {
+    /// The name of the institution.
+    pub name: Option<String>,
 }
 
 /// Represents the source (e.g., journal) in the Lens.org API response.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Source {
     /// The publisher of the source.
     pub publisher: Option<String>,
@@ -117,6 +271,45 @@ pub struct Source {
     /// The type of source (e.g., "journal").
     #[serde(rename = "type")]
     pub kind: Option<String>,
+    /// The ISSNs of the source.
+    pub issn: Option<Vec<Issn>>,
+}
+
+/// Represents an ISSN of a source in the Lens.org API response.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Issn {
+    /// The ISSN, with or without its hyphen.
+    pub value: Option<String>,
+    /// The medium the ISSN applies to (e.g., "print", "electronic").
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+/// Represents the open-access status in the Lens.org API response.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAccess {
+    /// The open-access colour (e.g., "gold", "green", "hybrid", "bronze").
+    pub colour: Option<String>,
+    /// The licence of the article (e.g., "cc-by").
+    pub license: Option<String>,
+}
+
+/// Represents a link to the article in the Lens.org API response.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SourceUrl {
+    /// The kind of document behind the link (e.g., "html", "pdf").
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub url: Option<String>,
+}
+
+/// Represents a MeSH term in the Lens.org API response.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MeshTerm {
+    /// The MeSH descriptor (e.g., "Neoplasms").
+    pub mesh_heading: Option<String>,
+    /// The MeSH qualifier, if any (e.g., "therapy").
+    pub qualifier_name: Option<String>,
 }
 
 /// Custom deserialization function for Option<ExternalIds> that handles both formats.
@@ -200,10 +393,11 @@ impl<'de> Visitor<'de> for ExternalIdsFlexibleVisitor {
                 .ok_or_else(|| de::Error::custom("failed to get value string"))?
                 .to_owned();
 
-            match value_type {
-                "pmid" => result.pmid.push(value_str),
-                "doi" => result.doi.push(value_str),
-                _ => {} // Ignore unknown types
+            // Ignore unknown types
+            if let Some(ids) =
+                IdType::from_lens_field(value_type).and_then(|id_type| result.ids_mut(id_type))
+            {
+                ids.push(value_str);
             }
         }
         Ok(Some(result))
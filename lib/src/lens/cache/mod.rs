@@ -8,18 +8,27 @@
 //! - References (outgoing edges): immutable once fetched
 //! - Citations (incoming edges): mutable, need periodic refresh
 //!
+//! Stored references are also indexed the other way round ("cited-by"), so that
+//! citation lookups can optionally be answered, incompletely, without calling Lens.
+//!
+//! PubMed snowballing is cached in two more tables keyed by PMID: the `elink` results
+//! (reference links never expire, cited-in links follow the citation TTL) and the
+//! PubMed records.
+//!
 //! ## Performance Considerations
 //!
 //! For high-concurrency scenarios (300+ users, 16+ async workers), use PostgreSQL with
 //! a properly sized connection pool via `PostgresBackend::from_pool()`. See the README
 //! for detailed configuration guidelines.
 
+use crate::identifier::Identifier;
 use crate::lens::article::ArticleWithData;
+use crate::pubmed::{Article as PubmedArticle, PubmedLink};
 
 use super::error::LensError;
 use super::lensid::LensId;
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[cfg(feature = "cache-sqlite")]
 pub mod sqlite;
@@ -34,6 +43,38 @@ pub use sqlite::SqliteBackend;
 #[cfg(feature = "cache-postgres")]
 pub use postgres::PostgresBackend;
 
+/// Name under which the one-off rewrite of `id_mappings` keys is recorded as applied
+pub(crate) const CANONICAL_ID_MAPPING_KEYS: &str = "canonical_id_mapping_keys";
+
+/// Splits `id_mappings` rows into the keys written before they were canonical, to be
+/// deleted, and the rows to insert under their canonical key instead
+///
+/// Keys that are not identifiers are only deleted. Of the rows that end up with the same
+/// key, the first one is kept, as `store_id_mapping` ignores conflicts.
+pub(crate) fn rekey_id_mappings(
+    rows: Vec<(String, String, i64)>,
+) -> (Vec<String>, Vec<(String, String, i64)>) {
+    let mut stale_keys = Vec::new();
+    let mut rekeyed = Vec::new();
+    let mut seen = HashSet::new();
+
+    for (string_id, lens_id, fetched_at) in rows {
+        let key = Identifier::parse(&string_id).map(|identifier| identifier.key());
+        if key.as_deref() == Some(string_id.as_str()) {
+            continue;
+        }
+
+        if let Some(key) = key
+            && seen.insert(key.clone())
+        {
+            rekeyed.push((key, lens_id, fetched_at));
+        }
+        stale_keys.push(string_id);
+    }
+
+    (stale_keys, rekeyed)
+}
+
 /// Computes which IDs were not found in the cache (misses)
 ///
 /// # Arguments
@@ -50,6 +91,34 @@ pub fn compute_misses<T>(requested: &[LensId], hits: &HashMap<LensId, T>) -> Vec
         .collect()
 }
 
+/// Citations of an article, as returned by `CacheBackend::get_citations_or_cited_by`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedCitations {
+    pub citations: Vec<LensId>,
+    /// False when the list was rebuilt from the cited-by index rather than fetched
+    /// from Lens: it then only holds the citing articles whose references are cached.
+    pub complete: bool,
+}
+
+/// Row counts of the cache tables, as returned by `CacheBackend::stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CacheStats {
+    pub references: i64,
+    pub citations: i64,
+    /// Citation lists older than the 14 day TTL (ignored by `get_citations`)
+    pub stale_citations: i64,
+    pub cited_by_edges: i64,
+    pub article_data: i64,
+    pub id_mappings: i64,
+    pub pending_fetches: i64,
+    /// Pending fetch marks older than 60 seconds (orphaned by a crash or timeout)
+    pub stale_pending_fetches: i64,
+    /// PubMed link lists, references and cited-in lists together
+    pub pubmed_links: i64,
+    pub pubmed_articles: i64,
+    pub pubmed_searches: i64,
+}
+
 /// Trait defining the cache backend interface
 ///
 /// Implementations must be thread-safe (Send + Sync) as they may be used
@@ -93,6 +162,68 @@ pub trait CacheBackend: Send + Sync {
     /// are updated (ON CONFLICT DO UPDATE behavior).
     async fn store_citations(&self, batch: &[(LensId, Vec<LensId>)]) -> Result<(), LensError>;
 
+    // Cited-by index (stored references, inverted)
+
+    /// Retrieve the articles known to cite the given IDs (LensId)
+    ///
+    /// The index is maintained by `store_references`, so it only knows the citing
+    /// articles whose references are cached. IDs without any known citing article
+    /// are not included in the result.
+    async fn get_cited_by(&self, ids: &[LensId])
+    -> Result<HashMap<LensId, Vec<LensId>>, LensError>;
+
+    /// Whether `get_citations_or_cited_by` may answer citation misses from the
+    /// cited-by index (opt-in, off by default)
+    fn cited_by_fallback(&self) -> bool {
+        false
+    }
+
+    /// Retrieve citations, falling back to the cited-by index for misses
+    ///
+    /// Citations stored with `store_citations` are returned with `complete: true`.
+    /// When `cited_by_fallback` is enabled, remaining misses are rebuilt from the
+    /// cited-by index and returned with `complete: false`.
+    async fn get_citations_or_cited_by(
+        &self,
+        ids: &[LensId],
+    ) -> Result<HashMap<LensId, CachedCitations>, LensError> {
+        let mut result: HashMap<LensId, CachedCitations> = self
+            .get_citations(ids)
+            .await?
+            .into_iter()
+            .map(|(id, citations)| {
+                (
+                    id,
+                    CachedCitations {
+                        citations,
+                        complete: true,
+                    },
+                )
+            })
+            .collect();
+
+        if !self.cited_by_fallback() {
+            return Ok(result);
+        }
+
+        let misses = compute_misses(ids, &result);
+        if misses.is_empty() {
+            return Ok(result);
+        }
+
+        for (id, citations) in self.get_cited_by(&misses).await? {
+            result.insert(
+                id,
+                CachedCitations {
+                    citations,
+                    complete: false,
+                },
+            );
+        }
+
+        Ok(result)
+    }
+
     // Article data
 
     /// Retrieve article data for the given LensIds
@@ -145,6 +276,68 @@ pub trait CacheBackend: Send + Sync {
     /// * `batch` - Slice of (raw_string_id, lens_id) tuples to store
     async fn store_id_mapping(&self, batch: &[(String, LensId)]) -> Result<(), LensError>;
 
+    /// List the stored ID mappings in order of their string ID, starting after `after`
+    ///
+    /// Returns at most `limit` mappings, keyed by canonical identifier; pass the last
+    /// string ID returned as `after` to read the next page, until an empty page.
+    async fn list_id_mappings(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<(String, LensId)>, LensError>;
+
+    // PubMed (keyed by PMID)
+
+    /// Retrieve the cached PubMed links of the given PMIDs
+    ///
+    /// Cited-in lists older than the 14 day citation TTL are ignored; reference lists
+    /// never expire. PMIDs without a cached list are not included in the result.
+    async fn get_pubmed_links(
+        &self,
+        link: PubmedLink,
+        pmids: &[u64],
+    ) -> Result<HashMap<u64, Vec<u64>>, LensError>;
+
+    /// Store PubMed links for PMIDs
+    ///
+    /// Lists already cached are replaced and their timestamp updated
+    /// (ON CONFLICT DO UPDATE behavior).
+    async fn store_pubmed_links(
+        &self,
+        link: PubmedLink,
+        batch: &[(u64, Vec<u64>)],
+    ) -> Result<(), LensError>;
+
+    /// Retrieve the cached PubMed records of the given PMIDs
+    async fn get_pubmed_articles(&self, pmids: &[u64]) -> Result<Vec<PubmedArticle>, LensError>;
+
+    /// Store PubMed records, keyed by their PMID
+    ///
+    /// Records whose PMID is not a number are skipped. Records already cached are
+    /// kept (ON CONFLICT DO NOTHING behavior).
+    async fn store_pubmed_articles(&self, batch: &[PubmedArticle]) -> Result<(), LensError>;
+
+    /// Retrieve the PMIDs found by a cached PubMed search, by relevance
+    ///
+    /// Searches older than one day are ignored, as new articles keep being indexed.
+    /// Returns `None` unless the cached search asked for at least `max_results`
+    /// PMIDs or found fewer than it asked for; the result is cut to `max_results`.
+    async fn get_pubmed_search(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Option<Vec<u64>>, LensError>;
+
+    /// Store the PMIDs found by a PubMed search asking for `max_results` of them
+    ///
+    /// A search already cached for this query is replaced (ON CONFLICT DO UPDATE behavior).
+    async fn store_pubmed_search(
+        &self,
+        query: &str,
+        max_results: usize,
+        pmids: &[u64],
+    ) -> Result<(), LensError>;
+
     // Pending fetch coordination (prevents thundering herd)
 
     /// Mark an ID as being fetched (in-flight API request)
@@ -185,6 +378,26 @@ pub trait CacheBackend: Send + Sync {
     /// Clear all pending fetch marks (for cleanup on startup/crashes)
     async fn clear_pending_fetches(&self) -> Result<(), LensError>;
 
-    /// Clear all cached data (both references and citations)
+    /// Clear all cached data (references, citations, the cited-by index and PubMed included)
     async fn clear(&self) -> Result<(), LensError>;
+
+    // Maintenance
+
+    /// Remove everything cached for the given IDs
+    ///
+    /// Deletes their references (and the matching cited-by rows), citations,
+    /// article data, the ID mappings pointing to them and any pending fetch mark.
+    /// Returns the number of rows deleted.
+    async fn purge(&self, ids: &[LensId]) -> Result<u64, LensError>;
+
+    /// Count the rows of each cache table
+    async fn stats(&self) -> Result<CacheStats, LensError>;
+
+    /// Remove pending fetch marks older than 60 seconds, returning how many were removed
+    ///
+    /// Unlike `clear_pending_fetches`, this is safe to run while the cache is in use.
+    async fn clear_stale_pending_fetches(&self) -> Result<u64, LensError>;
+
+    /// Reclaim free space and refresh the query planner statistics
+    async fn vacuum(&self) -> Result<(), LensError>;
 }
@@ -1,48 +1,106 @@
 //! PostgreSQL backend implementation for the Lens cache
 
-use crate::lens::article::{ArticleData, ArticleWithData};
+use crate::lens::article::{ARTICLE_DATA_VERSION, ArticleData, ArticleWithData};
 use crate::lens::error::LensError;
 use crate::lens::lensid::LensId;
+use crate::pubmed::{Article as PubmedArticle, PubmedLink};
 use async_trait::async_trait;
 use sqlx::PgPool;
 use std::collections::HashMap;
 
-use super::CacheBackend;
+use super::{CANONICAL_ID_MAPPING_KEYS, CacheBackend, CacheStats, rekey_id_mappings};
 
 #[derive(sqlx::FromRow)]
 struct ReferencesRow {
-    pub lens_id: String,
-    pub references_json: String,
+    pub lens_id: i64,
+    pub reference_ids: Vec<i64>,
 }
 
 impl ReferencesRow {
-    fn extract(self) -> Result<(LensId, Vec<LensId>), LensError> {
-        let lens_id = LensId::try_from(self.lens_id.as_str())?;
-        let relationships: Vec<LensId> = serde_json::from_str(&self.references_json)
-            .ok()
-            .unwrap_or_default();
-
-        Ok((lens_id, relationships))
+    fn extract(self) -> (LensId, Vec<LensId>) {
+        (
+            LensId::from(self.lens_id as u64),
+            self.reference_ids
+                .into_iter()
+                .map(|id| LensId::from(id as u64))
+                .collect(),
+        )
     }
 }
 
 #[derive(sqlx::FromRow)]
 struct CitationsRow {
-    pub lens_id: String,
-    pub citations_json: String,
+    pub lens_id: i64,
+    pub citation_ids: Vec<i64>,
 }
 
 impl CitationsRow {
-    fn extract(self) -> Result<(LensId, Vec<LensId>), LensError> {
+    fn extract(self) -> (LensId, Vec<LensId>) {
+        (
+            LensId::from(self.lens_id as u64),
+            self.citation_ids
+                .into_iter()
+                .map(|id| LensId::from(id as u64))
+                .collect(),
+        )
+    }
+}
+
+/// Edge row in the legacy JSON-text layout, only read while migrating old caches
+#[derive(sqlx::FromRow)]
+struct LegacyEdgesRow {
+    pub lens_id: String,
+    pub edges_json: String,
+    pub fetched_at: i64,
+}
+
+impl LegacyEdgesRow {
+    fn extract(self) -> Result<(i64, Vec<i64>, i64), LensError> {
         let lens_id = LensId::try_from(self.lens_id.as_str())?;
-        let citations: Vec<LensId> = serde_json::from_str(&self.citations_json)
-            .ok()
-            .unwrap_or_default();
+        let edges: Vec<LensId> = serde_json::from_str(&self.edges_json)?;
+
+        Ok((lens_id.as_u64() as i64, to_int_ids(&edges), self.fetched_at))
+    }
 
-        Ok((lens_id, citations))
+    /// Converts a batch of rows, skipping the ones that do not parse so that they are
+    /// fetched again instead of failing the migration
+    fn extract_all(rows: Vec<Self>, skipped: &mut usize) -> Vec<(i64, Vec<i64>, i64)> {
+        rows.into_iter()
+            .filter_map(|row| {
+                let lens_id = row.lens_id.clone();
+                row.extract()
+                    .inspect_err(|e| {
+                        log::debug!("Skipping cached edges of {lens_id}: {e}");
+                        *skipped += 1;
+                    })
+                    .ok()
+            })
+            .collect()
     }
 }
 
+/// LensId edge tables, keyed by the integer LensId (optimized with NoHasher)
+const CREATE_REFERENCES_TABLE: &str = r#"
+    CREATE UNLOGGED TABLE IF NOT EXISTS article_references (
+        lens_id BIGINT PRIMARY KEY,
+        reference_ids BIGINT[] NOT NULL,
+        fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
+    )
+"#;
+
+const CREATE_CITATIONS_TABLE: &str = r#"
+    CREATE UNLOGGED TABLE IF NOT EXISTS article_citations (
+        lens_id BIGINT PRIMARY KEY,
+        citation_ids BIGINT[] NOT NULL,
+        fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
+    )
+"#;
+
+/// Converts LensIds to the `BIGINT` form stored in the edge tables
+fn to_int_ids(ids: &[LensId]) -> Vec<i64> {
+    ids.iter().map(|id| id.as_u64() as i64).collect()
+}
+
 #[derive(sqlx::FromRow)]
 struct ArticleRow {
     pub lens_id: String,
@@ -63,17 +121,22 @@ impl ArticleRow {
 
 /// PostgreSQL-based cache backend
 ///
-/// Uses two tables:
+/// Uses three edge tables:
 /// - `article_references`: stores immutable outgoing edges
 /// - `article_citations`: stores mutable incoming edges with timestamps
+/// - `article_cited_by`: one `(cited_id, citing_id)` row per stored reference
+///
+/// PubMed `elink` results and records are kept apart, in `pubmed_links` and
+/// `pubmed_articles`, keyed by PMID, and keyword searches in `pubmed_searches`.
 ///
 /// Optimized for bulk operations with:
 /// - Chunked multi-row inserts (more generous limits than SQLite)
 /// - Single-transaction commits
 /// - Native array operations with ANY() for efficient queries
-/// - JSONB columns for better performance than TEXT
+/// - `BIGINT` keys and `BIGINT[]` edge lists, so reading a row needs no parsing
 pub struct PostgresBackend {
     pool: PgPool,
+    cited_by_fallback: bool,
 }
 
 impl PostgresBackend {
@@ -100,11 +163,11 @@ impl CacheBackend for PostgresBackend {
         }
 
         // PostgreSQL allows us to use ANY with an array - more efficient than JSON
-        let ids_vec: Vec<String> = ids.iter().map(|id| id.as_ref().to_string()).collect();
+        let ids_vec = to_int_ids(ids);
 
         let rows: Vec<ReferencesRow> = sqlx::query_as(
             r#"
-                SELECT lens_id, references_json
+                SELECT lens_id, reference_ids
                 FROM article_references
                 WHERE lens_id = ANY($1)
             "#,
@@ -113,7 +176,7 @@ impl CacheBackend for PostgresBackend {
         .fetch_all(&self.pool)
         .await?;
 
-        rows.into_iter().map(|x| x.extract()).collect()
+        Ok(rows.into_iter().map(ReferencesRow::extract).collect())
     }
 
     async fn store_references(&self, batch: &[(LensId, Vec<LensId>)]) -> Result<(), LensError> {
@@ -132,32 +195,33 @@ impl CacheBackend for PostgresBackend {
         let rough_timestamp = Self::get_unix_timestamp()?;
 
         for chunk in batch.chunks(CHUNK_SIZE) {
-            // Pre-serialize all JSON (handles errors before building query)
-            let rows: Vec<(String, String, i64)> = chunk
+            let rows: Vec<(i64, Vec<i64>, i64)> = chunk
                 .iter()
-                .map(|(id, refs)| {
-                    let id_str = id.as_ref().to_string();
-                    let refs_json = serde_json::to_string(refs)?;
-
-                    Ok((id_str, refs_json, rough_timestamp))
-                })
-                .collect::<Result<Vec<_>, LensError>>()?;
-
-            // Build multi-row INSERT
-            let mut builder = sqlx::QueryBuilder::new(
-                "INSERT INTO article_references (lens_id, references_json, fetched_at) ",
-            );
-
-            builder.push_values(rows, |mut b, (id_str, refs_json, timestamp)| {
-                b.push_bind(id_str)
-                    .push_bind(refs_json)
-                    .push_bind(timestamp);
-            });
+                .map(|(id, refs)| (id.as_u64() as i64, to_int_ids(refs), rough_timestamp))
+                .collect();
 
-            // References are immutable, so just ignore conflicts
-            builder.push(" ON CONFLICT (lens_id) DO NOTHING");
+            Self::insert_references_rows(&mut tx, rows).await?;
 
-            builder.build().execute(&mut *tx).await?;
+            // Keep the cited-by index in sync (same transaction)
+            let (cited_ids, citing_ids): (Vec<i64>, Vec<i64>) = chunk
+                .iter()
+                .flat_map(|(id, refs)| {
+                    refs.iter()
+                        .map(move |cited| (cited.as_u64() as i64, id.as_u64() as i64))
+                })
+                .unzip();
+
+            sqlx::query(
+                r#"
+                INSERT INTO article_cited_by (cited_id, citing_id)
+                SELECT * FROM UNNEST($1::BIGINT[], $2::BIGINT[])
+                ON CONFLICT (cited_id, citing_id) DO NOTHING
+                "#,
+            )
+            .bind(&cited_ids)
+            .bind(&citing_ids)
+            .execute(&mut *tx)
+            .await?;
         }
 
         // Commit once at the end
@@ -178,11 +242,11 @@ impl CacheBackend for PostgresBackend {
         let two_weeks_ago = Self::get_unix_timestamp()? - (14 * 24 * 60 * 60); // 14 days in seconds
 
         // Use PostgreSQL's native array operations + timestamp filter
-        let ids_vec: Vec<String> = ids.iter().map(|id| id.as_ref().to_string()).collect();
+        let ids_vec = to_int_ids(ids);
 
         let rows: Vec<CitationsRow> = sqlx::query_as(
             r#"
-                SELECT lens_id, citations_json
+                SELECT lens_id, citation_ids
                 FROM article_citations
                 WHERE lens_id = ANY($1)
                 AND fetched_at >= $2
@@ -193,7 +257,7 @@ impl CacheBackend for PostgresBackend {
         .fetch_all(&self.pool)
         .await?;
 
-        rows.into_iter().map(|x| x.extract()).collect()
+        Ok(rows.into_iter().map(CitationsRow::extract).collect())
     }
 
     async fn store_citations(&self, batch: &[(LensId, Vec<LensId>)]) -> Result<(), LensError> {
@@ -209,34 +273,12 @@ impl CacheBackend for PostgresBackend {
         let rough_timestamp = Self::get_unix_timestamp()?;
 
         for chunk in batch.chunks(CHUNK_SIZE) {
-            // Pre-serialize all JSON (handles errors before building query)
-            let rows: Vec<(String, String, i64)> = chunk
+            let rows: Vec<(i64, Vec<i64>, i64)> = chunk
                 .iter()
-                .map(|(id, citations)| {
-                    let id_str = id.as_ref().to_string();
-                    let citations_json = serde_json::to_string(citations)?;
-
-                    Ok((id_str, citations_json, rough_timestamp))
-                })
-                .collect::<Result<Vec<_>, LensError>>()?;
-
-            // Build multi-row INSERT
-            let mut builder = sqlx::QueryBuilder::new(
-                "INSERT INTO article_citations (lens_id, citations_json, fetched_at) ",
-            );
-
-            builder.push_values(rows, |mut b, (id_str, citations_json, timestamp)| {
-                b.push_bind(id_str)
-                    .push_bind(citations_json)
-                    .push_bind(timestamp);
-            });
-
-            // For citations, we want to update with fresh data on conflict
-            builder.push(
-                " ON CONFLICT (lens_id) DO UPDATE SET citations_json = EXCLUDED.citations_json, fetched_at = EXCLUDED.fetched_at",
-            );
+                .map(|(id, citations)| (id.as_u64() as i64, to_int_ids(citations), rough_timestamp))
+                .collect();
 
-            builder.build().execute(&mut *tx).await?;
+            Self::insert_citations_rows(&mut tx, rows).await?;
         }
 
         // Commit once at the end
@@ -245,6 +287,46 @@ impl CacheBackend for PostgresBackend {
         Ok(())
     }
 
+    async fn get_cited_by(
+        &self,
+        ids: &[LensId],
+    ) -> Result<HashMap<LensId, Vec<LensId>>, LensError> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let ids_vec = to_int_ids(ids);
+
+        let rows: Vec<(i64, Vec<i64>)> = sqlx::query_as(
+            r#"
+                SELECT cited_id, array_agg(citing_id ORDER BY citing_id)
+                FROM article_cited_by
+                WHERE cited_id = ANY($1)
+                GROUP BY cited_id
+            "#,
+        )
+        .bind(&ids_vec)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(cited_id, citing_ids)| {
+                (
+                    LensId::from(cited_id as u64),
+                    citing_ids
+                        .into_iter()
+                        .map(|id| LensId::from(id as u64))
+                        .collect(),
+                )
+            })
+            .collect())
+    }
+
+    fn cited_by_fallback(&self) -> bool {
+        self.cited_by_fallback
+    }
+
     async fn get_article_data(&self, ids: &[LensId]) -> Result<Vec<ArticleWithData>, LensError> {
         if ids.is_empty() {
             return Ok(Vec::new());
@@ -257,10 +339,11 @@ impl CacheBackend for PostgresBackend {
             r#"
                 SELECT lens_id, article_json
                 FROM article_data
-                WHERE lens_id = ANY($1)
+                WHERE lens_id = ANY($1) AND data_version >= $2
             "#,
         )
         .bind(&ids_vec)
+        .bind(ARTICLE_DATA_VERSION)
         .fetch_all(&self.pool)
         .await?;
 
@@ -296,17 +379,20 @@ impl CacheBackend for PostgresBackend {
 
             // Build multi-row INSERT
             let mut builder = sqlx::QueryBuilder::new(
-                "INSERT INTO article_data (lens_id, article_json, fetched_at) ",
+                "INSERT INTO article_data (lens_id, article_json, fetched_at, data_version) ",
             );
 
             builder.push_values(rows, |mut b, (id_str, refs_json, timestamp)| {
                 b.push_bind(id_str)
                     .push_bind(refs_json)
-                    .push_bind(timestamp);
+                    .push_bind(timestamp)
+                    .push(ARTICLE_DATA_VERSION);
             });
 
-            // References are immutable, so just ignore conflicts
-            builder.push(" ON CONFLICT (lens_id) DO NOTHING");
+            // Article data is immutable, only entries of an older version are replaced
+            builder.push(
+                " ON CONFLICT (lens_id) DO UPDATE SET article_json = EXCLUDED.article_json, fetched_at = EXCLUDED.fetched_at, data_version = EXCLUDED.data_version WHERE article_data.data_version < EXCLUDED.data_version",
+            );
 
             builder.build().execute(&mut *tx).await?;
         }
@@ -386,6 +472,239 @@ impl CacheBackend for PostgresBackend {
         Ok(())
     }
 
+    async fn list_id_mappings(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<(String, LensId)>, LensError> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            r#"
+                SELECT string_id, lens_id
+                FROM id_mappings
+                WHERE $1::TEXT IS NULL OR string_id > $1
+                ORDER BY string_id
+                LIMIT $2
+            "#,
+        )
+        .bind(after)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(string_id, lens_id_str)| {
+                let lens_id = LensId::try_from(lens_id_str.as_str())?;
+                Ok((string_id, lens_id))
+            })
+            .collect()
+    }
+    async fn get_pubmed_links(
+        &self,
+        link: PubmedLink,
+        pmids: &[u64],
+    ) -> Result<HashMap<u64, Vec<u64>>, LensError> {
+        if pmids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        // Cited-in lists follow the citation TTL, reference lists never expire
+        let oldest_allowed = match link {
+            PubmedLink::References => i64::MIN,
+            PubmedLink::Citations => Self::get_unix_timestamp()? - (14 * 24 * 60 * 60), // 14 days in seconds
+        };
+
+        let pmids_vec: Vec<i64> = pmids.iter().map(|&pmid| pmid as i64).collect();
+
+        let rows: Vec<(i64, Vec<i64>)> = sqlx::query_as(
+            r#"
+                SELECT pmid, linked_pmids
+                FROM pubmed_links
+                WHERE link_name = $1
+                AND pmid = ANY($2)
+                AND fetched_at >= $3
+            "#,
+        )
+        .bind(link.link_name())
+        .bind(&pmids_vec)
+        .bind(oldest_allowed)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(pmid, linked_pmids)| {
+                (
+                    pmid as u64,
+                    linked_pmids.into_iter().map(|pmid| pmid as u64).collect(),
+                )
+            })
+            .collect())
+    }
+
+    async fn store_pubmed_links(
+        &self,
+        link: PubmedLink,
+        batch: &[(u64, Vec<u64>)],
+    ) -> Result<(), LensError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        // With 4 params per row: 32767 / 4 = ~8191, use 5000 for safety
+        const CHUNK_SIZE: usize = 5000;
+
+        let mut tx = self.pool.begin().await?;
+
+        let rough_timestamp = Self::get_unix_timestamp()?;
+
+        for chunk in batch.chunks(CHUNK_SIZE) {
+            let mut builder = sqlx::QueryBuilder::new(
+                "INSERT INTO pubmed_links (pmid, link_name, linked_pmids, fetched_at) ",
+            );
+
+            builder.push_values(chunk, |mut b, (pmid, linked_pmids)| {
+                let linked_pmids: Vec<i64> = linked_pmids.iter().map(|&pmid| pmid as i64).collect();
+                b.push_bind(*pmid as i64)
+                    .push_bind(link.link_name())
+                    .push_bind(linked_pmids)
+                    .push_bind(rough_timestamp);
+            });
+
+            builder.push(
+                " ON CONFLICT (pmid, link_name) DO UPDATE SET linked_pmids = EXCLUDED.linked_pmids, fetched_at = EXCLUDED.fetched_at",
+            );
+
+            builder.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_pubmed_articles(&self, pmids: &[u64]) -> Result<Vec<PubmedArticle>, LensError> {
+        if pmids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pmids_vec: Vec<i64> = pmids.iter().map(|&pmid| pmid as i64).collect();
+
+        let rows: Vec<(String,)> = sqlx::query_as(
+            r#"
+                SELECT article_json
+                FROM pubmed_articles
+                WHERE pmid = ANY($1)
+            "#,
+        )
+        .bind(&pmids_vec)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(article_json,)| Ok(serde_json::from_str(&article_json)?))
+            .collect()
+    }
+
+    async fn store_pubmed_articles(&self, batch: &[PubmedArticle]) -> Result<(), LensError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        const CHUNK_SIZE: usize = 5000;
+
+        let mut tx = self.pool.begin().await?;
+
+        let rough_timestamp = Self::get_unix_timestamp()?;
+
+        for chunk in batch.chunks(CHUNK_SIZE) {
+            let rows: Vec<(i64, String, i64)> = chunk
+                .iter()
+                .filter_map(|article| Some((article.pmid.parse::<i64>().ok()?, article)))
+                .map(|(pmid, article)| Ok((pmid, serde_json::to_string(article)?, rough_timestamp)))
+                .collect::<Result<Vec<_>, LensError>>()?;
+            if rows.is_empty() {
+                continue;
+            }
+
+            let mut builder = sqlx::QueryBuilder::new(
+                "INSERT INTO pubmed_articles (pmid, article_json, fetched_at) ",
+            );
+
+            builder.push_values(rows, |mut b, (pmid, article_json, timestamp)| {
+                b.push_bind(pmid)
+                    .push_bind(article_json)
+                    .push_bind(timestamp);
+            });
+
+            builder.push(" ON CONFLICT (pmid) DO NOTHING");
+
+            builder.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_pubmed_search(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Option<Vec<u64>>, LensError> {
+        let now = Self::get_unix_timestamp()?;
+
+        let row: Option<(i64, Vec<i64>)> = sqlx::query_as(
+            r#"
+                SELECT max_results, pmids
+                FROM pubmed_searches
+                WHERE query = $1
+                AND fetched_at >= $2
+            "#,
+        )
+        .bind(query)
+        .bind(now - (24 * 60 * 60)) // 1 day in seconds
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.and_then(|(cached_max_results, pmids)| {
+            let complete = cached_max_results as usize >= max_results
+                || (pmids.len() as i64) < cached_max_results;
+            complete.then(|| {
+                pmids
+                    .into_iter()
+                    .take(max_results)
+                    .map(|pmid| pmid as u64)
+                    .collect()
+            })
+        }))
+    }
+
+    async fn store_pubmed_search(
+        &self,
+        query: &str,
+        max_results: usize,
+        pmids: &[u64],
+    ) -> Result<(), LensError> {
+        let rough_timestamp = Self::get_unix_timestamp()?;
+        let pmids: Vec<i64> = pmids.iter().map(|&pmid| pmid as i64).collect();
+
+        sqlx::query(
+            r#"
+                INSERT INTO pubmed_searches (query, max_results, pmids, fetched_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (query) DO UPDATE SET max_results = EXCLUDED.max_results, pmids = EXCLUDED.pmids, fetched_at = EXCLUDED.fetched_at
+            "#,
+        )
+        .bind(query)
+        .bind(max_results as i64)
+        .bind(pmids)
+        .bind(rough_timestamp)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     async fn mark_as_fetching(&self, id: &LensId) -> Result<bool, LensError> {
         let id_str = id.as_ref().to_string();
         let now = Self::get_unix_timestamp()?;
@@ -530,6 +849,10 @@ impl CacheBackend for PostgresBackend {
             .execute(&mut *tx)
             .await?;
 
+        sqlx::query("DELETE FROM article_cited_by")
+            .execute(&mut *tx)
+            .await?;
+
         sqlx::query("DELETE FROM article_data")
             .execute(&mut *tx)
             .await?;
@@ -542,44 +865,171 @@ impl CacheBackend for PostgresBackend {
             .execute(&mut *tx)
             .await?;
 
+        sqlx::query("DELETE FROM pubmed_links")
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM pubmed_articles")
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM pubmed_searches")
+            .execute(&mut *tx)
+            .await?;
+
         tx.commit().await?;
 
         Ok(())
     }
-}
 
-impl PostgresBackend {
-    /// Create a new PostgreSQL backend from a pre-configured connection pool
-    ///
-    /// This method allows you to configure the connection pool size and other
-    /// parameters before creating the backend. **Recommended for high-concurrency
-    /// scenarios** where the default pool size (10 connections) may be insufficient.
-    ///
-    /// # Arguments
-    /// * `pool` - Pre-configured sqlx PgPool
-    ///
-    /// # Example
-    /// ```ignore
-    /// use sqlx::postgres::PgPoolOptions;
-    ///
-    /// // Configure pool for high concurrency (e.g., 300+ concurrent users)
-    /// let pool = PgPoolOptions::new()
-    ///     .max_connections(50)  // Increase for high concurrency
-    ///     .min_connections(10)  // Keep connections warm
-    ///     .acquire_timeout(std::time::Duration::from_secs(5))
-    ///     .connect("postgres://localhost/lens_cache")
-    ///     .await?;
-    ///
+    async fn purge(&self, ids: &[LensId]) -> Result<u64, LensError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let int_ids = to_int_ids(ids);
+        let string_ids: Vec<String> = ids.iter().map(|id| id.as_ref().to_string()).collect();
+
+        let mut tx = self.pool.begin().await?;
+        let mut deleted = 0;
+
+        for query in [
+            "DELETE FROM article_references WHERE lens_id = ANY($1)",
+            "DELETE FROM article_citations WHERE lens_id = ANY($1)",
+            "DELETE FROM article_cited_by WHERE citing_id = ANY($1)",
+        ] {
+            deleted += sqlx::query(query)
+                .bind(&int_ids)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        for query in [
+            "DELETE FROM article_data WHERE lens_id = ANY($1)",
+            "DELETE FROM id_mappings WHERE lens_id = ANY($1)",
+            "DELETE FROM pending_fetches WHERE lens_id = ANY($1)",
+        ] {
+            deleted += sqlx::query(query)
+                .bind(&string_ids)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+
+        Ok(deleted)
+    }
+
+    async fn stats(&self) -> Result<CacheStats, LensError> {
+        let now = Self::get_unix_timestamp()?;
+
+        let row: (i64, i64, i64, i64, i64, i64, i64, i64, i64, i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM article_references),
+                (SELECT COUNT(*) FROM article_citations),
+                (SELECT COUNT(*) FROM article_citations WHERE fetched_at < $1),
+                (SELECT COUNT(*) FROM article_cited_by),
+                (SELECT COUNT(*) FROM article_data),
+                (SELECT COUNT(*) FROM id_mappings),
+                (SELECT COUNT(*) FROM pending_fetches),
+                (SELECT COUNT(*) FROM pending_fetches WHERE started_at < $2),
+                (SELECT COUNT(*) FROM pubmed_links),
+                (SELECT COUNT(*) FROM pubmed_articles),
+                (SELECT COUNT(*) FROM pubmed_searches)
+            "#,
+        )
+        .bind(now - (14 * 24 * 60 * 60)) // 14 days in seconds
+        .bind(now - 60)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(CacheStats {
+            references: row.0,
+            citations: row.1,
+            stale_citations: row.2,
+            cited_by_edges: row.3,
+            article_data: row.4,
+            id_mappings: row.5,
+            pending_fetches: row.6,
+            stale_pending_fetches: row.7,
+            pubmed_links: row.8,
+            pubmed_articles: row.9,
+            pubmed_searches: row.10,
+        })
+    }
+
+    async fn clear_stale_pending_fetches(&self) -> Result<u64, LensError> {
+        let stale_threshold = Self::get_unix_timestamp()? - 60;
+
+        let result = sqlx::query("DELETE FROM pending_fetches WHERE started_at < $1")
+            .bind(stale_threshold)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn vacuum(&self) -> Result<(), LensError> {
+        // VACUUM cannot run inside a transaction block, so it goes through the simple query protocol
+        sqlx::raw_sql(
+            "VACUUM ANALYZE article_references, article_citations, article_cited_by, \
+             article_data, id_mappings, pending_fetches, pubmed_links, pubmed_articles, \
+             pubmed_searches",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl PostgresBackend {
+    /// Create a new PostgreSQL backend from a pre-configured connection pool
+    ///
+    /// This method allows you to configure the connection pool size and other
+    /// parameters before creating the backend. **Recommended for high-concurrency
+    /// scenarios** where the default pool size (10 connections) may be insufficient.
+    ///
+    /// # Arguments
+    /// * `pool` - Pre-configured sqlx PgPool
+    ///
+    /// # Example
+    /// ```ignore
+    /// use sqlx::postgres::PgPoolOptions;
+    ///
+    /// // Configure pool for high concurrency (e.g., 300+ concurrent users)
+    /// let pool = PgPoolOptions::new()
+    ///     .max_connections(50)  // Increase for high concurrency
+    ///     .min_connections(10)  // Keep connections warm
+    ///     .acquire_timeout(std::time::Duration::from_secs(5))
+    ///     .connect("postgres://localhost/lens_cache")
+    ///     .await?;
+    ///
     /// let backend = PostgresBackend::from_pool(pool).await?;
     /// ```
     pub async fn from_pool(pool: PgPool) -> Result<Self, LensError> {
-        let backend = Self { pool };
+        let backend = Self {
+            pool,
+            cited_by_fallback: false,
+        };
         backend.run_migrations().await?;
         backend.optimize_postgres().await?;
 
         Ok(backend)
     }
 
+    /// Let citation misses be answered from the cited-by index (off by default)
+    ///
+    /// See `CacheBackend::get_citations_or_cited_by`. Citations built this way are
+    /// marked incomplete, as they only contain citing articles already in the cache.
+    pub fn with_cited_by_fallback(mut self, enabled: bool) -> Self {
+        self.cited_by_fallback = enabled;
+        self
+    }
+
     /// Create a new PostgreSQL backend from a connection URL
     ///
     /// Uses default connection pool settings (max 10 connections). For high-concurrency
@@ -600,50 +1050,78 @@ impl PostgresBackend {
 
     /// Run database migrations (creates tables if they don't exist)
     async fn run_migrations(&self) -> Result<(), LensError> {
-        // LensId tables (optimized with NoHasher)
+        // Re-encode edge tables still in the JSON-text layout
+        let legacy_references = self.migrate_legacy_references().await?;
+        self.migrate_legacy_citations().await?;
+
+        sqlx::query(CREATE_REFERENCES_TABLE)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(CREATE_CITATIONS_TABLE)
+            .execute(&self.pool)
+            .await?;
+
+        // Create index on fetched_at for citations (useful for TTL queries)
+        // Created after the legacy tables are dropped, as they may still own this index name
         sqlx::query(
-            r#"
-            CREATE UNLOGGED TABLE IF NOT EXISTS article_references (
-                lens_id TEXT PRIMARY KEY,
-                references_json TEXT NOT NULL,
-                fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
-            )
-            "#,
+            "CREATE INDEX IF NOT EXISTS idx_citations_fetched ON article_citations(fetched_at)",
         )
         .execute(&self.pool)
         .await?;
 
+        // Inverted references, filled from existing references when first created
+        let (cited_by_exists,): (bool,) =
+            sqlx::query_as("SELECT to_regclass('article_cited_by') IS NOT NULL")
+                .fetch_one(&self.pool)
+                .await?;
+
         sqlx::query(
             r#"
-            CREATE UNLOGGED TABLE IF NOT EXISTS article_citations (
-                lens_id TEXT PRIMARY KEY,
-                citations_json TEXT NOT NULL,
-                fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
+            CREATE UNLOGGED TABLE IF NOT EXISTS article_cited_by (
+                cited_id BIGINT NOT NULL,
+                citing_id BIGINT NOT NULL,
+                PRIMARY KEY (cited_id, citing_id)
             )
             "#,
         )
         .execute(&self.pool)
         .await?;
 
-        // Create index on fetched_at for citations (useful for TTL queries)
-        sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_citations_fetched ON article_citations(fetched_at)",
-        )
-        .execute(&self.pool)
-        .await?;
+        // Also after migrating legacy references, which may be resumed into a cache that
+        // already has the index
+        if !cited_by_exists || legacy_references {
+            sqlx::query(
+                r#"
+                INSERT INTO article_cited_by (cited_id, citing_id)
+                SELECT DISTINCT UNNEST(reference_ids), lens_id FROM article_references
+                ON CONFLICT (cited_id, citing_id) DO NOTHING
+                "#,
+            )
+            .execute(&self.pool)
+            .await?;
+        }
 
         sqlx::query(
             r#"
             CREATE UNLOGGED TABLE IF NOT EXISTS article_data (
                 lens_id TEXT PRIMARY KEY,
                 article_json TEXT NOT NULL,
-                fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
+                fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT,
+                data_version BIGINT NOT NULL DEFAULT 0
             )
             "#,
         )
         .execute(&self.pool)
         .await?;
 
+        // Entries cached before it was added are version 0, fetched again when read
+        sqlx::query(
+            "ALTER TABLE article_data ADD COLUMN IF NOT EXISTS data_version BIGINT NOT NULL DEFAULT 0",
+        )
+        .execute(&self.pool)
+        .await?;
+
         // ID mappings table (PMID/DOI/etc → LensId)
         sqlx::query(
             r#"
@@ -657,6 +1135,20 @@ impl PostgresBackend {
         .execute(&self.pool)
         .await?;
 
+        // One-off data migrations already applied to this cache
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS cache_migrations (
+                name TEXT PRIMARY KEY,
+                applied_at BIGINT NOT NULL DEFAULT extract(epoch from now())
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.migrate_id_mapping_keys().await?;
+
         // Pending fetches table (for request deduplication)
         sqlx::query(
             r#"
@@ -669,6 +1161,346 @@ impl PostgresBackend {
         .execute(&self.pool)
         .await?;
 
+        // PubMed tables, keyed by PMID
+        sqlx::query(
+            r#"
+            CREATE UNLOGGED TABLE IF NOT EXISTS pubmed_links (
+                pmid BIGINT NOT NULL,
+                link_name TEXT NOT NULL,
+                linked_pmids BIGINT[] NOT NULL,
+                fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT,
+                PRIMARY KEY (pmid, link_name)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE UNLOGGED TABLE IF NOT EXISTS pubmed_articles (
+                pmid BIGINT PRIMARY KEY,
+                article_json TEXT NOT NULL,
+                fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE UNLOGGED TABLE IF NOT EXISTS pubmed_searches (
+                query TEXT PRIMARY KEY,
+                max_results BIGINT NOT NULL,
+                pmids BIGINT[] NOT NULL,
+                fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Rewrite the `id_mappings` keys written before they were canonical, once
+    ///
+    /// See `rekey_id_mappings`. Runs in a single transaction, recorded in
+    /// `cache_migrations` when done.
+    async fn migrate_id_mapping_keys(&self) -> Result<(), LensError> {
+        let mut tx = self.pool.begin().await?;
+
+        let (applied,): (bool,) =
+            sqlx::query_as("SELECT EXISTS (SELECT 1 FROM cache_migrations WHERE name = $1)")
+                .bind(CANONICAL_ID_MAPPING_KEYS)
+                .fetch_one(&mut *tx)
+                .await?;
+        if applied {
+            return Ok(());
+        }
+
+        let mut last_id = String::new();
+        let mut stale = 0usize;
+
+        loop {
+            let rows: Vec<(String, String, i64)> = sqlx::query_as(
+                r#"
+                SELECT string_id, lens_id, fetched_at
+                FROM id_mappings
+                WHERE string_id > $1
+                ORDER BY string_id
+                LIMIT 5000
+                "#,
+            )
+            .bind(&last_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let Some(last_row) = rows.last() else {
+                break;
+            };
+            last_id = last_row.0.clone();
+
+            let (stale_keys, rekeyed) = rekey_id_mappings(rows);
+            if stale_keys.is_empty() {
+                continue;
+            }
+            stale += stale_keys.len();
+
+            sqlx::query("DELETE FROM id_mappings WHERE string_id = ANY($1)")
+                .bind(&stale_keys)
+                .execute(&mut *tx)
+                .await?;
+
+            if !rekeyed.is_empty() {
+                let mut builder = sqlx::QueryBuilder::new(
+                    "INSERT INTO id_mappings (string_id, lens_id, fetched_at) ",
+                );
+                builder.push_values(rekeyed, |mut b, (string_id, lens_id, fetched_at)| {
+                    b.push_bind(string_id)
+                        .push_bind(lens_id)
+                        .push_bind(fetched_at);
+                });
+                builder.push(" ON CONFLICT (string_id) DO NOTHING");
+                builder.build().execute(&mut *tx).await?;
+            }
+        }
+
+        sqlx::query("INSERT INTO cache_migrations (name) VALUES ($1)")
+            .bind(CANONICAL_ID_MAPPING_KEYS)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        if stale > 0 {
+            log::info!(
+                "Rekeyed {stale} cached ID mappings under their canonical key, dropping duplicates and invalid keys"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Insert converted rows into `article_references` (conflicts are ignored)
+    async fn insert_references_rows(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        rows: Vec<(i64, Vec<i64>, i64)>,
+    ) -> Result<(), LensError> {
+        let mut builder = sqlx::QueryBuilder::new(
+            "INSERT INTO article_references (lens_id, reference_ids, fetched_at) ",
+        );
+
+        builder.push_values(rows, |mut b, (lens_id, reference_ids, timestamp)| {
+            b.push_bind(lens_id)
+                .push_bind(reference_ids)
+                .push_bind(timestamp);
+        });
+
+        // References are immutable, so just ignore conflicts
+        builder.push(" ON CONFLICT (lens_id) DO NOTHING");
+
+        builder.build().execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    /// Insert converted rows into `article_citations` (conflicts are updated)
+    async fn insert_citations_rows(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        rows: Vec<(i64, Vec<i64>, i64)>,
+    ) -> Result<(), LensError> {
+        let mut builder = sqlx::QueryBuilder::new(
+            "INSERT INTO article_citations (lens_id, citation_ids, fetched_at) ",
+        );
+
+        builder.push_values(rows, |mut b, (lens_id, citation_ids, timestamp)| {
+            b.push_bind(lens_id)
+                .push_bind(citation_ids)
+                .push_bind(timestamp);
+        });
+
+        // For citations, we want to update with fresh data on conflict, but never with
+        // older data (a resumed migration copies lists older than the ones already there)
+        builder.push(
+            " ON CONFLICT (lens_id) DO UPDATE SET citation_ids = EXCLUDED.citation_ids, fetched_at = EXCLUDED.fetched_at WHERE article_citations.fetched_at <= EXCLUDED.fetched_at",
+        );
+
+        builder.build().execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    /// Rename an edge table to `*_legacy` if it still uses the JSON-text layout
+    ///
+    /// Returns true if there is a `*_legacy` table to migrate, renamed now or left behind
+    /// by a migration interrupted before this one was done in a single transaction.
+    async fn rename_legacy_edge_table(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        table: &str,
+        legacy_column: &str,
+        rename_query: &'static str,
+        legacy_exists_query: &'static str,
+    ) -> Result<bool, LensError> {
+        let (legacy_columns,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*)
+            FROM information_schema.columns
+            WHERE table_schema = current_schema()
+            AND table_name = $1
+            AND column_name = $2
+            "#,
+        )
+        .bind(table)
+        .bind(legacy_column)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        if legacy_columns > 0 {
+            sqlx::query(rename_query).execute(&mut **tx).await?;
+            return Ok(true);
+        }
+
+        let (legacy_exists,): (bool,) = sqlx::query_as(legacy_exists_query)
+            .fetch_one(&mut **tx)
+            .await?;
+
+        Ok(legacy_exists)
+    }
+
+    /// Re-encode `article_references` from the JSON-text layout, if needed
+    ///
+    /// The rename, the new table and the copy share one transaction, so an interrupted
+    /// migration leaves the cache as it was. Returns true if rows were migrated.
+    async fn migrate_legacy_references(&self) -> Result<bool, LensError> {
+        let mut tx = self.pool.begin().await?;
+
+        let legacy = Self::rename_legacy_edge_table(
+            &mut tx,
+            "article_references",
+            "references_json",
+            "ALTER TABLE article_references RENAME TO article_references_legacy",
+            "SELECT to_regclass('article_references_legacy') IS NOT NULL",
+        )
+        .await?;
+        if !legacy {
+            return Ok(false);
+        }
+
+        sqlx::query(CREATE_REFERENCES_TABLE)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut last_id = String::new();
+        let mut migrated = 0usize;
+        let mut skipped = 0usize;
+
+        loop {
+            let rows: Vec<LegacyEdgesRow> = sqlx::query_as(
+                r#"
+                SELECT lens_id, references_json AS edges_json, fetched_at
+                FROM article_references_legacy
+                WHERE lens_id > $1
+                ORDER BY lens_id
+                LIMIT 5000
+                "#,
+            )
+            .bind(&last_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let Some(last_row) = rows.last() else {
+                break;
+            };
+            last_id = last_row.lens_id.clone();
+
+            let rows = LegacyEdgesRow::extract_all(rows, &mut skipped);
+            migrated += rows.len();
+
+            if !rows.is_empty() {
+                Self::insert_references_rows(&mut tx, rows).await?;
+            }
+        }
+
+        sqlx::query("DROP TABLE article_references_legacy")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        log::info!("Migrated {migrated} cached reference lists to BIGINT[]");
+        if skipped > 0 {
+            log::warn!("Skipped {skipped} cached reference lists that could not be parsed");
+        }
+
+        Ok(true)
+    }
+
+    /// Re-encode `article_citations` from the JSON-text layout, if needed
+    ///
+    /// Same as `migrate_legacy_references`.
+    async fn migrate_legacy_citations(&self) -> Result<(), LensError> {
+        let mut tx = self.pool.begin().await?;
+
+        let legacy = Self::rename_legacy_edge_table(
+            &mut tx,
+            "article_citations",
+            "citations_json",
+            "ALTER TABLE article_citations RENAME TO article_citations_legacy",
+            "SELECT to_regclass('article_citations_legacy') IS NOT NULL",
+        )
+        .await?;
+        if !legacy {
+            return Ok(());
+        }
+
+        sqlx::query(CREATE_CITATIONS_TABLE)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut last_id = String::new();
+        let mut migrated = 0usize;
+        let mut skipped = 0usize;
+
+        loop {
+            let rows: Vec<LegacyEdgesRow> = sqlx::query_as(
+                r#"
+                SELECT lens_id, citations_json AS edges_json, fetched_at
+                FROM article_citations_legacy
+                WHERE lens_id > $1
+                ORDER BY lens_id
+                LIMIT 5000
+                "#,
+            )
+            .bind(&last_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let Some(last_row) = rows.last() else {
+                break;
+            };
+            last_id = last_row.lens_id.clone();
+
+            let rows = LegacyEdgesRow::extract_all(rows, &mut skipped);
+            migrated += rows.len();
+
+            if !rows.is_empty() {
+                Self::insert_citations_rows(&mut tx, rows).await?;
+            }
+        }
+
+        sqlx::query("DROP TABLE article_citations_legacy")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        log::info!("Migrated {migrated} cached citation lists to BIGINT[]");
+        if skipped > 0 {
+            log::warn!("Skipped {skipped} cached citation lists that could not be parsed");
+        }
+
         Ok(())
     }
 
@@ -691,7 +1523,7 @@ impl PostgresBackend {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lens::cache::compute_misses;
+    use crate::lens::cache::{CachedCitations, compute_misses};
 
     /// Helper to create an isolated PostgreSQL backend for testing
     ///
@@ -743,7 +1575,10 @@ mod tests {
             .map_err(LensError::SqlxError)?;
 
         // Run migrations in the isolated schema
-        let backend = PostgresBackend { pool };
+        let backend = PostgresBackend {
+            pool,
+            cited_by_fallback: false,
+        };
         backend.run_migrations().await?;
 
         Ok(backend)
@@ -982,35 +1817,92 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_store_and_get_article_data() -> Result<(), LensError> {
-        use crate::lens::article::{Author, ExternalIds, Source};
-
+    async fn test_article_data_of_older_versions_is_fetched_again() -> Result<(), LensError> {
         let backend = create_test_backend().await?;
+        let id = LensId::from(12345678901234);
 
-        // Create test articles
-        let id1 = LensId::from(12345678901234);
-        let id2 = LensId::from(98765432109876);
-        let id3 = LensId::from(11111111111111);
-
-        let article_data1 = ArticleData {
+        // Cached before the retraction status was fetched
+        sqlx::query(
+            "INSERT INTO article_data (lens_id, article_json, data_version) VALUES ($1, $2, 0)",
+        )
+        .bind(id.as_ref())
+        .bind(r#"{"title": "Old entry"}"#)
+        .execute(&backend.pool)
+        .await?;
+        assert!(
+            backend
+                .get_article_data(std::slice::from_ref(&id))
+                .await?
+                .is_empty()
+        );
+
+        let fetched = ArticleWithData {
+            lens_id: id.clone(),
+            article_data: ArticleData {
+                title: Some("Fetched again".to_string()),
+                is_retracted: Some(true),
+                ..Default::default()
+            },
+        };
+        backend.store_article_data(&[fetched]).await?;
+
+        let cached = backend.get_article_data(std::slice::from_ref(&id)).await?;
+        assert_eq!(
+            cached[0].article_data.title.as_deref(),
+            Some("Fetched again")
+        );
+        assert_eq!(cached[0].article_data.is_retracted, Some(true));
+
+        // Entries of the current version are not replaced
+        let refetched = ArticleWithData {
+            lens_id: id.clone(),
+            article_data: ArticleData::default(),
+        };
+        backend.store_article_data(&[refetched]).await?;
+        let cached = backend.get_article_data(std::slice::from_ref(&id)).await?;
+        assert_eq!(
+            cached[0].article_data.title.as_deref(),
+            Some("Fetched again")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_store_and_get_article_data() -> Result<(), LensError> {
+        use crate::lens::article::{Author, ExternalIds, Source};
+
+        let backend = create_test_backend().await?;
+
+        // Create test articles
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+        let id3 = LensId::from(11111111111111);
+
+        let article_data1 = ArticleData {
             title: Some("Test Article 1".to_string()),
             summary: Some("This is a test abstract".to_string()),
             scholarly_citations_count: Some(42),
             external_ids: Some(ExternalIds {
                 pmid: vec!["12345".to_string()],
                 doi: vec!["10.1234/test".to_string()],
+                pmcid: vec!["PMC12345".to_string()],
+                ..Default::default()
             }),
             authors: Some(vec![Author {
                 first_name: Some("John".to_string()),
                 initials: Some("J".to_string()),
                 last_name: Some("Doe".to_string()),
+                affiliations: None,
             }]),
             source: Some(Source {
                 publisher: Some("Test Publisher".to_string()),
                 title: Some("Test Journal".to_string()),
                 kind: Some("journal".to_string()),
+                issn: None,
             }),
             year_published: Some(2023),
+            ..Default::default()
         };
 
         let article_data2 = ArticleData {
@@ -1021,6 +1913,7 @@ mod tests {
             authors: None,
             source: None,
             year_published: Some(2024),
+            ..Default::default()
         };
 
         let article1 = ArticleWithData {
@@ -1116,6 +2009,36 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_list_id_mappings() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        let lens_id1 = LensId::from(12345678901234);
+        let lens_id2 = LensId::from(98765432109876);
+        backend
+            .store_id_mapping(&[
+                ("12345".to_string(), lens_id1.clone()),
+                ("10.5678/foo".to_string(), lens_id2.clone()),
+                ("10.1234/test".to_string(), lens_id2.clone()),
+            ])
+            .await?;
+
+        // Pages follow the order of the string IDs
+        let first = backend.list_id_mappings(None, 2).await?;
+        assert_eq!(
+            first,
+            [
+                ("10.1234/test".to_string(), lens_id2.clone()),
+                ("10.5678/foo".to_string(), lens_id2.clone()),
+            ]
+        );
+        let second = backend.list_id_mappings(Some("10.5678/foo"), 2).await?;
+        assert_eq!(second, [("12345".to_string(), lens_id1)]);
+        assert!(backend.list_id_mappings(Some("12345"), 2).await?.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_mark_and_unmark_fetching() -> Result<(), LensError> {
         let backend = create_test_backend().await?;
@@ -1240,7 +2163,7 @@ mod tests {
                 for _ in 0..20 {
                     tokio::time::sleep(std::time::Duration::from_millis(10)).await;
                     let cached = backend2
-                        .get_references(&[id1_clone2.clone()])
+                        .get_references(std::slice::from_ref(&id1_clone2))
                         .await
                         .unwrap();
                     if cached.contains_key(&id1_clone2) {
@@ -1264,7 +2187,7 @@ mod tests {
         );
 
         // Verify data is in cache
-        let cached = backend.get_references(&[id1.clone()]).await?;
+        let cached = backend.get_references(std::slice::from_ref(&id1)).await?;
         assert_eq!(cached.get(&id1).unwrap(), &refs);
 
         // Verify no pending marks remain
@@ -1296,4 +2219,538 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_get_cited_by_from_stored_references() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        let citing_a = LensId::from(20000000000001);
+        let citing_b = LensId::from(20000000000002);
+        let cited_x = LensId::from(30000000000001);
+        let cited_y = LensId::from(30000000000002);
+        let unknown = LensId::from(30000000000003);
+
+        backend
+            .store_references(&[
+                (citing_b.clone(), vec![cited_x.clone()]),
+                (citing_a.clone(), vec![cited_x.clone(), cited_y.clone()]),
+            ])
+            .await?;
+
+        let cited_by = backend
+            .get_cited_by(&[cited_x.clone(), cited_y.clone(), unknown.clone()])
+            .await?;
+
+        assert_eq!(cited_by.len(), 2);
+        assert_eq!(
+            cited_by.get(&cited_x).unwrap(),
+            &vec![citing_a.clone(), citing_b]
+        );
+        assert_eq!(cited_by.get(&cited_y).unwrap(), &vec![citing_a]);
+        assert!(!cited_by.contains_key(&unknown));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_citations_fall_back_to_cited_by_when_enabled() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        let citing = LensId::from(20000000000001);
+        let cited = LensId::from(30000000000001);
+        backend
+            .store_references(&[(citing.clone(), vec![cited.clone()])])
+            .await?;
+
+        // Off by default: a citation miss stays a miss
+        let result = backend
+            .get_citations_or_cited_by(std::slice::from_ref(&cited))
+            .await?;
+        assert!(result.is_empty());
+
+        // Opted in: the miss is answered from the index, marked incomplete
+        let backend = backend.with_cited_by_fallback(true);
+        let result = backend
+            .get_citations_or_cited_by(std::slice::from_ref(&cited))
+            .await?;
+        assert_eq!(
+            result.get(&cited).unwrap(),
+            &CachedCitations {
+                citations: vec![citing.clone()],
+                complete: false,
+            }
+        );
+
+        // Citations fetched from Lens always win
+        let full = vec![citing, LensId::from(20000000000009)];
+        backend
+            .store_citations(&[(cited.clone(), full.clone())])
+            .await?;
+        let result = backend
+            .get_citations_or_cited_by(std::slice::from_ref(&cited))
+            .await?;
+        assert_eq!(
+            result.get(&cited).unwrap(),
+            &CachedCitations {
+                citations: full,
+                complete: true,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cited_by_backfilled_from_existing_references() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        let citing = LensId::from(20000000000001);
+        let cited = LensId::from(30000000000001);
+        backend
+            .store_references(&[(citing.clone(), vec![cited.clone()])])
+            .await?;
+
+        // Simulate a cache created before the index existed
+        sqlx::query("DROP TABLE article_cited_by")
+            .execute(&backend.pool)
+            .await?;
+        backend.run_migrations().await?;
+
+        let cited_by = backend.get_cited_by(std::slice::from_ref(&cited)).await?;
+        assert_eq!(cited_by.get(&cited).unwrap(), &vec![citing]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migrates_legacy_json_edge_tables() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+        let refs1 = vec![LensId::from(1), LensId::from(2), LensId::from(3)];
+        let citations2 = vec![LensId::from(4), LensId::from(5)];
+
+        // Swap the fresh edge tables for ones in the old JSON-text layout
+        sqlx::query("DROP TABLE article_references, article_citations")
+            .execute(&backend.pool)
+            .await?;
+        sqlx::query(
+            "CREATE TABLE article_references (lens_id TEXT PRIMARY KEY, references_json TEXT NOT NULL, fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT)",
+        )
+        .execute(&backend.pool)
+        .await?;
+        sqlx::query(
+            "CREATE TABLE article_citations (lens_id TEXT PRIMARY KEY, citations_json TEXT NOT NULL, fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT)",
+        )
+        .execute(&backend.pool)
+        .await?;
+        sqlx::query("CREATE INDEX idx_citations_fetched ON article_citations(fetched_at)")
+            .execute(&backend.pool)
+            .await?;
+
+        // Rows that do not parse are skipped, to be fetched again
+        sqlx::query(
+            "INSERT INTO article_references (lens_id, references_json) VALUES ($1, $2), ($3, $4), ($5, $6)",
+        )
+        .bind(id1.as_ref())
+        .bind(serde_json::to_string(&refs1)?)
+        .bind(id2.as_ref())
+        .bind("not json")
+        .bind("not a lens id")
+        .bind(serde_json::to_string(&refs1)?)
+        .execute(&backend.pool)
+        .await?;
+        sqlx::query("INSERT INTO article_citations (lens_id, citations_json) VALUES ($1, $2)")
+            .bind(id2.as_ref())
+            .bind(serde_json::to_string(&citations2)?)
+            .execute(&backend.pool)
+            .await?;
+
+        backend.run_migrations().await?;
+
+        let references = backend.get_references(&[id1.clone(), id2.clone()]).await?;
+        assert_eq!(references.get(&id1).unwrap(), &refs1);
+        assert!(!references.contains_key(&id2));
+
+        let citations = backend.get_citations(std::slice::from_ref(&id2)).await?;
+        assert_eq!(citations.get(&id2).unwrap(), &citations2);
+
+        let (legacy_tables,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name LIKE '%_legacy'",
+        )
+        .fetch_one(&backend.pool)
+        .await?;
+        assert_eq!(legacy_tables, 0);
+
+        // Running migrations on an already migrated cache is a no-op
+        backend.run_migrations().await?;
+        let references = backend.get_references(std::slice::from_ref(&id1)).await?;
+        assert_eq!(references.get(&id1).unwrap(), &refs1);
+
+        Ok(())
+    }
+
+    /// Mappings written before keys were canonical are rekeyed once, keeping the mapping
+    /// already stored under the canonical key.
+    #[tokio::test]
+    async fn test_id_mapping_keys_are_made_canonical() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        let lens_id1 = LensId::from(12345678901234);
+        let lens_id2 = LensId::from(98765432109876);
+        backend
+            .store_id_mapping(&[
+                ("10.1000/ABC".to_string(), lens_id1.clone()),
+                ("doi:10.1000/abc".to_string(), lens_id2.clone()),
+                ("10.5678/foo".to_string(), lens_id2.clone()),
+                ("https://doi.org/10.5678/FOO".to_string(), lens_id1.clone()),
+                ("pmc7000001".to_string(), lens_id1.clone()),
+                ("mag:3000000001".to_string(), lens_id2.clone()),
+                ("not an identifier".to_string(), lens_id1.clone()),
+            ])
+            .await?;
+        sqlx::query("DELETE FROM cache_migrations")
+            .execute(&backend.pool)
+            .await?;
+
+        backend.run_migrations().await?;
+
+        let mappings = backend.list_id_mappings(None, 10).await?;
+        assert_eq!(
+            mappings,
+            [
+                ("10.1000/abc".to_string(), lens_id1.clone()),
+                ("10.5678/foo".to_string(), lens_id2.clone()),
+                ("PMC7000001".to_string(), lens_id1.clone()),
+                ("mag:3000000001".to_string(), lens_id2),
+            ]
+        );
+
+        // Applied once: keys written since are left as they are
+        backend
+            .store_id_mapping(&[("10.9999/LATER".to_string(), lens_id1)])
+            .await?;
+        backend.run_migrations().await?;
+        assert_eq!(backend.list_id_mappings(None, 10).await?.len(), 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resumes_interrupted_legacy_migration() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+        let refs1 = vec![LensId::from(1), LensId::from(2)];
+        let fresh_refs2 = vec![LensId::from(3)];
+
+        // A legacy table renamed by an earlier migration that stopped before copying it,
+        // while the new table has been filled since
+        sqlx::query(
+            "CREATE TABLE article_references_legacy (lens_id TEXT PRIMARY KEY, references_json TEXT NOT NULL, fetched_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT)",
+        )
+        .execute(&backend.pool)
+        .await?;
+        sqlx::query(
+            "INSERT INTO article_references_legacy (lens_id, references_json) VALUES ($1, $2), ($3, $4)",
+        )
+        .bind(id1.as_ref())
+        .bind(serde_json::to_string(&refs1)?)
+        .bind(id2.as_ref())
+        .bind(serde_json::to_string(&[LensId::from(4)])?)
+        .execute(&backend.pool)
+        .await?;
+        backend
+            .store_references(&[(id2.clone(), fresh_refs2.clone())])
+            .await?;
+
+        backend.run_migrations().await?;
+
+        let references = backend.get_references(&[id1.clone(), id2.clone()]).await?;
+        assert_eq!(references.get(&id1).unwrap(), &refs1);
+        assert_eq!(references.get(&id2).unwrap(), &fresh_refs2);
+
+        // The cited-by index also covers the migrated references
+        let cited_by = backend.get_cited_by(&[LensId::from(1)]).await?;
+        assert_eq!(cited_by.get(&LensId::from(1)).unwrap(), &vec![id1]);
+
+        let (legacy_exists,): (bool,) =
+            sqlx::query_as("SELECT to_regclass('article_references_legacy') IS NOT NULL")
+                .fetch_one(&backend.pool)
+                .await?;
+        assert!(!legacy_exists);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "benchmark: stores and reads 2000 x 200 edges"]
+    async fn test_bigint_edges_read_performance() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        // 2000 articles with 200 edges each
+        let batch: Vec<(LensId, Vec<LensId>)> = (0..2000u64)
+            .map(|i| {
+                let edges = (0..200u64)
+                    .map(|j| LensId::from(20000000000000u64 + i * 1000 + j))
+                    .collect();
+                (LensId::from(10000000000000u64 + i), edges)
+            })
+            .collect();
+        let ids: Vec<LensId> = batch.iter().map(|(id, _)| id.clone()).collect();
+
+        backend.store_references(&batch).await?;
+
+        // Same data in the old JSON-text layout
+        sqlx::query(
+            "CREATE TABLE json_references (lens_id TEXT PRIMARY KEY, references_json TEXT NOT NULL)",
+        )
+        .execute(&backend.pool)
+        .await?;
+        for chunk in batch.chunks(5000) {
+            let mut builder =
+                sqlx::QueryBuilder::new("INSERT INTO json_references (lens_id, references_json) ");
+            builder.push_values(chunk, |mut b, (id, refs)| {
+                b.push_bind(id.as_ref().to_string())
+                    .push_bind(serde_json::to_string(refs).unwrap());
+            });
+            builder.build().execute(&backend.pool).await?;
+        }
+
+        // Fetch and decode the stored edge lists in both layouts
+        let id_strings: Vec<String> = ids.iter().map(|id| id.as_ref().to_string()).collect();
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT lens_id, references_json FROM json_references WHERE lens_id = ANY($1)",
+        )
+        .bind(&id_strings)
+        .fetch_all(&backend.pool)
+        .await?;
+        let json_edges = rows
+            .iter()
+            .map(|(_, json)| Ok(serde_json::from_str::<Vec<LensId>>(json)?.len()))
+            .sum::<Result<usize, LensError>>()?;
+
+        let rows: Vec<(i64, Vec<i64>)> = sqlx::query_as(
+            "SELECT lens_id, reference_ids FROM article_references WHERE lens_id = ANY($1)",
+        )
+        .bind(to_int_ids(&ids))
+        .fetch_all(&backend.pool)
+        .await?;
+        let bigint_edges: usize = rows.iter().map(|(_, edges)| edges.len()).sum();
+
+        let result = backend.get_references(&ids).await?;
+
+        assert_eq!(json_edges, 400_000);
+        assert_eq!(bigint_edges, 400_000);
+        assert_eq!(result.get(&ids[0]).unwrap(), &batch[0].1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_purge_removes_everything_about_an_id() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+        let ref1 = LensId::from(11111111111111);
+
+        backend
+            .store_references(&[
+                (id1.clone(), vec![ref1.clone()]),
+                (id2.clone(), vec![ref1.clone()]),
+            ])
+            .await?;
+        backend
+            .store_citations(&[(id1.clone(), vec![id2.clone()])])
+            .await?;
+        backend
+            .store_id_mapping(&[("10.1234/test".to_string(), id1.clone())])
+            .await?;
+        backend.mark_as_fetching(&id1).await?;
+
+        // 1 reference list, 1 citation list, 1 cited-by edge, 1 mapping, 1 pending mark
+        assert_eq!(backend.purge(std::slice::from_ref(&id1)).await?, 5);
+        assert_eq!(backend.purge(&[]).await?, 0);
+
+        assert!(
+            backend
+                .get_references(std::slice::from_ref(&id1))
+                .await?
+                .is_empty()
+        );
+        assert!(
+            backend
+                .get_citations(std::slice::from_ref(&id1))
+                .await?
+                .is_empty()
+        );
+        assert!(
+            backend
+                .get_id_mapping(&["10.1234/test".to_string()])
+                .await?
+                .is_empty()
+        );
+        assert!(!backend.is_being_fetched(&id1).await?);
+
+        // Other articles are untouched, including their share of the cited-by index
+        assert_eq!(
+            backend
+                .get_references(std::slice::from_ref(&id2))
+                .await?
+                .len(),
+            1
+        );
+        let cited_by = backend.get_cited_by(std::slice::from_ref(&ref1)).await?;
+        assert_eq!(cited_by.get(&ref1), Some(&vec![id2.clone()]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stats_and_stale_pending_fetches() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+
+        assert_eq!(backend.stats().await?, CacheStats::default());
+
+        backend
+            .store_references(&[(id1.clone(), vec![id2.clone()])])
+            .await?;
+        backend.mark_as_fetching(&id1).await?;
+
+        let stale_timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+            - 61;
+
+        sqlx::query("INSERT INTO pending_fetches (lens_id, started_at) VALUES ($1, $2)")
+            .bind(id2.as_ref().to_string())
+            .bind(stale_timestamp)
+            .execute(&backend.pool)
+            .await?;
+
+        let stats = backend.stats().await?;
+        assert_eq!(stats.references, 1);
+        assert_eq!(stats.cited_by_edges, 1);
+        assert_eq!(stats.pending_fetches, 2);
+        assert_eq!(stats.stale_pending_fetches, 1);
+
+        // Only the stale mark is removed
+        assert_eq!(backend.clear_stale_pending_fetches().await?, 1);
+        assert!(backend.is_being_fetched(&id1).await?);
+        assert_eq!(backend.stats().await?.pending_fetches, 1);
+
+        backend.vacuum().await?;
+        assert_eq!(backend.stats().await?.references, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pubmed_links_and_articles() -> Result<(), LensError> {
+        let backend = create_test_backend().await?;
+
+        backend
+            .store_pubmed_links(
+                PubmedLink::References,
+                &[(31000001, vec![31000003, 31000010]), (31000003, vec![])],
+            )
+            .await?;
+        backend
+            .store_pubmed_links(PubmedLink::Citations, &[(31000001, vec![31000020])])
+            .await?;
+
+        let references = backend
+            .get_pubmed_links(PubmedLink::References, &[31000001, 31000003, 31000099])
+            .await?;
+        assert_eq!(references.len(), 2);
+        assert_eq!(references[&31000001], [31000003, 31000010]);
+        assert_eq!(references[&31000003], Vec::<u64>::new());
+
+        // Cited-in lists are replaced, and expire like citations
+        backend
+            .store_pubmed_links(
+                PubmedLink::Citations,
+                &[(31000001, vec![31000020, 31000021])],
+            )
+            .await?;
+        let citations = backend
+            .get_pubmed_links(PubmedLink::Citations, &[31000001])
+            .await?;
+        assert_eq!(citations[&31000001], [31000020, 31000021]);
+
+        sqlx::query("UPDATE pubmed_links SET fetched_at = $1")
+            .bind(0i64)
+            .execute(&backend.pool)
+            .await?;
+        assert!(
+            backend
+                .get_pubmed_links(PubmedLink::Citations, &[31000001])
+                .await?
+                .is_empty()
+        );
+        assert_eq!(
+            backend
+                .get_pubmed_links(PubmedLink::References, &[31000001])
+                .await?
+                .len(),
+            1
+        );
+
+        let article = PubmedArticle {
+            pmid: "31000001".to_string(),
+            title: Some("A title".to_string()),
+            mesh_terms: vec!["Humans".to_string()],
+            ..Default::default()
+        };
+        let invalid = PubmedArticle {
+            pmid: "not a pmid".to_string(),
+            ..Default::default()
+        };
+        backend
+            .store_pubmed_articles(&[article.clone(), invalid])
+            .await?;
+        assert_eq!(
+            backend.get_pubmed_articles(&[31000001, 31000002]).await?,
+            [article]
+        );
+
+        backend
+            .store_pubmed_search("covid triage", 2, &[31000001, 31000003])
+            .await?;
+        backend
+            .store_pubmed_search("rare query", 20, &[31000002])
+            .await?;
+        assert_eq!(
+            backend.get_pubmed_search("covid triage", 1).await?,
+            Some(vec![31000001])
+        );
+        assert_eq!(backend.get_pubmed_search("covid triage", 5).await?, None);
+        assert_eq!(
+            backend.get_pubmed_search("rare query", 100).await?,
+            Some(vec![31000002]),
+            "Searches that found fewer PMIDs than asked are complete"
+        );
+        sqlx::query("UPDATE pubmed_searches SET fetched_at = $1")
+            .bind(0i64)
+            .execute(&backend.pool)
+            .await?;
+        assert_eq!(backend.get_pubmed_search("rare query", 1).await?, None);
+
+        let stats = backend.stats().await?;
+        assert_eq!(stats.pubmed_links, 3);
+        assert_eq!(stats.pubmed_articles, 1);
+        assert_eq!(stats.pubmed_searches, 2);
+
+        backend.clear().await?;
+        assert_eq!(backend.stats().await?, CacheStats::default());
+
+        Ok(())
+    }
 }
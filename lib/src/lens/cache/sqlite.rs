@@ -1,46 +1,136 @@
 //! SQLite backend implementation for the Lens cache
 
-use crate::lens::article::{ArticleData, ArticleWithData};
+use crate::lens::article::{ARTICLE_DATA_VERSION, ArticleData, ArticleWithData};
 use crate::lens::error::LensError;
 use crate::lens::lensid::LensId;
+use crate::pubmed::{Article as PubmedArticle, PubmedLink};
 use async_trait::async_trait;
 use sqlx::SqlitePool;
 use std::collections::HashMap;
 
-use super::CacheBackend;
+use super::{CANONICAL_ID_MAPPING_KEYS, CacheBackend, CacheStats, rekey_id_mappings};
 
 #[derive(sqlx::FromRow)]
 struct ReferencesRow {
-    pub lens_id: String,
-    pub references_json: String,
+    pub lens_id: i64,
+    pub reference_ids: Vec<u8>,
 }
 
 impl ReferencesRow {
-    fn extract(self) -> Result<(LensId, Vec<LensId>), LensError> {
-        let lens_id = LensId::try_from(self.lens_id.as_str())?;
-        let relationships: Vec<LensId> = serde_json::from_str(&self.references_json)
-            .ok()
-            .unwrap_or_default();
-
-        Ok((lens_id, relationships))
+    fn extract(self) -> (LensId, Vec<LensId>) {
+        (
+            LensId::from(self.lens_id as u64),
+            unpack_lens_ids(&self.reference_ids),
+        )
     }
 }
 
 #[derive(sqlx::FromRow)]
 struct CitationsRow {
-    pub lens_id: String,
-    pub citations_json: String,
+    pub lens_id: i64,
+    pub citation_ids: Vec<u8>,
 }
 
 impl CitationsRow {
-    fn extract(self) -> Result<(LensId, Vec<LensId>), LensError> {
+    fn extract(self) -> (LensId, Vec<LensId>) {
+        (
+            LensId::from(self.lens_id as u64),
+            unpack_lens_ids(&self.citation_ids),
+        )
+    }
+}
+
+/// Edge row in the legacy JSON-text layout, only read while migrating old caches
+#[derive(sqlx::FromRow)]
+struct LegacyEdgesRow {
+    pub lens_id: String,
+    pub edges_json: String,
+    pub fetched_at: i64,
+}
+
+impl LegacyEdgesRow {
+    fn extract(self) -> Result<(i64, Vec<u8>, i64), LensError> {
         let lens_id = LensId::try_from(self.lens_id.as_str())?;
-        let citations: Vec<LensId> = serde_json::from_str(&self.citations_json)
-            .ok()
-            .unwrap_or_default();
+        let edges: Vec<LensId> = serde_json::from_str(&self.edges_json)?;
+
+        Ok((
+            lens_id.as_u64() as i64,
+            pack_lens_ids(&edges),
+            self.fetched_at,
+        ))
+    }
+
+    /// Converts a batch of rows, skipping the ones that do not parse so that they are
+    /// fetched again instead of failing the migration
+    fn extract_all(rows: Vec<Self>, skipped: &mut usize) -> Vec<(i64, Vec<u8>, i64)> {
+        rows.into_iter()
+            .filter_map(|row| {
+                let lens_id = row.lens_id.clone();
+                row.extract()
+                    .inspect_err(|e| {
+                        log::debug!("Skipping cached edges of {lens_id}: {e}");
+                        *skipped += 1;
+                    })
+                    .ok()
+            })
+            .collect()
+    }
+}
 
-        Ok((lens_id, citations))
+/// LensId edge tables, keyed by the integer LensId (optimized with NoHasher)
+const CREATE_REFERENCES_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS article_references (
+        lens_id INTEGER PRIMARY KEY,
+        reference_ids BLOB NOT NULL,
+        fetched_at INTEGER NOT NULL DEFAULT (unixepoch())
+    )
+"#;
+
+const CREATE_CITATIONS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS article_citations (
+        lens_id INTEGER PRIMARY KEY,
+        citation_ids BLOB NOT NULL,
+        fetched_at INTEGER NOT NULL DEFAULT (unixepoch())
+    )
+"#;
+
+/// Packs LensIds into a blob of little-endian `u64`s (8 bytes per ID)
+fn pack_lens_ids(ids: &[LensId]) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(ids.len() * 8);
+    for id in ids {
+        blob.extend_from_slice(&id.as_u64().to_le_bytes());
     }
+    blob
+}
+
+/// Unpacks a blob written by `pack_lens_ids`
+///
+/// Trailing bytes that do not form a whole ID are ignored, mirroring the lenient
+/// handling of malformed JSON in the legacy layout.
+fn unpack_lens_ids(blob: &[u8]) -> Vec<LensId> {
+    blob.chunks_exact(8)
+        .map(|chunk| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(chunk);
+            LensId::from(u64::from_le_bytes(bytes))
+        })
+        .collect()
+}
+
+/// Packs PMIDs into a blob of little-endian `u64`s, like `pack_lens_ids`
+fn pack_pmids(pmids: &[u64]) -> Vec<u8> {
+    pmids.iter().flat_map(|pmid| pmid.to_le_bytes()).collect()
+}
+
+/// Unpacks a blob written by `pack_pmids`
+fn unpack_pmids(blob: &[u8]) -> Vec<u64> {
+    blob.chunks_exact(8)
+        .map(|chunk| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(chunk);
+            u64::from_le_bytes(bytes)
+        })
+        .collect()
 }
 
 #[derive(sqlx::FromRow)]
@@ -63,9 +153,16 @@ impl ArticleRow {
 
 /// SQLite-based cache backend
 ///
-/// Uses two tables:
+/// Uses three edge tables:
 /// - `article_references`: stores immutable outgoing edges
 /// - `article_citations`: stores mutable incoming edges with timestamps
+/// - `article_cited_by`: one `(cited_id, citing_id)` row per stored reference
+///
+/// PubMed `elink` results and records are kept apart, in `pubmed_links` and
+/// `pubmed_articles`, keyed by PMID, and keyword searches in `pubmed_searches`.
+///
+/// The first two are keyed by the integer form of the LensId and store their
+/// edge lists as packed little-endian `u64` blobs, so reading a row needs no parsing.
 ///
 /// Optimized for bulk operations with:
 /// - Chunked multi-row inserts (respects SQLite parameter limits)
@@ -74,6 +171,7 @@ impl ArticleRow {
 /// - JSON-based queries to avoid parameter count limits
 pub struct SqliteBackend {
     pool: SqlitePool,
+    cited_by_fallback: bool,
 }
 
 #[async_trait]
@@ -86,11 +184,11 @@ impl CacheBackend for SqliteBackend {
             return Ok(HashMap::new());
         }
 
-        let ids_json = Self::ids_to_json(ids)?;
+        let ids_json = Self::ids_to_int_json(ids)?;
 
         let rows: Vec<ReferencesRow> = sqlx::query_as(
             r#"
-                SELECT lens_id, references_json
+                SELECT lens_id, reference_ids
                 FROM article_references
                 WHERE lens_id IN (SELECT value FROM json_each(?))
             "#,
@@ -99,7 +197,7 @@ impl CacheBackend for SqliteBackend {
         .fetch_all(&self.pool)
         .await?;
 
-        rows.into_iter().map(|x| x.extract()).collect()
+        Ok(rows.into_iter().map(ReferencesRow::extract).collect())
     }
 
     async fn store_references(&self, batch: &[(LensId, Vec<LensId>)]) -> Result<(), LensError> {
@@ -118,32 +216,18 @@ impl CacheBackend for SqliteBackend {
             .as_secs() as i64;
 
         for chunk in batch.chunks(CHUNK_SIZE) {
-            // Pre-serialize all JSON (handles errors before building query)
-            let rows: Vec<(String, String, i64)> = chunk
+            let rows: Vec<(i64, Vec<u8>, i64)> = chunk
                 .iter()
-                .map(|(id, refs)| {
-                    let id_str = id.as_ref().to_string();
-                    let refs_json = serde_json::to_string(refs)?;
-
-                    Ok((id_str, refs_json, rough_timestamp))
-                })
-                .collect::<Result<Vec<_>, LensError>>()?;
-
-            // Build multi-row INSERT
-            let mut builder = sqlx::QueryBuilder::new(
-                "INSERT INTO article_references (lens_id, references_json, fetched_at) ",
-            );
-
-            builder.push_values(rows, |mut b, (id_str, refs_json, timestamp)| {
-                b.push_bind(id_str)
-                    .push_bind(refs_json)
-                    .push_bind(timestamp);
-            });
+                .map(|(id, refs)| (id.as_u64() as i64, pack_lens_ids(refs), rough_timestamp))
+                .collect();
 
-            // References are immutable, so just ignore conflicts
-            builder.push(" ON CONFLICT (lens_id) DO NOTHING");
+            Self::insert_references_rows(&mut tx, rows).await?;
+        }
 
-            builder.build().execute(&mut *tx).await?;
+        // Keep the cited-by index in sync (same transaction)
+        for (id, refs) in batch {
+            Self::insert_cited_by(&mut tx, id.as_u64() as i64, &Self::ids_to_int_json(refs)?)
+                .await?;
         }
 
         // Commit once at the end (single fsync)
@@ -167,11 +251,11 @@ impl CacheBackend for SqliteBackend {
             .as_secs() as i64
             - (14 * 24 * 60 * 60); // 14 days in seconds
 
-        let ids_json = Self::ids_to_json(ids)?;
+        let ids_json = Self::ids_to_int_json(ids)?;
 
         let rows: Vec<CitationsRow> = sqlx::query_as(
             r#"
-                SELECT lens_id, citations_json
+                SELECT lens_id, citation_ids
                 FROM article_citations
                 WHERE lens_id IN (SELECT value FROM json_each(?))
                 AND fetched_at >= ?
@@ -182,7 +266,7 @@ impl CacheBackend for SqliteBackend {
         .fetch_all(&self.pool)
         .await?;
 
-        rows.into_iter().map(|x| x.extract()).collect()
+        Ok(rows.into_iter().map(CitationsRow::extract).collect())
     }
 
     async fn store_citations(&self, batch: &[(LensId, Vec<LensId>)]) -> Result<(), LensError> {
@@ -201,34 +285,18 @@ impl CacheBackend for SqliteBackend {
             .as_secs() as i64;
 
         for chunk in batch.chunks(CHUNK_SIZE) {
-            // Pre-serialize all JSON (handles errors before building query)
-            let rows: Vec<(String, String, i64)> = chunk
+            let rows: Vec<(i64, Vec<u8>, i64)> = chunk
                 .iter()
                 .map(|(id, citations)| {
-                    let id_str = id.as_ref().to_string();
-                    let citations_json = serde_json::to_string(citations)?;
-
-                    Ok((id_str, citations_json, rough_timestamp))
+                    (
+                        id.as_u64() as i64,
+                        pack_lens_ids(citations),
+                        rough_timestamp,
+                    )
                 })
-                .collect::<Result<Vec<_>, LensError>>()?;
-
-            // Build multi-row INSERT
-            let mut builder = sqlx::QueryBuilder::new(
-                "INSERT INTO article_citations (lens_id, citations_json, fetched_at) ",
-            );
-
-            builder.push_values(rows, |mut b, (id_str, citations_json, timestamp)| {
-                b.push_bind(id_str)
-                    .push_bind(citations_json)
-                    .push_bind(timestamp);
-            });
-
-            // For citations, we want to update with fresh data on conflict
-            builder.push(
-                " ON CONFLICT (lens_id) DO UPDATE SET citations_json = excluded.citations_json, fetched_at = excluded.fetched_at",
-            );
+                .collect();
 
-            builder.build().execute(&mut *tx).await?;
+            Self::insert_citations_rows(&mut tx, rows).await?;
         }
 
         // Commit once at the end (single fsync)
@@ -237,6 +305,43 @@ impl CacheBackend for SqliteBackend {
         Ok(())
     }
 
+    async fn get_cited_by(
+        &self,
+        ids: &[LensId],
+    ) -> Result<HashMap<LensId, Vec<LensId>>, LensError> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let ids_json = Self::ids_to_int_json(ids)?;
+
+        let rows: Vec<(i64, i64)> = sqlx::query_as(
+            r#"
+                SELECT cited_id, citing_id
+                FROM article_cited_by
+                WHERE cited_id IN (SELECT value FROM json_each(?))
+                ORDER BY cited_id, citing_id
+            "#,
+        )
+        .bind(&ids_json)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut cited_by: HashMap<LensId, Vec<LensId>> = HashMap::new();
+        for (cited_id, citing_id) in rows {
+            cited_by
+                .entry(LensId::from(cited_id as u64))
+                .or_default()
+                .push(LensId::from(citing_id as u64));
+        }
+
+        Ok(cited_by)
+    }
+
+    fn cited_by_fallback(&self) -> bool {
+        self.cited_by_fallback
+    }
+
     async fn get_article_data(&self, ids: &[LensId]) -> Result<Vec<ArticleWithData>, LensError> {
         if ids.is_empty() {
             return Ok(Vec::new());
@@ -248,10 +353,11 @@ impl CacheBackend for SqliteBackend {
             r#"
                 SELECT lens_id, article_json
                 FROM article_data
-                WHERE lens_id IN (SELECT value FROM json_each(?))
+                WHERE lens_id IN (SELECT value FROM json_each(?)) AND data_version >= ?
             "#,
         )
         .bind(&ids_json)
+        .bind(ARTICLE_DATA_VERSION)
         .fetch_all(&self.pool)
         .await?;
 
@@ -286,17 +392,20 @@ impl CacheBackend for SqliteBackend {
 
             // Build multi-row INSERT
             let mut builder = sqlx::QueryBuilder::new(
-                "INSERT INTO article_data (lens_id, article_json, fetched_at) ",
+                "INSERT INTO article_data (lens_id, article_json, fetched_at, data_version) ",
             );
 
             builder.push_values(rows, |mut b, (id_str, refs_json, timestamp)| {
                 b.push_bind(id_str)
                     .push_bind(refs_json)
-                    .push_bind(timestamp);
+                    .push_bind(timestamp)
+                    .push(ARTICLE_DATA_VERSION);
             });
 
-            // References are immutable, so just ignore conflicts
-            builder.push(" ON CONFLICT (lens_id) DO NOTHING");
+            // Article data is immutable, only entries of an older version are replaced
+            builder.push(
+                " ON CONFLICT (lens_id) DO UPDATE SET article_json = excluded.article_json, fetched_at = excluded.fetched_at, data_version = excluded.data_version WHERE article_data.data_version < excluded.data_version",
+            );
 
             builder.build().execute(&mut *tx).await?;
         }
@@ -379,6 +488,244 @@ impl CacheBackend for SqliteBackend {
         Ok(())
     }
 
+    async fn list_id_mappings(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<(String, LensId)>, LensError> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            r#"
+                SELECT string_id, lens_id
+                FROM id_mappings
+                WHERE ? IS NULL OR string_id > ?
+                ORDER BY string_id
+                LIMIT ?
+            "#,
+        )
+        .bind(after)
+        .bind(after)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(string_id, lens_id_str)| {
+                let lens_id = LensId::try_from(lens_id_str.as_str())?;
+                Ok((string_id, lens_id))
+            })
+            .collect()
+    }
+    async fn get_pubmed_links(
+        &self,
+        link: PubmedLink,
+        pmids: &[u64],
+    ) -> Result<HashMap<u64, Vec<u64>>, LensError> {
+        if pmids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        // Cited-in lists follow the citation TTL, reference lists never expire
+        let oldest_allowed = match link {
+            PubmedLink::References => i64::MIN,
+            PubmedLink::Citations => now - (14 * 24 * 60 * 60), // 14 days in seconds
+        };
+
+        let pmids_json = serde_json::to_string(pmids)?;
+
+        let rows: Vec<(i64, Vec<u8>)> = sqlx::query_as(
+            r#"
+                SELECT pmid, linked_pmids
+                FROM pubmed_links
+                WHERE link_name = ?
+                AND pmid IN (SELECT value FROM json_each(?))
+                AND fetched_at >= ?
+            "#,
+        )
+        .bind(link.link_name())
+        .bind(&pmids_json)
+        .bind(oldest_allowed)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(pmid, linked_pmids)| (pmid as u64, unpack_pmids(&linked_pmids)))
+            .collect())
+    }
+
+    async fn store_pubmed_links(
+        &self,
+        link: PubmedLink,
+        batch: &[(u64, Vec<u64>)],
+    ) -> Result<(), LensError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        const CHUNK_SIZE: usize = 333;
+
+        let mut tx = self.pool.begin().await?;
+
+        let rough_timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        for chunk in batch.chunks(CHUNK_SIZE) {
+            let mut builder = sqlx::QueryBuilder::new(
+                "INSERT INTO pubmed_links (pmid, link_name, linked_pmids, fetched_at) ",
+            );
+
+            builder.push_values(chunk, |mut b, (pmid, linked_pmids)| {
+                b.push_bind(*pmid as i64)
+                    .push_bind(link.link_name())
+                    .push_bind(pack_pmids(linked_pmids))
+                    .push_bind(rough_timestamp);
+            });
+
+            builder.push(
+                " ON CONFLICT (pmid, link_name) DO UPDATE SET linked_pmids = excluded.linked_pmids, fetched_at = excluded.fetched_at",
+            );
+
+            builder.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_pubmed_articles(&self, pmids: &[u64]) -> Result<Vec<PubmedArticle>, LensError> {
+        if pmids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pmids_json = serde_json::to_string(pmids)?;
+
+        let rows: Vec<(String,)> = sqlx::query_as(
+            r#"
+                SELECT article_json
+                FROM pubmed_articles
+                WHERE pmid IN (SELECT value FROM json_each(?))
+            "#,
+        )
+        .bind(&pmids_json)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(article_json,)| Ok(serde_json::from_str(&article_json)?))
+            .collect()
+    }
+
+    async fn store_pubmed_articles(&self, batch: &[PubmedArticle]) -> Result<(), LensError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        const CHUNK_SIZE: usize = 333;
+
+        let mut tx = self.pool.begin().await?;
+
+        let rough_timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        for chunk in batch.chunks(CHUNK_SIZE) {
+            let rows: Vec<(i64, String, i64)> = chunk
+                .iter()
+                .filter_map(|article| Some((article.pmid.parse::<i64>().ok()?, article)))
+                .map(|(pmid, article)| Ok((pmid, serde_json::to_string(article)?, rough_timestamp)))
+                .collect::<Result<Vec<_>, LensError>>()?;
+            if rows.is_empty() {
+                continue;
+            }
+
+            let mut builder = sqlx::QueryBuilder::new(
+                "INSERT INTO pubmed_articles (pmid, article_json, fetched_at) ",
+            );
+
+            builder.push_values(rows, |mut b, (pmid, article_json, timestamp)| {
+                b.push_bind(pmid)
+                    .push_bind(article_json)
+                    .push_bind(timestamp);
+            });
+
+            builder.push(" ON CONFLICT (pmid) DO NOTHING");
+
+            builder.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_pubmed_search(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Option<Vec<u64>>, LensError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let row: Option<(i64, Vec<u8>)> = sqlx::query_as(
+            r#"
+                SELECT max_results, pmids
+                FROM pubmed_searches
+                WHERE query = ?
+                AND fetched_at >= ?
+            "#,
+        )
+        .bind(query)
+        .bind(now - (24 * 60 * 60)) // 1 day in seconds
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.and_then(|(cached_max_results, pmids)| {
+            let mut pmids = unpack_pmids(&pmids);
+            let complete = cached_max_results as usize >= max_results
+                || (pmids.len() as i64) < cached_max_results;
+            pmids.truncate(max_results);
+            complete.then_some(pmids)
+        }))
+    }
+
+    async fn store_pubmed_search(
+        &self,
+        query: &str,
+        max_results: usize,
+        pmids: &[u64],
+    ) -> Result<(), LensError> {
+        let rough_timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        sqlx::query(
+            r#"
+                INSERT INTO pubmed_searches (query, max_results, pmids, fetched_at)
+                VALUES (?, ?, ?, ?)
+                ON CONFLICT (query) DO UPDATE SET max_results = excluded.max_results, pmids = excluded.pmids, fetched_at = excluded.fetched_at
+            "#,
+        )
+        .bind(query)
+        .bind(max_results as i64)
+        .bind(pack_pmids(pmids))
+        .bind(rough_timestamp)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     async fn mark_as_fetching(&self, id: &LensId) -> Result<bool, LensError> {
         let id_str = id.as_ref().to_string();
         let now = std::time::SystemTime::now()
@@ -539,6 +886,10 @@ impl CacheBackend for SqliteBackend {
             .execute(&mut *tx)
             .await?;
 
+        sqlx::query("DELETE FROM article_cited_by")
+            .execute(&mut *tx)
+            .await?;
+
         sqlx::query("DELETE FROM article_data")
             .execute(&mut *tx)
             .await?;
@@ -551,11 +902,126 @@ impl CacheBackend for SqliteBackend {
             .execute(&mut *tx)
             .await?;
 
+        sqlx::query("DELETE FROM pubmed_links")
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM pubmed_articles")
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM pubmed_searches")
+            .execute(&mut *tx)
+            .await?;
+
         tx.commit().await?;
 
         Ok(())
     }
-}
+
+    async fn purge(&self, ids: &[LensId]) -> Result<u64, LensError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let int_ids_json = Self::ids_to_int_json(ids)?;
+        let ids_json = Self::ids_to_json(ids)?;
+
+        let mut tx = self.pool.begin().await?;
+        let mut deleted = 0;
+
+        for query in [
+            "DELETE FROM article_references WHERE lens_id IN (SELECT value FROM json_each(?))",
+            "DELETE FROM article_citations WHERE lens_id IN (SELECT value FROM json_each(?))",
+            "DELETE FROM article_cited_by WHERE citing_id IN (SELECT value FROM json_each(?))",
+        ] {
+            deleted += sqlx::query(query)
+                .bind(&int_ids_json)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        for query in [
+            "DELETE FROM article_data WHERE lens_id IN (SELECT value FROM json_each(?))",
+            "DELETE FROM id_mappings WHERE lens_id IN (SELECT value FROM json_each(?))",
+            "DELETE FROM pending_fetches WHERE lens_id IN (SELECT value FROM json_each(?))",
+        ] {
+            deleted += sqlx::query(query)
+                .bind(&ids_json)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+
+        Ok(deleted)
+    }
+
+    async fn stats(&self) -> Result<CacheStats, LensError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let row: (i64, i64, i64, i64, i64, i64, i64, i64, i64, i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM article_references),
+                (SELECT COUNT(*) FROM article_citations),
+                (SELECT COUNT(*) FROM article_citations WHERE fetched_at < ?),
+                (SELECT COUNT(*) FROM article_cited_by),
+                (SELECT COUNT(*) FROM article_data),
+                (SELECT COUNT(*) FROM id_mappings),
+                (SELECT COUNT(*) FROM pending_fetches),
+                (SELECT COUNT(*) FROM pending_fetches WHERE started_at < ?),
+                (SELECT COUNT(*) FROM pubmed_links),
+                (SELECT COUNT(*) FROM pubmed_articles),
+                (SELECT COUNT(*) FROM pubmed_searches)
+            "#,
+        )
+        .bind(now - (14 * 24 * 60 * 60)) // 14 days in seconds
+        .bind(now - 60)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(CacheStats {
+            references: row.0,
+            citations: row.1,
+            stale_citations: row.2,
+            cited_by_edges: row.3,
+            article_data: row.4,
+            id_mappings: row.5,
+            pending_fetches: row.6,
+            stale_pending_fetches: row.7,
+            pubmed_links: row.8,
+            pubmed_articles: row.9,
+            pubmed_searches: row.10,
+        })
+    }
+
+    async fn clear_stale_pending_fetches(&self) -> Result<u64, LensError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let result = sqlx::query("DELETE FROM pending_fetches WHERE started_at < ?")
+            .bind(now - 60)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn vacuum(&self) -> Result<(), LensError> {
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+        sqlx::query("ANALYZE").execute(&self.pool).await?;
+
+        Ok(())
+    }
+}
 
 impl SqliteBackend {
     /// Create a new SQLite backend from a connection URL
@@ -572,54 +1038,91 @@ impl SqliteBackend {
         Self::run_migrations(&pool).await?;
         Self::optimize_sqlite(&pool).await?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            cited_by_fallback: false,
+        })
+    }
+
+    /// Let citation misses be answered from the cited-by index (off by default)
+    ///
+    /// See `CacheBackend::get_citations_or_cited_by`. Citations built this way are
+    /// marked incomplete, as they only contain citing articles already in the cache.
+    pub fn with_cited_by_fallback(mut self, enabled: bool) -> Self {
+        self.cited_by_fallback = enabled;
+        self
     }
 
     /// Run database migrations (creates tables if they don't exist)
     async fn run_migrations(pool: &SqlitePool) -> Result<(), LensError> {
-        // LensId tables (optimized with NoHasher)
+        // Re-encode edge tables still in the JSON-text layout
+        let legacy_references = Self::migrate_legacy_references(pool).await?;
+        Self::migrate_legacy_citations(pool).await?;
+
+        sqlx::query(CREATE_REFERENCES_TABLE).execute(pool).await?;
+
+        sqlx::query(CREATE_CITATIONS_TABLE).execute(pool).await?;
+
+        // Created after the legacy tables are dropped, as they may still own this index name
         sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS article_references (
-                lens_id TEXT PRIMARY KEY,
-                references_json TEXT NOT NULL,
-                fetched_at INTEGER NOT NULL DEFAULT (unixepoch())
-            ) WITHOUT ROWID
-            "#,
+            "CREATE INDEX IF NOT EXISTS idx_citations_fetched ON article_citations(fetched_at)",
         )
         .execute(pool)
         .await?;
 
+        // Inverted references, filled from existing references when first created
+        let (cited_by_exists,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'article_cited_by'",
+        )
+        .fetch_one(pool)
+        .await?;
+
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS article_citations (
-                lens_id TEXT PRIMARY KEY,
-                citations_json TEXT NOT NULL,
-                fetched_at INTEGER NOT NULL DEFAULT (unixepoch())
+            CREATE TABLE IF NOT EXISTS article_cited_by (
+                cited_id INTEGER NOT NULL,
+                citing_id INTEGER NOT NULL,
+                PRIMARY KEY (cited_id, citing_id)
             ) WITHOUT ROWID
             "#,
         )
         .execute(pool)
         .await?;
 
-        sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_citations_fetched ON article_citations(fetched_at)",
-        )
-        .execute(pool)
-        .await?;
+        // Also after migrating legacy references, which may be resumed into a cache that
+        // already has the index
+        if cited_by_exists == 0 || legacy_references {
+            Self::backfill_cited_by(pool).await?;
+        }
 
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS article_data (
                 lens_id TEXT PRIMARY KEY,
                 article_json TEXT NOT NULL,
-                fetched_at INTEGER NOT NULL DEFAULT (unixepoch())
+                fetched_at INTEGER NOT NULL DEFAULT (unixepoch()),
+                data_version INTEGER NOT NULL DEFAULT 0
             ) WITHOUT ROWID
             "#,
         )
         .execute(pool)
         .await?;
 
+        // Entries cached before it was added are version 0, fetched again when read
+        let (data_version_columns,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM pragma_table_info('article_data') WHERE name = 'data_version'",
+        )
+        .fetch_one(pool)
+        .await?;
+
+        if data_version_columns == 0 {
+            sqlx::query(
+                "ALTER TABLE article_data ADD COLUMN data_version INTEGER NOT NULL DEFAULT 0",
+            )
+            .execute(pool)
+            .await?;
+        }
+
         // ID mappings table (PMID/DOI/etc → LensId)
         sqlx::query(
             r#"
@@ -633,6 +1136,20 @@ impl SqliteBackend {
         .execute(pool)
         .await?;
 
+        // One-off data migrations already applied to this cache
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS cache_migrations (
+                name TEXT PRIMARY KEY,
+                applied_at INTEGER NOT NULL DEFAULT (unixepoch())
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        Self::migrate_id_mapping_keys(pool).await?;
+
         // Pending fetches table (for request deduplication)
         sqlx::query(
             r#"
@@ -645,6 +1162,46 @@ impl SqliteBackend {
         .execute(pool)
         .await?;
 
+        // PubMed tables, keyed by PMID; link lists are packed like the LensId edge lists
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS pubmed_links (
+                pmid INTEGER NOT NULL,
+                link_name TEXT NOT NULL,
+                linked_pmids BLOB NOT NULL,
+                fetched_at INTEGER NOT NULL DEFAULT (unixepoch()),
+                PRIMARY KEY (pmid, link_name)
+            ) WITHOUT ROWID
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS pubmed_articles (
+                pmid INTEGER PRIMARY KEY,
+                article_json TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL DEFAULT (unixepoch())
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS pubmed_searches (
+                query TEXT PRIMARY KEY,
+                max_results INTEGER NOT NULL,
+                pmids BLOB NOT NULL,
+                fetched_at INTEGER NOT NULL DEFAULT (unixepoch())
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
         Ok(())
     }
 
@@ -680,12 +1237,364 @@ impl SqliteBackend {
         let json = serde_json::to_string(&ids.iter().map(|id| id.as_ref()).collect::<Vec<_>>())?;
         Ok(json)
     }
+
+    /// Convert a slice of LensIds to a JSON array of their integer form
+    ///
+    /// Used for the edge tables, which are keyed by `LensId::as_u64()`
+    fn ids_to_int_json(ids: &[LensId]) -> Result<String, LensError> {
+        let json = serde_json::to_string(&ids.iter().map(|id| id.as_u64()).collect::<Vec<_>>())?;
+        Ok(json)
+    }
+
+    /// Insert pre-packed rows into `article_references` (conflicts are ignored)
+    async fn insert_references_rows(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        rows: Vec<(i64, Vec<u8>, i64)>,
+    ) -> Result<(), LensError> {
+        let mut builder = sqlx::QueryBuilder::new(
+            "INSERT INTO article_references (lens_id, reference_ids, fetched_at) ",
+        );
+
+        builder.push_values(rows, |mut b, (lens_id, reference_ids, timestamp)| {
+            b.push_bind(lens_id)
+                .push_bind(reference_ids)
+                .push_bind(timestamp);
+        });
+
+        // References are immutable, so just ignore conflicts
+        builder.push(" ON CONFLICT (lens_id) DO NOTHING");
+
+        builder.build().execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    /// Insert pre-packed rows into `article_citations` (conflicts are updated)
+    async fn insert_citations_rows(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        rows: Vec<(i64, Vec<u8>, i64)>,
+    ) -> Result<(), LensError> {
+        let mut builder = sqlx::QueryBuilder::new(
+            "INSERT INTO article_citations (lens_id, citation_ids, fetched_at) ",
+        );
+
+        builder.push_values(rows, |mut b, (lens_id, citation_ids, timestamp)| {
+            b.push_bind(lens_id)
+                .push_bind(citation_ids)
+                .push_bind(timestamp);
+        });
+
+        // For citations, we want to update with fresh data on conflict, but never with
+        // older data (a resumed migration copies lists older than the ones already there)
+        builder.push(
+            " ON CONFLICT (lens_id) DO UPDATE SET citation_ids = excluded.citation_ids, fetched_at = excluded.fetched_at WHERE article_citations.fetched_at <= excluded.fetched_at",
+        );
+
+        builder.build().execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    /// Add one cited-by row per reference of `citing_id` (`cited_ids_json` is a JSON array)
+    async fn insert_cited_by(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        citing_id: i64,
+        cited_ids_json: &str,
+    ) -> Result<(), LensError> {
+        // The WHERE clause is required by SQLite to parse ON CONFLICT after a SELECT
+        sqlx::query(
+            r#"
+            INSERT INTO article_cited_by (cited_id, citing_id)
+            SELECT value, ? FROM json_each(?) WHERE true
+            ON CONFLICT (cited_id, citing_id) DO NOTHING
+            "#,
+        )
+        .bind(citing_id)
+        .bind(cited_ids_json)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Rewrite the `id_mappings` keys written before they were canonical, once
+    ///
+    /// See `rekey_id_mappings`. Runs in a single transaction, recorded in
+    /// `cache_migrations` when done.
+    async fn migrate_id_mapping_keys(pool: &SqlitePool) -> Result<(), LensError> {
+        let mut tx = pool.begin().await?;
+
+        let (applied,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM cache_migrations WHERE name = ?")
+                .bind(CANONICAL_ID_MAPPING_KEYS)
+                .fetch_one(&mut *tx)
+                .await?;
+        if applied > 0 {
+            return Ok(());
+        }
+
+        let mut last_id = String::new();
+        let mut stale = 0usize;
+
+        loop {
+            let rows: Vec<(String, String, i64)> = sqlx::query_as(
+                r#"
+                SELECT string_id, lens_id, fetched_at
+                FROM id_mappings
+                WHERE string_id > ?
+                ORDER BY string_id
+                LIMIT 333
+                "#,
+            )
+            .bind(&last_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let Some(last_row) = rows.last() else {
+                break;
+            };
+            last_id = last_row.0.clone();
+
+            let (stale_keys, rekeyed) = rekey_id_mappings(rows);
+            if stale_keys.is_empty() {
+                continue;
+            }
+            stale += stale_keys.len();
+
+            sqlx::query(
+                "DELETE FROM id_mappings WHERE string_id IN (SELECT value FROM json_each(?))",
+            )
+            .bind(serde_json::to_string(&stale_keys)?)
+            .execute(&mut *tx)
+            .await?;
+
+            if !rekeyed.is_empty() {
+                let mut builder = sqlx::QueryBuilder::new(
+                    "INSERT INTO id_mappings (string_id, lens_id, fetched_at) ",
+                );
+                builder.push_values(rekeyed, |mut b, (string_id, lens_id, fetched_at)| {
+                    b.push_bind(string_id)
+                        .push_bind(lens_id)
+                        .push_bind(fetched_at);
+                });
+                builder.push(" ON CONFLICT (string_id) DO NOTHING");
+                builder.build().execute(&mut *tx).await?;
+            }
+        }
+
+        sqlx::query("INSERT INTO cache_migrations (name) VALUES (?)")
+            .bind(CANONICAL_ID_MAPPING_KEYS)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        if stale > 0 {
+            log::info!(
+                "Rekeyed {stale} cached ID mappings under their canonical key, dropping duplicates and invalid keys"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Fill `article_cited_by` from all the references already in the cache
+    async fn backfill_cited_by(pool: &SqlitePool) -> Result<(), LensError> {
+        let mut tx = pool.begin().await?;
+        let mut last_id = i64::MIN;
+
+        loop {
+            let rows: Vec<ReferencesRow> = sqlx::query_as(
+                r#"
+                SELECT lens_id, reference_ids
+                FROM article_references
+                WHERE lens_id > ?
+                ORDER BY lens_id
+                LIMIT 333
+                "#,
+            )
+            .bind(last_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let Some(last_row) = rows.last() else {
+                break;
+            };
+            last_id = last_row.lens_id;
+
+            for (id, refs) in rows.into_iter().map(ReferencesRow::extract) {
+                Self::insert_cited_by(&mut tx, id.as_u64() as i64, &Self::ids_to_int_json(&refs)?)
+                    .await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Rename an edge table to `*_legacy` if it still uses the JSON-text layout
+    ///
+    /// Returns true if there is a `*_legacy` table to migrate, renamed now or left behind
+    /// by a migration interrupted before this one was done in a single transaction.
+    async fn rename_legacy_edge_table(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        detect_query: &'static str,
+        rename_query: &'static str,
+        legacy_exists_query: &'static str,
+    ) -> Result<bool, LensError> {
+        let (legacy_columns,): (i64,) = sqlx::query_as(detect_query).fetch_one(&mut **tx).await?;
+
+        if legacy_columns > 0 {
+            sqlx::query(rename_query).execute(&mut **tx).await?;
+            return Ok(true);
+        }
+
+        let (legacy_tables,): (i64,) = sqlx::query_as(legacy_exists_query)
+            .fetch_one(&mut **tx)
+            .await?;
+
+        Ok(legacy_tables > 0)
+    }
+
+    /// Re-encode `article_references` from the JSON-text layout, if needed
+    ///
+    /// The rename, the new table and the copy share one transaction, so an interrupted
+    /// migration leaves the cache as it was. Returns true if rows were migrated.
+    async fn migrate_legacy_references(pool: &SqlitePool) -> Result<bool, LensError> {
+        let mut tx = pool.begin().await?;
+
+        let legacy = Self::rename_legacy_edge_table(
+            &mut tx,
+            "SELECT COUNT(*) FROM pragma_table_info('article_references') WHERE name = 'references_json'",
+            "ALTER TABLE article_references RENAME TO article_references_legacy",
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'article_references_legacy'",
+        )
+        .await?;
+        if !legacy {
+            return Ok(false);
+        }
+
+        sqlx::query(CREATE_REFERENCES_TABLE)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut last_id = String::new();
+        let mut migrated = 0usize;
+        let mut skipped = 0usize;
+
+        loop {
+            let rows: Vec<LegacyEdgesRow> = sqlx::query_as(
+                r#"
+                SELECT lens_id, references_json AS edges_json, fetched_at
+                FROM article_references_legacy
+                WHERE lens_id > ?
+                ORDER BY lens_id
+                LIMIT 333
+                "#,
+            )
+            .bind(&last_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let Some(last_row) = rows.last() else {
+                break;
+            };
+            last_id = last_row.lens_id.clone();
+
+            let rows = LegacyEdgesRow::extract_all(rows, &mut skipped);
+            migrated += rows.len();
+
+            if !rows.is_empty() {
+                Self::insert_references_rows(&mut tx, rows).await?;
+            }
+        }
+
+        sqlx::query("DROP TABLE article_references_legacy")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        log::info!("Migrated {migrated} cached reference lists to the packed layout");
+        if skipped > 0 {
+            log::warn!("Skipped {skipped} cached reference lists that could not be parsed");
+        }
+
+        Ok(true)
+    }
+
+    /// Re-encode `article_citations` from the JSON-text layout, if needed
+    ///
+    /// Same as `migrate_legacy_references`.
+    async fn migrate_legacy_citations(pool: &SqlitePool) -> Result<(), LensError> {
+        let mut tx = pool.begin().await?;
+
+        let legacy = Self::rename_legacy_edge_table(
+            &mut tx,
+            "SELECT COUNT(*) FROM pragma_table_info('article_citations') WHERE name = 'citations_json'",
+            "ALTER TABLE article_citations RENAME TO article_citations_legacy",
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'article_citations_legacy'",
+        )
+        .await?;
+        if !legacy {
+            return Ok(());
+        }
+
+        sqlx::query(CREATE_CITATIONS_TABLE)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut last_id = String::new();
+        let mut migrated = 0usize;
+        let mut skipped = 0usize;
+
+        loop {
+            let rows: Vec<LegacyEdgesRow> = sqlx::query_as(
+                r#"
+                SELECT lens_id, citations_json AS edges_json, fetched_at
+                FROM article_citations_legacy
+                WHERE lens_id > ?
+                ORDER BY lens_id
+                LIMIT 333
+                "#,
+            )
+            .bind(&last_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let Some(last_row) = rows.last() else {
+                break;
+            };
+            last_id = last_row.lens_id.clone();
+
+            let rows = LegacyEdgesRow::extract_all(rows, &mut skipped);
+            migrated += rows.len();
+
+            if !rows.is_empty() {
+                Self::insert_citations_rows(&mut tx, rows).await?;
+            }
+        }
+
+        sqlx::query("DROP TABLE article_citations_legacy")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        log::info!("Migrated {migrated} cached citation lists to the packed layout");
+        if skipped > 0 {
+            log::warn!("Skipped {skipped} cached citation lists that could not be parsed");
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lens::cache::compute_misses;
+    use crate::lens::cache::{CachedCitations, compute_misses};
 
     #[tokio::test]
     async fn test_store_and_get_references() -> Result<(), LensError> {
@@ -914,35 +1823,115 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_store_and_get_article_data() -> Result<(), LensError> {
-        use crate::lens::article::{Author, ExternalIds, Source};
+    async fn test_article_data_of_older_versions_is_fetched_again() -> Result<(), LensError> {
+        use crate::lens::completion::complete_articles;
+        use crate::lens::stub::StubLens;
 
+        let stub = StubLens::start().await;
         let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+        let retracted = LensId::try_from("830-182-661-063-60X")?;
 
-        // Create test articles
-        let id1 = LensId::from(12345678901234);
-        let id2 = LensId::from(98765432109876);
-        let id3 = LensId::from(11111111111111);
-
-        let article_data1 = ArticleData {
-            title: Some("Test Article 1".to_string()),
-            summary: Some("This is a test abstract".to_string()),
+        // Cached before the retraction status was fetched
+        sqlx::query(
+            "INSERT INTO article_data (lens_id, article_json, data_version) VALUES (?, ?, 0)",
+        )
+        .bind(retracted.as_ref())
+        .bind(r#"{"title": "Citation networks in evidence synthesis"}"#)
+        .execute(&backend.pool)
+        .await?;
+        assert!(
+            backend
+                .get_article_data(std::slice::from_ref(&retracted))
+                .await?
+                .is_empty()
+        );
+
+        let articles = complete_articles(
+            std::slice::from_ref(&retracted),
+            "stub-key",
+            Some(&stub.client()),
+            Some(&backend),
+        )
+        .await?;
+        assert_eq!(articles[0].article_data.is_retracted, Some(true));
+        assert_eq!(stub.request_count(), 1);
+
+        // Replaced in the cache
+        let cached = backend
+            .get_article_data(std::slice::from_ref(&retracted))
+            .await?;
+        assert_eq!(cached[0].article_data.is_retracted, Some(true));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_article_data_without_bibliographic_fields_is_fetched_again()
+    -> Result<(), LensError> {
+        use crate::lens::completion::complete_articles;
+        use crate::lens::stub::StubLens;
+
+        let stub = StubLens::start().await;
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+        let id = LensId::try_from("565-646-398-006-312")?;
+
+        // Cached before authors, volume, pages and the other bibliographic fields were fetched
+        sqlx::query(
+            "INSERT INTO article_data (lens_id, article_json, data_version) VALUES (?, ?, 0)",
+        )
+        .bind(id.as_ref())
+        .bind(r#"{"title": "Snowball sampling for systematic reviews", "year_published": 2020}"#)
+        .execute(&backend.pool)
+        .await?;
+
+        let articles = complete_articles(
+            std::slice::from_ref(&id),
+            "stub-key",
+            Some(&stub.client()),
+            Some(&backend),
+        )
+        .await?;
+        assert_eq!(articles[0].article_data.volume.as_deref(), Some("12"));
+        assert!(!articles[0].article_data.authors.clone().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_store_and_get_article_data() -> Result<(), LensError> {
+        use crate::lens::article::{Author, ExternalIds, Source};
+
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        // Create test articles
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+        let id3 = LensId::from(11111111111111);
+
+        let article_data1 = ArticleData {
+            title: Some("Test Article 1".to_string()),
+            summary: Some("This is a test abstract".to_string()),
             scholarly_citations_count: Some(42),
             external_ids: Some(ExternalIds {
                 pmid: vec!["12345".to_string()],
                 doi: vec!["10.1234/test".to_string()],
+                pmcid: vec!["PMC12345".to_string()],
+                ..Default::default()
             }),
             authors: Some(vec![Author {
                 first_name: Some("John".to_string()),
                 initials: Some("J".to_string()),
                 last_name: Some("Doe".to_string()),
+                affiliations: None,
             }]),
             source: Some(Source {
                 publisher: Some("Test Publisher".to_string()),
                 title: Some("Test Journal".to_string()),
                 kind: Some("journal".to_string()),
+                issn: None,
             }),
             year_published: Some(2023),
+            ..Default::default()
         };
 
         let article_data2 = ArticleData {
@@ -953,6 +1942,7 @@ mod tests {
             authors: None,
             source: None,
             year_published: Some(2024),
+            ..Default::default()
         };
 
         let article1 = ArticleWithData {
@@ -1048,6 +2038,36 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_list_id_mappings() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let lens_id1 = LensId::from(12345678901234);
+        let lens_id2 = LensId::from(98765432109876);
+        backend
+            .store_id_mapping(&[
+                ("12345".to_string(), lens_id1.clone()),
+                ("10.5678/foo".to_string(), lens_id2.clone()),
+                ("10.1234/test".to_string(), lens_id2.clone()),
+            ])
+            .await?;
+
+        // Pages follow the order of the string IDs
+        let first = backend.list_id_mappings(None, 2).await?;
+        assert_eq!(
+            first,
+            [
+                ("10.1234/test".to_string(), lens_id2.clone()),
+                ("10.5678/foo".to_string(), lens_id2.clone()),
+            ]
+        );
+        let second = backend.list_id_mappings(Some("10.5678/foo"), 2).await?;
+        assert_eq!(second, [("12345".to_string(), lens_id1)]);
+        assert!(backend.list_id_mappings(Some("12345"), 2).await?.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_mark_and_unmark_fetching() -> Result<(), LensError> {
         let backend = SqliteBackend::from_url("sqlite::memory:").await?;
@@ -1172,7 +2192,7 @@ mod tests {
                 for _ in 0..20 {
                     tokio::time::sleep(std::time::Duration::from_millis(10)).await;
                     let cached = backend2
-                        .get_references(&[id1_clone2.clone()])
+                        .get_references(std::slice::from_ref(&id1_clone2))
                         .await
                         .unwrap();
                     if cached.contains_key(&id1_clone2) {
@@ -1196,7 +2216,7 @@ mod tests {
         );
 
         // Verify data is in cache
-        let cached = backend.get_references(&[id1.clone()]).await?;
+        let cached = backend.get_references(std::slice::from_ref(&id1)).await?;
         assert_eq!(cached.get(&id1).unwrap(), &refs);
 
         // Verify no pending marks remain
@@ -1289,4 +2309,580 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_get_cited_by_from_stored_references() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let citing_a = LensId::from(20000000000001);
+        let citing_b = LensId::from(20000000000002);
+        let cited_x = LensId::from(30000000000001);
+        let cited_y = LensId::from(30000000000002);
+        let unknown = LensId::from(30000000000003);
+
+        backend
+            .store_references(&[
+                (citing_b.clone(), vec![cited_x.clone()]),
+                (citing_a.clone(), vec![cited_x.clone(), cited_y.clone()]),
+            ])
+            .await?;
+
+        let cited_by = backend
+            .get_cited_by(&[cited_x.clone(), cited_y.clone(), unknown.clone()])
+            .await?;
+
+        assert_eq!(cited_by.len(), 2);
+        assert_eq!(
+            cited_by.get(&cited_x).unwrap(),
+            &vec![citing_a.clone(), citing_b]
+        );
+        assert_eq!(cited_by.get(&cited_y).unwrap(), &vec![citing_a]);
+        assert!(!cited_by.contains_key(&unknown));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_citations_fall_back_to_cited_by_when_enabled() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let citing = LensId::from(20000000000001);
+        let cited = LensId::from(30000000000001);
+        backend
+            .store_references(&[(citing.clone(), vec![cited.clone()])])
+            .await?;
+
+        // Off by default: a citation miss stays a miss
+        let result = backend
+            .get_citations_or_cited_by(std::slice::from_ref(&cited))
+            .await?;
+        assert!(result.is_empty());
+
+        // Opted in: the miss is answered from the index, marked incomplete
+        let backend = backend.with_cited_by_fallback(true);
+        let result = backend
+            .get_citations_or_cited_by(std::slice::from_ref(&cited))
+            .await?;
+        assert_eq!(
+            result.get(&cited).unwrap(),
+            &CachedCitations {
+                citations: vec![citing.clone()],
+                complete: false,
+            }
+        );
+
+        // Citations fetched from Lens always win
+        let full = vec![citing, LensId::from(20000000000009)];
+        backend
+            .store_citations(&[(cited.clone(), full.clone())])
+            .await?;
+        let result = backend
+            .get_citations_or_cited_by(std::slice::from_ref(&cited))
+            .await?;
+        assert_eq!(
+            result.get(&cited).unwrap(),
+            &CachedCitations {
+                citations: full,
+                complete: true,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cited_by_backfilled_from_existing_references() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let citing = LensId::from(20000000000001);
+        let cited = LensId::from(30000000000001);
+        backend
+            .store_references(&[(citing.clone(), vec![cited.clone()])])
+            .await?;
+
+        // Simulate a cache created before the index existed
+        sqlx::query("DROP TABLE article_cited_by")
+            .execute(&backend.pool)
+            .await?;
+        SqliteBackend::run_migrations(&backend.pool).await?;
+
+        let cited_by = backend.get_cited_by(std::slice::from_ref(&cited)).await?;
+        assert_eq!(cited_by.get(&cited).unwrap(), &vec![citing]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pack_unpack_lens_ids() {
+        let ids = vec![
+            LensId::from(1),
+            LensId::from(12345678901234),
+            LensId::from(99999999999999),
+        ];
+
+        let blob = pack_lens_ids(&ids);
+        assert_eq!(blob.len(), ids.len() * 8);
+        assert_eq!(unpack_lens_ids(&blob), ids);
+
+        // Empty lists and truncated trailing bytes are handled gracefully
+        assert!(unpack_lens_ids(&pack_lens_ids(&[])).is_empty());
+        assert_eq!(unpack_lens_ids(&blob[..blob.len() - 3]), ids[..2]);
+    }
+
+    #[tokio::test]
+    async fn test_migrates_legacy_json_edge_tables() -> Result<(), LensError> {
+        let path =
+            std::env::temp_dir().join(format!("biblizap_legacy_edges_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite:{}?mode=rwc", path.display());
+
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+        let refs1 = vec![LensId::from(1), LensId::from(2), LensId::from(3)];
+        let citations2 = vec![LensId::from(4), LensId::from(5)];
+
+        // Build a cache in the old JSON-text layout
+        {
+            let pool = SqlitePool::connect(&url).await?;
+            sqlx::query(
+                "CREATE TABLE article_references (lens_id TEXT PRIMARY KEY, references_json TEXT NOT NULL, fetched_at INTEGER NOT NULL DEFAULT (unixepoch())) WITHOUT ROWID",
+            )
+            .execute(&pool)
+            .await?;
+            sqlx::query(
+                "CREATE TABLE article_citations (lens_id TEXT PRIMARY KEY, citations_json TEXT NOT NULL, fetched_at INTEGER NOT NULL DEFAULT (unixepoch())) WITHOUT ROWID",
+            )
+            .execute(&pool)
+            .await?;
+            sqlx::query("CREATE INDEX idx_citations_fetched ON article_citations(fetched_at)")
+                .execute(&pool)
+                .await?;
+
+            // Rows that do not parse are skipped, to be fetched again
+            sqlx::query(
+                "INSERT INTO article_references (lens_id, references_json) VALUES (?, ?), (?, ?), (?, ?)",
+            )
+            .bind(id1.as_ref())
+            .bind(serde_json::to_string(&refs1)?)
+            .bind(id2.as_ref())
+            .bind("not json")
+            .bind("not a lens id")
+            .bind(serde_json::to_string(&refs1)?)
+            .execute(&pool)
+            .await?;
+            sqlx::query("INSERT INTO article_citations (lens_id, citations_json) VALUES (?, ?)")
+                .bind(id2.as_ref())
+                .bind(serde_json::to_string(&citations2)?)
+                .execute(&pool)
+                .await?;
+
+            pool.close().await;
+        }
+
+        let backend = SqliteBackend::from_url(&url).await?;
+
+        let references = backend.get_references(&[id1.clone(), id2.clone()]).await?;
+        assert_eq!(references.get(&id1).unwrap(), &refs1);
+        assert!(!references.contains_key(&id2));
+
+        let citations = backend.get_citations(std::slice::from_ref(&id2)).await?;
+        assert_eq!(citations.get(&id2).unwrap(), &citations2);
+
+        let (legacy_tables,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM sqlite_master WHERE name LIKE '%_legacy'")
+                .fetch_one(&backend.pool)
+                .await?;
+        assert_eq!(legacy_tables, 0);
+
+        // Re-opening an already migrated cache is a no-op
+        backend.pool.close().await;
+        let backend = SqliteBackend::from_url(&url).await?;
+        let references = backend.get_references(std::slice::from_ref(&id1)).await?;
+        assert_eq!(references.get(&id1).unwrap(), &refs1);
+
+        backend.pool.close().await;
+        let _ = std::fs::remove_file(&path);
+
+        Ok(())
+    }
+
+    /// Mappings written before keys were canonical are rekeyed once, keeping the mapping
+    /// already stored under the canonical key.
+    #[tokio::test]
+    async fn test_id_mapping_keys_are_made_canonical() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let lens_id1 = LensId::from(12345678901234);
+        let lens_id2 = LensId::from(98765432109876);
+        backend
+            .store_id_mapping(&[
+                ("10.1000/ABC".to_string(), lens_id1.clone()),
+                ("doi:10.1000/abc".to_string(), lens_id2.clone()),
+                ("10.5678/foo".to_string(), lens_id2.clone()),
+                ("https://doi.org/10.5678/FOO".to_string(), lens_id1.clone()),
+                ("pmc7000001".to_string(), lens_id1.clone()),
+                ("mag:3000000001".to_string(), lens_id2.clone()),
+                ("not an identifier".to_string(), lens_id1.clone()),
+            ])
+            .await?;
+        sqlx::query("DELETE FROM cache_migrations")
+            .execute(&backend.pool)
+            .await?;
+
+        SqliteBackend::run_migrations(&backend.pool).await?;
+
+        let mappings = backend.list_id_mappings(None, 10).await?;
+        assert_eq!(
+            mappings,
+            [
+                ("10.1000/abc".to_string(), lens_id1.clone()),
+                ("10.5678/foo".to_string(), lens_id2.clone()),
+                ("PMC7000001".to_string(), lens_id1.clone()),
+                ("mag:3000000001".to_string(), lens_id2),
+            ]
+        );
+
+        // Applied once: keys written since are left as they are
+        backend
+            .store_id_mapping(&[("10.9999/LATER".to_string(), lens_id1)])
+            .await?;
+        SqliteBackend::run_migrations(&backend.pool).await?;
+        assert_eq!(backend.list_id_mappings(None, 10).await?.len(), 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resumes_interrupted_legacy_migration() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+        let refs1 = vec![LensId::from(1), LensId::from(2)];
+        let fresh_refs2 = vec![LensId::from(3)];
+
+        // A legacy table renamed by an earlier migration that stopped before copying it,
+        // while the new table has been filled since
+        sqlx::query(
+            "CREATE TABLE article_references_legacy (lens_id TEXT PRIMARY KEY, references_json TEXT NOT NULL, fetched_at INTEGER NOT NULL DEFAULT (unixepoch())) WITHOUT ROWID",
+        )
+        .execute(&backend.pool)
+        .await?;
+        sqlx::query(
+            "INSERT INTO article_references_legacy (lens_id, references_json) VALUES (?, ?), (?, ?)",
+        )
+        .bind(id1.as_ref())
+        .bind(serde_json::to_string(&refs1)?)
+        .bind(id2.as_ref())
+        .bind(serde_json::to_string(&[LensId::from(4)])?)
+        .execute(&backend.pool)
+        .await?;
+        backend
+            .store_references(&[(id2.clone(), fresh_refs2.clone())])
+            .await?;
+
+        SqliteBackend::run_migrations(&backend.pool).await?;
+
+        let references = backend.get_references(&[id1.clone(), id2.clone()]).await?;
+        assert_eq!(references.get(&id1).unwrap(), &refs1);
+        assert_eq!(references.get(&id2).unwrap(), &fresh_refs2);
+
+        // The cited-by index also covers the migrated references
+        let cited_by = backend.get_cited_by(&[LensId::from(1)]).await?;
+        assert_eq!(cited_by.get(&LensId::from(1)).unwrap(), &vec![id1]);
+
+        let (legacy_tables,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM sqlite_master WHERE name LIKE '%_legacy'")
+                .fetch_one(&backend.pool)
+                .await?;
+        assert_eq!(legacy_tables, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "benchmark: stores and reads 2000 x 200 edges"]
+    async fn test_packed_edges_read_performance() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        // 2000 articles with 200 edges each
+        let batch: Vec<(LensId, Vec<LensId>)> = (0..2000u64)
+            .map(|i| {
+                let edges = (0..200u64)
+                    .map(|j| LensId::from(20000000000000u64 + i * 1000 + j))
+                    .collect();
+                (LensId::from(10000000000000u64 + i), edges)
+            })
+            .collect();
+        let ids: Vec<LensId> = batch.iter().map(|(id, _)| id.clone()).collect();
+
+        backend.store_references(&batch).await?;
+
+        // Same data in the old JSON-text layout
+        sqlx::query(
+            "CREATE TABLE json_references (lens_id TEXT PRIMARY KEY, references_json TEXT NOT NULL) WITHOUT ROWID",
+        )
+        .execute(&backend.pool)
+        .await?;
+        for chunk in batch.chunks(333) {
+            let mut builder =
+                sqlx::QueryBuilder::new("INSERT INTO json_references (lens_id, references_json) ");
+            builder.push_values(chunk, |mut b, (id, refs)| {
+                b.push_bind(id.as_ref().to_string())
+                    .push_bind(serde_json::to_string(refs).unwrap());
+            });
+            builder.build().execute(&backend.pool).await?;
+        }
+
+        // Fetch and decode the stored edge lists in both layouts
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT lens_id, references_json FROM json_references WHERE lens_id IN (SELECT value FROM json_each(?))",
+        )
+        .bind(SqliteBackend::ids_to_json(&ids)?)
+        .fetch_all(&backend.pool)
+        .await?;
+        let json_edges = rows
+            .iter()
+            .map(|(_, json)| Ok(serde_json::from_str::<Vec<LensId>>(json)?.len()))
+            .sum::<Result<usize, LensError>>()?;
+
+        let rows: Vec<(i64, Vec<u8>)> = sqlx::query_as(
+            "SELECT lens_id, reference_ids FROM article_references WHERE lens_id IN (SELECT value FROM json_each(?))",
+        )
+        .bind(SqliteBackend::ids_to_int_json(&ids)?)
+        .fetch_all(&backend.pool)
+        .await?;
+        let packed_edges: usize = rows
+            .iter()
+            .map(|(_, blob)| {
+                blob.chunks_exact(8)
+                    .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+                    .collect::<Vec<_>>()
+                    .len()
+            })
+            .sum();
+
+        let (json_bytes,): (i64,) =
+            sqlx::query_as("SELECT SUM(LENGTH(references_json)) FROM json_references")
+                .fetch_one(&backend.pool)
+                .await?;
+        let (packed_bytes,): (i64,) =
+            sqlx::query_as("SELECT SUM(LENGTH(reference_ids)) FROM article_references")
+                .fetch_one(&backend.pool)
+                .await?;
+
+        let result = backend.get_references(&ids).await?;
+
+        assert_eq!(json_edges, 400_000);
+        assert_eq!(packed_edges, 400_000);
+        assert_eq!(result.get(&ids[0]).unwrap(), &batch[0].1);
+        assert!(packed_bytes * 2 < json_bytes);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_purge_removes_everything_about_an_id() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+        let ref1 = LensId::from(11111111111111);
+
+        backend
+            .store_references(&[
+                (id1.clone(), vec![ref1.clone()]),
+                (id2.clone(), vec![ref1.clone()]),
+            ])
+            .await?;
+        backend
+            .store_citations(&[(id1.clone(), vec![id2.clone()])])
+            .await?;
+        backend
+            .store_id_mapping(&[("10.1234/test".to_string(), id1.clone())])
+            .await?;
+        backend.mark_as_fetching(&id1).await?;
+
+        // 1 reference list, 1 citation list, 1 cited-by edge, 1 mapping, 1 pending mark
+        assert_eq!(backend.purge(std::slice::from_ref(&id1)).await?, 5);
+        assert_eq!(backend.purge(&[]).await?, 0);
+
+        assert!(
+            backend
+                .get_references(std::slice::from_ref(&id1))
+                .await?
+                .is_empty()
+        );
+        assert!(
+            backend
+                .get_citations(std::slice::from_ref(&id1))
+                .await?
+                .is_empty()
+        );
+        assert!(
+            backend
+                .get_id_mapping(&["10.1234/test".to_string()])
+                .await?
+                .is_empty()
+        );
+        assert!(!backend.is_being_fetched(&id1).await?);
+
+        // Other articles are untouched, including their share of the cited-by index
+        assert_eq!(
+            backend
+                .get_references(std::slice::from_ref(&id2))
+                .await?
+                .len(),
+            1
+        );
+        let cited_by = backend.get_cited_by(std::slice::from_ref(&ref1)).await?;
+        assert_eq!(cited_by.get(&ref1), Some(&vec![id2.clone()]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stats_and_stale_pending_fetches() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let id1 = LensId::from(12345678901234);
+        let id2 = LensId::from(98765432109876);
+
+        assert_eq!(backend.stats().await?, CacheStats::default());
+
+        backend
+            .store_references(&[(id1.clone(), vec![id2.clone()])])
+            .await?;
+        backend.mark_as_fetching(&id1).await?;
+
+        let stale_timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+            - 61;
+
+        sqlx::query("INSERT INTO pending_fetches (lens_id, started_at) VALUES (?, ?)")
+            .bind(id2.as_ref().to_string())
+            .bind(stale_timestamp)
+            .execute(&backend.pool)
+            .await?;
+
+        let stats = backend.stats().await?;
+        assert_eq!(stats.references, 1);
+        assert_eq!(stats.cited_by_edges, 1);
+        assert_eq!(stats.pending_fetches, 2);
+        assert_eq!(stats.stale_pending_fetches, 1);
+
+        // Only the stale mark is removed
+        assert_eq!(backend.clear_stale_pending_fetches().await?, 1);
+        assert!(backend.is_being_fetched(&id1).await?);
+        assert_eq!(backend.stats().await?.pending_fetches, 1);
+
+        backend.vacuum().await?;
+        assert_eq!(backend.stats().await?.references, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pubmed_links_and_articles() -> Result<(), LensError> {
+        let backend = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        backend
+            .store_pubmed_links(
+                PubmedLink::References,
+                &[(31000001, vec![31000003, 31000010]), (31000003, vec![])],
+            )
+            .await?;
+        backend
+            .store_pubmed_links(PubmedLink::Citations, &[(31000001, vec![31000020])])
+            .await?;
+
+        let references = backend
+            .get_pubmed_links(PubmedLink::References, &[31000001, 31000003, 31000099])
+            .await?;
+        assert_eq!(references.len(), 2);
+        assert_eq!(references[&31000001], [31000003, 31000010]);
+        assert_eq!(references[&31000003], Vec::<u64>::new());
+
+        // Cited-in lists are replaced, and expire like citations
+        backend
+            .store_pubmed_links(
+                PubmedLink::Citations,
+                &[(31000001, vec![31000020, 31000021])],
+            )
+            .await?;
+        let citations = backend
+            .get_pubmed_links(PubmedLink::Citations, &[31000001])
+            .await?;
+        assert_eq!(citations[&31000001], [31000020, 31000021]);
+
+        sqlx::query("UPDATE pubmed_links SET fetched_at = ?")
+            .bind(0i64)
+            .execute(&backend.pool)
+            .await?;
+        assert!(
+            backend
+                .get_pubmed_links(PubmedLink::Citations, &[31000001])
+                .await?
+                .is_empty()
+        );
+        assert_eq!(
+            backend
+                .get_pubmed_links(PubmedLink::References, &[31000001])
+                .await?
+                .len(),
+            1
+        );
+
+        let article = PubmedArticle {
+            pmid: "31000001".to_string(),
+            title: Some("A title".to_string()),
+            mesh_terms: vec!["Humans".to_string()],
+            ..Default::default()
+        };
+        let invalid = PubmedArticle {
+            pmid: "not a pmid".to_string(),
+            ..Default::default()
+        };
+        backend
+            .store_pubmed_articles(&[article.clone(), invalid])
+            .await?;
+        assert_eq!(
+            backend.get_pubmed_articles(&[31000001, 31000002]).await?,
+            [article]
+        );
+
+        backend
+            .store_pubmed_search("covid triage", 2, &[31000001, 31000003])
+            .await?;
+        backend
+            .store_pubmed_search("rare query", 20, &[31000002])
+            .await?;
+        assert_eq!(
+            backend.get_pubmed_search("covid triage", 1).await?,
+            Some(vec![31000001])
+        );
+        assert_eq!(backend.get_pubmed_search("covid triage", 5).await?, None);
+        assert_eq!(
+            backend.get_pubmed_search("rare query", 100).await?,
+            Some(vec![31000002]),
+            "Searches that found fewer PMIDs than asked are complete"
+        );
+        sqlx::query("UPDATE pubmed_searches SET fetched_at = ?")
+            .bind(0i64)
+            .execute(&backend.pool)
+            .await?;
+        assert_eq!(backend.get_pubmed_search("rare query", 1).await?, None);
+
+        let stats = backend.stats().await?;
+        assert_eq!(stats.pubmed_links, 3);
+        assert_eq!(stats.pubmed_articles, 1);
+        assert_eq!(stats.pubmed_searches, 2);
+
+        backend.clear().await?;
+        assert_eq!(backend.stats().await?, CacheStats::default());
+
+        Ok(())
+    }
 }
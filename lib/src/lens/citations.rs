@@ -183,21 +183,14 @@ pub struct ArticleWithReferencesAndCitations {
 
 impl ArticleWithReferencesAndCitations {
     pub fn id_mappings_single_article(&self) -> Option<HashMap<String, LensId>> {
-        let external_ids = self.external_ids.clone()?;
-
-        let pmid = external_ids
-            .pmid
-            .clone()
-            .into_iter()
-            .map(|x| (x, self.lens_id.clone()));
-
-        let doi = external_ids
-            .doi
-            .clone()
-            .into_iter()
-            .map(|x| (x, self.lens_id.clone()));
-
-        Some(pmid.chain(doi).collect())
+        let external_ids = self.external_ids.as_ref()?;
+
+        Some(
+            external_ids
+                .mapping_keys()
+                .map(|key| (key, self.lens_id.clone()))
+                .collect(),
+        )
     }
 
     pub fn id_mappings<'a, I>(articles: I) -> HashMap<String, LensId>
@@ -236,6 +229,8 @@ impl ArticleWithReferencesAndCitations {
 pub struct ArticleWithReferencesAndCitationsMerged {
     pub parent_id: LensId,
     pub children: Vec<LensId>,
+    /// The citations were rebuilt from the cited-by index of the cache, so some are missing
+    pub incomplete_citations: bool,
 }
 
 impl From<ArticleWithReferencesAndCitations> for ArticleWithReferencesAndCitationsMerged {
@@ -243,6 +238,7 @@ impl From<ArticleWithReferencesAndCitations> for ArticleWithReferencesAndCitatio
         Self {
             parent_id: article.lens_id,
             children: article.refs_and_cites.get_both(),
+            incomplete_citations: false,
         }
     }
 }
@@ -252,6 +248,7 @@ impl From<(LensId, Vec<LensId>)> for ArticleWithReferencesAndCitationsMerged {
         Self {
             parent_id,
             children,
+            incomplete_citations: false,
         }
     }
 }
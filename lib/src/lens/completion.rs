@@ -1,10 +1,11 @@
+use crate::identifier::Identifier;
 use crate::lens::{
-    article::{ArticleData, ArticleWithData},
+    article::ArticleWithData,
     cache::CacheBackend,
     error::LensError,
     id_types::TypedIdList,
     lensid::LensId,
-    request::request_and_parse,
+    request::{LensClient, request_and_parse},
 };
 
 /// Completes the information for a list of articles using the Lens.org API.
@@ -18,7 +19,7 @@ use crate::lens::{
 ///
 /// * `id_list`: A slice of LensIds to fetch article data for.
 /// * `api_key`: The API key for Lens.org.
-/// * `client`: An optional `reqwest::Client` to use for requests. If `None`, a new client is created.
+/// * `client`: An optional `LensClient` to use for requests. If `None`, a new client is created.
 /// * `cache`: An optional cache backend to use for caching results.
 ///
 /// # Returns
@@ -27,7 +28,7 @@ use crate::lens::{
 pub async fn complete_articles(
     id_list: &[LensId],
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
     cache: Option<&dyn CacheBackend>,
 ) -> Result<Vec<ArticleWithData>, LensError> {
     let Some(cache_backend) = cache else {
@@ -62,16 +63,17 @@ pub async fn complete_articles(
     Ok(cached_articles)
 }
 
-/// Enriches a mixed list of raw article identifiers (DOIs, PMIDs, Lens IDs) with full
-/// article data from the Lens.org API, using cache where available.
+/// Enriches a mixed list of raw article identifiers (DOIs, PMIDs, Lens IDs, PMCIDs, arXiv,
+/// OpenAlex, MAG and CORE IDs) with full article data from the Lens.org API, using cache
+/// where available.
 ///
 /// Unlike [`complete_articles`], this function accepts raw string identifiers of any
-/// supported type and handles the resolution from DOI/PMID to `LensId` internally.
+/// supported type and handles the resolution from DOI/PMID/etc. to `LensId` internally.
 /// The id-mapping and article-data caches are populated as a side-effect.
 pub async fn complete_articles_by_raw_ids(
     raw_ids: &[&str],
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
     cache: Option<&dyn CacheBackend>,
 ) -> Result<Vec<ArticleWithData>, LensError> {
     use std::collections::HashMap;
@@ -86,23 +88,26 @@ pub async fn complete_articles_by_raw_ids(
     let mut lens_ids: Vec<LensId> = typed
         .lens_id
         .iter()
-        .filter_map(|s| LensId::try_from(*s).ok())
+        .filter_map(|id| LensId::try_from(id.value()).ok())
         .collect();
 
-    let non_lens_strs: Vec<&str> = typed.doi.iter().chain(typed.pmid.iter()).copied().collect();
+    // Other IDs are looked up under their canonical id_mappings key, which is also a valid ID.
+    let non_lens_keys: Vec<String> = typed
+        .external_groups()
+        .flat_map(|(_, ids)| ids.iter().map(Identifier::key))
+        .collect();
 
-    // Resolve DOIs/PMIDs via id_mappings cache, collect misses.
-    let mapping_misses: Vec<&str> = if !non_lens_strs.is_empty() {
+    // Resolve DOIs/PMIDs/etc. via id_mappings cache, collect misses.
+    let mapping_misses: Vec<String> = if !non_lens_keys.is_empty() {
         if let Some(cb) = cache {
-            let keys: Vec<String> = non_lens_strs.iter().map(|s| s.to_string()).collect();
-            let hits: HashMap<String, LensId> = cb.get_id_mapping(&keys).await?;
+            let hits: HashMap<String, LensId> = cb.get_id_mapping(&non_lens_keys).await?;
             lens_ids.extend(hits.values().cloned());
-            non_lens_strs
+            non_lens_keys
                 .into_iter()
-                .filter(|s| !hits.contains_key(*s))
+                .filter(|s| !hits.contains_key(s))
                 .collect()
         } else {
-            non_lens_strs
+            non_lens_keys
         }
     } else {
         Vec::new()
@@ -135,17 +140,17 @@ pub async fn complete_articles_by_raw_ids(
 ///
 /// This function performs the network requests only; caching is handled by the caller.
 /// Works with any ID type that implements `AsRef<str>` (e.g., `&str`, `LensId`, `String`).
-async fn fetch_articles_no_cache<T>(
+pub(crate) async fn fetch_articles_no_cache<T>(
     ids: &[T],
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
 ) -> Result<Vec<ArticleWithData>, LensError>
 where
     T: AsRef<str>,
 {
     let client_owned = match client {
         Some(c) => c.to_owned(),
-        None => reqwest::Client::new(),
+        None => LensClient::default(),
     };
 
     let results: Vec<ArticleWithData> = futures::future::join_all(
@@ -165,7 +170,7 @@ where
 async fn complete_articles_no_cache(
     id_list: &[LensId],
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
 ) -> Result<Vec<ArticleWithData>, LensError> {
     fetch_articles_no_cache(id_list, api_key, client).await
 }
@@ -179,7 +184,7 @@ async fn complete_articles_no_cache(
 ///
 /// * `id_list`: A slice of items that can be referenced as strings for this chunk.
 /// * `api_key`: The API key for Lens.org.
-/// * `client`: An optional `reqwest::Client` to use for requests. If `None`, a new client is created.
+/// * `client`: An optional `LensClient` to use for requests. If `None`, a new client is created.
 ///
 /// # Returns
 ///
@@ -187,7 +192,7 @@ async fn complete_articles_no_cache(
 async fn request_batch<T>(
     id_list: &[T],
     api_key: &str,
-    client: &reqwest::Client,
+    client: &LensClient,
 ) -> Result<Vec<ArticleWithData>, LensError>
 where
     T: AsRef<str>,
@@ -199,14 +204,13 @@ where
     let mut complete_articles = Vec::<ArticleWithData>::with_capacity(iter.len());
 
     // Fetch articles by each ID type
-    complete_articles.append(
-        &mut request_batch_one_id_type(&typed_id_list.pmid, "pmid", api_key, client).await?,
-    );
-    complete_articles.append(
-        &mut request_batch_one_id_type(&typed_id_list.lens_id, "lens_id", api_key, client).await?,
-    );
-    complete_articles
-        .append(&mut request_batch_one_id_type(&typed_id_list.doi, "doi", api_key, client).await?);
+    for (id_type, ids) in typed_id_list.groups() {
+        let query_values: Vec<&str> = ids.iter().map(Identifier::value).collect();
+        complete_articles.append(
+            &mut request_batch_one_id_type(&query_values, id_type.lens_field(), api_key, client)
+                .await?,
+        );
+    }
 
     Ok(complete_articles)
 }
@@ -220,7 +224,7 @@ where
 /// * `id_list`: A slice of string slices representing IDs of a single type (e.g., all PMIDs).
 /// * `id_type`: The type of IDs in `id_list` (e.g., "pmid", "lens_id", "doi").
 /// * `api_key`: The API key for Lens.org.
-/// * `client`: The `reqwest::Client` to use for the request.
+/// * `client`: The `LensClient` to use for the request.
 ///
 /// # Returns
 ///
@@ -229,7 +233,7 @@ async fn request_batch_one_id_type(
     id_list: &[&str],
     id_type: &str,
     api_key: &str,
-    client: &reqwest::Client,
+    client: &LensClient,
 ) -> Result<Vec<ArticleWithData>, LensError> {
     // Fields to include in the API response
     let include = [
@@ -241,119 +245,186 @@ async fn request_batch_one_id_type(
         "scholarly_citations_count",
         "source",
         "year_published",
+        "date_published",
+        "volume",
+        "issue",
+        "start_page",
+        "end_page",
+        "publication_type",
+        "languages",
+        "keywords",
+        "mesh_terms",
+        "is_retracted",
+        "is_open_access",
+        "open_access",
+        "source_urls",
     ];
 
     // Fetch articles from API
     let articles: Vec<crate::lens::article::Article> =
         request_and_parse(client, api_key, id_list, id_type, &include).await?;
 
-    // Convert Article to ArticleWithData
-    articles
-        .into_iter()
-        .map(|article| {
-            Ok(ArticleWithData {
-                lens_id: article.lens_id,
-                article_data: ArticleData {
-                    title: article.title,
-                    summary: article.summary,
-                    scholarly_citations_count: article.scholarly_citations_count,
-                    external_ids: article.external_ids,
-                    authors: article.authors,
-                    source: article.source,
-                    year_published: article.year_published,
-                },
-            })
-        })
-        .collect()
+    Ok(articles.into_iter().map(ArticleWithData::from).collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    /// Tests the `complete_articles` function by fetching details for known IDs.
+    /// Tests the `complete_articles` function by fetching details of fixture works.
     #[tokio::test]
-    async fn complete_articles_test() {
-        let src_id = [LensId::from(2020040130733), LensId::from(5070897679125)];
-
-        let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
+    async fn complete_articles_test() -> Result<(), LensError> {
+        let stub = crate::lens::stub::StubLens::start().await;
+        let src_id = [
+            LensId::try_from("565-646-398-006-312").unwrap(),
+            LensId::try_from("077-974-340-045-600").unwrap(),
+        ];
 
-        let articles = complete_articles(&src_id, &api_key, None, None)
-            .await
-            .unwrap();
+        let articles = complete_articles(&src_id, "stub-key", Some(&stub.client()), None).await?;
 
         assert_eq!(articles.len(), src_id.len());
+        assert!(
+            articles
+                .iter()
+                .all(|article| article.article_data.title.is_some())
+        );
 
-        for article in articles.into_iter() {
-            println!("{article:#?}");
-        }
+        Ok(())
     }
 
     /// Tests that `complete_articles` correctly uses caching
     #[cfg(feature = "cache-sqlite")]
     #[tokio::test]
-    async fn complete_articles_with_cache_test() {
+    async fn complete_articles_with_cache_test() -> Result<(), LensError> {
         use crate::lens::cache::SqliteBackend;
 
+        let stub = crate::lens::stub::StubLens::start().await;
+        let client = stub.client();
         let src_id = [
-            LensId::from(2020040130733),
-            LensId::from(5070897679125),
-            LensId::from(10_000_000_000_001), // Non-existent ID to test partial misses
+            LensId::try_from("565-646-398-006-312").unwrap(),
+            LensId::try_from("077-974-340-045-600").unwrap(),
+            LensId::try_from("142-479-807-365-550").unwrap(),
         ];
 
-        let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
-
         // Create an in-memory cache
-        let cache = SqliteBackend::from_url("sqlite::memory:")
-            .await
-            .expect("Failed to create cache backend");
+        let cache = SqliteBackend::from_url("sqlite::memory:").await?;
 
         // First call - should fetch from API and populate cache
-        let articles_first = complete_articles(&src_id[..2], &api_key, None, Some(&cache))
-            .await
-            .unwrap();
-
+        let articles_first =
+            complete_articles(&src_id[..2], "stub-key", Some(&client), Some(&cache)).await?;
         assert_eq!(articles_first.len(), 2);
-        println!("First call fetched {} articles", articles_first.len());
 
         // Verify the articles were stored in cache
-        let cached = cache.get_article_data(&src_id[..2]).await.unwrap();
+        let cached = cache.get_article_data(&src_id[..2]).await?;
         assert_eq!(cached.len(), 2, "Cache should contain 2 articles");
 
         // Second call - should retrieve from cache (no API call needed)
-        let articles_second = complete_articles(&src_id[..2], &api_key, None, Some(&cache))
-            .await
-            .unwrap();
-
+        let requests = stub.request_count();
+        let articles_second =
+            complete_articles(&src_id[..2], "stub-key", Some(&client), Some(&cache)).await?;
         assert_eq!(articles_second.len(), 2);
-        println!(
-            "Second call retrieved {} articles from cache",
-            articles_second.len()
-        );
+        assert_eq!(stub.request_count(), requests);
 
         // Verify the data is the same
-        assert_eq!(articles_first[0].lens_id, articles_second[0].lens_id);
-        assert_eq!(
-            articles_first[0].article_data.title,
-            articles_second[0].article_data.title
-        );
+        let titles = |articles: Vec<ArticleWithData>| {
+            let mut titles: Vec<_> = articles
+                .into_iter()
+                .map(|article| (article.lens_id, article.article_data.title))
+                .collect();
+            titles.sort();
+            titles
+        };
+        assert_eq!(titles(articles_first), titles(articles_second));
 
         // Test partial cache hit - one cached, one new
-        let mixed_ids = [src_id[0].clone(), LensId::from(5070897679125)];
-        let articles_mixed = complete_articles(&mixed_ids, &api_key, None, Some(&cache))
-            .await
-            .unwrap();
-
+        let mixed_ids = [src_id[0].clone(), src_id[2].clone()];
+        let articles_mixed =
+            complete_articles(&mixed_ids, "stub-key", Some(&client), Some(&cache)).await?;
         assert_eq!(articles_mixed.len(), 2);
-        println!(
-            "Mixed call (1 cached + 1 fetched) returned {} articles",
-            articles_mixed.len()
-        );
+        assert_eq!(stub.request_count(), requests + 1);
 
         // Verify cache now has all fetched articles
-        let final_cached = cache.get_article_data(&mixed_ids).await.unwrap();
+        let final_cached = cache.get_article_data(&mixed_ids).await?;
         assert_eq!(final_cached.len(), 2, "Cache should contain both articles");
 
-        println!("Cache test completed successfully!");
+        Ok(())
+    }
+
+    /// Raw DOIs, PMIDs and Lens IDs are resolved against the stub, then served from cache.
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn complete_articles_by_raw_ids_with_stub_lens() -> Result<(), LensError> {
+        use crate::lens::cache::SqliteBackend;
+        use crate::lens::stub::StubLens;
+
+        let stub = StubLens::start().await;
+        let cache = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let raw_ids = ["30000001", "10.1000/stub.b", "830-182-661-063-60X"];
+
+        let first =
+            complete_articles_by_raw_ids(&raw_ids, "stub-key", Some(&stub.client()), Some(&cache))
+                .await?;
+        assert_eq!(first.len(), 3);
+        let requests = stub.request_count();
+
+        let second =
+            complete_articles_by_raw_ids(&raw_ids, "stub-key", Some(&stub.client()), Some(&cache))
+                .await?;
+        assert_eq!(second.len(), 3);
+        assert_eq!(
+            stub.request_count(),
+            requests,
+            "Second call should hit the cache"
+        );
+
+        let mut titles: Vec<Option<String>> = second
+            .into_iter()
+            .map(|article| article.article_data.title)
+            .collect();
+        titles.sort();
+        assert_eq!(
+            titles,
+            [
+                Some("Citation networks in evidence synthesis".to_string()),
+                Some("Forward and backward citation searching".to_string()),
+                Some("Snowball sampling for systematic reviews".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    /// PMCIDs, arXiv, OpenAlex, MAG and CORE IDs are resolved and kept in `external_ids`.
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn complete_articles_by_other_raw_ids_with_stub_lens() -> Result<(), LensError> {
+        use crate::lens::cache::SqliteBackend;
+        use crate::lens::stub::StubLens;
+
+        let stub = StubLens::start().await;
+        let cache = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let raw_ids = ["PMC7000001", "core:82573464"];
+        let mut articles =
+            complete_articles_by_raw_ids(&raw_ids, "stub-key", Some(&stub.client()), Some(&cache))
+                .await?;
+        articles.sort_by_key(|article| article.article_data.title.clone());
+        assert_eq!(articles.len(), 2);
+
+        let external_ids = articles[1].article_data.external_ids.clone().unwrap();
+        assert_eq!(external_ids.pmcid, ["PMC7000001"]);
+        assert_eq!(external_ids.openalex, ["W3000000001"]);
+        assert_eq!(external_ids.magid, ["3000000001"]);
+        let external_ids = articles[0].article_data.external_ids.clone().unwrap();
+        assert_eq!(external_ids.arxiv, ["2101.00002"]);
+        assert_eq!(external_ids.coreid, ["82573464"]);
+
+        let mappings = cache
+            .get_id_mapping(&["W3000000001".to_string(), "2101.00002".to_string()])
+            .await?;
+        assert_eq!(mappings.len(), 2);
+
+        Ok(())
     }
 }
@@ -15,6 +15,8 @@ pub enum LensError {
     LensApi(LensApiErrorInfo),
     #[error("Failed to parse JSON response from Lens API : {0}")]
     SerdeJson(#[from] serde_json::Error),
+    #[error("Every Lens API key was rejected, please ask your administrator to check them")]
+    NoUsableApiKey,
     #[error("No articles found for the given query, please check if your input IDs exist")]
     NoArticlesFound,
     #[error(transparent)]
@@ -1,17 +1,33 @@
+pub use crate::identifier::IdType;
+use crate::identifier::Identifier;
 use crate::lens::error::LensError;
 
-/// A helper struct to categorize raw string IDs into known types (PMID, Lens ID, DOI).
-pub struct TypedIdList<'a> {
+/// A helper struct to categorize raw string IDs into known types.
+///
+/// The IDs are parsed and normalised with [`Identifier::parse`], so they can be sent to
+/// Lens with [`Identifier::value`] and looked up in `id_mappings` with [`Identifier::key`].
+#[derive(Debug, Default)]
+pub struct TypedIdList {
     /// List of potential PubMed IDs.
-    pub pmid: Vec<&'a str>,
+    pub pmid: Vec<Identifier>,
     /// List of potential Lens.org IDs.
-    pub lens_id: Vec<&'a str>,
+    pub lens_id: Vec<Identifier>,
     /// List of potential DOIs.
-    pub doi: Vec<&'a str>,
+    pub doi: Vec<Identifier>,
+    /// List of potential PubMed Central IDs.
+    pub pmcid: Vec<Identifier>,
+    /// List of potential arXiv IDs.
+    pub arxiv: Vec<Identifier>,
+    /// List of potential OpenAlex work IDs.
+    pub openalex: Vec<Identifier>,
+    /// List of potential MAG IDs.
+    pub mag: Vec<Identifier>,
+    /// List of potential CORE IDs.
+    pub core: Vec<Identifier>,
 }
 
-impl<'a> TypedIdList<'a> {
-    /// Categorizes a list of raw string IDs into known types using regular expressions.
+impl TypedIdList {
+    /// Categorizes a list of raw string IDs into known types.
     ///
     /// # Arguments
     ///
@@ -19,41 +35,103 @@ impl<'a> TypedIdList<'a> {
     ///
     /// # Returns
     ///
-    /// A `TypedIdList` containing the categorized IDs.
-    pub fn from_raw_id_list<I>(id_list: I) -> Result<Self, LensError>
+    /// A `TypedIdList` containing the categorized IDs. Unrecognised IDs are dropped, and
+    /// `LensError::NoValidIdsInInputList` is returned if none is left.
+    pub fn from_raw_id_list<'a, I>(id_list: I) -> Result<Self, LensError>
     where
-        I: IntoIterator<Item = &'a str> + Clone,
+        I: IntoIterator<Item = &'a str>,
     {
-        use regex::Regex;
-        // Regex for matching PMIDs (digits only)
-        let pmid_regex = Regex::new("^[0-9]+$").expect("Failed to create PMID regex");
-        // Regex for matching Lens IDs (format like XXX-XXX-...)
-        let lens_id_regex =
-            Regex::new("^...-...-...-...-...$").expect("Failed to create Lens ID regex");
-        // Regex for matching DOIs (starts with 10.)
-        let doi_regex = Regex::new("^10\\.").expect("Failed to create DOI regex");
-
-        let pmid = id_list
-            .clone()
-            .into_iter()
-            .filter(|n| pmid_regex.is_match(n))
-            .collect::<Vec<_>>();
+        let mut typed = Self::default();
+        for identifier in id_list.into_iter().filter_map(Identifier::parse) {
+            typed.ids_mut(identifier.id_type()).push(identifier);
+        }
 
-        let lens_id = id_list
-            .clone()
-            .into_iter()
-            .filter(|n| lens_id_regex.is_match(n))
-            .collect::<Vec<_>>();
+        if typed.is_empty() {
+            return Err(LensError::NoValidIdsInInputList);
+        }
 
-        let doi = id_list
-            .into_iter()
-            .filter(|n| doi_regex.is_match(n))
-            .collect::<Vec<&str>>();
+        Ok(typed)
+    }
 
-        if pmid.is_empty() && lens_id.is_empty() && doi.is_empty() {
-            return Err(LensError::NoValidIdsInInputList);
+    /// The IDs of one type.
+    pub fn ids(&self, id_type: IdType) -> &[Identifier] {
+        match id_type {
+            IdType::LensId => &self.lens_id,
+            IdType::Pmid => &self.pmid,
+            IdType::Doi => &self.doi,
+            IdType::Pmcid => &self.pmcid,
+            IdType::Arxiv => &self.arxiv,
+            IdType::OpenAlex => &self.openalex,
+            IdType::Mag => &self.mag,
+            IdType::Core => &self.core,
+        }
+    }
+
+    fn ids_mut(&mut self, id_type: IdType) -> &mut Vec<Identifier> {
+        match id_type {
+            IdType::LensId => &mut self.lens_id,
+            IdType::Pmid => &mut self.pmid,
+            IdType::Doi => &mut self.doi,
+            IdType::Pmcid => &mut self.pmcid,
+            IdType::Arxiv => &mut self.arxiv,
+            IdType::OpenAlex => &mut self.openalex,
+            IdType::Mag => &mut self.mag,
+            IdType::Core => &mut self.core,
         }
+    }
+
+    /// Non-empty groups of IDs, by type.
+    pub fn groups(&self) -> impl Iterator<Item = (IdType, &[Identifier])> {
+        IdType::ALL
+            .into_iter()
+            .map(|id_type| (id_type, self.ids(id_type)))
+            .filter(|(_, ids)| !ids.is_empty())
+    }
+
+    /// Non-empty groups of IDs other than Lens IDs, by type.
+    pub fn external_groups(&self) -> impl Iterator<Item = (IdType, &[Identifier])> {
+        self.groups()
+            .filter(|(id_type, _)| *id_type != IdType::LensId)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_id_list_groups_ids() {
+        let typed = TypedIdList::from_raw_id_list([
+            "30000001",
+            "pmc7000001",
+            "mag:2741809807",
+            "https://doi.org/10.1000/STUB.A",
+            "garbage",
+            "30000002",
+        ])
+        .unwrap();
+
+        let groups: Vec<(IdType, Vec<String>)> = typed
+            .groups()
+            .map(|(id_type, ids)| (id_type, ids.iter().map(Identifier::key).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (IdType::Pmid, vec!["30000001".into(), "30000002".into()]),
+                (IdType::Doi, vec!["10.1000/stub.a".into()]),
+                (IdType::Pmcid, vec!["PMC7000001".into()]),
+                (IdType::Mag, vec!["mag:2741809807".into()]),
+            ]
+        );
 
-        Ok(Self { pmid, lens_id, doi })
+        assert!(matches!(
+            TypedIdList::from_raw_id_list(["garbage", "10.1/x"]),
+            Err(LensError::NoValidIdsInInputList)
+        ));
     }
 }
@@ -0,0 +1,318 @@
+//! Pool of Lens.org API keys shared by the requests of a `LensClient`.
+//!
+//! Every Lens key has its own rate limit, so spreading requests over several keys
+//! multiplies the throughput of an instance. The pool picks a key for every request,
+//! reads the remaining quota from the rate-limit headers of the responses, moves on to
+//! another key while one is rate limited, and takes a key out of rotation for good
+//! once Lens rejects it with 401 or 403.
+
+use std::cmp::Reverse;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+const REMAINING_PER_MINUTE_HEADER: &str = "x-rate-limit-remaining-request-per-minute";
+const REMAINING_PER_MONTH_HEADER: &str = "x-rate-limit-remaining-request-per-month";
+
+/// How the pool picks the key of the next request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySelection {
+    /// Use the keys one after the other
+    #[default]
+    RoundRobin,
+    /// Use the key with the most remaining requests this minute, then this month.
+    /// Keys without any response yet come first.
+    MostRemaining,
+}
+
+/// Usage of one key of the pool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ApiKeyStats {
+    /// The last four characters of the key, enough to tell keys apart in logs
+    pub key_suffix: String,
+    /// Requests sent with this key
+    pub requests: u64,
+    /// Responses asking to retry later
+    pub rate_limited: u64,
+    pub remaining_per_minute: Option<u64>,
+    pub remaining_per_month: Option<u64>,
+    /// HTTP status that took the key out of rotation, if any
+    pub disabled_with_status: Option<u16>,
+}
+
+#[derive(Debug)]
+struct KeyState {
+    key: String,
+    stats: ApiKeyStats,
+    rate_limited_until: Option<Instant>,
+}
+
+impl KeyState {
+    fn is_enabled(&self) -> bool {
+        self.stats.disabled_with_status.is_none()
+    }
+
+    fn is_ready(&self, now: Instant) -> bool {
+        self.is_enabled() && self.rate_limited_until.is_none_or(|until| until <= now)
+    }
+
+    fn update_remaining(&mut self, headers: &HeaderMap) {
+        let read = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+
+        if let Some(remaining) = read(REMAINING_PER_MINUTE_HEADER) {
+            self.stats.remaining_per_minute = Some(remaining);
+        }
+        if let Some(remaining) = read(REMAINING_PER_MONTH_HEADER) {
+            self.stats.remaining_per_month = Some(remaining);
+        }
+    }
+}
+
+/// A key picked for one request, to report its outcome to the pool
+#[derive(Debug, Clone)]
+pub(crate) struct KeyLease {
+    index: usize,
+    pub(crate) key: String,
+}
+
+/// Pool of API keys, see the module documentation
+#[derive(Debug)]
+pub struct KeyPool {
+    selection: KeySelection,
+    keys: Mutex<Vec<KeyState>>,
+    next: AtomicUsize,
+}
+
+impl KeyPool {
+    /// Creates a pool from a list of keys. Empty and duplicated keys are ignored.
+    pub fn new<S: Into<String>>(
+        keys: impl IntoIterator<Item = S>,
+        selection: KeySelection,
+    ) -> Self {
+        let mut states: Vec<KeyState> = Vec::new();
+        for key in keys {
+            let key: String = key.into();
+            let key = key.trim().to_string();
+            if key.is_empty() || states.iter().any(|state| state.key == key) {
+                continue;
+            }
+
+            let key_suffix = key
+                .chars()
+                .rev()
+                .take(4)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .collect();
+
+            states.push(KeyState {
+                key,
+                stats: ApiKeyStats {
+                    key_suffix,
+                    ..Default::default()
+                },
+                rate_limited_until: None,
+            });
+        }
+
+        Self {
+            selection,
+            keys: Mutex::new(states),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of keys in the pool, disabled ones included.
+    pub fn len(&self) -> usize {
+        self.keys.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Usage of every key of the pool, in configuration order.
+    pub fn stats(&self) -> Vec<ApiKeyStats> {
+        self.keys
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|state| state.stats.clone())
+            .collect()
+    }
+
+    /// Picks the key of the next request, or `None` when every key has been disabled.
+    ///
+    /// Keys that are not rate limited are preferred. When they all are, the key that
+    /// gets out of its rate limit first is returned.
+    pub(crate) fn pick(&self) -> Option<KeyLease> {
+        let now = Instant::now();
+        let mut keys = self.keys.lock().unwrap();
+
+        let ready: Vec<usize> = (0..keys.len()).filter(|&i| keys[i].is_ready(now)).collect();
+
+        let index = if ready.is_empty() {
+            (0..keys.len())
+                .filter(|&i| keys[i].is_enabled())
+                .min_by_key(|&i| keys[i].rate_limited_until)?
+        } else {
+            match self.selection {
+                KeySelection::RoundRobin => {
+                    let turn = self.next.fetch_add(1, Ordering::Relaxed);
+                    ready[turn % ready.len()]
+                }
+                // Ties go to the first key, in configuration order
+                KeySelection::MostRemaining => *ready
+                    .iter()
+                    .min_by_key(|&&i| {
+                        Reverse((
+                            keys[i].stats.remaining_per_minute.unwrap_or(u64::MAX),
+                            keys[i].stats.remaining_per_month.unwrap_or(u64::MAX),
+                        ))
+                    })
+                    .expect("ready keys are not empty"),
+            }
+        };
+
+        keys[index].stats.requests += 1;
+        Some(KeyLease {
+            index,
+            key: keys[index].key.clone(),
+        })
+    }
+
+    /// Whether a key can be used right away, without waiting for a rate limit.
+    pub(crate) fn has_ready_key(&self) -> bool {
+        let now = Instant::now();
+        self.keys
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|state| state.is_ready(now))
+    }
+
+    /// Whether a key is still in rotation.
+    pub(crate) fn has_enabled_key(&self) -> bool {
+        self.keys.lock().unwrap().iter().any(KeyState::is_enabled)
+    }
+
+    pub(crate) fn record_success(&self, lease: &KeyLease, headers: &HeaderMap) {
+        let mut keys = self.keys.lock().unwrap();
+        let state = &mut keys[lease.index];
+        state.rate_limited_until = None;
+        state.update_remaining(headers);
+    }
+
+    pub(crate) fn record_rate_limited(
+        &self,
+        lease: &KeyLease,
+        retry_after: Duration,
+        headers: &HeaderMap,
+    ) {
+        let mut keys = self.keys.lock().unwrap();
+        let state = &mut keys[lease.index];
+        state.stats.rate_limited += 1;
+        state.rate_limited_until = Some(Instant::now() + retry_after);
+        state.update_remaining(headers);
+    }
+
+    /// Takes a key out of rotation after Lens rejected it.
+    pub(crate) fn disable(&self, lease: &KeyLease, status_code: u16) {
+        let mut keys = self.keys.lock().unwrap();
+        let state = &mut keys[lease.index];
+        log::error!(
+            "Lens rejected API key ending in {} with status {status_code}, removing it from rotation",
+            state.stats.key_suffix
+        );
+        state.stats.disabled_with_status = Some(status_code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lease_keys(pool: &KeyPool, count: usize) -> Vec<String> {
+        (0..count).map(|_| pool.pick().unwrap().key).collect()
+    }
+
+    #[test]
+    fn round_robin_skips_disabled_and_rate_limited_keys() {
+        let pool = KeyPool::new(
+            ["key-a", "key-b", "key-c", "key-a", ""],
+            KeySelection::RoundRobin,
+        );
+        assert_eq!(pool.len(), 3);
+        assert_eq!(lease_keys(&pool, 4), ["key-a", "key-b", "key-c", "key-a"]);
+
+        let lease_b = KeyLease {
+            index: 1,
+            key: "key-b".to_string(),
+        };
+        pool.disable(&lease_b, 401);
+
+        let lease_c = KeyLease {
+            index: 2,
+            key: "key-c".to_string(),
+        };
+        pool.record_rate_limited(&lease_c, Duration::from_secs(60), &HeaderMap::new());
+
+        assert_eq!(lease_keys(&pool, 3), ["key-a", "key-a", "key-a"]);
+
+        let stats = pool.stats();
+        assert_eq!(stats[0].key_suffix, "ey-a");
+        assert_eq!(stats[0].requests, 5);
+        assert_eq!(stats[1].disabled_with_status, Some(401));
+        assert_eq!(stats[2].rate_limited, 1);
+    }
+
+    #[test]
+    fn rate_limited_pool_returns_the_key_available_first() {
+        let pool = KeyPool::new(["key-a", "key-b"], KeySelection::RoundRobin);
+        let lease_a = pool.pick().unwrap();
+        let lease_b = pool.pick().unwrap();
+
+        pool.record_rate_limited(&lease_a, Duration::from_secs(60), &HeaderMap::new());
+        pool.record_rate_limited(&lease_b, Duration::from_secs(5), &HeaderMap::new());
+
+        assert!(!pool.has_ready_key());
+        assert_eq!(pool.pick().unwrap().key, "key-b");
+
+        pool.disable(&lease_a, 403);
+        pool.disable(&lease_b, 403);
+        assert!(pool.pick().is_none());
+    }
+
+    #[test]
+    fn most_remaining_prefers_unused_then_fullest_keys() {
+        let pool = KeyPool::new(["key-a", "key-b"], KeySelection::MostRemaining);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(REMAINING_PER_MINUTE_HEADER, "10".parse().unwrap());
+        let lease_a = pool.pick().unwrap();
+        pool.record_success(&lease_a, &headers);
+
+        // key-b has not answered yet, so its quota is presumed full
+        assert_eq!(pool.pick().unwrap().key, "key-b");
+
+        let lease_b = KeyLease {
+            index: 1,
+            key: "key-b".to_string(),
+        };
+        headers.insert(REMAINING_PER_MINUTE_HEADER, "3".parse().unwrap());
+        pool.record_success(&lease_b, &headers);
+
+        assert_eq!(pool.pick().unwrap().key, "key-a");
+        assert_eq!(pool.stats()[1].remaining_per_minute, Some(3));
+    }
+}
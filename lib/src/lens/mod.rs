@@ -4,9 +4,15 @@ pub mod citations;
 mod completion;
 pub mod counter;
 pub mod error;
-mod id_types;
+pub mod id_types;
+pub mod keys;
 pub mod lensid;
+pub mod prefetch;
 pub mod request;
+pub mod resolve;
+pub mod retry;
+#[cfg(test)]
+pub(crate) mod stub;
 
 pub use completion::complete_articles;
 pub use completion::complete_articles_by_raw_ids;
@@ -17,11 +23,13 @@ use crate::lens::citations::{
 
 use super::common::SearchFor;
 
+use crate::identifier::Identifier;
 use cache::CacheBackend;
 use counter::LensIdCounter;
 use error::LensError;
+use id_types::{IdType, TypedIdList};
 use lensid::LensId;
-use request::request_and_parse;
+use request::{LensClient, request_and_parse};
 use std::collections::{HashMap, HashSet};
 
 /// Estimates a probable output size for the snowballing process based on depth.
@@ -52,18 +60,19 @@ fn probable_output_size(max_depth: u8) -> usize {
 /// * `id_list`: A slice of items that can be referenced as strings (e.g., `&str`, `String`, `LensId`).
 /// * `search_for`: Specifies whether to search for references, citations, or both.
 /// * `api_key`: The API key for Lens.org.
-/// * `client`: An optional `reqwest::Client` to use for requests. If `None`, a new client is created.
+/// * `client`: An optional `LensClient` to use for requests. If `None`, a new client is created.
 ///
 /// # Returns
 ///
-/// A `Result` containing a vector of `LensId`s of the related articles, or a `LensError`.
+/// A `Result` containing a vector of `LensId`s of the related articles and whether some
+/// citations were incomplete, or a `LensError`.
 async fn request_references_and_citations<T>(
     id_list: &[T],
     search_for: &SearchFor,
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
     cache: Option<&dyn CacheBackend>,
-) -> Result<Vec<LensId>, LensError>
+) -> Result<(Vec<LensId>, bool), LensError>
 where
     T: AsRef<str>,
 {
@@ -73,6 +82,10 @@ where
         request_references_and_citations_with_parents(id_list, search_for, api_key, client, cache)
             .await?;
 
+    let incomplete_citations = parents_with_children
+        .iter()
+        .any(|pwc| pwc.incomplete_citations);
+
     // Flatten to just the children IDs
     let results: Vec<LensId> = parents_with_children
         .into_iter()
@@ -83,7 +96,7 @@ where
         return Err(LensError::NoArticlesFound);
     }
 
-    Ok(results)
+    Ok((results, incomplete_citations))
 }
 
 /// Requests references and/or citations while preserving parent-child relationships.
@@ -100,7 +113,7 @@ where
 /// * `id_list`: A slice of items that can be referenced as LensIds.
 /// * `search_for`: Specifies whether to search for references, citations, or both.
 /// * `api_key`: The API key for Lens.org.
-/// * `client`: An optional `reqwest::Client` to use for requests.
+/// * `client`: An optional `LensClient` to use for requests.
 ///
 /// # Returns
 ///
@@ -109,103 +122,35 @@ async fn request_references_and_citations_with_parents<T>(
     id_list: &[T],
     search_for: &SearchFor,
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
     cache: Option<&dyn CacheBackend>,
 ) -> Result<Vec<ArticleWithReferencesAndCitationsMerged>, LensError>
 where
     T: AsRef<str>,
 {
-    // Separate LensIds from non-LensIds (PMID, DOI, etc.)
-    let mut lens_ids: Vec<LensId> = Vec::new();
-    let mut non_lens_ids: Vec<String> = Vec::new();
-
-    for id in id_list {
-        let id_str = id.as_ref();
-        if let Ok(lens_id) = LensId::try_from(id_str) {
-            lens_ids.push(lens_id);
-        } else {
-            non_lens_ids.push(id_str.to_string());
-        }
-    }
-
-    // Validate that at least one ID has a recognized format
-    let has_valid_lens_ids = !lens_ids.is_empty();
-    let has_valid_pmids = non_lens_ids
-        .iter()
-        .any(|id| id.chars().all(|c| c.is_ascii_digit()));
-    let has_valid_dois = non_lens_ids.iter().any(|id| id.starts_with("10."));
-
-    if !has_valid_lens_ids && !has_valid_pmids && !has_valid_dois {
-        return Err(LensError::NoValidIdsInInputList);
-    }
+    // Separate LensIds from the other recognised ID types (PMID, DOI, PMCID, etc.)
+    let typed = TypedIdList::from_raw_id_list(id_list.iter().map(|id| id.as_ref()))?;
 
-    // If no cache, fall back to direct HTTP (categorize and chunk by type)
+    // If no cache, fall back to direct HTTP (chunk by type)
     let Some(cache_backend) = cache else {
         let mut all_results = Vec::new();
 
-        // Separate by type for no-cache path
-        let mut lens_id_strs = Vec::new();
-        let mut pmid_strs = Vec::new();
-        let mut doi_strs = Vec::new();
-
-        for id in id_list {
-            let id_str = id.as_ref();
-            if LensId::try_from(id_str).is_ok() {
-                lens_id_strs.push(id_str);
-            } else if id_str.starts_with("10.") {
-                doi_strs.push(id_str);
-            } else if id_str.chars().all(|c| c.is_ascii_digit()) {
-                pmid_strs.push(id_str);
-            }
-        }
-
-        // Fetch each type separately
-        if !lens_id_strs.is_empty() {
-            let results = futures::future::join_all(lens_id_strs.chunks(1000).map(|chunk| {
-                request_references_and_citations_with_parents_chunk(
-                    chunk, "lens_id", search_for, api_key, client, None,
-                )
-            }))
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, LensError>>()?
-            .into_iter()
-            .flatten()
-            .map(ArticleWithReferencesAndCitationsMerged::from)
-            .collect::<Vec<_>>();
-            all_results.extend(results);
-        }
-
-        if !pmid_strs.is_empty() {
-            let results = futures::future::join_all(pmid_strs.chunks(1000).map(|chunk| {
-                request_references_and_citations_with_parents_chunk(
-                    chunk, "pmid", search_for, api_key, client, None,
-                )
-            }))
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, LensError>>()?
-            .into_iter()
-            .flatten()
-            .map(ArticleWithReferencesAndCitationsMerged::from)
-            .collect::<Vec<_>>();
-            all_results.extend(results);
-        }
-
-        if !doi_strs.is_empty() {
-            let results = futures::future::join_all(doi_strs.chunks(1000).map(|chunk| {
-                request_references_and_citations_with_parents_chunk(
-                    chunk, "doi", search_for, api_key, client, None,
-                )
-            }))
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, LensError>>()?
-            .into_iter()
-            .flatten()
-            .map(ArticleWithReferencesAndCitationsMerged::from)
-            .collect::<Vec<_>>();
-            all_results.extend(results);
+        for (id_type, ids) in typed.groups() {
+            let query_values: Vec<&str> = ids.iter().map(Identifier::value).collect();
+            let results = request_references_and_citations_chunked(
+                &query_values,
+                id_type,
+                search_for,
+                api_key,
+                client,
+                None,
+            )
+            .await?;
+            all_results.extend(
+                results
+                    .into_iter()
+                    .map(ArticleWithReferencesAndCitationsMerged::from),
+            );
         }
 
         if all_results.is_empty() {
@@ -215,6 +160,18 @@ where
         return Ok(all_results);
     };
 
+    let lens_ids: Vec<LensId> = typed
+        .lens_id
+        .iter()
+        .filter_map(|id| LensId::try_from(id.value()).ok())
+        .collect();
+
+    // Non-LensIds are looked up under their canonical id_mappings key
+    let non_lens_ids: Vec<String> = typed
+        .external_groups()
+        .flat_map(|(_, ids)| ids.iter().map(Identifier::key))
+        .collect();
+
     // Resolve non-LensIds to LensIds via mappings FIRST
     let non_lens_id_mappings = if !non_lens_ids.is_empty() {
         cache_backend.get_id_mapping(&non_lens_ids).await?
@@ -230,24 +187,24 @@ where
         .collect();
 
     // Single cache query for all LensIds (original + mapped)
-    let (cached_refs, cached_cites) = if !all_lens_ids.is_empty() {
+    let (cached_refs, (cached_cites, incomplete_cites)) = if !all_lens_ids.is_empty() {
         match search_for {
             SearchFor::References => {
                 let refs = cache_backend.get_references(&all_lens_ids).await?;
-                (refs, HashMap::new())
+                (refs, Default::default())
             }
             SearchFor::Citations => {
-                let cites = cache_backend.get_citations(&all_lens_ids).await?;
+                let cites = get_cached_citations(cache_backend, &all_lens_ids).await?;
                 (HashMap::new(), cites)
             }
             SearchFor::Both => {
                 let refs = cache_backend.get_references(&all_lens_ids).await?;
-                let cites = cache_backend.get_citations(&all_lens_ids).await?;
+                let cites = get_cached_citations(cache_backend, &all_lens_ids).await?;
                 (refs, cites)
             }
         }
     } else {
-        (HashMap::new(), HashMap::new())
+        Default::default()
     };
 
     // Determine which LensIds have complete cache hits
@@ -297,6 +254,7 @@ where
             ArticleWithReferencesAndCitationsMerged {
                 parent_id: id.clone(),
                 children,
+                incomplete_citations: incomplete_cites.contains(id),
             }
         })
         .collect();
@@ -318,6 +276,7 @@ where
             results.push(ArticleWithReferencesAndCitationsMerged {
                 parent_id: parent_id.clone(),
                 children,
+                incomplete_citations: incomplete_cites.contains(parent_id),
             });
         }
     }
@@ -407,6 +366,7 @@ where
                 waited_results.push(ArticleWithReferencesAndCitationsMerged {
                     parent_id: lens_id.clone(),
                     children,
+                    incomplete_citations: false,
                 });
             } else {
                 // Still not in cache after waiting, need to fetch from API
@@ -425,284 +385,44 @@ where
         .map(|id| id.as_ref().to_string())
         .collect();
 
-    // Non-LensIds: separate PMIDs and DOIs from non_lens_id_misses
-    let mut pmids_to_fetch = Vec::new();
-    let mut dois_to_fetch = Vec::new();
-
-    for id_str in &non_lens_id_misses {
-        if id_str.starts_with("10.") {
-            dois_to_fetch.push(id_str.clone());
-        } else if id_str.chars().all(|c| c.is_ascii_digit()) {
-            pmids_to_fetch.push(id_str.clone());
-        } else {
-            // Unknown format, skip (will be caught by API)
-            continue;
-        }
-    }
-
     // Fetch from API by type
     let mut fetched_results = Vec::new();
 
-    // Fetch LensIds
     if !lens_ids_to_fetch.is_empty() {
         let lens_id_refs: Vec<&str> = lens_ids_to_fetch.iter().map(|s| s.as_str()).collect();
-
-        // Fetch from API (single call per chunk, even for SearchFor::Both)
-        let articles_results = futures::future::join_all(lens_id_refs.chunks(1000).map(|chunk| {
-            request_references_and_citations_with_parents_chunk(
-                chunk,
-                "lens_id",
+        fetched_results.extend(
+            fetch_and_store_references_and_citations(
+                &lens_id_refs,
+                IdType::LensId,
                 search_for,
                 api_key,
                 client,
-                Some(cache_backend),
+                cache_backend,
             )
-        }))
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>, LensError>>()?
-        .into_iter()
-        .flatten()
-        .collect::<Vec<ArticleWithReferencesAndCitations>>();
-
-        // Store in cache (split refs and cites for SearchFor::Both)
-        if !articles_results.is_empty() {
-            match search_for {
-                SearchFor::References => {
-                    let refs_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.references.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_references(&refs_batch).await?;
-                }
-                SearchFor::Citations => {
-                    let cites_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.scholarly_citations.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_citations(&cites_batch).await?;
-                }
-                SearchFor::Both => {
-                    // Store refs and cites separately
-                    let refs_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.references.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_references(&refs_batch).await?;
-
-                    let cites_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.scholarly_citations.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_citations(&cites_batch).await?;
-                }
-            }
-        }
-
-        // Convert to merged results
-        let lens_results: Vec<ArticleWithReferencesAndCitationsMerged> = articles_results
-            .into_iter()
-            .map(ArticleWithReferencesAndCitationsMerged::from)
-            .collect();
-
-        fetched_results.extend(lens_results);
-    }
-
-    // Fetch PMIDs
-    if !pmids_to_fetch.is_empty() {
-        let pmid_refs: Vec<&str> = pmids_to_fetch.iter().map(|s| s.as_str()).collect();
-
-        // Fetch from API (single call per chunk)
-        let articles_results = futures::future::join_all(pmid_refs.chunks(1000).map(|chunk| {
-            request_references_and_citations_with_parents_chunk(
-                chunk,
-                "pmid",
-                search_for,
-                api_key,
-                client,
-                Some(cache_backend),
-            )
-        }))
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>, LensError>>()?
-        .into_iter()
-        .flatten()
-        .collect::<Vec<ArticleWithReferencesAndCitations>>();
-
-        // Store in cache (split refs and cites for SearchFor::Both)
-        if !articles_results.is_empty() {
-            match search_for {
-                SearchFor::References => {
-                    let refs_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.references.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_references(&refs_batch).await?;
-                }
-                SearchFor::Citations => {
-                    let cites_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.scholarly_citations.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_citations(&cites_batch).await?;
-                }
-                SearchFor::Both => {
-                    // Store refs and cites separately
-                    let refs_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.references.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_references(&refs_batch).await?;
-
-                    let cites_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.scholarly_citations.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_citations(&cites_batch).await?;
-                }
-            }
-        }
-
-        // Convert to merged results
-        let pmid_results: Vec<ArticleWithReferencesAndCitationsMerged> = articles_results
-            .into_iter()
-            .map(ArticleWithReferencesAndCitationsMerged::from)
-            .collect();
-
-        fetched_results.extend(pmid_results);
+            .await?,
+        );
     }
 
-    // Fetch DOIs
-    if !dois_to_fetch.is_empty() {
-        let doi_refs: Vec<&str> = dois_to_fetch.iter().map(|s| s.as_str()).collect();
-
-        // Fetch from API (single call per chunk)
-        let articles_results = futures::future::join_all(doi_refs.chunks(1000).map(|chunk| {
-            request_references_and_citations_with_parents_chunk(
-                chunk,
-                "doi",
+    // Non-LensId misses, grouped by type. Mapping keys are valid IDs of their own type.
+    let misses = TypedIdList::from_raw_id_list(non_lens_id_misses.iter().map(|s| s.as_str()))
+        .unwrap_or_default();
+    for (id_type, ids) in misses.external_groups() {
+        let query_values: Vec<&str> = ids.iter().map(Identifier::value).collect();
+        fetched_results.extend(
+            fetch_and_store_references_and_citations(
+                &query_values,
+                id_type,
                 search_for,
                 api_key,
                 client,
-                Some(cache_backend),
+                cache_backend,
             )
-        }))
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>, LensError>>()?
-        .into_iter()
-        .flatten()
-        .collect::<Vec<ArticleWithReferencesAndCitations>>();
-
-        // Store in cache (split refs and cites for SearchFor::Both)
-        if !articles_results.is_empty() {
-            match search_for {
-                SearchFor::References => {
-                    let refs_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.references.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_references(&refs_batch).await?;
-                }
-                SearchFor::Citations => {
-                    let cites_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.scholarly_citations.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_citations(&cites_batch).await?;
-                }
-                SearchFor::Both => {
-                    // Store refs and cites separately
-                    let refs_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.references.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_references(&refs_batch).await?;
-
-                    let cites_batch: Vec<(LensId, Vec<LensId>)> = articles_results
-                        .iter()
-                        .map(|article| {
-                            (
-                                article.lens_id.clone(),
-                                article.refs_and_cites.scholarly_citations.0.clone(),
-                            )
-                        })
-                        .collect();
-                    cache_backend.store_citations(&cites_batch).await?;
-                }
-            }
-        }
-
-        // Convert to merged results
-        let doi_results: Vec<ArticleWithReferencesAndCitationsMerged> = articles_results
-            .into_iter()
-            .map(ArticleWithReferencesAndCitationsMerged::from)
-            .collect();
-
-        fetched_results.extend(doi_results);
+            .await?,
+        );
     }
 
     // Unmark fetched IDs (both successful and failed)
-    let fetched_lens_ids: Vec<LensId> = ids_to_fetch
-        .into_iter()
-        .chain(still_missing.into_iter())
-        .collect();
+    let fetched_lens_ids: Vec<LensId> = ids_to_fetch.into_iter().chain(still_missing).collect();
     if !fetched_lens_ids.is_empty() {
         let _ = cache_backend
             .unmark_as_fetching_batch(&fetched_lens_ids)
@@ -718,6 +438,111 @@ where
     Ok(results)
 }
 
+/// Fetches the references and/or citations of IDs of a single type in chunks of 1000,
+/// and stores them in the cache (refs and cites separately for `SearchFor::Both`).
+async fn fetch_and_store_references_and_citations(
+    ids: &[&str],
+    id_type: IdType,
+    search_for: &SearchFor,
+    api_key: &str,
+    client: Option<&LensClient>,
+    cache_backend: &dyn CacheBackend,
+) -> Result<Vec<ArticleWithReferencesAndCitationsMerged>, LensError> {
+    let articles_results = request_references_and_citations_chunked(
+        ids,
+        id_type,
+        search_for,
+        api_key,
+        client,
+        Some(cache_backend),
+    )
+    .await?;
+
+    if !articles_results.is_empty() {
+        if matches!(search_for, SearchFor::References | SearchFor::Both) {
+            let refs_batch: Vec<(LensId, Vec<LensId>)> = articles_results
+                .iter()
+                .map(|article| {
+                    (
+                        article.lens_id.clone(),
+                        article.refs_and_cites.references.0.clone(),
+                    )
+                })
+                .collect();
+            cache_backend.store_references(&refs_batch).await?;
+        }
+
+        if matches!(search_for, SearchFor::Citations | SearchFor::Both) {
+            let cites_batch: Vec<(LensId, Vec<LensId>)> = articles_results
+                .iter()
+                .map(|article| {
+                    (
+                        article.lens_id.clone(),
+                        article.refs_and_cites.scholarly_citations.0.clone(),
+                    )
+                })
+                .collect();
+            cache_backend.store_citations(&cites_batch).await?;
+        }
+    }
+
+    Ok(articles_results
+        .into_iter()
+        .map(ArticleWithReferencesAndCitationsMerged::from)
+        .collect())
+}
+
+/// Requests IDs of a single type in chunks of 1000, the Lens API limit.
+async fn request_references_and_citations_chunked(
+    ids: &[&str],
+    id_type: IdType,
+    search_for: &SearchFor,
+    api_key: &str,
+    client: Option<&LensClient>,
+    cache: Option<&dyn CacheBackend>,
+) -> Result<Vec<ArticleWithReferencesAndCitations>, LensError> {
+    Ok(futures::future::join_all(ids.chunks(1000).map(|chunk| {
+        request_references_and_citations_with_parents_chunk(
+            chunk, id_type, search_for, api_key, client, cache,
+        )
+    }))
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, LensError>>()?
+    .into_iter()
+    .flatten()
+    .collect())
+}
+
+/// Retrieve cached citations, including the incomplete ones rebuilt from the
+/// cited-by index when the backend opted into `cited_by_fallback`.
+/// Also returns the IDs of which the citations are incomplete.
+async fn get_cached_citations(
+    cache: &dyn CacheBackend,
+    ids: &[LensId],
+) -> Result<(HashMap<LensId, Vec<LensId>>, HashSet<LensId>), LensError> {
+    let citations = cache.get_citations_or_cited_by(ids).await?;
+
+    let incomplete: HashSet<LensId> = citations
+        .iter()
+        .filter(|(_, cached)| !cached.complete)
+        .map(|(id, _)| id.clone())
+        .collect();
+    if !incomplete.is_empty() {
+        log::info!(
+            "Using incomplete citations rebuilt from cached references for {} articles",
+            incomplete.len()
+        );
+    }
+
+    let citations = citations
+        .into_iter()
+        .map(|(id, cached)| (id, cached.citations))
+        .collect();
+
+    Ok((citations, incomplete))
+}
+
 /// Wait for an ID to be fetched by another caller, with timeout.
 ///
 /// Polls the cache every 100ms to check if the data has appeared.
@@ -736,16 +561,16 @@ async fn wait_for_fetch_completion(
         // Check if data has appeared in cache
         let found = match search_for {
             SearchFor::References => {
-                let result = cache.get_references(&[lens_id.clone()]).await?;
+                let result = cache.get_references(std::slice::from_ref(lens_id)).await?;
                 result.contains_key(lens_id)
             }
             SearchFor::Citations => {
-                let result = cache.get_citations(&[lens_id.clone()]).await?;
+                let result = cache.get_citations(std::slice::from_ref(lens_id)).await?;
                 result.contains_key(lens_id)
             }
             SearchFor::Both => {
-                let refs = cache.get_references(&[lens_id.clone()]).await?;
-                let cites = cache.get_citations(&[lens_id.clone()]).await?;
+                let refs = cache.get_references(std::slice::from_ref(lens_id)).await?;
+                let cites = cache.get_citations(std::slice::from_ref(lens_id)).await?;
                 refs.contains_key(lens_id) && cites.contains_key(lens_id)
             }
         };
@@ -772,10 +597,10 @@ async fn wait_for_fetch_completion(
 /// Returns unmerged results so parent can store refs and cites separately in cache.
 async fn request_references_and_citations_with_parents_chunk<T>(
     id_list: &[T],
-    id_type: &str, // "lens_id", "pmid", or "doi"
+    id_type: IdType,
     search_for: &SearchFor,
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
     cache: Option<&dyn CacheBackend>,
 ) -> Result<Vec<ArticleWithReferencesAndCitations>, LensError>
 where
@@ -783,7 +608,7 @@ where
 {
     let client = match client {
         Some(t) => t,
-        None => &reqwest::Client::new(),
+        None => &LensClient::default(),
     };
 
     // Determine which fields to include based on the search direction
@@ -793,8 +618,8 @@ where
         SearchFor::References => vec!["lens_id", "references"],
     };
 
-    // For non-LensId types (PMID, DOI), include external_ids to populate id_mappings
-    if id_type != "lens_id" {
+    // For non-LensId types (PMID, DOI, etc.), include external_ids to populate id_mappings
+    if id_type != IdType::LensId {
         include.push("external_ids");
     }
 
@@ -803,15 +628,24 @@ where
 
     // Make API request with specified ID type
     let articles: Vec<ArticleWithReferencesAndCitations> =
-        request_and_parse(client, api_key, &id_refs, id_type, &include).await?;
+        request_and_parse(client, api_key, &id_refs, id_type.lens_field(), &include).await?;
 
-    // Store any ID mappings (for PMIDs/DOIs)
+    // Store any ID mappings (for PMIDs, DOIs, etc.)
     ArticleWithReferencesAndCitations::store_any_mappings(&articles, cache).await?;
 
     // Return unmerged articles so parent can store refs and cites separately
     Ok(articles)
 }
 
+/// Occurrence counts found by [`snowball`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SnowballCounts {
+    pub counts: LensIdCounter,
+    /// Some citations were rebuilt from the cited-by index of the cache (see
+    /// `cited_by_fallback`), so articles citing the visited ones may be missing
+    pub incomplete_citations: bool,
+}
+
 /// Optimized snowball function that deduplicates API requests.
 ///
 /// This function performs the same citation expansion as `snowball`, but with
@@ -832,19 +666,19 @@ where
 /// * `max_depth`: The maximum depth of the snowballing process.
 /// * `search_for`: Specifies whether to search for references, citations, or both.
 /// * `api_key`: The API key for Lens.org.
-/// * `client`: An optional `reqwest::Client` to use for requests.
+/// * `client`: An optional `LensClient` to use for requests.
 ///
 /// # Returns
 ///
-/// A `Result` containing a `LensIdCounter` with occurrence counts, or a `LensError`.
+/// A `Result` containing the `SnowballCounts` with occurrence counts, or a `LensError`.
 pub async fn snowball<T>(
     src_lensid: &[T],
     max_depth: u8,
     search_for: &SearchFor,
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
     cache: Option<&dyn CacheBackend>,
-) -> Result<LensIdCounter, LensError>
+) -> Result<SnowballCounts, LensError>
 where
     T: AsRef<str>,
 {
@@ -852,7 +686,7 @@ where
     let mut all_counts = LensIdCounter::with_capacity(probable_output_size(max_depth));
 
     // Start with depth 1: direct references/citations of the source IDs
-    let depth1_results =
+    let (depth1_results, mut incomplete_citations) =
         request_references_and_citations(src_lensid, search_for, api_key, client, cache).await?;
     let mut current_counts = LensIdCounter::from(depth1_results);
 
@@ -883,6 +717,7 @@ where
         // MULTIPLICATION: each child inherits the parent's count
         // If parent appears 5 times and cites child D, then D gets +5 to its count
         for parent_with_children in parents_with_children {
+            incomplete_citations |= parent_with_children.incomplete_citations;
             let parent_count = current_counts.get(&parent_with_children.parent_id);
 
             for child_id in parent_with_children.children {
@@ -895,7 +730,10 @@ where
         current_counts = next_counts;
     }
 
-    Ok(all_counts)
+    Ok(SnowballCounts {
+        counts: all_counts,
+        incomplete_citations,
+    })
 }
 
 #[cfg(test)]
@@ -914,11 +752,18 @@ mod tests {
     #[tokio::test]
     async fn snowball_fail_invalid_ids() {
         let id_list = ["I AM AN INVALID ID", "I AM AN INVALID ID TOO"];
-        let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
-        let client = reqwest::Client::new();
-        let error = snowball(&id_list, 2, &SearchFor::Both, &api_key, Some(&client), None)
-            .await
-            .unwrap_err();
+        let stub = crate::lens::stub::StubLens::start().await;
+        let client = stub.client();
+        let error = snowball(
+            &id_list,
+            2,
+            &SearchFor::Both,
+            "stub-key",
+            Some(&client),
+            None,
+        )
+        .await
+        .unwrap_err();
 
         match error {
             LensError::NoValidIdsInInputList => (),
@@ -930,11 +775,18 @@ mod tests {
     #[tokio::test]
     async fn snowball_fail_valid_but_nonexistent() {
         let id_list = ["10.9999/invalid.doi"];
-        let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
-        let client = reqwest::Client::new();
-        let error = snowball(&id_list, 2, &SearchFor::Both, &api_key, Some(&client), None)
-            .await
-            .unwrap_err();
+        let stub = crate::lens::stub::StubLens::start().await;
+        let client = stub.client();
+        let error = snowball(
+            &id_list,
+            2,
+            &SearchFor::Both,
+            "stub-key",
+            Some(&client),
+            None,
+        )
+        .await
+        .unwrap_err();
 
         match error {
             LensError::NoArticlesFound => (),
@@ -944,6 +796,7 @@ mod tests {
 
     /// Tests the `snowball` function by expanding a network from seed IDs.
     #[tokio::test]
+    #[ignore = "needs LENS_API_KEY"]
     async fn snowball_test() {
         let id_list = [
             "020-200-401-307-33X",
@@ -952,16 +805,16 @@ mod tests {
             "10.1016/j.nephro.2007.05.005",
         ];
         let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
-        let client = reqwest::Client::new();
+        let client = LensClient::default();
         let new_id = snowball(&id_list, 2, &SearchFor::Both, &api_key, Some(&client), None)
             .await
             .unwrap();
 
-        println!("Articles found : {}", new_id.len());
+        println!("Articles found : {}", new_id.counts.len());
         // Assertions based on expected results from the API for these specific IDs and depth
-        assert!(new_id.len() >= 14080);
+        assert!(new_id.counts.len() >= 14080);
 
-        let score_hashmap = new_id.into_inner();
+        let score_hashmap = new_id.counts.into_inner();
 
         let max_score_lens_id = score_hashmap.iter().max_by_key(|entry| entry.1).unwrap();
         println!(
@@ -987,11 +840,12 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore = "needs LENS_API_KEY"]
     async fn depth1_snowball() {
         let id_list = ["10.1111/j.1468-0262.2006.00668.x"];
 
         let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
-        let client = reqwest::Client::new();
+        let client = LensClient::default();
         let direct_citations = snowball(
             &id_list,
             1,
@@ -1003,7 +857,7 @@ mod tests {
         .await
         .unwrap();
 
-        assert!(direct_citations.len() == citations::MAX_RELATIONSHIPS_PER_ARTICLE);
+        assert!(direct_citations.counts.len() == citations::MAX_RELATIONSHIPS_PER_ARTICLE);
 
         let direct_references = snowball(
             &id_list,
@@ -1016,9 +870,9 @@ mod tests {
         .await
         .unwrap();
 
-        println!("Direct references : {}", direct_references.len());
+        println!("Direct references : {}", direct_references.counts.len());
 
-        assert!(direct_references.len() >= 74);
+        assert!(direct_references.counts.len() >= 74);
     }
 
     /// Tests snowball with Postgres cache integration.
@@ -1028,13 +882,14 @@ mod tests {
     /// 2. Second call uses cache (cache hit)
     /// 3. Results are identical with and without cache
     #[tokio::test]
+    #[ignore = "needs LENS_API_KEY"]
     #[cfg(feature = "cache-postgres")]
     async fn snowball_with_postgres() {
         use crate::lens::cache::postgres::PostgresBackend;
         use std::time::Instant;
 
         let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
-        let client = reqwest::Client::new();
+        let client = LensClient::default();
         let id_list = ["020-200-401-307-33X", "050-708-976-791-252"];
 
         // Create test backend with unique schema
@@ -1079,7 +934,11 @@ mod tests {
         .await
         .unwrap();
         let first_duration = start.elapsed();
-        println!("  Took: {:?}, Found {} IDs", first_duration, result1.len());
+        println!(
+            "  Took: {:?}, Found {} IDs",
+            first_duration,
+            result1.counts.len()
+        );
 
         // Second call - should use cache (much faster)
         println!("Second snowball call (using cache)...");
@@ -1095,12 +954,16 @@ mod tests {
         .await
         .unwrap();
         let cached_duration = start.elapsed();
-        println!("  Took: {:?}, Found {} IDs", cached_duration, result2.len());
+        println!(
+            "  Took: {:?}, Found {} IDs",
+            cached_duration,
+            result2.counts.len()
+        );
 
         // Results should have same number of IDs
         assert_eq!(
-            result1.len(),
-            result2.len(),
+            result1.counts.len(),
+            result2.counts.len(),
             "Cached and uncached results should have same number of IDs"
         );
 
@@ -1148,11 +1011,11 @@ mod tests {
     async fn test_pmid_twice_with_cache() {
         use crate::lens::cache::sqlite::SqliteBackend;
 
-        let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
-        let client = reqwest::Client::new();
+        let stub = crate::lens::stub::StubLens::start().await;
+        let client = stub.client();
 
         // Use PMID as input (not a LensId)
-        let pmid = "30507730";
+        let pmid = "30000001";
         let id_list = [pmid];
 
         // Create in-memory SQLite cache
@@ -1166,41 +1029,47 @@ mod tests {
             &id_list,
             1,
             &SearchFor::Both,
-            &api_key,
+            "stub-key",
             Some(&client),
             Some(&cache),
         )
         .await
         .expect("First snowball call failed");
 
-        println!("  Found {} IDs", result1.len());
+        println!("  Found {} IDs", result1.counts.len());
         assert!(
-            !result1.is_empty(),
+            !result1.counts.is_empty(),
             "Should find some results on first call"
         );
 
         // Second call - should use cache
         println!("Second snowball call with PMID (using cache)...");
+        let requests = stub.request_count();
         let result2 = snowball(
             &id_list,
             1,
             &SearchFor::Both,
-            &api_key,
+            "stub-key",
             Some(&client),
             Some(&cache),
         )
         .await
         .expect("Second snowball call failed");
 
-        println!("  Found {} IDs", result2.len());
+        println!("  Found {} IDs", result2.counts.len());
 
         // Results should be identical
         assert_eq!(
-            result1.len(),
-            result2.len(),
+            result1.counts.len(),
+            result2.counts.len(),
             "Cached and uncached results should have same number of IDs"
         );
         assert_eq!(result1, result2, "Cached results should match first call");
+        assert_eq!(
+            stub.request_count(),
+            requests,
+            "Second call should hit the cache"
+        );
 
         println!("✓ PMID can be requested twice with cache!");
     }
@@ -1211,7 +1080,7 @@ mod tests {
     async fn test_id_mapping_population_for_non_lens_id() {
         use crate::lens::cache::sqlite::SqliteBackend;
 
-        let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
+        let stub = crate::lens::stub::StubLens::start().await;
 
         // Create an in-memory cache
         let cache = SqliteBackend::from_url("sqlite::memory:")
@@ -1219,15 +1088,15 @@ mod tests {
             .expect("Failed to create cache backend");
 
         // Use a PMID (non-LensId input)
-        let pmid = "11748933"; // This is a real PMID
+        let pmid = "30000001";
         let ids = vec![pmid];
 
         // First call - should fetch from API and populate both references/citations AND id_mappings
         let result1 = request_references_and_citations_with_parents(
             &ids,
             &SearchFor::References,
-            &api_key,
-            None,
+            "stub-key",
+            Some(&stub.client()),
             Some(&cache),
         )
         .await
@@ -1262,7 +1131,7 @@ mod tests {
     async fn test_no_id_mapping_for_lens_id() {
         use crate::lens::cache::sqlite::SqliteBackend;
 
-        let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
+        let stub = crate::lens::stub::StubLens::start().await;
 
         // Create an in-memory cache
         let cache = SqliteBackend::from_url("sqlite::memory:")
@@ -1270,15 +1139,15 @@ mod tests {
             .expect("Failed to create cache backend");
 
         // Use a LensId directly
-        let lens_id_str = "005-371-014-653-198"; // Valid LensId
+        let lens_id_str = STUB_A;
         let ids = vec![lens_id_str];
 
         // Fetch references using LensId
         let result = request_references_and_citations_with_parents(
             &ids,
             &SearchFor::References,
-            &api_key,
-            None,
+            "stub-key",
+            Some(&stub.client()),
             Some(&cache),
         )
         .await
@@ -1309,7 +1178,7 @@ mod tests {
         use crate::lens::cache::sqlite::SqliteBackend;
         use std::time::Duration;
 
-        let api_key = dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file");
+        let stub = crate::lens::stub::StubLens::start().await;
 
         // Create an in-memory cache
         let cache = SqliteBackend::from_url("sqlite::memory:")
@@ -1317,16 +1186,16 @@ mod tests {
             .expect("Failed to create cache backend");
 
         // Use a known PMID
-        let pmid = "11748933";
+        let pmid = "30000001";
         let ids = vec![pmid];
 
         // Step 1: Populate cache with normal client (online)
-        println!("Step 1: Populating cache with normal client (online)...");
-        let normal_client = reqwest::Client::new();
+        println!("Step 1: Populating cache with the stub client (online)...");
+        let normal_client = stub.client();
         let result1 = request_references_and_citations_with_parents(
             &ids,
             &SearchFor::References,
-            &api_key,
+            "stub-key",
             Some(&normal_client),
             Some(&cache),
         )
@@ -1338,11 +1207,17 @@ mod tests {
 
         // Step 2: Create a broken client that cannot make network requests
         println!("Step 2: Creating broken client (simulating offline)...");
-        let broken_client = reqwest::Client::builder()
-            .proxy(reqwest::Proxy::all("http://0.0.0.0:1").expect("Failed to create invalid proxy"))
-            .timeout(Duration::from_secs(1)) // Fast timeout for quick failure
-            .build()
-            .expect("Failed to build broken client");
+        let broken_client = LensClient::from(
+            reqwest::Client::builder()
+                .proxy(
+                    reqwest::Proxy::all("http://0.0.0.0:1")
+                        .expect("Failed to create invalid proxy"),
+                )
+                .timeout(Duration::from_secs(1)) // Fast timeout for quick failure
+                .build()
+                .expect("Failed to build broken client"),
+        )
+        .with_retry_policy(crate::lens::retry::RetryPolicy::none());
 
         println!("  ✓ Client configured to fail all network requests");
 
@@ -1351,7 +1226,7 @@ mod tests {
         let verification_result = request_references_and_citations_with_parents(
             &[pmid],
             &SearchFor::References,
-            &api_key,
+            "stub-key",
             Some(&broken_client),
             None,
         )
@@ -1368,7 +1243,7 @@ mod tests {
         let result2 = request_references_and_citations_with_parents(
             &ids,
             &SearchFor::References,
-            &api_key,
+            "stub-key",
             Some(&broken_client),
             Some(&cache),
         )
@@ -1388,4 +1263,257 @@ mod tests {
         println!("  ✓ Query succeeded using only cache (no network access)");
         println!("\n✓ OFFLINE TEST PASSED: System works without network when cache is populated!");
     }
+
+    const STUB_A: &str = "565-646-398-006-312";
+    const STUB_B: &str = "077-974-340-045-600";
+    const STUB_D: &str = "830-182-661-063-60X";
+    const STUB_H: &str = "348-704-121-230-237";
+
+    fn stub_id(id: &str) -> LensId {
+        LensId::try_from(id).expect("Invalid fixture Lens ID")
+    }
+
+    /// Snowballs over the recorded fixtures, seeded with a PMID and a DOI in non-canonical case.
+    #[tokio::test]
+    async fn snowball_with_stub_lens() -> Result<(), LensError> {
+        let stub = crate::lens::stub::StubLens::start().await;
+        let client = stub.client();
+
+        let counts = snowball(
+            &["30000001", "10.1000/STUB.B"],
+            2,
+            &SearchFor::Both,
+            "stub-key",
+            Some(&client),
+            None,
+        )
+        .await?;
+
+        // D is referenced by both seeds, B is reached through five depth-1 paths
+        assert_eq!(counts.counts.get(&stub_id(STUB_D)), 2);
+        assert_eq!(counts.counts.get(&stub_id(STUB_B)), 6);
+        assert_eq!(counts.counts.get(&stub_id(STUB_H)), 3);
+        assert_eq!(counts.counts.len(), 9);
+
+        Ok(())
+    }
+
+    /// PMCID, arXiv, OpenAlex, MAG and CORE seeds resolve like the Lens IDs they map to,
+    /// and their mappings let a second snowball skip Lens entirely.
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn snowball_with_stub_lens_other_id_types() -> Result<(), LensError> {
+        use crate::lens::cache::sqlite::SqliteBackend;
+
+        let stub = crate::lens::stub::StubLens::start().await;
+        let client = stub.client();
+        let cache = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let by_lens_id = snowball(
+            &[STUB_A, STUB_B],
+            1,
+            &SearchFor::Both,
+            "stub-key",
+            Some(&client),
+            None,
+        )
+        .await?;
+
+        for ids in [
+            ["PMC7000001", "arXiv:2101.00002v1"],
+            ["https://openalex.org/W3000000001", "core:82573464"],
+            ["mag:3000000001", "core:82573464"],
+        ] {
+            let counts = snowball(
+                &ids,
+                1,
+                &SearchFor::Both,
+                "stub-key",
+                Some(&client),
+                Some(&cache),
+            )
+            .await?;
+            assert_eq!(counts, by_lens_id, "{ids:?}");
+        }
+
+        let mappings = cache
+            .get_id_mapping(&["mag:3000000001".to_string(), "3000000001".to_string()])
+            .await?;
+        assert_eq!(mappings.get("mag:3000000001"), Some(&stub_id(STUB_A)));
+        assert!(!mappings.contains_key("3000000001"));
+
+        // Both articles are cached and every ID type is mapped under its canonical key, so
+        // other spellings of the same IDs need no further request
+        let requests = stub.request_count();
+        snowball(
+            &[
+                "pmc7000001",
+                "arXiv:2101.00002v3",
+                "https://openalex.org/w3000000001",
+                "doi:10.1000/STUB.A.",
+            ],
+            1,
+            &SearchFor::Both,
+            "stub-key",
+            Some(&client),
+            Some(&cache),
+        )
+        .await?;
+        assert_eq!(stub.request_count(), requests);
+
+        Ok(())
+    }
+
+    /// Rate limiting in the middle of a snowball only delays it.
+    #[tokio::test]
+    async fn snowball_with_stub_lens_rate_limited() -> Result<(), LensError> {
+        let stub = crate::lens::stub::StubLens::start().await;
+        let client = stub.client();
+        stub.rate_limit_next(1, 1);
+
+        let counts = snowball(
+            &[STUB_A],
+            1,
+            &SearchFor::References,
+            "stub-key",
+            Some(&client),
+            None,
+        )
+        .await?;
+
+        assert_eq!(counts.counts.len(), 2);
+        assert_eq!(stub.rate_limited_count(), 1);
+
+        Ok(())
+    }
+
+    /// A snowball served by the stub can be replayed from cache without any request.
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn snowball_with_stub_lens_then_offline() -> Result<(), LensError> {
+        use crate::lens::cache::sqlite::SqliteBackend;
+
+        let stub = crate::lens::stub::StubLens::start().await;
+        let cache = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let ids = ["30000001", STUB_B];
+        let online = snowball(
+            &ids,
+            2,
+            &SearchFor::Both,
+            "stub-key",
+            Some(&stub.client()),
+            Some(&cache),
+        )
+        .await?;
+
+        let broken_client = LensClient::from(
+            reqwest::Client::builder()
+                .proxy(
+                    reqwest::Proxy::all("http://0.0.0.0:1")
+                        .expect("Failed to create invalid proxy"),
+                )
+                .timeout(std::time::Duration::from_secs(1))
+                .build()
+                .expect("Failed to build broken client"),
+        )
+        .with_retry_policy(crate::lens::retry::RetryPolicy::none());
+        let offline = snowball(
+            &ids,
+            2,
+            &SearchFor::Both,
+            "stub-key",
+            Some(&broken_client),
+            Some(&cache),
+        )
+        .await?;
+
+        assert_eq!(online.counts.into_inner(), offline.counts.into_inner());
+
+        Ok(())
+    }
+
+    /// Citations rebuilt from the cited-by index answer a snowball without any request,
+    /// which is then marked as incomplete.
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn snowball_with_cited_by_fallback_is_incomplete() -> Result<(), LensError> {
+        use crate::lens::cache::sqlite::SqliteBackend;
+
+        let stub = crate::lens::stub::StubLens::start().await;
+        let client = stub.client();
+        let cache = SqliteBackend::from_url("sqlite::memory:")
+            .await?
+            .with_cited_by_fallback(true);
+
+        let references = snowball(
+            &[STUB_A],
+            1,
+            &SearchFor::References,
+            "stub-key",
+            Some(&client),
+            Some(&cache),
+        )
+        .await?;
+        assert!(!references.incomplete_citations);
+
+        // The citations of a reference of A were never fetched, but A is known to cite it
+        let cited = references.counts.keys().next().unwrap().clone();
+        let requests = stub.request_count();
+        let citations = snowball(
+            &[cited],
+            1,
+            &SearchFor::Citations,
+            "stub-key",
+            Some(&client),
+            Some(&cache),
+        )
+        .await?;
+
+        assert!(citations.incomplete_citations);
+        assert_eq!(citations.counts.get(&stub_id(STUB_A)), 1);
+        assert_eq!(stub.request_count(), requests);
+
+        Ok(())
+    }
+
+    /// Two concurrent snowballs sharing a cache fetch each article once: the second
+    /// one waits for the fetch the first one marked as pending.
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn concurrent_snowballs_share_pending_fetches() -> Result<(), LensError> {
+        use crate::lens::cache::sqlite::SqliteBackend;
+
+        let stub = crate::lens::stub::StubLens::start().await;
+        let client = stub.client();
+        let cache = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        // Keep the first request in flight long enough for the second caller to see it pending
+        stub.rate_limit_next(1, 1);
+
+        let ids = [STUB_A, STUB_B];
+        let (first, second) = tokio::join!(
+            snowball(
+                &ids,
+                1,
+                &SearchFor::Both,
+                "stub-key",
+                Some(&client),
+                Some(&cache)
+            ),
+            snowball(
+                &ids,
+                1,
+                &SearchFor::Both,
+                "stub-key",
+                Some(&client),
+                Some(&cache)
+            ),
+        );
+
+        assert_eq!(first?.counts.into_inner(), second?.counts.into_inner());
+        assert_eq!(stub.request_count() - stub.rate_limited_count(), 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,294 @@
+//! Cache warm-up for a set of seed articles.
+//!
+//! `prefetch` walks the citation network the same way `snowball` does, but only
+//! fills the cache: nothing is scored or returned. Every step goes through the
+//! cache first, so re-running an interrupted prefetch skips all the work that was
+//! already stored and only spends Lens quota on what is still missing.
+
+use serde::Serialize;
+
+use super::{
+    cache::CacheBackend, complete_articles, error::LensError, lensid::LensId, request::LensClient,
+    request_references_and_citations_with_parents,
+};
+use crate::common::SearchFor;
+
+/// Number of articles expanded or completed per step (the Lens per-request limit)
+const PREFETCH_BATCH_SIZE: usize = 1000;
+
+/// Current step of a prefetch run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrefetchPhase {
+    /// Fetching references and/or citations, one depth at a time
+    #[default]
+    Edges,
+    /// Fetching article metadata for every discovered article
+    Metadata,
+    /// Everything is in the cache
+    Done,
+}
+
+/// Progress report for a prefetch run, passed to the progress callback after every batch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PrefetchProgress {
+    pub phase: PrefetchPhase,
+    /// Depth currently being expanded (1-based)
+    pub depth: u8,
+    pub max_depth: u8,
+    /// Articles expanded so far at the current depth
+    pub expanded: usize,
+    /// Articles to expand at the current depth
+    pub to_expand: usize,
+    /// Distinct articles discovered so far, seeds included
+    pub discovered: usize,
+    /// Articles whose metadata is in the cache
+    pub completed: usize,
+}
+
+/// Fills the cache with the citation network around a set of seed articles.
+///
+/// Depth has the same meaning as in `snowball`: depth 1 caches the references and/or
+/// citations of the seeds, depth 2 additionally caches those of every article found
+/// at depth 1, and so on. When `with_metadata` is set, the article data of every
+/// discovered article is cached as well, so a later `snowball` with the same seeds,
+/// depth and direction runs without calling the Lens API.
+///
+/// # Arguments
+///
+/// * `id_list`: Seed article identifiers (LensIds, PMIDs, DOIs).
+/// * `max_depth`: Number of levels to expand.
+/// * `search_for`: Specifies whether to cache references, citations, or both.
+/// * `with_metadata`: Also cache article metadata for all discovered articles.
+/// * `api_key`: The API key for Lens.org.
+/// * `client`: An optional `LensClient` to use for requests.
+/// * `cache`: The cache backend to fill.
+/// * `on_progress`: Called after every batch with the current progress.
+///
+/// # Returns
+///
+/// The final `PrefetchProgress`, or a `LensError`. Batches stored before an error
+/// stay in the cache, so the same call can simply be retried to resume.
+#[allow(clippy::too_many_arguments)]
+pub async fn prefetch<T, F>(
+    id_list: &[T],
+    max_depth: u8,
+    search_for: &SearchFor,
+    with_metadata: bool,
+    api_key: &str,
+    client: Option<&LensClient>,
+    cache: &dyn CacheBackend,
+    mut on_progress: F,
+) -> Result<PrefetchProgress, LensError>
+where
+    T: AsRef<str>,
+    F: FnMut(&PrefetchProgress),
+{
+    let client = match client {
+        Some(c) => c,
+        None => &LensClient::default(),
+    };
+
+    let mut progress = PrefetchProgress {
+        max_depth,
+        ..Default::default()
+    };
+
+    // Every article seen so far, in discovery order
+    let mut discovered: nohash_hasher::IntSet<LensId> = Default::default();
+    let mut discovered_order: Vec<LensId> = Vec::new();
+
+    // Articles already expanded, so overlapping levels are only requested once
+    let mut expanded: nohash_hasher::IntSet<LensId> = Default::default();
+
+    // The seeds may be PMIDs or DOIs, so the first level is expanded from the raw strings
+    let seeds: Vec<String> = id_list.iter().map(|id| id.as_ref().to_string()).collect();
+    let mut frontier: Vec<String> = seeds;
+
+    for depth in 1..=max_depth {
+        if frontier.is_empty() {
+            break;
+        }
+
+        progress.depth = depth;
+        progress.expanded = 0;
+        progress.to_expand = frontier.len();
+        on_progress(&progress);
+
+        let mut next_frontier: Vec<String> = Vec::new();
+        let mut queued: nohash_hasher::IntSet<LensId> = Default::default();
+
+        for batch in frontier.chunks(PREFETCH_BATCH_SIZE) {
+            let parents_with_children = match request_references_and_citations_with_parents(
+                batch,
+                search_for,
+                api_key,
+                Some(client),
+                Some(cache),
+            )
+            .await
+            {
+                Ok(results) => results,
+                // A batch of articles without any match is not fatal past the seeds
+                Err(LensError::NoArticlesFound) if depth > 1 => Vec::new(),
+                Err(e) => return Err(e),
+            };
+
+            for parent_with_children in parents_with_children {
+                expanded.insert(parent_with_children.parent_id.clone());
+                if discovered.insert(parent_with_children.parent_id.clone()) {
+                    discovered_order.push(parent_with_children.parent_id);
+                }
+
+                for child_id in parent_with_children.children {
+                    if discovered.insert(child_id.clone()) {
+                        discovered_order.push(child_id.clone());
+                    }
+                    if !expanded.contains(&child_id) && queued.insert(child_id.clone()) {
+                        next_frontier.push(child_id.as_ref().to_string());
+                    }
+                }
+            }
+
+            progress.expanded += batch.len();
+            progress.discovered = discovered_order.len();
+            on_progress(&progress);
+        }
+
+        frontier = next_frontier;
+    }
+
+    if with_metadata {
+        progress.phase = PrefetchPhase::Metadata;
+        on_progress(&progress);
+
+        for batch in discovered_order.chunks(PREFETCH_BATCH_SIZE) {
+            complete_articles(batch, api_key, Some(client), Some(cache)).await?;
+
+            progress.completed += batch.len();
+            on_progress(&progress);
+        }
+    }
+
+    progress.phase = PrefetchPhase::Done;
+    on_progress(&progress);
+
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "cache-sqlite")]
+    use super::*;
+
+    /// A client that cannot reach the network, to prove everything is served from cache
+    #[cfg(feature = "cache-sqlite")]
+    fn offline_client() -> LensClient {
+        LensClient::from(
+            reqwest::Client::builder()
+                .proxy(
+                    reqwest::Proxy::all("http://0.0.0.0:1")
+                        .expect("Failed to create invalid proxy"),
+                )
+                .timeout(std::time::Duration::from_secs(1))
+                .build()
+                .expect("Failed to build broken client"),
+        )
+        .with_retry_policy(crate::lens::retry::RetryPolicy::none())
+    }
+
+    /// A fully cached network is walked without any request, which is what makes
+    /// re-running an interrupted prefetch cheap.
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn test_prefetch_resumes_from_cache() -> Result<(), LensError> {
+        use crate::lens::article::{ArticleData, ArticleWithData};
+        use crate::lens::cache::sqlite::SqliteBackend;
+
+        let cache = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let seed = LensId::from(10000000000001);
+        let level1 = vec![LensId::from(10000000000002), LensId::from(10000000000003)];
+        let level2 = vec![LensId::from(10000000000004), LensId::from(10000000000001)];
+
+        cache
+            .store_references(&[
+                (seed.clone(), level1.clone()),
+                (level1[0].clone(), level2.clone()),
+                (level1[1].clone(), vec![level2[0].clone()]),
+            ])
+            .await?;
+
+        let all_ids = [
+            seed.clone(),
+            level1[0].clone(),
+            level1[1].clone(),
+            level2[0].clone(),
+        ];
+        let article_data: Vec<ArticleWithData> = all_ids
+            .iter()
+            .map(|id| ArticleWithData {
+                lens_id: id.clone(),
+                article_data: ArticleData {
+                    title: Some(format!("Article {}", id.as_ref())),
+                    ..Default::default()
+                },
+            })
+            .collect();
+        cache.store_article_data(&article_data).await?;
+
+        let mut reports = Vec::new();
+        let progress = prefetch(
+            &[seed.as_ref()],
+            2,
+            &SearchFor::References,
+            true,
+            "no-api-key",
+            Some(&offline_client()),
+            &cache,
+            |p| reports.push(p.clone()),
+        )
+        .await?;
+
+        assert_eq!(progress.phase, PrefetchPhase::Done);
+        assert_eq!(progress.depth, 2);
+        assert_eq!(progress.discovered, 4);
+        assert_eq!(progress.completed, 4);
+
+        // Level 2 only expands the two new articles, not the seed found again
+        assert!(
+            reports
+                .iter()
+                .any(|p| p.depth == 2 && p.to_expand == 2 && p.phase == PrefetchPhase::Edges)
+        );
+        assert!(reports.iter().any(|p| p.phase == PrefetchPhase::Metadata));
+
+        Ok(())
+    }
+
+    /// Missing data is requested from Lens, so a broken client surfaces an error
+    /// instead of a silently incomplete cache
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn test_prefetch_fails_on_uncached_seed_offline() -> Result<(), LensError> {
+        use crate::lens::cache::sqlite::SqliteBackend;
+
+        let cache = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let result = prefetch(
+            &["020-200-401-307-33X"],
+            1,
+            &SearchFor::Citations,
+            false,
+            "no-api-key",
+            Some(&offline_client()),
+            &cache,
+            |_| {},
+        )
+        .await;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+}
@@ -1,13 +1,175 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
 use crate::lens::error::LensApiErrorInfo;
 
 use super::error::*;
-use serde::Deserialize;
+use super::keys::{ApiKeyStats, KeyPool, KeySelection};
+use super::retry::{RetryPolicy, RetryReason};
+use serde::{Deserialize, Serialize};
 
 pub const INCLUDES_SEARCH_FOR_CITATIONS_AND_REFERENCES: [&str; 3] =
     ["lens_id", "references", "scholarly_citations"];
 
 pub const INCLUDES_SEARCH_FOR_CITATIONS: [&str; 2] = ["lens_id", "scholarly_citations"];
 
+/// Lens.org scholarly search endpoint used when no other base URL is configured.
+pub const LENS_API_URL: &str = "https://api.lens.org/scholarly/search";
+
+/// HTTP transport for the Lens.org API.
+///
+/// Bundles the `reqwest::Client` used for the requests (proxies, timeouts, headers, etc.)
+/// with the URL of the scholarly search endpoint, so the library can be pointed at a
+/// mirror or a local stub server, and with the `RetryPolicy` applied to failed requests.
+///
+/// A client can also hold a pool of API keys (see `LensClient::with_api_keys`), in which
+/// case the pool picks the key of every request and the `api_key` arguments of the
+/// request functions are ignored.
+///
+/// Clones share their request counters and key pool, see `LensClient::stats`.
+#[derive(Debug, Clone)]
+pub struct LensClient {
+    http: reqwest::Client,
+    base_url: String,
+    retry_policy: RetryPolicy,
+    counters: Arc<RequestCounters>,
+    key_pool: Option<Arc<KeyPool>>,
+}
+
+/// Request counters shared by a `LensClient` and its clones
+#[derive(Debug, Default)]
+struct RequestCounters {
+    attempts: AtomicU64,
+    rate_limited_retries: AtomicU64,
+    server_error_retries: AtomicU64,
+    network_retries: AtomicU64,
+    key_rejected_retries: AtomicU64,
+    retry_wait_ms: AtomicU64,
+    failed_requests: AtomicU64,
+}
+
+/// Snapshot of the request counters of a `LensClient`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LensClientStats {
+    /// HTTP requests sent, retries included
+    pub attempts: u64,
+    /// Retries after a response carrying `x-rate-limit-retry-after-seconds`
+    pub rate_limited_retries: u64,
+    /// Retries after a 5xx response
+    pub server_error_retries: u64,
+    /// Retries after a timeout or connection error
+    pub network_retries: u64,
+    /// Retries with the next key of the pool after a 401 or 403
+    pub key_rejected_retries: u64,
+    /// Time spent sleeping between attempts
+    pub retry_wait_ms: u64,
+    /// Requests that returned an error, after retrying when allowed
+    pub failed_requests: u64,
+    /// Usage of each key of the pool, empty when the client has no key pool
+    pub keys: Vec<ApiKeyStats>,
+}
+
+impl LensClient {
+    /// Creates a client sending requests to the Lens.org API through `http`.
+    pub fn new(http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url: LENS_API_URL.to_string(),
+            retry_policy: RetryPolicy::default(),
+            counters: Default::default(),
+            key_pool: None,
+        }
+    }
+
+    /// Sends the requests to `base_url` instead of the Lens.org scholarly search endpoint.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Retries failed requests according to `retry_policy` instead of the default policy.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Spreads the requests over several API keys, picked according to `selection`.
+    ///
+    /// Keys answering 401 or 403 are taken out of rotation, and rate-limited keys are
+    /// skipped until their retry-after delay is over. An empty list keeps the `api_key`
+    /// passed to each request.
+    pub fn with_api_keys<S: Into<String>>(
+        mut self,
+        keys: impl IntoIterator<Item = S>,
+        selection: KeySelection,
+    ) -> Self {
+        let pool = KeyPool::new(keys, selection);
+        self.key_pool = (!pool.is_empty()).then(|| Arc::new(pool));
+        self
+    }
+
+    /// The underlying HTTP client.
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// The URL of the scholarly search endpoint.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The policy applied to failed requests.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// The API key pool, if the client has one.
+    pub fn key_pool(&self) -> Option<&KeyPool> {
+        self.key_pool.as_deref()
+    }
+
+    /// Request and retry counts since this client (or the client it was cloned from) was created.
+    pub fn stats(&self) -> LensClientStats {
+        let counters = &self.counters;
+        LensClientStats {
+            attempts: counters.attempts.load(Ordering::Relaxed),
+            rate_limited_retries: counters.rate_limited_retries.load(Ordering::Relaxed),
+            server_error_retries: counters.server_error_retries.load(Ordering::Relaxed),
+            network_retries: counters.network_retries.load(Ordering::Relaxed),
+            key_rejected_retries: counters.key_rejected_retries.load(Ordering::Relaxed),
+            retry_wait_ms: counters.retry_wait_ms.load(Ordering::Relaxed),
+            failed_requests: counters.failed_requests.load(Ordering::Relaxed),
+            keys: self.key_pool().map(KeyPool::stats).unwrap_or_default(),
+        }
+    }
+
+    fn record_retry(&self, reason: RetryReason, delay: Duration) {
+        let counter = match reason {
+            RetryReason::RateLimited => &self.counters.rate_limited_retries,
+            RetryReason::ServerError => &self.counters.server_error_retries,
+            RetryReason::Network => &self.counters.network_retries,
+            RetryReason::KeyRejected => &self.counters.key_rejected_retries,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .retry_wait_ms
+            .fetch_add(delay.as_millis() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Default for LensClient {
+    fn default() -> Self {
+        Self::new(reqwest::Client::new())
+    }
+}
+
+impl From<reqwest::Client> for LensClient {
+    fn from(http: reqwest::Client) -> Self {
+        Self::new(http)
+    }
+}
+
 /// Makes a POST request to the Lens.org API's scholarly search endpoint.
 ///
 /// This function constructs the request body based on the provided IDs, ID type,
@@ -15,7 +177,7 @@ pub const INCLUDES_SEARCH_FOR_CITATIONS: [&str; 2] = ["lens_id", "scholarly_cita
 ///
 /// # Arguments
 ///
-/// * `client`: The `reqwest::Client` to use for the request.
+/// * `client`: The `LensClient` to use for the request.
 /// * `api_key`: The API key for Lens.org.
 /// * `id_list`: An iterator of IDs to search for. Must be serializable.
 /// * `id_type`: The type of IDs in `id_list` (e.g., "pmid", "lens_id", "doi").
@@ -25,7 +187,7 @@ pub const INCLUDES_SEARCH_FOR_CITATIONS: [&str; 2] = ["lens_id", "scholarly_cita
 ///
 /// A `Result` containing the `reqwest::Response` if successful, or a `LensError` if an error occurs.
 pub async fn request_response(
-    client: &reqwest::Client,
+    client: &LensClient,
     api_key: &str,
     id_list: impl IntoIterator<Item = impl serde::Serialize> + serde::Serialize,
     id_type: &str,
@@ -42,51 +204,143 @@ pub async fn request_response(
 /// Sends a POST request with a pre-built JSON body to the Lens.org API.
 ///
 /// This function handles sending the HTTP request, adding necessary headers
-/// (Authorization, Content-Type), and retrying the request if a rate limit
-/// error is encountered based on the `x-rate-limit-retry-after-seconds` header.
+/// (Authorization, Content-Type), and retrying it according to the client's
+/// `RetryPolicy`: rate-limited responses are retried after the delay from the
+/// `x-rate-limit-retry-after-seconds` header, 5xx responses and network errors
+/// after an exponential backoff. Every retry is logged and counted in the client stats.
+///
+/// With a key pool, a rate-limited key is swapped for another one right away when one
+/// is available, and a key answering 401 or 403 is disabled and the request retried
+/// with the next key, without waiting but within the same retry limits.
 ///
 /// # Arguments
 ///
-/// * `client`: The `reqwest::Client` to use for the request.
+/// * `client`: The `LensClient` to use for the request.
 /// * `api_key`: The API key for Lens.org.
 /// * `body`: The JSON request body as a string.
 ///
 /// # Returns
 ///
 /// A `Result` containing the `reqwest::Response` if successful (status 200),
-/// or a `LensError` if an error occurs (e.g., HTTP error, missing rate limit header, parse error).
+/// or a `LensError` if an error occurs (e.g., HTTP error, missing rate limit header, parse error),
+/// which is the last error seen once retries are exhausted.
 async fn request_response_with_body(
-    client: &reqwest::Client,
+    client: &LensClient,
     api_key: &str,
     body: &str,
 ) -> Result<reqwest::Response, LensError> {
-    let base_url: &str = "https://api.lens.org/scholarly/search";
+    let result = send_with_retries(client, api_key, body).await;
+    if result.is_err() {
+        client
+            .counters
+            .failed_requests
+            .fetch_add(1, Ordering::Relaxed);
+    }
+    result
+}
+
+async fn send_with_retries(
+    client: &LensClient,
+    api_key: &str,
+    body: &str,
+) -> Result<reqwest::Response, LensError> {
+    let policy = client.retry_policy();
+    let mut total_wait = Duration::ZERO;
+    let mut backoff_retries = 0;
 
     loop {
-        let response = client
-            .post(base_url)
-            .header("Authorization", api_key)
+        client.counters.attempts.fetch_add(1, Ordering::Relaxed);
+
+        let lease = match client.key_pool() {
+            Some(pool) => Some(pool.pick().ok_or(LensError::NoUsableApiKey)?),
+            None => None,
+        };
+        let key = lease.as_ref().map_or(api_key, |lease| lease.key.as_str());
+
+        let mut request = client
+            .http()
+            .post(client.base_url())
+            .header("Authorization", key)
             .header("Content-Type", "application/json")
-            .body(body.to_owned())
-            .send()
-            .await?;
+            .body(body.to_owned());
+        if let Some(timeout) = policy.request_timeout {
+            request = request.timeout(timeout);
+        }
 
-        if response.status() == 200 {
-            return Ok(response);
-        } else {
-            let header_value = response
-                .headers()
-                .get("x-rate-limit-retry-after-seconds")
-                .ok_or(LensError::LensApi(LensApiErrorInfo {
-                    status_code: response.status().as_u16(),
+        let (reason, delay, error) = match request.send().await {
+            Ok(response) if response.status() == 200 => {
+                if let (Some(pool), Some(lease)) = (client.key_pool(), &lease) {
+                    pool.record_success(lease, response.headers());
+                }
+                return Ok(response);
+            }
+            Ok(response) => {
+                let status_code = response.status().as_u16();
+                let error = LensError::LensApi(LensApiErrorInfo {
+                    status_code,
                     message: format!("{:#?}", response.headers()),
-                }))?;
+                });
+
+                if let Some(header_value) =
+                    response.headers().get("x-rate-limit-retry-after-seconds")
+                {
+                    let seconds_to_wait = seconds_to_wait_from_response(header_value)?;
+                    log::debug!("Told to wait for {seconds_to_wait} seconds");
+
+                    let delay = Duration::from_secs(seconds_to_wait).min(policy.max_retry_after);
+                    match (client.key_pool(), &lease) {
+                        (Some(pool), Some(lease)) => {
+                            pool.record_rate_limited(lease, delay, response.headers());
+                            // Another key can take the request without waiting
+                            let delay = if pool.has_ready_key() {
+                                Duration::ZERO
+                            } else {
+                                delay
+                            };
+                            (RetryReason::RateLimited, delay, error)
+                        }
+                        _ => (RetryReason::RateLimited, delay, error),
+                    }
+                } else if response.status().is_server_error() {
+                    (
+                        RetryReason::ServerError,
+                        policy.backoff(backoff_retries),
+                        error,
+                    )
+                } else if let (Some(pool), Some(lease), 401 | 403) =
+                    (client.key_pool(), &lease, status_code)
+                {
+                    pool.disable(lease, status_code);
+                    if !pool.has_enabled_key() {
+                        return Err(error);
+                    }
+                    (RetryReason::KeyRejected, Duration::ZERO, error)
+                } else {
+                    return Err(error);
+                }
+            }
+            Err(e) if e.is_timeout() || e.is_connect() => (
+                RetryReason::Network,
+                policy.backoff(backoff_retries),
+                e.into(),
+            ),
+            Err(e) => return Err(e.into()),
+        };
 
-            let seconds_to_wait = seconds_to_wait_from_response(header_value)?;
+        if total_wait + delay > policy.max_total_wait {
+            log::warn!(
+                "Giving up on Lens request after waiting {total_wait:?} in retries: {error:?}"
+            );
+            return Err(error);
+        }
 
-            log::debug!("Told to wait for {seconds_to_wait} seconds");
+        log::warn!("Lens request failed ({reason:?}), retrying in {delay:?}: {error:?}");
+        client.record_retry(reason, delay);
 
-            async_std::task::sleep(std::time::Duration::from_secs(seconds_to_wait)).await;
+        async_std::task::sleep(delay).await;
+        total_wait += delay;
+        if reason != RetryReason::RateLimited {
+            backoff_retries += 1;
         }
     }
 }
@@ -141,7 +395,7 @@ struct LensApiResponse<T> {
 ///
 /// # Arguments
 ///
-/// * `client`: The `reqwest::Client` to use for the request.
+/// * `client`: The `LensClient` to use for the request.
 /// * `api_key`: The API key for Lens.org.
 /// * `id_list`: An iterator of IDs to search for. Must be serializable.
 /// * `id_type`: The type of IDs in `id_list` (e.g., "pmid", "lens_id", "doi").
@@ -173,7 +427,7 @@ struct LensApiResponse<T> {
 /// ).await?;
 /// ```
 pub async fn request_and_parse<T>(
-    client: &reqwest::Client,
+    client: &LensClient,
     api_key: &str,
     id_list: impl IntoIterator<Item = impl serde::Serialize> + serde::Serialize,
     id_type: &str,
@@ -226,6 +480,235 @@ fn make_request_body(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lens::stub::StubLens;
+
+    #[derive(Debug, Deserialize)]
+    struct TitleOnly {
+        title: String,
+    }
+
+    /// A 429 with a retry-after header is waited out and retried until the request succeeds.
+    #[tokio::test]
+    async fn test_rate_limited_request_is_retried() -> Result<(), LensError> {
+        let stub = StubLens::start().await;
+        stub.rate_limit_next(2, 0);
+
+        let articles: Vec<TitleOnly> = request_and_parse(
+            &stub.client(),
+            "stub-key",
+            ["10.1000/STUB.A"],
+            "doi",
+            &["title"],
+        )
+        .await?;
+
+        assert_eq!(articles.len(), 1);
+        assert_eq!(
+            articles[0].title,
+            "Snowball sampling for systematic reviews"
+        );
+        assert_eq!(stub.rate_limited_count(), 2);
+        assert_eq!(stub.request_count(), 3);
+
+        Ok(())
+    }
+
+    fn fast_retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+            ..Default::default()
+        }
+    }
+
+    /// 5xx responses are retried with a backoff and counted in the client stats.
+    #[tokio::test]
+    async fn test_server_errors_are_retried() -> Result<(), LensError> {
+        let stub = StubLens::start().await;
+        let client = stub.client().with_retry_policy(fast_retry_policy());
+        stub.server_error_next(3);
+
+        let articles: Vec<TitleOnly> =
+            request_and_parse(&client, "stub-key", ["30000001"], "pmid", &["title"]).await?;
+
+        assert_eq!(articles.len(), 1);
+        assert_eq!(stub.request_count(), 4);
+
+        let stats = client.stats();
+        assert_eq!(stats.attempts, 4);
+        assert_eq!(stats.server_error_retries, 3);
+        assert_eq!(stats.failed_requests, 0);
+
+        Ok(())
+    }
+
+    /// Retries stop once the total wait would exceed the policy limit.
+    #[tokio::test]
+    async fn test_retries_give_up_after_max_total_wait() {
+        let stub = StubLens::start().await;
+        let client = stub.client().with_retry_policy(RetryPolicy {
+            max_total_wait: Duration::from_millis(50),
+            ..fast_retry_policy()
+        });
+        stub.server_error_next(usize::MAX);
+
+        let result =
+            request_and_parse::<TitleOnly>(&client, "stub-key", ["30000001"], "pmid", &["title"])
+                .await;
+
+        match result {
+            Err(LensError::LensApi(info)) => assert_eq!(info.status_code, 503),
+            other => panic!("Expected a 503 LensApi error, got {other:?}"),
+        }
+
+        let stats = client.stats();
+        assert!(stats.server_error_retries >= 1);
+        assert!(stats.retry_wait_ms <= 50);
+        assert_eq!(stats.failed_requests, 1);
+    }
+
+    /// A long retry-after is capped instead of blocking the request.
+    #[tokio::test]
+    async fn test_retry_after_is_capped() -> Result<(), LensError> {
+        let stub = StubLens::start().await;
+        let client = stub.client().with_retry_policy(RetryPolicy {
+            max_retry_after: Duration::from_millis(20),
+            ..Default::default()
+        });
+        stub.rate_limit_next(1, 3600);
+
+        let started = std::time::Instant::now();
+        let articles: Vec<TitleOnly> =
+            request_and_parse(&client, "stub-key", ["30000001"], "pmid", &["title"]).await?;
+
+        assert_eq!(articles.len(), 1);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(client.stats().rate_limited_retries, 1);
+
+        Ok(())
+    }
+
+    /// Connection errors are retried too, then reported as request errors.
+    #[tokio::test]
+    async fn test_network_errors_are_retried() {
+        // Nothing listens on the port of a stopped stub
+        let client = {
+            let stub = StubLens::start().await;
+            stub.client()
+        }
+        .with_retry_policy(RetryPolicy {
+            max_total_wait: Duration::from_millis(100),
+            ..fast_retry_policy()
+        });
+
+        let result =
+            request_and_parse::<TitleOnly>(&client, "stub-key", ["30000001"], "pmid", &["title"])
+                .await;
+
+        assert!(matches!(result, Err(LensError::Request(_))));
+        assert!(client.stats().network_retries >= 1);
+    }
+
+    /// Any other error status fails right away with the status code.
+    #[tokio::test]
+    async fn test_unauthorized_request_fails() {
+        let stub = StubLens::start().await;
+
+        let result =
+            request_and_parse::<TitleOnly>(&stub.client(), "", ["30000001"], "pmid", &["title"])
+                .await;
+
+        match result {
+            Err(LensError::LensApi(info)) => assert_eq!(info.status_code, 401),
+            other => panic!("Expected a 401 LensApi error, got {other:?}"),
+        }
+        assert_eq!(stub.request_count(), 1);
+    }
+
+    /// The key pool spreads requests over its keys and overrides the `api_key` argument.
+    #[tokio::test]
+    async fn test_key_pool_round_robin() -> Result<(), LensError> {
+        let stub = StubLens::start().await;
+        let client = stub
+            .client()
+            .with_api_keys(["key-a", "key-b"], KeySelection::RoundRobin);
+
+        for _ in 0..4 {
+            let articles: Vec<TitleOnly> =
+                request_and_parse(&client, "ignored", ["30000001"], "pmid", &["title"]).await?;
+            assert_eq!(articles.len(), 1);
+        }
+
+        assert_eq!(stub.request_count_for_key("key-a"), 2);
+        assert_eq!(stub.request_count_for_key("key-b"), 2);
+        assert_eq!(stub.request_count_for_key("ignored"), 0);
+
+        let keys = client.stats().keys;
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].requests, 2);
+        assert_eq!(keys[0].remaining_per_minute, Some(98));
+
+        Ok(())
+    }
+
+    /// A rejected key is taken out of rotation and the request goes on with the next one.
+    #[tokio::test]
+    async fn test_key_pool_disables_rejected_keys() -> Result<(), LensError> {
+        let stub = StubLens::start().await;
+        let client = stub
+            .client()
+            .with_api_keys(["key-a", "key-b"], KeySelection::RoundRobin);
+        stub.revoke_key("key-a");
+
+        for _ in 0..3 {
+            let articles: Vec<TitleOnly> =
+                request_and_parse(&client, "", ["30000001"], "pmid", &["title"]).await?;
+            assert_eq!(articles.len(), 1);
+        }
+
+        assert_eq!(stub.request_count_for_key("key-a"), 1);
+        assert_eq!(stub.request_count_for_key("key-b"), 3);
+        let stats = client.stats();
+        assert_eq!(stats.keys[0].disabled_with_status, Some(401));
+        assert_eq!(stats.key_rejected_retries, 1);
+        assert_eq!(stats.attempts, 4);
+
+        stub.revoke_key("key-b");
+        let result =
+            request_and_parse::<TitleOnly>(&client, "", ["30000001"], "pmid", &["title"]).await;
+        assert!(matches!(result, Err(LensError::LensApi(_))));
+
+        let result =
+            request_and_parse::<TitleOnly>(&client, "", ["30000001"], "pmid", &["title"]).await;
+        assert!(matches!(result, Err(LensError::NoUsableApiKey)));
+
+        Ok(())
+    }
+
+    /// A rate-limited key is swapped for another one instead of waiting.
+    #[tokio::test]
+    async fn test_key_pool_skips_rate_limited_key() -> Result<(), LensError> {
+        let stub = StubLens::start().await;
+        let client = stub
+            .client()
+            .with_api_keys(["key-a", "key-b"], KeySelection::MostRemaining);
+        stub.rate_limit_next(1, 3600);
+
+        let started = std::time::Instant::now();
+        let articles: Vec<TitleOnly> =
+            request_and_parse(&client, "", ["30000001"], "pmid", &["title"]).await?;
+
+        assert_eq!(articles.len(), 1);
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let stats = client.stats();
+        assert_eq!(stats.rate_limited_retries, 1);
+        assert_eq!(stats.retry_wait_ms, 0);
+        assert_eq!(stats.keys[0].rate_limited, 1);
+        assert_eq!(stub.request_count_for_key("key-b"), 1);
+
+        Ok(())
+    }
 
     /// Test requesting scholarly_citations for PMID 2231712.
     /// This PMID has ~120k citations, so we need to see how Lens responds
@@ -241,7 +724,7 @@ mod tests {
             }
         };
 
-        let client = reqwest::Client::new();
+        let client = LensClient::default();
         let pmid = "2231712";
 
         println!("\n=== Testing PMID {} (high citation count) ===", pmid);
@@ -0,0 +1,288 @@
+//! Resolution of raw seed identifiers to Lens IDs.
+//!
+//! Unlike [`complete_articles_by_raw_ids`](super::complete_articles_by_raw_ids), which
+//! silently drops what it cannot find, this reports the outcome for every input so that
+//! seeds can be checked before a snowball is run.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::identifier::{IdType, Identifier};
+use crate::lens::{
+    article::ArticleWithData,
+    cache::CacheBackend,
+    completion::{complete_articles, fetch_articles_no_cache},
+    error::LensError,
+    lensid::LensId,
+    request::LensClient,
+};
+
+/// Outcome of the resolution of one raw identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionStatus {
+    /// The identifier matches exactly one Lens.org record.
+    Resolved,
+    /// The input is not a DOI, PMID, PMCID, arXiv, OpenAlex, MAG, CORE or Lens ID.
+    InvalidFormat,
+    /// Lens.org has no record for this identifier.
+    NotFound,
+    /// Several Lens.org records carry this identifier.
+    Ambiguous,
+}
+
+/// Resolution of one raw identifier, in the order of the input list.
+#[derive(Debug, Clone, Serialize)]
+pub struct Resolution {
+    /// The identifier as given.
+    pub input: String,
+    /// The normalised identifier, as used for `id_mappings`, when the input could be parsed.
+    pub identifier: Option<String>,
+    pub status: ResolutionStatus,
+    /// The matching Lens ID, when resolved.
+    pub lens_id: Option<LensId>,
+    /// The matching article, when resolved.
+    pub article: Option<ArticleWithData>,
+    /// All matching Lens IDs, when ambiguous.
+    pub candidates: Vec<LensId>,
+}
+
+impl Resolution {
+    fn new(input: &str, identifier: Option<&Identifier>, status: ResolutionStatus) -> Self {
+        Self {
+            input: input.to_string(),
+            identifier: identifier.map(Identifier::key),
+            status,
+            lens_id: None,
+            article: None,
+            candidates: Vec::new(),
+        }
+    }
+}
+
+/// Resolves a mixed list of raw identifiers to Lens IDs and article data.
+///
+/// Identifiers are looked up in the id-mapping cache first, and the misses are fetched
+/// from Lens.org. Unambiguous mappings and fetched articles are stored in the cache.
+///
+/// # Arguments
+///
+/// * `raw_ids`: The identifiers to resolve, in any supported format.
+/// * `api_key`: The API key for Lens.org.
+/// * `client`: An optional `LensClient` to use for requests. If `None`, a new client is created.
+/// * `cache`: An optional cache backend to use for caching results.
+///
+/// # Returns
+///
+/// A `Result` containing one `Resolution` per input, in order, or a `LensError` if a
+/// request fails.
+pub async fn resolve_raw_ids(
+    raw_ids: &[&str],
+    api_key: &str,
+    client: Option<&LensClient>,
+    cache: Option<&dyn CacheBackend>,
+) -> Result<Vec<Resolution>, LensError> {
+    let parsed: Vec<Option<Identifier>> =
+        raw_ids.iter().map(|raw| Identifier::parse(raw)).collect();
+
+    // Candidate Lens IDs for each normalised identifier
+    let mut candidates: HashMap<String, Vec<LensId>> = HashMap::new();
+    let mut external_keys: Vec<String> = Vec::new();
+    for identifier in parsed.iter().flatten() {
+        let key = identifier.key();
+        if candidates.contains_key(&key) || external_keys.contains(&key) {
+            continue;
+        }
+        if identifier.id_type() == IdType::LensId {
+            if let Ok(lens_id) = LensId::try_from(identifier.value()) {
+                candidates.insert(key, vec![lens_id]);
+            }
+        } else {
+            external_keys.push(key);
+        }
+    }
+
+    let mapping_misses: Vec<String> = match cache {
+        Some(cb) if !external_keys.is_empty() => {
+            let hits = cb.get_id_mapping(&external_keys).await?;
+            let misses = external_keys
+                .into_iter()
+                .filter(|key| !hits.contains_key(key))
+                .collect();
+            for (key, lens_id) in hits {
+                candidates.insert(key, vec![lens_id]);
+            }
+            misses
+        }
+        _ => external_keys,
+    };
+
+    let mut articles: HashMap<LensId, ArticleWithData> = HashMap::new();
+    if !mapping_misses.is_empty() {
+        let fetched = fetch_articles_no_cache(&mapping_misses, api_key, client).await?;
+
+        let mut fetched_mappings: HashMap<String, Vec<LensId>> = HashMap::new();
+        for (key, lens_id) in fetched.iter().flat_map(ArticleWithData::all_id_mappings) {
+            let lens_ids = fetched_mappings.entry(key).or_default();
+            if !lens_ids.contains(&lens_id) {
+                lens_ids.push(lens_id);
+            }
+        }
+
+        if let Some(cb) = cache {
+            let unambiguous: Vec<(String, LensId)> = fetched_mappings
+                .iter()
+                .filter(|(_, lens_ids)| lens_ids.len() == 1)
+                .map(|(key, lens_ids)| (key.clone(), lens_ids[0].clone()))
+                .collect();
+            cb.store_id_mapping(&unambiguous).await?;
+            cb.store_article_data(&fetched).await?;
+        }
+
+        for key in mapping_misses {
+            if let Some(lens_ids) = fetched_mappings.remove(&key) {
+                candidates.insert(key, lens_ids);
+            }
+        }
+        articles.extend(
+            fetched
+                .into_iter()
+                .map(|article| (article.lens_id.clone(), article)),
+        );
+    }
+
+    // Article data for the Lens IDs given directly or found in the id-mapping cache
+    let mut missing_data: Vec<LensId> = candidates
+        .values()
+        .filter(|lens_ids| lens_ids.len() == 1)
+        .map(|lens_ids| lens_ids[0].clone())
+        .filter(|lens_id| !articles.contains_key(lens_id))
+        .collect();
+    missing_data.sort();
+    missing_data.dedup();
+    if !missing_data.is_empty() {
+        articles.extend(
+            complete_articles(&missing_data, api_key, client, cache)
+                .await?
+                .into_iter()
+                .map(|article| (article.lens_id.clone(), article)),
+        );
+    }
+
+    let resolutions = raw_ids
+        .iter()
+        .zip(parsed.iter())
+        .map(|(raw, identifier)| {
+            let Some(identifier) = identifier else {
+                return Resolution::new(raw, None, ResolutionStatus::InvalidFormat);
+            };
+            match candidates.get(&identifier.key()).map(Vec::as_slice) {
+                Some([lens_id]) => match articles.get(lens_id) {
+                    Some(article) => Resolution {
+                        lens_id: Some(lens_id.clone()),
+                        article: Some(article.clone()),
+                        ..Resolution::new(raw, Some(identifier), ResolutionStatus::Resolved)
+                    },
+                    None => Resolution::new(raw, Some(identifier), ResolutionStatus::NotFound),
+                },
+                Some(lens_ids) if lens_ids.len() > 1 => {
+                    let mut lens_ids = lens_ids.to_vec();
+                    lens_ids.sort();
+                    Resolution {
+                        candidates: lens_ids,
+                        ..Resolution::new(raw, Some(identifier), ResolutionStatus::Ambiguous)
+                    }
+                }
+                _ => Resolution::new(raw, Some(identifier), ResolutionStatus::NotFound),
+            }
+        })
+        .collect();
+
+    Ok(resolutions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every input gets a status, and a second call is answered from the cache.
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn resolve_raw_ids_with_stub_lens() -> Result<(), LensError> {
+        use crate::lens::cache::SqliteBackend;
+        use crate::lens::stub::StubLens;
+
+        let stub = StubLens::start().await;
+        let cache = SqliteBackend::from_url("sqlite::memory:").await?;
+
+        let raw_ids = [
+            "https://doi.org/10.1000/STUB.A",
+            "not an id",
+            "10.1000/stub.missing",
+            "10.1000/stub.dup",
+            "830-182-661-063-60X",
+            "39999999",
+        ];
+
+        let first =
+            resolve_raw_ids(&raw_ids, "stub-key", Some(&stub.client()), Some(&cache)).await?;
+        let requests = stub.request_count();
+        let second =
+            resolve_raw_ids(&raw_ids, "stub-key", Some(&stub.client()), Some(&cache)).await?;
+
+        for resolutions in [first, second] {
+            let statuses: Vec<ResolutionStatus> = resolutions
+                .iter()
+                .map(|resolution| resolution.status)
+                .collect();
+            assert_eq!(
+                statuses,
+                [
+                    ResolutionStatus::Resolved,
+                    ResolutionStatus::InvalidFormat,
+                    ResolutionStatus::NotFound,
+                    ResolutionStatus::Ambiguous,
+                    ResolutionStatus::Resolved,
+                    ResolutionStatus::NotFound,
+                ]
+            );
+
+            assert_eq!(resolutions[0].input, "https://doi.org/10.1000/STUB.A");
+            assert_eq!(resolutions[0].identifier.as_deref(), Some("10.1000/stub.a"));
+            assert_eq!(
+                resolutions[0].lens_id,
+                Some(LensId::try_from("565-646-398-006-312").unwrap())
+            );
+            assert_eq!(
+                resolutions[0]
+                    .article
+                    .as_ref()
+                    .and_then(|article| article.article_data.title.as_deref()),
+                Some("Snowball sampling for systematic reviews")
+            );
+            assert_eq!(resolutions[1].identifier, None);
+            assert_eq!(
+                resolutions[3].candidates,
+                [
+                    LensId::try_from("100-200-300-400-510").unwrap(),
+                    LensId::try_from("100-200-300-400-520").unwrap(),
+                ]
+            );
+            assert!(resolutions[3].lens_id.is_none());
+            assert!(resolutions[4].article.is_some());
+        }
+
+        // Only the identifiers that were not found or ambiguous are requested again
+        assert!(stub.request_count() > requests);
+        assert!(
+            cache
+                .get_id_mapping(&["10.1000/stub.dup".to_string()])
+                .await?
+                .is_empty(),
+            "Ambiguous identifiers should not be cached"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+//! Retry policy for requests to the Lens.org API.
+
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// How `LensClient` retries failed requests.
+///
+/// Rate-limited responses (carrying `x-rate-limit-retry-after-seconds`) are retried
+/// after the delay asked by Lens, capped at `max_retry_after`. Server errors (5xx) and
+/// network errors (timeouts and failed connections) are retried with an
+/// exponential backoff with jitter. With a key pool, a request rejected with 401 or 403
+/// is retried right away with the next key, and that retry counts as one like the others.
+/// A request gives up, returning the last error, once the next sleep would take its total
+/// waiting time past `max_total_wait`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Backoff before the first retry of a 5xx or network error, doubled at every retry
+    pub initial_backoff: Duration,
+    /// Upper bound for a single backoff
+    pub max_backoff: Duration,
+    /// Upper bound for a sleep asked by a rate-limited response
+    pub max_retry_after: Duration,
+    /// Upper bound for the time a request spends sleeping between attempts
+    pub max_total_wait: Duration,
+    /// Timeout of each attempt, `None` to only rely on the timeout of the HTTP client
+    pub request_timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_retry_after: Duration::from_secs(60),
+            max_total_wait: Duration::from_secs(300),
+            request_timeout: Some(Duration::from_secs(120)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never sleeps: every failure is returned right away.
+    pub fn none() -> Self {
+        Self {
+            max_total_wait: Duration::ZERO,
+            ..Default::default()
+        }
+    }
+
+    /// Backoff before retry number `retry` (0-based) of a 5xx or network error.
+    ///
+    /// Uses "equal jitter": half of the exponential delay is fixed and the other half
+    /// random, so concurrent requests failing together do not retry in lockstep.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+
+        let half = exponential / 2;
+        let jitter_nanos = random_u64() % (half.as_nanos() as u64).max(1);
+
+        half + Duration::from_nanos(jitter_nanos)
+    }
+}
+
+/// Why a request to Lens was retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryReason {
+    RateLimited,
+    ServerError,
+    Network,
+    KeyRejected,
+}
+
+/// A random number from the standard library's randomly seeded hasher (no `rand` needed).
+fn random_u64() -> u64 {
+    std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_exponentially_within_bounds() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000),
+            ..Default::default()
+        };
+
+        for (retry, full) in [
+            (0, 100),
+            (1, 200),
+            (2, 400),
+            (3, 800),
+            (4, 1000),
+            (30, 1000),
+        ] {
+            let backoff = policy.backoff(retry);
+            assert!(backoff >= Duration::from_millis(full / 2), "retry {retry}");
+            assert!(backoff <= Duration::from_millis(full), "retry {retry}");
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Local stand-in for the Lens.org scholarly search endpoint, for tests.
+//!
+//! `StubLens` serves the recorded works in `fixtures/scholarly_works.json` over plain
+//! HTTP on a random local port. It answers `terms` queries on `lens_id` and on any
+//! `external_ids` type (`pmid`, `doi`, `pmcid`, `arxiv`, ...) with the requested `include` fields only, like the real API, and can be told
+//! to answer the next requests with `429 Too Many Requests` and a
+//! `x-rate-limit-retry-after-seconds` header, or with `503 Service Unavailable`. Keys can
+//! be revoked to get `401 Unauthorized`, and successful responses carry per-key
+//! `x-rate-limit-remaining-request-per-minute` headers. Every test gets its own server, so
+//! request counts and rate limits do not leak between tests running in parallel.
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{Map, Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::request::LensClient;
+
+const FIXTURE_WORKS: &str = include_str!("fixtures/scholarly_works.json");
+
+#[derive(Debug, Default)]
+struct StubState {
+    /// Number of upcoming requests to answer with a 429
+    rate_limited: usize,
+    retry_after_seconds: u64,
+    /// Number of upcoming requests to answer with a 503
+    server_errors: usize,
+    /// Requests received, rate limited ones included
+    requests: usize,
+    /// Requests answered with a 429
+    rate_limited_requests: usize,
+    /// Keys answered with a 401
+    revoked_keys: HashSet<String>,
+    /// Requests received per API key
+    requests_per_key: HashMap<String, usize>,
+}
+
+/// Requests per minute allowed to each key, for the remaining-quota header
+const STUB_REQUESTS_PER_MINUTE: usize = 100;
+
+pub(crate) struct StubLens {
+    addr: SocketAddr,
+    state: Arc<Mutex<StubState>>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl StubLens {
+    /// Starts a stub server on `127.0.0.1` serving the recorded fixtures.
+    pub(crate) async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind stub Lens server");
+        let addr = listener
+            .local_addr()
+            .expect("Stub Lens server has no address");
+
+        let works: Arc<Vec<Value>> =
+            Arc::new(serde_json::from_str(FIXTURE_WORKS).expect("Invalid Lens fixtures"));
+        let state = Arc::new(Mutex::new(StubState::default()));
+
+        let server_state = state.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(
+                    stream,
+                    works.clone(),
+                    server_state.clone(),
+                ));
+            }
+        });
+
+        Self {
+            addr,
+            state,
+            server,
+        }
+    }
+
+    /// A client sending its requests to this stub.
+    pub(crate) fn client(&self) -> LensClient {
+        LensClient::default().with_base_url(format!("http://{}/scholarly/search", self.addr))
+    }
+
+    /// Answers the next `count` requests with a 429 asking to retry after `retry_after_seconds`.
+    pub(crate) fn rate_limit_next(&self, count: usize, retry_after_seconds: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.rate_limited = count;
+        state.retry_after_seconds = retry_after_seconds;
+    }
+
+    /// Answers the next `count` requests with a 503, without any retry-after header.
+    pub(crate) fn server_error_next(&self, count: usize) {
+        self.state.lock().unwrap().server_errors = count;
+    }
+
+    /// Answers the requests made with `key` with a 401 from now on.
+    pub(crate) fn revoke_key(&self, key: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .revoked_keys
+            .insert(key.to_string());
+    }
+
+    /// Number of requests received with `key` so far.
+    pub(crate) fn request_count_for_key(&self, key: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests_per_key
+            .get(key)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Number of requests received so far, rate limited ones included.
+    pub(crate) fn request_count(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+
+    /// Number of requests answered with a 429 so far.
+    pub(crate) fn rate_limited_count(&self) -> usize {
+        self.state.lock().unwrap().rate_limited_requests
+    }
+}
+
+impl Drop for StubLens {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Serves the HTTP/1.1 requests of one keep-alive connection.
+async fn serve_connection(stream: TcpStream, works: Arc<Vec<Value>>, state: Arc<Mutex<StubState>>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+            return;
+        }
+
+        let mut content_length = 0;
+        let mut api_key = String::new();
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                match name.to_ascii_lowercase().as_str() {
+                    "content-length" => content_length = value.trim().parse().unwrap_or(0),
+                    "authorization" => api_key = value.trim().to_string(),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        if reader.read_exact(&mut body).await.is_err() {
+            return;
+        }
+
+        let (rate_limit, server_error, authorized, key_requests) = {
+            let mut state = state.lock().unwrap();
+            state.requests += 1;
+            let key_requests = {
+                let count = state.requests_per_key.entry(api_key.clone()).or_default();
+                *count += 1;
+                *count
+            };
+            let authorized = !api_key.is_empty() && !state.revoked_keys.contains(&api_key);
+
+            if state.rate_limited > 0 {
+                state.rate_limited -= 1;
+                state.rate_limited_requests += 1;
+                (
+                    Some(state.retry_after_seconds),
+                    false,
+                    authorized,
+                    key_requests,
+                )
+            } else if state.server_errors > 0 {
+                state.server_errors -= 1;
+                (None, true, authorized, key_requests)
+            } else {
+                (None, false, authorized, key_requests)
+            }
+        };
+
+        let response = if let Some(retry_after_seconds) = rate_limit {
+            format!(
+                "HTTP/1.1 429 Too Many Requests\r\nx-rate-limit-retry-after-seconds: {retry_after_seconds}\r\ncontent-length: 0\r\n\r\n"
+            )
+        } else if server_error {
+            "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n".to_string()
+        } else if !authorized {
+            "HTTP/1.1 401 Unauthorized\r\ncontent-length: 0\r\n\r\n".to_string()
+        } else if !request_line.starts_with("POST /scholarly/search ") {
+            "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n".to_string()
+        } else {
+            match serde_json::from_slice::<Value>(&body) {
+                Ok(query) => {
+                    let payload = search(&works, &query).to_string();
+                    format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nx-rate-limit-remaining-request-per-minute: {}\r\ncontent-length: {}\r\n\r\n{payload}",
+                        STUB_REQUESTS_PER_MINUTE.saturating_sub(key_requests),
+                        payload.len()
+                    )
+                }
+                Err(_) => "HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\n\r\n".to_string(),
+            }
+        };
+
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Answers a `{"query": {"terms": {id_type: [...]}}, "include": [...]}` request.
+fn search(works: &[Value], query: &Value) -> Value {
+    let Some((id_type, ids)) = query["query"]["terms"]
+        .as_object()
+        .and_then(|terms| terms.iter().next())
+    else {
+        return json!({ "total": 0, "data": [] });
+    };
+
+    let ids: Vec<String> = ids
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|id| id.as_str())
+        .map(|id| id.to_lowercase())
+        .collect();
+
+    let include: Vec<&str> = query["include"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|field| field.as_str())
+        .collect();
+
+    let data: Vec<Value> = works
+        .iter()
+        .filter(|work| {
+            work_ids(work, id_type)
+                .iter()
+                .any(|work_id| ids.contains(work_id))
+        })
+        .map(|work| {
+            let fields = work
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(field, _)| include.is_empty() || include.contains(&field.as_str()))
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect::<Map<String, Value>>();
+            Value::Object(fields)
+        })
+        .collect();
+
+    json!({ "total": data.len(), "data": data })
+}
+
+/// Identifiers of a work for a query field, lowercased (Lens matches DOIs case-insensitively).
+fn work_ids(work: &Value, id_type: &str) -> Vec<String> {
+    match id_type {
+        "lens_id" => work["lens_id"]
+            .as_str()
+            .map(str::to_lowercase)
+            .into_iter()
+            .collect(),
+        _ => work["external_ids"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|external_id| external_id["type"] == id_type)
+            .filter_map(|external_id| external_id["value"].as_str())
+            .map(str::to_lowercase)
+            .collect(),
+    }
+}
@@ -1,14 +1,21 @@
 //! BibliZap is a library for building citation networks starting from seed articles.
 //!
 //! It interacts with APIs like Lens.org and PubMed to retrieve article data
-//! and expand the network by finding references and citations.
+//! and expand the network by finding references and citations, or reads them from an
+//! imported OpenAlex snapshot (`openalex` feature).
 use lens::lensid;
 
 pub mod common;
+pub mod identifier;
 pub mod lens;
+#[cfg(feature = "openalex")]
+pub mod openalex;
 pub mod pubmed;
 
 pub use common::SearchFor;
+pub use lens::request::LensClient;
+pub use lens::resolve::ResolutionStatus;
+pub use pubmed::PubmedClient;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -18,6 +25,11 @@ use crate::lens::{cache::CacheBackend, lensid::LensId};
 pub enum Error {
     #[error(transparent)]
     LensError(#[from] lens::error::LensError),
+    #[error("PubMed request failed: {0:#}")]
+    PubmedError(#[from] anyhow::Error),
+    #[cfg(feature = "openalex")]
+    #[error(transparent)]
+    OpenAlexError(#[from] openalex::OpenAlexError),
 }
 
 /// Represents an article with core bibliographic information.
@@ -25,31 +37,100 @@ pub enum Error {
 /// This struct is used throughout the library to represent articles
 /// retrieved from various sources.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::ToSchema),
+    schema(description = "An article with its bibliographic metadata.")
+)]
 pub struct Article {
     pub first_author: Option<String>,
     pub year_published: Option<i32>,
     pub journal: Option<String>,
     pub title: Option<String>,
+    /// Abstract.
     pub summary: Option<String>,
     pub doi: Option<String>,
     pub pmid: Option<String>,
+    /// Number of citing articles.
     pub citations: Option<i32>,
+    /// Number of times the snowball search reached the article, `None` outside of
+    /// snowball searches.
     pub score: Option<i32>,
+    /// All authors, in publication order.
+    #[serde(default)]
+    pub authors: Vec<ArticleAuthor>,
+    /// Full publication date, `YYYY-MM-DD`.
+    #[serde(default)]
+    pub date_published: Option<String>,
+    #[serde(default)]
+    pub volume: Option<String>,
+    #[serde(default)]
+    pub issue: Option<String>,
+    /// Page range, e.g. `123-130`, or the first page only.
+    #[serde(default)]
+    pub pages: Option<String>,
+    /// ISSNs of the journal.
+    #[serde(default)]
+    pub issn: Vec<String>,
+    #[serde(default)]
+    pub publisher: Option<String>,
+    /// Publication type as given by Lens, e.g. `journal article` or `review`.
+    #[serde(default)]
+    pub publication_type: Option<String>,
+    /// ISO 639-1 language codes.
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// MeSH headings, with their qualifier as `Heading/qualifier` when there is one.
+    #[serde(default)]
+    pub mesh_terms: Vec<String>,
+    /// OpenAlex topics, best match first.
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// Whether the article has been retracted, dropped from snowball results with
+    /// `exclude_retracted`.
+    #[serde(default)]
+    pub retracted: bool,
+    /// Whether the article can be read for free.
+    #[serde(default)]
+    pub open_access: bool,
+    /// Open-access colour, e.g. `gold`, `green`, `hybrid` or `bronze`.
+    #[serde(default)]
+    pub open_access_colour: Option<String>,
+    /// Licence of the article, e.g. `cc-by`.
+    #[serde(default)]
+    pub license: Option<String>,
+    /// Links to the full text, PDFs first.
+    #[serde(default)]
+    pub full_text_urls: Vec<String>,
+}
+
+/// The articles found by a Lens.org `snowball`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Snowball {
+    /// Sorted by ascending score
+    pub articles: Vec<Article>,
+    /// Some citations were rebuilt from references cached by earlier searches, because
+    /// the cache was opted into `cited_by_fallback`. The scores of articles citing the
+    /// visited ones may be too low, and some of them may be missing.
+    pub incomplete_citations: bool,
+}
+
+/// An author of an `Article`, with the institutions listed for this article.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ArticleAuthor {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub initials: Option<String>,
+    #[serde(default)]
+    pub affiliations: Vec<String>,
 }
 
 impl From<lens::article::Article> for Article {
     fn from(article: lens::article::Article) -> Self {
-        Article {
-            first_author: article.first_author_name(),
-            year_published: article.year_published,
-            journal: article.journal(),
-            title: article.title.to_owned(),
-            summary: article.summary.to_owned(),
-            doi: article.doi(),
-            pmid: article.pmid(),
-            citations: article.scholarly_citations_count,
-            score: None,
-        }
+        lens::article::ArticleWithData::from(article).into()
     }
 }
 
@@ -78,21 +159,300 @@ impl From<lens::article::ArticleWithData> for Article {
                 )
             });
 
-        let journal = article_data
-            .source
-            .as_ref()
-            .and_then(|source| source.title.clone());
+        let authors = article_data
+            .authors
+            .unwrap_or_default()
+            .into_iter()
+            .map(|author| ArticleAuthor {
+                first_name: author.first_name,
+                last_name: author.last_name,
+                initials: author.initials,
+                affiliations: author
+                    .affiliations
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|affiliation| affiliation.name)
+                    .collect(),
+            })
+            .collect();
+
+        let source = article_data.source.unwrap_or_default();
+
+        let issn = source
+            .issn
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|issn| issn.value)
+            .collect();
+
+        let pages = match (article_data.start_page, article_data.end_page) {
+            (Some(start), Some(end)) if start != end => Some(format!("{start}-{end}")),
+            (start, end) => start.or(end),
+        };
+
+        let mesh_terms = article_data
+            .mesh_terms
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|term| match (term.mesh_heading, term.qualifier_name) {
+                (Some(heading), Some(qualifier)) => Some(format!("{heading}/{qualifier}")),
+                (heading, _) => heading,
+            })
+            .collect();
+
+        let (open_access_colour, license) = article_data
+            .open_access
+            .map(|open_access| (open_access.colour, open_access.license))
+            .unwrap_or_default();
+
+        let mut source_urls = article_data.source_urls.unwrap_or_default();
+        source_urls.sort_by_key(|source_url| source_url.kind.as_deref() != Some("pdf"));
+        let full_text_urls = source_urls
+            .into_iter()
+            .filter_map(|source_url| source_url.url)
+            .collect();
 
         Article {
             first_author,
             year_published: article_data.year_published,
-            journal,
+            journal: source.title,
             title: article_data.title,
             summary: article_data.summary,
             doi,
             pmid,
             citations: article_data.scholarly_citations_count,
             score: None,
+            authors,
+            // Lens dates may carry a time part
+            date_published: article_data
+                .date_published
+                .map(|date| date.chars().take(10).collect()),
+            volume: article_data.volume,
+            issue: article_data.issue,
+            pages,
+            issn,
+            publisher: source.publisher,
+            publication_type: article_data.publication_type,
+            languages: article_data.languages.unwrap_or_default(),
+            keywords: article_data.keywords.unwrap_or_default(),
+            mesh_terms,
+            topics: Vec::new(),
+            retracted: article_data.is_retracted.unwrap_or(false),
+            open_access: article_data.is_open_access.unwrap_or(false),
+            open_access_colour,
+            license,
+            full_text_urls,
+        }
+    }
+}
+
+/// MEDLINE three-letter language codes and their ISO 639-1 equivalent, for the most
+/// common languages in PubMed.
+const MEDLINE_LANGUAGES: [(&str, &str); 12] = [
+    ("eng", "en"),
+    ("fre", "fr"),
+    ("ger", "de"),
+    ("spa", "es"),
+    ("ita", "it"),
+    ("por", "pt"),
+    ("dut", "nl"),
+    ("rus", "ru"),
+    ("jpn", "ja"),
+    ("chi", "zh"),
+    ("pol", "pl"),
+    ("kor", "ko"),
+];
+
+/// Turns a MEDLINE date such as `2020 Mar 5` into `2020-03-05`. Dates without a day
+/// give `None`, as `Article::date_published` is a full date.
+fn medline_full_date(date: &str) -> Option<String> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let mut parts = date.split(' ');
+    let year: u32 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|name| *name == month)? + 1;
+    let day: u32 = parts.next()?.parse().ok()?;
+    Some(format!("{year:04}-{month:02}-{day:02}"))
+}
+
+impl From<pubmed::Article> for Article {
+    fn from(article: pubmed::Article) -> Self {
+        let year_published = article.year_published();
+        let authors: Vec<ArticleAuthor> = article
+            .authors
+            .unwrap_or_default()
+            .into_iter()
+            .map(|author| match author.rsplit_once(' ') {
+                // `Martin C`; collective names have no initials
+                Some((last_name, initials)) if initials.chars().all(|c| c.is_ascii_uppercase()) => {
+                    ArticleAuthor {
+                        last_name: Some(last_name.to_string()),
+                        initials: Some(initials.to_string()),
+                        ..Default::default()
+                    }
+                }
+                _ => ArticleAuthor {
+                    last_name: Some(author),
+                    ..Default::default()
+                },
+            })
+            .collect();
+
+        let first_author = authors.first().map(|author| {
+            [author.last_name.as_deref(), author.initials.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+
+        // `*COVID-19/blood/diagnosis` gives `COVID-19/blood` and `COVID-19/diagnosis`
+        let mesh_terms = article
+            .mesh_terms
+            .iter()
+            .flat_map(|heading| {
+                let heading = heading.replace('*', "");
+                let mut parts = heading.split('/');
+                let descriptor = parts.next().unwrap_or_default().to_string();
+                let qualified: Vec<String> = parts
+                    .map(|qualifier| format!("{descriptor}/{qualifier}"))
+                    .collect();
+                if qualified.is_empty() {
+                    vec![descriptor]
+                } else {
+                    qualified
+                }
+            })
+            .collect();
+
+        let languages = article
+            .languages
+            .into_iter()
+            .map(|language| {
+                MEDLINE_LANGUAGES
+                    .iter()
+                    .find(|(medline, _)| *medline == language)
+                    .map_or(language, |(_, iso)| iso.to_string())
+            })
+            .collect();
+
+        let publication_type = article
+            .publication_types
+            .iter()
+            .find(|publication_type| *publication_type != "Journal Article")
+            .or(article.publication_types.first())
+            .map(|publication_type| publication_type.to_lowercase());
+        let retracted = article
+            .publication_types
+            .iter()
+            .any(|publication_type| publication_type == "Retracted Publication");
+
+        // Articles in PubMed Central can be read for free
+        let full_text_urls = article
+            .pmcid
+            .iter()
+            .map(|pmcid| format!("https://www.ncbi.nlm.nih.gov/pmc/articles/{pmcid}/"))
+            .collect::<Vec<_>>();
+
+        Article {
+            first_author,
+            year_published,
+            journal: article.journal,
+            title: article.title,
+            summary: article.summary,
+            doi: article.doi,
+            pmid: Some(article.pmid),
+            citations: None,
+            score: None,
+            authors,
+            date_published: article
+                .date_published
+                .as_deref()
+                .and_then(medline_full_date),
+            volume: article.volume,
+            issue: article.issue,
+            pages: article.pages,
+            issn: Vec::new(),
+            publisher: None,
+            publication_type,
+            languages,
+            keywords: Vec::new(),
+            mesh_terms,
+            topics: Vec::new(),
+            retracted,
+            open_access: !full_text_urls.is_empty(),
+            open_access_colour: None,
+            license: None,
+            full_text_urls,
+        }
+    }
+}
+
+#[cfg(feature = "openalex")]
+impl From<openalex::Work> for Article {
+    fn from(work: openalex::Work) -> Self {
+        // OpenAlex only gives display names, e.g. `Alice Martin`
+        let authors: Vec<ArticleAuthor> = work
+            .authors
+            .into_iter()
+            .map(|author| match author.name.rsplit_once(' ') {
+                Some((first_name, last_name)) => ArticleAuthor {
+                    first_name: Some(first_name.to_string()),
+                    last_name: Some(last_name.to_string()),
+                    initials: None,
+                    affiliations: author.institutions,
+                },
+                None => ArticleAuthor {
+                    last_name: Some(author.name),
+                    affiliations: author.institutions,
+                    ..Default::default()
+                },
+            })
+            .collect();
+
+        let first_author = authors.first().map(|author| {
+            [author.first_name.as_deref(), author.last_name.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+
+        let pages = match (work.first_page, work.last_page) {
+            (Some(first), Some(last)) if first != last => Some(format!("{first}-{last}")),
+            (first, last) => first.or(last),
+        };
+
+        Article {
+            first_author,
+            year_published: work.publication_year,
+            journal: work.journal,
+            title: work.title,
+            summary: work.abstract_text,
+            doi: work.doi,
+            pmid: work.pmid,
+            citations: Some(work.cited_by_count),
+            score: None,
+            authors,
+            date_published: work.publication_date,
+            volume: work.volume,
+            issue: work.issue,
+            pages,
+            issn: work.issns,
+            publisher: work.publisher,
+            publication_type: work.work_type,
+            languages: work.language.into_iter().collect(),
+            keywords: Vec::new(),
+            mesh_terms: Vec::new(),
+            topics: work.topics.into_iter().map(|topic| topic.name).collect(),
+            retracted: work.is_retracted,
+            open_access: work.is_oa,
+            open_access_colour: None,
+            license: work.license,
+            full_text_urls: work.oa_url.into_iter().collect(),
         }
     }
 }
@@ -112,31 +472,35 @@ impl From<lens::article::ArticleWithData> for Article {
 ///   direct references/citations are included, and so on.
 /// * `output_max_size`: Maximum number of articles to return (top N by score).
 /// * `search_for`: Specifies whether to search for references, citations, or both.
+/// * `exclude_retracted`: Drops retracted articles from the output. The next best ranked
+///   articles take their place, so up to `output_max_size` articles are still returned.
 /// * `api_key`: The API key for Lens.org. Required for using the Lens.org API.
-/// * `client`: Optional `reqwest::Client` for making HTTP requests. If `None`, a new client is created.
-///   Pass a custom client to configure proxies, timeouts, headers, etc.
+/// * `client`: Optional `LensClient` for making HTTP requests. If `None`, a new client is created.
+///   Pass a custom client to configure proxies, timeouts, headers, or the Lens API base URL.
 /// * `cache`: Optional cache backend for storing and retrieving data.
 ///
 /// # Returns
 ///
-/// A `Result` containing a `Vec` of `Article` structs sorted by score,
+/// A `Result` containing the `Snowball` with `Article` structs sorted by score,
 /// or an `Error` if the operation fails.
+#[allow(clippy::too_many_arguments)]
 pub async fn snowball<S>(
     id_list: &[S],
     max_depth: u8,
     output_max_size: usize,
     search_for: &SearchFor,
+    exclude_retracted: bool,
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
     cache: Option<&dyn CacheBackend>,
-) -> Result<Vec<Article>, Error>
+) -> Result<Snowball, Error>
 where
     S: AsRef<str>,
 {
     // Create a client if none provided
     let client_ref = match client {
         Some(c) => c,
-        None => &reqwest::Client::new(),
+        None => &LensClient::default(),
     };
 
     let snowball_id = lens::snowball(
@@ -149,16 +513,21 @@ where
     )
     .await?;
 
-    let score_hashmap = snowball_id.into_inner();
+    let score_hashmap = snowball_id.counts.into_inner();
 
     let mut s = score_hashmap.iter().collect::<Vec<_>>();
     s.sort_by_key(|x| std::cmp::Reverse(x.1));
-    s.truncate(output_max_size);
 
-    let selected_id: Vec<LensId> = s.iter().map(|(id, _)| (*id).clone()).collect();
-
-    let lens_articles =
-        lens::complete_articles(&selected_id, api_key, Some(client_ref), cache).await?;
+    let lens_articles = complete_best_ranked(
+        s.iter().map(|(id, _)| (*id).clone()),
+        output_max_size,
+        exclude_retracted,
+        async |selected_id: Vec<LensId>| {
+            lens::complete_articles(&selected_id, api_key, Some(client_ref), cache).await
+        },
+        |article| article.article_data.is_retracted == Some(true),
+    )
+    .await?;
 
     let mut articles_kv = lens_articles
         .into_iter()
@@ -177,12 +546,183 @@ where
 
     articles.sort_by_key(|v| v.score.unwrap_or_default());
 
+    Ok(Snowball {
+        articles,
+        incomplete_citations: snowball_id.incomplete_citations,
+    })
+}
+
+/// Expands a citation network starting from seed PMIDs, using PubMed instead of Lens.org.
+///
+/// Works like `snowball`, from the references and citations PubMed knows through
+/// PubMed Central. It needs no API key, but coverage is limited to biomedical articles
+/// and citation counts are not available.
+///
+/// # Arguments
+///
+/// * `id_list`: A slice of PMIDs. Other identifiers are ignored.
+/// * `max_depth`, `output_max_size`, `search_for`, `exclude_retracted`: as for `snowball`.
+/// * `client`: Optional `PubmedClient` for making HTTP requests. If `None`, a new client
+///   is created. Pass a client holding an NCBI API key to get the higher rate limit.
+/// * `cache`: Optional cache backend for storing and retrieving links and records.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of `Article` structs sorted by score,
+/// or an `Error` if no seed is a PMID or a request fails.
+pub async fn pubmed_snowball<S>(
+    id_list: &[S],
+    max_depth: u8,
+    output_max_size: usize,
+    search_for: &SearchFor,
+    exclude_retracted: bool,
+    client: Option<&PubmedClient>,
+    cache: Option<&dyn CacheBackend>,
+) -> Result<Vec<Article>, Error>
+where
+    S: AsRef<str>,
+{
+    let client_ref = match client {
+        Some(c) => c,
+        None => &PubmedClient::default(),
+    };
+
+    let score_hashmap =
+        pubmed::snowball(id_list, max_depth, search_for, Some(client_ref), cache).await?;
+
+    let mut s = score_hashmap.iter().collect::<Vec<_>>();
+    s.sort_by_key(|x| std::cmp::Reverse(x.1));
+
+    let mut articles = complete_best_ranked(
+        s.iter().map(|(pmid, _)| **pmid),
+        output_max_size,
+        exclude_retracted,
+        async |selected_pmids: Vec<u64>| {
+            let completed =
+                pubmed::Article::complete_articles(&selected_pmids, Some(client_ref), cache)
+                    .await?;
+            anyhow::Ok(completed.into_iter().map(Article::from).collect())
+        },
+        |article: &Article| article.retracted,
+    )
+    .await?;
+
+    for article in articles.iter_mut() {
+        let pmid = article.pmid.as_deref().and_then(|pmid| pmid.parse().ok());
+        article.score = pmid
+            .and_then(|pmid| score_hashmap.get(&pmid))
+            .map(|x| *x as i32);
+    }
+
+    let mut articles = articles
+        .into_iter()
+        .filter(|article| article.score.is_some())
+        .collect::<Vec<_>>();
+
+    articles.sort_by_key(|v| v.score.unwrap_or_default());
+
+    Ok(articles)
+}
+
+/// Expands a citation network starting from a set of seed articles, using an imported
+/// OpenAlex snapshot instead of Lens.org.
+///
+/// Works like `snowball`, with references, citations and metadata read from the
+/// database. It needs no API key nor network access, but only knows the works of the
+/// snapshot, and Lens IDs cannot be used as seeds.
+///
+/// # Arguments
+///
+/// * `id_list`: A slice of DOIs, PMIDs, PMCIDs, MAG or OpenAlex IDs. Other identifiers
+///   are ignored.
+/// * `max_depth`, `output_max_size`, `search_for`, `exclude_retracted`: as for `snowball`.
+/// * `database`: The OpenAlex database.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of `Article` structs sorted by score,
+/// or an `Error` if no seed is in the database or a query fails.
+#[cfg(feature = "openalex")]
+pub async fn openalex_snowball<S>(
+    id_list: &[S],
+    max_depth: u8,
+    output_max_size: usize,
+    search_for: &SearchFor,
+    exclude_retracted: bool,
+    database: &openalex::OpenAlexDatabase,
+) -> Result<Vec<Article>, Error>
+where
+    S: AsRef<str>,
+{
+    let score_hashmap = openalex::snowball(id_list, max_depth, search_for, database).await?;
+
+    let mut s = score_hashmap.iter().collect::<Vec<_>>();
+    s.sort_by_key(|x| std::cmp::Reverse(x.1));
+
+    let works = complete_best_ranked(
+        s.iter().map(|(id, _)| **id),
+        output_max_size,
+        exclude_retracted,
+        async |selected_ids: Vec<i64>| database.works(&selected_ids).await,
+        |work| work.is_retracted,
+    )
+    .await?;
+
+    let mut articles = works
+        .into_iter()
+        .filter_map(|work| {
+            let score = score_hashmap.get(&work.id).map(|x| *x as i32);
+            let mut article = Article::from(work);
+            article.score = score;
+            article.score.is_some().then_some(article)
+        })
+        .collect::<Vec<_>>();
+
+    articles.sort_by_key(|v| v.score.unwrap_or_default());
+
+    Ok(articles)
+}
+
+/// Completes the best ranked IDs, up to `output_max_size` articles.
+///
+/// Retracted articles are only known once completed, so with `exclude_retracted` the
+/// next best ranked IDs are completed in place of the retracted ones, until enough
+/// articles are left or the ranking is exhausted.
+async fn complete_best_ranked<I, T, E>(
+    mut ranked_ids: impl Iterator<Item = I>,
+    output_max_size: usize,
+    exclude_retracted: bool,
+    mut complete: impl AsyncFnMut(Vec<I>) -> Result<Vec<T>, E>,
+    is_retracted: impl Fn(&T) -> bool,
+) -> Result<Vec<T>, E> {
+    let mut articles = Vec::new();
+
+    loop {
+        let wanted = output_max_size.saturating_sub(articles.len());
+        let selected_ids: Vec<I> = ranked_ids.by_ref().take(wanted).collect();
+        if selected_ids.is_empty() {
+            break;
+        }
+
+        let completed = complete(selected_ids).await?;
+        articles.extend(
+            completed
+                .into_iter()
+                .filter(|article| !(exclude_retracted && is_retracted(article))),
+        );
+
+        if !exclude_retracted {
+            break;
+        }
+    }
+
     Ok(articles)
 }
 
 /// Fetches full article metadata for a mixed list of raw identifiers.
 ///
-/// Accepts DOIs, PMIDs, and Lens IDs in any combination. Resolution from
+/// Accepts DOIs, PMIDs, and Lens IDs in any combination. Identifiers that cannot be
+/// resolved are left out; use `resolve_ids` to find out which and why. Resolution from
 /// DOI/PMID to internal `LensId` is handled automatically, using the id-mapping
 /// and article-data caches as both a read and write layer.
 ///
@@ -190,7 +730,7 @@ where
 ///
 /// * `raw_ids` – Slice of identifiers in any supported format.
 /// * `api_key` – Lens.org API key.
-/// * `client` – Optional `reqwest::Client`; a new one is created if `None`.
+/// * `client` – Optional `LensClient`; a new one is created if `None`.
 /// * `cache` – Optional cache backend; results are stored when provided.
 ///
 /// # Returns
@@ -199,21 +739,162 @@ where
 pub async fn enrich_by_raw_ids(
     raw_ids: &[&str],
     api_key: &str,
-    client: Option<&reqwest::Client>,
+    client: Option<&LensClient>,
     cache: Option<&dyn lens::cache::CacheBackend>,
 ) -> Result<Vec<Article>, Error> {
     let articles = lens::complete_articles_by_raw_ids(raw_ids, api_key, client, cache).await?;
     Ok(articles.into_iter().map(Article::from).collect())
 }
 
+/// Resolution of one raw identifier, as returned by `resolve_ids`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IdResolution {
+    /// The identifier as given.
+    pub input: String,
+    /// The normalised identifier, when the input could be parsed.
+    pub identifier: Option<String>,
+    pub status: ResolutionStatus,
+    pub lens_id: Option<String>,
+    pub article: Option<Article>,
+    /// All matching Lens IDs, when ambiguous.
+    #[serde(default)]
+    pub candidates: Vec<String>,
+}
+
+impl From<lens::resolve::Resolution> for IdResolution {
+    fn from(resolution: lens::resolve::Resolution) -> Self {
+        IdResolution {
+            input: resolution.input,
+            identifier: resolution.identifier,
+            status: resolution.status,
+            lens_id: resolution.lens_id.map(|id| id.as_ref().to_string()),
+            article: resolution.article.map(Article::from),
+            candidates: resolution
+                .candidates
+                .iter()
+                .map(|id| id.as_ref().to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Resolves a mixed list of raw identifiers, reporting the outcome for each of them.
+///
+/// Unlike `enrich_by_raw_ids`, which only returns the articles that were found, this
+/// tells for every input whether it is resolved, invalid, not found or ambiguous.
+///
+/// # Arguments
+///
+/// * `raw_ids` – Slice of identifiers in any supported format.
+/// * `api_key` – Lens.org API key.
+/// * `client` – Optional `LensClient`; a new one is created if `None`.
+/// * `cache` – Optional cache backend; results are stored when provided.
+///
+/// # Returns
+///
+/// A `Vec<IdResolution>` with one entry per identifier, in order.
+pub async fn resolve_ids(
+    raw_ids: &[&str],
+    api_key: &str,
+    client: Option<&LensClient>,
+    cache: Option<&dyn lens::cache::CacheBackend>,
+) -> Result<Vec<IdResolution>, Error> {
+    let resolutions = lens::resolve::resolve_raw_ids(raw_ids, api_key, client, cache).await?;
+    Ok(resolutions.into_iter().map(IdResolution::from).collect())
+}
+
+/// Fills the cache with the citation network around a set of seed articles, without scoring.
+///
+/// See `lens::prefetch::prefetch` for the meaning of each argument. Everything goes
+/// through the cache first, so an interrupted prefetch is resumed by calling it again.
+///
+/// # Returns
+///
+/// The final `PrefetchProgress` once everything is cached.
+#[allow(clippy::too_many_arguments)]
+pub async fn prefetch<S, F>(
+    id_list: &[S],
+    max_depth: u8,
+    search_for: &SearchFor,
+    with_metadata: bool,
+    api_key: &str,
+    client: Option<&LensClient>,
+    cache: &dyn CacheBackend,
+    on_progress: F,
+) -> Result<lens::prefetch::PrefetchProgress, Error>
+where
+    S: AsRef<str>,
+    F: FnMut(&lens::prefetch::PrefetchProgress),
+{
+    let progress = lens::prefetch::prefetch(
+        id_list,
+        max_depth,
+        search_for,
+        with_metadata,
+        api_key,
+        client,
+        cache,
+        on_progress,
+    )
+    .await?;
+
+    Ok(progress)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    /// Helper function to get API key from environment
-    #[cfg(feature = "cache-sqlite")]
-    fn get_api_key() -> String {
-        dotenvy::var("LENS_API_KEY").expect("LENS_API_KEY must be set in .env file")
+    #[cfg(feature = "openalex")]
+    #[tokio::test]
+    async fn test_openalex_snowball() {
+        let database = openalex::tests::create_test_database().await;
+
+        let articles = openalex_snowball(
+            &["10.1000/one"],
+            2,
+            usize::MAX,
+            &SearchFor::Both,
+            false,
+            &database,
+        )
+        .await
+        .unwrap();
+        let scores: Vec<(Option<&str>, Option<i32>)> = articles
+            .iter()
+            .map(|article| (article.title.as_deref(), article.score))
+            .collect();
+        assert_eq!(scores.len(), 5);
+        assert_eq!(scores[4], (Some("Work one"), Some(3)));
+
+        let work_one = &articles[4];
+        // The current name of the author, from the imported authors
+        assert_eq!(work_one.first_author.as_deref(), Some("Alice M. Martin"));
+        assert_eq!(work_one.authors[0].last_name.as_deref(), Some("Martin"));
+        assert_eq!(work_one.journal.as_deref(), Some("PLOS ONE"));
+        assert_eq!(work_one.issn, ["1932-6203"]);
+        assert_eq!(work_one.topics, ["COVID-19", "Sepsis"]);
+        assert_eq!(work_one.doi.as_deref(), Some("10.1000/one"));
+        assert_eq!(work_one.pages.as_deref(), Some("e1"));
+        assert_eq!(
+            work_one.summary.as_deref(),
+            Some("The abstract of work one.")
+        );
+        assert_eq!(work_one.full_text_urls, ["https://example.org/one.pdf"]);
+
+        // The retracted W4 is replaced by nothing, as every work was already returned
+        let articles = openalex_snowball(
+            &["10.1000/one"],
+            2,
+            usize::MAX,
+            &SearchFor::Both,
+            true,
+            &database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(articles.len(), 4);
+        assert!(articles.iter().all(|article| !article.retracted));
     }
 
     /// Test that the full snowball API (including article completion) works completely offline when cache is populated.
@@ -227,7 +908,7 @@ mod tests {
     async fn test_snowball_offline_with_cache() {
         use crate::lens::cache::sqlite::SqliteBackend;
 
-        let api_key = get_api_key();
+        let stub = lens::stub::StubLens::start().await;
 
         // Create an in-memory cache
         let cache = SqliteBackend::from_url("sqlite::memory:")
@@ -235,22 +916,24 @@ mod tests {
             .expect("Failed to create cache backend");
 
         // Use a known PMID
-        let pmid = "11748933";
+        let pmid = "30000001";
         let ids = vec![pmid];
 
         // Step 1: Populate cache with normal client (online)
-        println!("Step 1: Populating cache with snowball() using normal client (online)...");
+        println!("Step 1: Populating cache with snowball() using the stub client (online)...");
         let result1 = snowball(
             &ids,
             1,  // depth 1
             20, // get 20 articles
             &SearchFor::References,
-            &api_key,
-            None, // Use default client
+            false,
+            "stub-key",
+            Some(&stub.client()),
             Some(&cache),
         )
         .await
-        .expect("First snowball call with normal client should succeed");
+        .expect("First snowball call with normal client should succeed")
+        .articles;
 
         assert!(
             !result1.is_empty(),
@@ -270,11 +953,17 @@ mod tests {
 
         // Step 2: Create a broken client that cannot make network requests
         println!("Step 2: Creating broken client (simulating offline)...");
-        let broken_client = reqwest::Client::builder()
-            .proxy(reqwest::Proxy::all("http://0.0.0.0:1").expect("Failed to create invalid proxy"))
-            .timeout(std::time::Duration::from_secs(1)) // Fast timeout for quick failure
-            .build()
-            .expect("Failed to build broken client");
+        let broken_client = LensClient::from(
+            reqwest::Client::builder()
+                .proxy(
+                    reqwest::Proxy::all("http://0.0.0.0:1")
+                        .expect("Failed to create invalid proxy"),
+                )
+                .timeout(std::time::Duration::from_secs(1)) // Fast timeout for quick failure
+                .build()
+                .expect("Failed to build broken client"),
+        )
+        .with_retry_policy(crate::lens::retry::RetryPolicy::none());
 
         println!("  ✓ Client configured to fail all network requests");
 
@@ -285,7 +974,8 @@ mod tests {
             1,
             20,
             &SearchFor::References,
-            &api_key,
+            false,
+            "stub-key",
             Some(&broken_client),
             None, // No cache
         )
@@ -306,12 +996,14 @@ mod tests {
             1,
             20,
             &SearchFor::References,
-            &api_key,
+            false,
+            "stub-key",
             Some(&broken_client),
             Some(&cache),
         )
         .await
-        .expect("Second snowball call should succeed from cache despite broken client");
+        .expect("Second snowball call should succeed from cache despite broken client")
+        .articles;
 
         assert_eq!(
             result1.len(),
@@ -356,6 +1048,19 @@ mod tests {
             external_ids: None,
             authors: None,
             source: None,
+            date_published: None,
+            volume: None,
+            issue: None,
+            start_page: None,
+            end_page: None,
+            publication_type: None,
+            languages: None,
+            keywords: None,
+            mesh_terms: None,
+            is_retracted: None,
+            is_open_access: None,
+            open_access: None,
+            source_urls: None,
         };
 
         let article: Article = lens_article.into();
@@ -374,20 +1079,20 @@ mod tests {
     async fn test_enrich_by_raw_ids_cache() {
         use crate::lens::cache::sqlite::SqliteBackend;
 
-        let api_key = get_api_key();
+        let stub = lens::stub::StubLens::start().await;
         let cache = SqliteBackend::from_url("sqlite::memory:")
             .await
             .expect("Failed to create in-memory cache");
 
-        const DOIS: &[&str] = &["10.2196/64348", "10.1093/ckj/sfaf308"];
+        const DOIS: &[&str] = &["10.1000/stub.a", "10.1000/stub.b"];
         const EXPECTED_TITLES: &[&str] = &[
-            "Token Probabilities to Mitigate Large Language Models Overconfidence in Answering Medical Questions: Quantitative Study",
-            "ECOSBot: a multicenter validation pilot study of a generative AI tool for OSCE-based nephrology training",
+            "Snowball sampling for systematic reviews",
+            "Forward and backward citation searching",
         ];
 
-        // --- First call: real network, populates cache ---
-        println!("Pass 1: fetching via Lens API and populating cache...");
-        let result1 = enrich_by_raw_ids(DOIS, &api_key, None, Some(&cache))
+        // --- First call: stub network, populates cache ---
+        println!("Pass 1: fetching via the stub Lens API and populating cache...");
+        let result1 = enrich_by_raw_ids(DOIS, "stub-key", Some(&stub.client()), Some(&cache))
             .await
             .expect("First enrich_by_raw_ids call should succeed");
 
@@ -405,22 +1110,245 @@ mod tests {
 
         // --- Second call: broken client, must hit cache ---
         println!("Pass 2: re-querying with broken client (offline cache hit)...");
-        let broken_client = reqwest::Client::builder()
-            .proxy(reqwest::Proxy::all("http://0.0.0.0:1").expect("invalid proxy"))
-            .timeout(std::time::Duration::from_secs(1))
-            .build()
-            .expect("Failed to build broken client");
+        let broken_client = LensClient::from(
+            reqwest::Client::builder()
+                .proxy(reqwest::Proxy::all("http://0.0.0.0:1").expect("invalid proxy"))
+                .timeout(std::time::Duration::from_secs(1))
+                .build()
+                .expect("Failed to build broken client"),
+        )
+        .with_retry_policy(crate::lens::retry::RetryPolicy::none());
 
-        let result2 = enrich_by_raw_ids(DOIS, &api_key, Some(&broken_client), Some(&cache))
+        let result2 = enrich_by_raw_ids(DOIS, "stub-key", Some(&broken_client), Some(&cache))
             .await
             .expect("Second enrich_by_raw_ids call should succeed from cache");
 
         assert_eq!(result1.len(), result2.len(), "Cache hit count must match");
 
-        let titles1: Vec<Option<&str>> = result1.iter().map(|a| a.title.as_deref()).collect();
-        let titles2: Vec<Option<&str>> = result2.iter().map(|a| a.title.as_deref()).collect();
+        let mut titles1: Vec<Option<&str>> = result1.iter().map(|a| a.title.as_deref()).collect();
+        let mut titles2: Vec<Option<&str>> = result2.iter().map(|a| a.title.as_deref()).collect();
+        titles1.sort();
+        titles2.sort();
         assert_eq!(titles1, titles2, "Titles must be identical on cache hit");
 
         println!("  ✓ Cache hit confirmed — identical results without network access");
     }
+
+    /// The full public workflow (snowball, scoring and completion) against the stub Lens server.
+    #[tokio::test]
+    async fn test_snowball_with_stub_lens() {
+        let stub = lens::stub::StubLens::start().await;
+
+        let articles = snowball(
+            &["30000001", "10.1000/stub.b"],
+            1,
+            3,
+            &SearchFor::Both,
+            false,
+            "stub-key",
+            Some(&stub.client()),
+            None,
+        )
+        .await
+        .expect("Snowball against the stub should succeed")
+        .articles;
+
+        assert_eq!(articles.len(), 3);
+        assert!(
+            articles
+                .iter()
+                .all(|a| a.title.is_some() && a.score.is_some())
+        );
+
+        // Sorted by ascending score: D (shared reference) and E (cites both seeds) come last
+        let mut top_dois: Vec<&str> = articles[1..]
+            .iter()
+            .filter_map(|a| a.doi.as_deref())
+            .collect();
+        top_dois.sort();
+        assert_eq!(top_dois, ["10.1000/stub.d", "10.1000/stub.e"]);
+        assert_eq!(articles[0].score, Some(1));
+        assert_eq!(articles[2].score, Some(2));
+    }
+
+    /// Bibliographic metadata needed by reference managers is fetched and flattened.
+    #[tokio::test]
+    async fn test_enrich_by_raw_ids_full_metadata() {
+        let stub = lens::stub::StubLens::start().await;
+
+        let articles = enrich_by_raw_ids(&["30000001"], "stub-key", Some(&stub.client()), None)
+            .await
+            .expect("Enrichment against the stub should succeed");
+
+        assert_eq!(articles.len(), 1);
+        let article = &articles[0];
+
+        assert_eq!(article.first_author.as_deref(), Some("Alice Martin"));
+        assert_eq!(article.authors.len(), 2);
+        assert_eq!(article.authors[1].last_name.as_deref(), Some("Keller"));
+        assert_eq!(
+            article.authors[0].affiliations,
+            ["University of Stubville", "Stub Institute of Evidence"]
+        );
+        assert_eq!(article.date_published.as_deref(), Some("2019-03-14"));
+        assert_eq!(article.volume.as_deref(), Some("12"));
+        assert_eq!(article.issue.as_deref(), Some("3"));
+        assert_eq!(article.pages.as_deref(), Some("101-110"));
+        assert_eq!(article.issn, ["12345679", "2345678X"]);
+        assert_eq!(article.publisher.as_deref(), Some("Stub Press"));
+        assert_eq!(article.publication_type.as_deref(), Some("journal article"));
+        assert_eq!(article.languages, ["en"]);
+        assert_eq!(article.keywords, ["snowballing", "citation chasing"]);
+        assert_eq!(
+            article.mesh_terms,
+            ["Review Literature as Topic", "Bibliometrics/methods"]
+        );
+        assert!(!article.retracted);
+        assert!(article.open_access);
+        assert_eq!(article.open_access_colour.as_deref(), Some("gold"));
+        assert_eq!(article.license.as_deref(), Some("cc-by"));
+        assert_eq!(
+            article.full_text_urls,
+            ["https://stub.example/a.pdf", "https://stub.example/a"]
+        );
+    }
+
+    /// Retracted articles are flagged, and replaced by the next best ones when excluded.
+    #[tokio::test]
+    async fn test_snowball_exclude_retracted() {
+        let stub = lens::stub::StubLens::start().await;
+        let seeds = ["30000001", "10.1000/stub.b"];
+
+        let articles = snowball(
+            &seeds,
+            1,
+            3,
+            &SearchFor::Both,
+            false,
+            "stub-key",
+            Some(&stub.client()),
+            None,
+        )
+        .await
+        .expect("Snowball against the stub should succeed")
+        .articles;
+        let retracted: Vec<_> = articles.iter().filter(|a| a.retracted).collect();
+        assert_eq!(retracted.len(), 1);
+        assert_eq!(retracted[0].doi.as_deref(), Some("10.1000/stub.d"));
+
+        let articles = snowball(
+            &seeds,
+            1,
+            3,
+            &SearchFor::Both,
+            true,
+            "stub-key",
+            Some(&stub.client()),
+            None,
+        )
+        .await
+        .expect("Snowball against the stub should succeed")
+        .articles;
+        assert_eq!(articles.len(), 3);
+        assert!(articles.iter().all(|a| !a.retracted));
+        assert_eq!(articles[2].doi.as_deref(), Some("10.1000/stub.e"));
+    }
+
+    /// Retracted articles are replaced by the next best ranked ones, until the ranking
+    /// runs out.
+    #[tokio::test]
+    async fn complete_best_ranked_replaces_retracted() {
+        let retracted = [2, 3, 5];
+        let mut batches = Vec::new();
+        let completed = complete_best_ranked(
+            1..=6,
+            3,
+            true,
+            async |ids: Vec<i32>| {
+                batches.push(ids.clone());
+                Ok::<_, Error>(ids)
+            },
+            |id| retracted.contains(id),
+        )
+        .await
+        .unwrap();
+        assert_eq!(completed, [1, 4, 6]);
+        assert_eq!(batches, [vec![1, 2, 3], vec![4, 5], vec![6]]);
+
+        let completed = complete_best_ranked(
+            1..=4,
+            3,
+            true,
+            async |ids: Vec<i32>| Ok::<_, Error>(ids),
+            |id| retracted.contains(id),
+        )
+        .await
+        .unwrap();
+        assert_eq!(completed, [1, 4]);
+
+        // Kept as completed otherwise
+        let completed = complete_best_ranked(
+            1..=6,
+            3,
+            false,
+            async |ids: Vec<i32>| Ok::<_, Error>(ids),
+            |id| retracted.contains(id),
+        )
+        .await
+        .unwrap();
+        assert_eq!(completed, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn pubmed_snowball_ranks_completed_articles() {
+        let stub = pubmed::stub::StubPubmed::start().await;
+        let client = stub.client();
+
+        let articles = pubmed_snowball(
+            &["31000001", "31000002"],
+            2,
+            100,
+            &SearchFor::Both,
+            true,
+            Some(&client),
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Only the fixture records can be completed, and scores are sorted ascending
+        let ranked: Vec<(Option<&str>, Option<i32>)> = articles
+            .iter()
+            .map(|article| (article.pmid.as_deref(), article.score))
+            .collect();
+        assert_eq!(
+            ranked,
+            [
+                (Some("31000003"), Some(3)),
+                (Some("31000002"), Some(6)),
+                (Some("31000001"), Some(7)),
+            ]
+        );
+
+        let first = &articles[2];
+        assert_eq!(first.first_author.as_deref(), Some("Martin C"));
+        assert_eq!(first.authors[1].last_name.as_deref(), Some("Nguyen"));
+        assert_eq!(first.authors[1].initials.as_deref(), Some("TB"));
+        assert_eq!(first.year_published, Some(2020));
+        assert_eq!(first.date_published.as_deref(), Some("2020-03-05"));
+        assert_eq!(first.languages, ["en"]);
+        assert_eq!(first.publication_type.as_deref(), Some("multicenter study"));
+        assert!(first.mesh_terms.contains(&"COVID-19/blood".to_string()));
+        assert!(first.mesh_terms.contains(&"COVID-19/diagnosis".to_string()));
+        assert!(first.open_access);
+        assert!(!first.retracted);
+
+        let second = &articles[1];
+        assert_eq!(
+            second.authors[1].last_name.as_deref(),
+            Some("BibliZap Study Group")
+        );
+        assert_eq!(second.date_published, None, "No day in `2021 Feb`");
+        assert!(!second.open_access);
+    }
 }
@@ -0,0 +1,926 @@
+//! Citation network read from an OpenAlex database.
+//!
+//! The database is built from the OpenAlex works snapshot by `biblizap-openalex`, into
+//! the tables of [`schema`]: `openalex_works` (metadata), `openalex_work_ids` (DOIs,
+//! PMIDs, PMCIDs and MAG IDs) and `openalex_references` (`referenced_works` edges,
+//! indexed both ways). Every lookup is a query on these tables, so snowballing needs
+//! neither network access nor API key, but only knows the works of the imported snapshot.
+//!
+//! When the snapshot's sources, authors and topics were imported too
+//! (`openalex_sources`, `openalex_authors` and `openalex_topics`), works are given the
+//! current name, ISSNs and publisher of their journal, the current name and ORCID of
+//! their authors, and their topics.
+//!
+//! `openalex_lens_ids` links works to the Lens articles with the same identifiers. With
+//! the identifiers of `openalex_work_ids`, it makes a crosswalk between OpenAlex IDs,
+//! Lens IDs, DOIs and PMIDs, so that results of both sources can be joined. It is filled
+//! from the ID mappings of the Lens cache by [`OpenAlexDatabase::link_cached_lens_ids`].
+
+pub mod schema;
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use thiserror::Error;
+
+use crate::common::SearchFor;
+use crate::identifier::{IdType, Identifier};
+use crate::lens::cache::CacheBackend;
+use crate::lens::error::LensError;
+use crate::lens::lensid::LensId;
+
+/// Number of cached ID mappings linked at a time.
+const LINK_PAGE_SIZE: usize = 10_000;
+
+#[derive(Debug, Error)]
+pub enum OpenAlexError {
+    #[error("OpenAlex database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("None of the identifiers is in the OpenAlex database")]
+    NoSeedFound,
+    #[error("Lens cache error: {0}")]
+    Cache(#[from] LensError),
+}
+
+pub type Result<T> = std::result::Result<T, OpenAlexError>;
+
+/// An author of a work, as stored by the importer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkAuthor {
+    /// OpenAlex author number, without the `A`
+    pub id: Option<i64>,
+    pub name: String,
+    #[serde(default)]
+    pub institutions: Vec<String>,
+    /// ORCID URL, from `openalex_authors`
+    #[serde(default)]
+    pub orcid: Option<String>,
+}
+
+/// A topic of a work, from `openalex_topics`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkTopic {
+    /// OpenAlex topic number, without the `T`
+    pub id: i64,
+    pub name: String,
+    /// The subfield, field and domain of the topic, from the narrowest to the broadest
+    pub subfield: Option<String>,
+    pub field: Option<String>,
+    pub domain: Option<String>,
+}
+
+/// The metadata of a work.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Work {
+    /// OpenAlex work number, without the `W`
+    pub id: i64,
+    pub doi: Option<String>,
+    pub pmid: Option<String>,
+    pub title: Option<String>,
+    /// Plain text, rebuilt by the importer from the inverted index OpenAlex gives
+    pub abstract_text: Option<String>,
+    pub publication_year: Option<i32>,
+    /// `YYYY-MM-DD`
+    pub publication_date: Option<String>,
+    /// OpenAlex work type, e.g. `article`, `review` or `letter`
+    pub work_type: Option<String>,
+    /// ISO 639-1 code
+    pub language: Option<String>,
+    pub journal: Option<String>,
+    /// ISSNs of the journal
+    pub issns: Vec<String>,
+    pub publisher: Option<String>,
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    pub first_page: Option<String>,
+    pub last_page: Option<String>,
+    pub authors: Vec<WorkAuthor>,
+    /// Best match first
+    pub topics: Vec<WorkTopic>,
+    pub is_retracted: bool,
+    pub is_oa: bool,
+    pub oa_url: Option<String>,
+    pub license: Option<String>,
+    pub cited_by_count: i32,
+}
+
+/// The identifiers of a work in every source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crosswalk {
+    /// OpenAlex work number, without the `W`
+    pub work_id: i64,
+    /// The Lens articles linked to the work
+    pub lens_ids: Vec<LensId>,
+    /// The DOIs, PMIDs, PMCIDs and MAG IDs of the work
+    pub identifiers: Vec<Identifier>,
+}
+
+#[derive(sqlx::FromRow)]
+struct WorkRow {
+    id: i64,
+    doi: Option<String>,
+    pmid: Option<String>,
+    title: Option<String>,
+    abstract_text: Option<String>,
+    publication_year: Option<i32>,
+    publication_date: Option<String>,
+    work_type: Option<String>,
+    language: Option<String>,
+    journal: Option<String>,
+    issns: Vec<String>,
+    publisher: Option<String>,
+    volume: Option<String>,
+    issue: Option<String>,
+    first_page: Option<String>,
+    last_page: Option<String>,
+    authorships: String,
+    topic_ids: Vec<i64>,
+    is_retracted: bool,
+    is_oa: bool,
+    oa_url: Option<String>,
+    license: Option<String>,
+    cited_by_count: i32,
+}
+
+impl From<WorkRow> for Work {
+    fn from(row: WorkRow) -> Self {
+        Work {
+            id: row.id,
+            doi: row.doi,
+            pmid: row.pmid,
+            title: row.title,
+            abstract_text: row.abstract_text,
+            publication_year: row.publication_year,
+            publication_date: row.publication_date,
+            work_type: row.work_type,
+            language: row.language,
+            journal: row.journal,
+            issns: row.issns,
+            publisher: row.publisher,
+            volume: row.volume,
+            issue: row.issue,
+            first_page: row.first_page,
+            last_page: row.last_page,
+            authors: serde_json::from_str(&row.authorships).unwrap_or_else(|e| {
+                log::warn!("Invalid authors of OpenAlex work W{}: {e}", row.id);
+                Vec::new()
+            }),
+            topics: Vec::new(),
+            is_retracted: row.is_retracted,
+            is_oa: row.is_oa,
+            oa_url: row.oa_url,
+            license: row.license,
+            cited_by_count: row.cited_by_count,
+        }
+    }
+}
+
+/// Read access to an OpenAlex database imported by `biblizap-openalex`.
+#[derive(Debug, Clone)]
+pub struct OpenAlexDatabase {
+    pool: PgPool,
+}
+
+impl OpenAlexDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Whether the OpenAlex tables exist in the database, i.e. a dump has been imported.
+    pub async fn is_imported(&self) -> Result<bool> {
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT to_regclass('openalex_references') IS NOT NULL")
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(exists)
+    }
+
+    /// Finds the work of each identifier. DOIs, PMIDs, PMCIDs and MAG IDs are looked up in
+    /// `openalex_work_ids`, and OpenAlex IDs in `openalex_works`; other identifiers and
+    /// the ones not found are left out. OpenAlex sometimes gives one DOI to several
+    /// works, in which case the oldest work, the lowest ID, is taken.
+    pub async fn resolve(&self, identifiers: &[Identifier]) -> Result<HashMap<Identifier, i64>> {
+        let mut resolved = HashMap::new();
+
+        let (openalex, external): (Vec<&Identifier>, Vec<&Identifier>) = identifiers
+            .iter()
+            .partition(|identifier| identifier.id_type() == IdType::OpenAlex);
+
+        let work_ids: Vec<i64> = openalex
+            .iter()
+            .filter_map(|identifier| identifier.value()[1..].parse().ok())
+            .collect();
+        let existing: Vec<(i64,)> =
+            sqlx::query_as("SELECT id FROM openalex_works WHERE id = ANY($1)")
+                .bind(&work_ids)
+                .fetch_all(&self.pool)
+                .await?;
+        for (id,) in existing {
+            if let Some(identifier) = Identifier::parse_as(IdType::OpenAlex, &format!("W{id}")) {
+                resolved.insert(identifier, id);
+            }
+        }
+
+        let external: Vec<&Identifier> = external
+            .into_iter()
+            .filter(|identifier| {
+                matches!(
+                    identifier.id_type(),
+                    IdType::Doi | IdType::Pmid | IdType::Pmcid | IdType::Mag
+                )
+            })
+            .collect();
+        let rows: Vec<(String, String, i64)> = sqlx::query_as(
+            r#"
+            SELECT id_type, value, MIN(work_id)
+            FROM openalex_work_ids
+            WHERE (id_type, value) IN (SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[]))
+            GROUP BY id_type, value
+            "#,
+        )
+        .bind(
+            external
+                .iter()
+                .map(|identifier| identifier.id_type().lens_field())
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            external
+                .iter()
+                .map(|identifier| identifier.value())
+                .collect::<Vec<_>>(),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for (id_type, value, work_id) in rows {
+            if let Some(identifier) = IdType::from_lens_field(&id_type)
+                .and_then(|id_type| Identifier::parse_as(id_type, &value))
+            {
+                resolved.insert(identifier, work_id);
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// The works referenced by each of `work_ids`, from `referenced_works`. Works without
+    /// references are left out.
+    pub async fn references(&self, work_ids: &[i64]) -> Result<HashMap<i64, Vec<i64>>> {
+        let rows: Vec<(i64, Vec<i64>)> = sqlx::query_as(
+            r#"
+            SELECT citing_work_id, ARRAY_AGG(cited_work_id)
+            FROM openalex_references
+            WHERE citing_work_id = ANY($1)
+            GROUP BY citing_work_id
+            "#,
+        )
+        .bind(work_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// The works citing each of `work_ids`, from the reverse index of `referenced_works`.
+    /// Works without citations are left out.
+    pub async fn citations(&self, work_ids: &[i64]) -> Result<HashMap<i64, Vec<i64>>> {
+        let rows: Vec<(i64, Vec<i64>)> = sqlx::query_as(
+            r#"
+            SELECT cited_work_id, ARRAY_AGG(citing_work_id)
+            FROM openalex_references
+            WHERE cited_work_id = ANY($1)
+            GROUP BY cited_work_id
+            "#,
+        )
+        .bind(work_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// The metadata of `work_ids`, in no particular order. Works not in the database are
+    /// left out.
+    ///
+    /// The journal, authors and topics are completed from the imported sources, authors
+    /// and topics; the names stored with the work are kept for the ones not imported.
+    pub async fn works(&self, work_ids: &[i64]) -> Result<Vec<Work>> {
+        let rows: Vec<WorkRow> = sqlx::query_as(
+            r#"
+            SELECT
+                w.id, w.doi,
+                (
+                    SELECT MIN(i.value) FROM openalex_work_ids i
+                    WHERE i.work_id = w.id AND i.id_type = 'pmid'
+                ) AS pmid,
+                w.title, w.abstract_text, w.publication_year, w.publication_date, w.work_type, w.language,
+                COALESCE(s.display_name, w.journal) AS journal,
+                COALESCE(s.issns, '{}') AS issns, s.publisher,
+                w.volume, w.issue, w.first_page, w.last_page,
+                w.authorships::TEXT AS authorships, w.topic_ids, w.is_retracted, w.is_oa,
+                w.oa_url, w.license, w.cited_by_count
+            FROM openalex_works w
+            LEFT JOIN openalex_sources s ON s.id = w.source_id
+            WHERE w.id = ANY($1)
+            "#,
+        )
+        .bind(work_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let topic_ids: Vec<Vec<i64>> = rows.iter().map(|row| row.topic_ids.clone()).collect();
+        let mut works: Vec<Work> = rows.into_iter().map(Work::from).collect();
+
+        let author_ids: Vec<i64> = works
+            .iter()
+            .flat_map(|work| work.authors.iter().filter_map(|author| author.id))
+            .collect();
+        let authors: HashMap<i64, (Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT id, display_name, orcid FROM openalex_authors WHERE id = ANY($1)",
+        )
+        .bind(&author_ids)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(id, name, orcid)| (id, (name, orcid)))
+        .collect();
+
+        let topics: HashMap<i64, WorkTopic> = sqlx::query_as(
+            r#"
+            SELECT id, display_name, subfield, field, domain
+            FROM openalex_topics
+            WHERE id = ANY($1) AND display_name IS NOT NULL
+            "#,
+        )
+        .bind(topic_ids.iter().flatten().copied().collect::<Vec<_>>())
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(id, name, subfield, field, domain)| {
+            let topic = WorkTopic {
+                id,
+                name,
+                subfield,
+                field,
+                domain,
+            };
+            (id, topic)
+        })
+        .collect();
+
+        for (work, topic_ids) in works.iter_mut().zip(topic_ids) {
+            for author in &mut work.authors {
+                if let Some((name, orcid)) = author.id.and_then(|id| authors.get(&id)) {
+                    if let Some(name) = name {
+                        author.name = name.clone();
+                    }
+                    author.orcid = orcid.clone();
+                }
+            }
+            work.topics = topic_ids
+                .iter()
+                .filter_map(|id| topics.get(id).cloned())
+                .collect();
+        }
+
+        Ok(works)
+    }
+
+    /// Links works to Lens articles from ID mappings of the Lens cache: each key is
+    /// resolved like [`OpenAlexDatabase::resolve`] and, when found, its work is linked to
+    /// the Lens ID. Returns the number of new links.
+    pub async fn link_lens_ids(&self, mappings: &[(String, LensId)]) -> Result<u64> {
+        let parsed: Vec<(Identifier, &LensId)> = mappings
+            .iter()
+            .filter_map(|(key, lens_id)| {
+                Identifier::parse(key).map(|identifier| (identifier, lens_id))
+            })
+            .collect();
+        let identifiers: Vec<Identifier> = parsed
+            .iter()
+            .map(|(identifier, _)| identifier.clone())
+            .collect();
+        let resolved = self.resolve(&identifiers).await?;
+
+        let (work_ids, lens_ids): (Vec<i64>, Vec<&str>) = parsed
+            .iter()
+            .filter_map(|(identifier, lens_id)| {
+                resolved
+                    .get(identifier)
+                    .map(|work_id| (*work_id, lens_id.as_ref()))
+            })
+            .unzip();
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO openalex_lens_ids (work_id, lens_id)
+            SELECT * FROM UNNEST($1::BIGINT[], $2::TEXT[])
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(work_ids)
+        .bind(lens_ids)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(inserted.rows_affected())
+    }
+
+    /// Links works to Lens articles from every ID mapping of the Lens cache, page by
+    /// page. Returns the number of mappings read and of new links.
+    pub async fn link_cached_lens_ids(&self, cache: &dyn CacheBackend) -> Result<(u64, u64)> {
+        let mut read = 0;
+        let mut linked = 0;
+        let mut after: Option<String> = None;
+        loop {
+            let mappings = cache
+                .list_id_mappings(after.as_deref(), LINK_PAGE_SIZE)
+                .await?;
+            let Some((last, _)) = mappings.last() else {
+                break;
+            };
+            after = Some(last.clone());
+            read += mappings.len() as u64;
+            linked += self.link_lens_ids(&mappings).await?;
+        }
+
+        Ok((read, linked))
+    }
+
+    /// The Lens articles linked to each of `work_ids`. Works without any are left out.
+    pub async fn lens_ids(&self, work_ids: &[i64]) -> Result<HashMap<i64, Vec<LensId>>> {
+        let rows: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT work_id, lens_id FROM openalex_lens_ids WHERE work_id = ANY($1) ORDER BY lens_id",
+        )
+        .bind(work_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut lens_ids: HashMap<i64, Vec<LensId>> = HashMap::new();
+        for (work_id, lens_id) in rows {
+            if let Ok(lens_id) = LensId::try_from(lens_id.as_str()) {
+                lens_ids.entry(work_id).or_default().push(lens_id);
+            }
+        }
+        Ok(lens_ids)
+    }
+
+    /// The work linked to each of `lens_ids`, the lowest ID when several are. Lens IDs
+    /// without a work are left out.
+    pub async fn lens_works(&self, lens_ids: &[LensId]) -> Result<HashMap<LensId, i64>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+            SELECT lens_id, MIN(work_id)
+            FROM openalex_lens_ids
+            WHERE lens_id = ANY($1)
+            GROUP BY lens_id
+            "#,
+        )
+        .bind(lens_ids.iter().map(AsRef::as_ref).collect::<Vec<&str>>())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(lens_id, work_id)| {
+                LensId::try_from(lens_id.as_str())
+                    .ok()
+                    .map(|lens_id| (lens_id, work_id))
+            })
+            .collect())
+    }
+
+    /// The identifiers in every source of the work of each identifier. Lens IDs are
+    /// looked up in `openalex_lens_ids`, the others like [`OpenAlexDatabase::resolve`];
+    /// identifiers without a work are left out.
+    pub async fn crosswalk(
+        &self,
+        identifiers: &[Identifier],
+    ) -> Result<HashMap<Identifier, Crosswalk>> {
+        let (lens, others): (Vec<Identifier>, Vec<Identifier>) = identifiers
+            .iter()
+            .cloned()
+            .partition(|identifier| identifier.id_type() == IdType::LensId);
+
+        let mut work_of = self.resolve(&others).await?;
+        let lens: Vec<(Identifier, LensId)> = lens
+            .into_iter()
+            .filter_map(|identifier| {
+                let lens_id = LensId::try_from(identifier.value()).ok()?;
+                Some((identifier, lens_id))
+            })
+            .collect();
+        let lens_works = self
+            .lens_works(
+                &lens
+                    .iter()
+                    .map(|(_, lens_id)| lens_id.clone())
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+        for (identifier, lens_id) in lens {
+            if let Some(work_id) = lens_works.get(&lens_id) {
+                work_of.insert(identifier, *work_id);
+            }
+        }
+
+        let work_ids: Vec<i64> = work_of
+            .values()
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let lens_ids = self.lens_ids(&work_ids).await?;
+        let rows: Vec<(i64, String, String)> = sqlx::query_as(
+            r#"
+            SELECT work_id, id_type, value
+            FROM openalex_work_ids
+            WHERE work_id = ANY($1)
+            ORDER BY id_type, value
+            "#,
+        )
+        .bind(&work_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut external: HashMap<i64, Vec<Identifier>> = HashMap::new();
+        for (work_id, id_type, value) in rows {
+            if let Some(identifier) = IdType::from_lens_field(&id_type)
+                .and_then(|id_type| Identifier::parse_as(id_type, &value))
+            {
+                external.entry(work_id).or_default().push(identifier);
+            }
+        }
+
+        Ok(work_of
+            .into_iter()
+            .map(|(identifier, work_id)| {
+                let crosswalk = Crosswalk {
+                    work_id,
+                    lens_ids: lens_ids.get(&work_id).cloned().unwrap_or_default(),
+                    identifiers: external.get(&work_id).cloned().unwrap_or_default(),
+                };
+                (identifier, crosswalk)
+            })
+            .collect())
+    }
+}
+
+/// Performs a snowballing expansion of a citation network in the OpenAlex database.
+///
+/// Scores are computed like `lens::snowball`: within a depth, each linked work inherits
+/// the count of the work linking to it, and counts are added across depths. Seeds count
+/// once each.
+///
+/// # Arguments
+///
+/// * `id_list`: The seed identifiers: DOIs, PMIDs, PMCIDs, MAG or OpenAlex IDs. Others,
+///   and seeds missing from the database, are ignored.
+/// * `max_depth`: The maximum depth of the snowballing process.
+/// * `search_for`: Specifies whether to follow references, citations, or both.
+/// * `database`: The OpenAlex database.
+///
+/// # Returns
+///
+/// A `Result` containing the occurrence count of every work found, by OpenAlex work
+/// number, or an error if no seed is in the database or a query fails.
+pub async fn snowball<S>(
+    id_list: &[S],
+    max_depth: u8,
+    search_for: &SearchFor,
+    database: &OpenAlexDatabase,
+) -> Result<HashMap<i64, usize>>
+where
+    S: AsRef<str>,
+{
+    let identifiers: Vec<Identifier> = id_list
+        .iter()
+        .filter_map(|raw| Identifier::parse(raw.as_ref()))
+        .collect();
+    let mut current_counts: HashMap<i64, usize> = database
+        .resolve(&identifiers)
+        .await?
+        .into_values()
+        .map(|work_id| (work_id, 1))
+        .collect();
+    if current_counts.is_empty() {
+        return Err(OpenAlexError::NoSeedFound);
+    }
+
+    let mut all_counts: HashMap<i64, usize> = HashMap::new();
+
+    for _ in 0..max_depth {
+        let parents: Vec<i64> = current_counts.keys().copied().collect();
+        let mut next_counts: HashMap<i64, usize> = HashMap::new();
+
+        let mut links = Vec::new();
+        if matches!(search_for, SearchFor::References | SearchFor::Both) {
+            links.push(database.references(&parents).await?);
+        }
+        if matches!(search_for, SearchFor::Citations | SearchFor::Both) {
+            links.push(database.citations(&parents).await?);
+        }
+
+        // MULTIPLICATION: each child inherits the parent's count
+        for (parent, children) in links.into_iter().flatten() {
+            let parent_count = current_counts.get(&parent).copied().unwrap_or_default();
+            for child in children {
+                *next_counts.entry(child).or_default() += parent_count;
+            }
+        }
+
+        if next_counts.is_empty() {
+            break;
+        }
+
+        // ADDITION: add this depth's counts to the total
+        for (work_id, count) in &next_counts {
+            *all_counts.entry(*work_id).or_default() += count;
+        }
+        current_counts = next_counts;
+    }
+
+    Ok(all_counts)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An OpenAlex database in its own schema of `TEST_POSTGRES_DATABASE_URL`, holding
+    /// the tables of [`schema`] and seven works:
+    ///
+    /// - W1 (DOI `10.1000/one`, in source S21 by author A11, topics T32 and T31)
+    ///   references W2 and W3
+    /// - W4 (PMID `40000004`, retracted) references W2
+    /// - W5 references W1
+    /// - W6 and W7 share the DOI `10.1000/shared`
+    ///
+    /// The schema is dropped when the returned database is.
+    pub(crate) async fn create_test_database() -> TestDatabase {
+        let url = std::env::var("TEST_POSTGRES_DATABASE_URL")
+            .unwrap_or_else(|_| "postgres://postgres@localhost/lens_test".to_string());
+        let schema_name = format!(
+            "test_openalex_{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        );
+
+        let pool = PgPool::connect(&url).await.unwrap();
+        // The schema name only holds digits and underscores
+        sqlx::raw_sql(sqlx::AssertSqlSafe(format!("CREATE SCHEMA {schema_name}")))
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+        let pool = PgPool::connect(&format!("{url}?options=-c%20search_path%3D{schema_name}"))
+            .await
+            .unwrap();
+
+        schema::ensure_schema(&pool).await.unwrap();
+
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO openalex_works (id, doi, title, abstract_text, publication_year,
+                publication_date, work_type, source_id, journal, first_page, last_page,
+                authorships, topic_ids, is_retracted, is_oa, oa_url, cited_by_count)
+            VALUES
+                (1, '10.1000/one', 'Work one', 'The abstract of work one.', 2020, '2020-03-05',
+                 'article', 21, 'PLoS ONE', 'e1', NULL,
+                 '[{"id": 11, "name": "Alice Martin", "institutions": ["Université Paris Cité"]}, {"id": null, "name": "Bob Chen"}]',
+                 '{32, 31, 39}', FALSE, TRUE, 'https://example.org/one.pdf', 1),
+                (2, NULL, 'Work two', NULL, 2018, NULL, 'review', 29, 'Old Name', '10', '20', '[]', '{}', FALSE, FALSE, NULL, 2),
+                (3, NULL, 'Work three', NULL, 2015, NULL, 'article', NULL, NULL, NULL, NULL, '[]', '{}', FALSE, FALSE, NULL, 1),
+                (4, NULL, 'Work four', NULL, 2021, NULL, 'article', NULL, NULL, NULL, NULL, '[]', '{}', TRUE, FALSE, NULL, 0),
+                (5, NULL, 'Work five', NULL, 2022, NULL, 'letter', NULL, NULL, NULL, NULL, '[]', '{}', FALSE, FALSE, NULL, 0),
+                (6, '10.1000/shared', 'Work six', NULL, 2019, NULL, 'article', NULL, NULL, NULL, NULL, '[]', '{}', FALSE, FALSE, NULL, 0),
+                (7, '10.1000/shared', 'Work seven', NULL, 2019, NULL, 'erratum', NULL, NULL, NULL, NULL, '[]', '{}', FALSE, FALSE, NULL, 0);
+
+            INSERT INTO openalex_sources (id, display_name, issns, publisher)
+            VALUES (21, 'PLOS ONE', '{1932-6203}', 'Public Library of Science');
+            INSERT INTO openalex_authors (id, display_name, orcid)
+            VALUES (11, 'Alice M. Martin', 'https://orcid.org/0000-0002-1825-0097');
+            INSERT INTO openalex_topics (id, display_name, subfield, field, domain)
+            VALUES
+                (31, 'Sepsis', 'Critical Care', 'Medicine', 'Health Sciences'),
+                (32, 'COVID-19', 'Infectious Diseases', 'Medicine', 'Health Sciences');
+
+            INSERT INTO openalex_work_ids (id_type, value, work_id)
+            VALUES
+                ('doi', '10.1000/one', 1),
+                ('pmid', '40000004', 4),
+                ('doi', '10.1000/shared', 6),
+                ('doi', '10.1000/shared', 7);
+
+            INSERT INTO openalex_references (citing_work_id, cited_work_id)
+            VALUES (1, 2), (1, 3), (4, 2), (5, 1);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        TestDatabase {
+            database: OpenAlexDatabase::new(pool),
+            url,
+            schema_name,
+        }
+    }
+
+    /// The database of [`create_test_database`], dropping its schema when dropped.
+    pub(crate) struct TestDatabase {
+        database: OpenAlexDatabase,
+        url: String,
+        schema_name: String,
+    }
+
+    impl std::ops::Deref for TestDatabase {
+        type Target = OpenAlexDatabase;
+
+        fn deref(&self) -> &OpenAlexDatabase {
+            &self.database
+        }
+    }
+
+    impl Drop for TestDatabase {
+        fn drop(&mut self) {
+            let url = self.url.clone();
+            // The schema name only holds digits and underscores
+            let drop_schema = format!("DROP SCHEMA IF EXISTS {} CASCADE", self.schema_name);
+
+            // Drop cannot await, and the runtime of the test may be shutting down
+            let dropped = std::thread::spawn(move || {
+                tokio::runtime::Runtime::new()?.block_on(async {
+                    let pool = PgPool::connect(&url).await?;
+                    sqlx::raw_sql(sqlx::AssertSqlSafe(drop_schema))
+                        .execute(&pool)
+                        .await?;
+                    pool.close().await;
+                    Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+                })
+            })
+            .join();
+
+            if let Ok(Err(e)) = dropped {
+                eprintln!("Unable to drop the schema {}: {e}", self.schema_name);
+            }
+        }
+    }
+
+    fn counts(pairs: &[(i64, usize)]) -> HashMap<i64, usize> {
+        pairs.iter().copied().collect()
+    }
+
+    #[tokio::test]
+    async fn resolve_identifiers() -> Result<()> {
+        let database = create_test_database().await;
+        assert!(database.is_imported().await?);
+
+        let identifiers: Vec<Identifier> = [
+            "https://doi.org/10.1000/ONE",
+            "40000004",
+            "W5",
+            "W999",
+            "10.1000/shared",
+            "10.1000/missing",
+        ]
+        .iter()
+        .map(|raw| Identifier::parse(raw).unwrap())
+        .collect();
+        let resolved = database.resolve(&identifiers).await?;
+
+        assert_eq!(resolved.len(), 4);
+        assert_eq!(resolved[&identifiers[0]], 1);
+        assert_eq!(resolved[&identifiers[1]], 4);
+        assert_eq!(resolved[&identifiers[2]], 5);
+        assert_eq!(
+            resolved[&identifiers[4]], 6,
+            "The oldest work sharing a DOI"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn references_and_citations() -> Result<()> {
+        let database = create_test_database().await;
+
+        let references = database.references(&[1, 2]).await?;
+        let mut of_one = references[&1].clone();
+        of_one.sort();
+        assert_eq!(of_one, [2, 3]);
+        assert!(!references.contains_key(&2));
+
+        let citations = database.citations(&[1, 2]).await?;
+        let mut of_two = citations[&2].clone();
+        of_two.sort();
+        assert_eq!(of_two, [1, 4]);
+        assert_eq!(citations[&1], [5]);
+
+        let mut works = database.works(&[1, 2, 999]).await?;
+        works.sort_by_key(|work| work.id);
+        assert_eq!(works.len(), 2);
+        assert_eq!(works[0].authors[0].name, "Alice M. Martin");
+        assert_eq!(
+            works[0].authors[0].orcid.as_deref(),
+            Some("https://orcid.org/0000-0002-1825-0097")
+        );
+        assert_eq!(works[0].authors[0].institutions, ["Université Paris Cité"]);
+        assert_eq!(works[0].authors[1].name, "Bob Chen");
+        assert_eq!(works[0].journal.as_deref(), Some("PLOS ONE"));
+        assert_eq!(works[0].issns, ["1932-6203"]);
+        assert_eq!(
+            works[0].publisher.as_deref(),
+            Some("Public Library of Science")
+        );
+        // Best match first, and T39 was not imported
+        let topics: Vec<&str> = works[0]
+            .topics
+            .iter()
+            .map(|topic| topic.name.as_str())
+            .collect();
+        assert_eq!(topics, ["COVID-19", "Sepsis"]);
+        assert_eq!(
+            works[0].topics[1].subfield.as_deref(),
+            Some("Critical Care")
+        );
+        // A source not imported leaves the stored journal name
+        assert_eq!(works[1].journal.as_deref(), Some("Old Name"));
+        assert_eq!(works[1].publisher, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn crosswalk_both_ways() -> Result<()> {
+        let database = create_test_database().await;
+        let lens_one = LensId::from(11111111111111);
+        let lens_four = LensId::from(44444444444444);
+        let lens_shared = LensId::from(66666666666666);
+        let lens_missing = LensId::from(99999999999999);
+
+        let mappings = [
+            ("10.1000/one".to_string(), lens_one.clone()),
+            ("W1".to_string(), lens_one.clone()),
+            ("40000004".to_string(), lens_four.clone()),
+            ("10.1000/shared".to_string(), lens_shared.clone()),
+            ("10.1000/missing".to_string(), lens_missing.clone()),
+            ("not an identifier".to_string(), lens_missing.clone()),
+        ];
+        assert_eq!(database.link_lens_ids(&mappings).await?, 3);
+        assert_eq!(
+            database.link_lens_ids(&mappings).await?,
+            0,
+            "Links are only added once"
+        );
+
+        // OpenAlex to Lens
+        let lens_ids = database.lens_ids(&[1, 2, 4]).await?;
+        assert_eq!(lens_ids.len(), 2);
+        assert_eq!(lens_ids[&1], std::slice::from_ref(&lens_one));
+        assert_eq!(lens_ids[&4], std::slice::from_ref(&lens_four));
+
+        // Lens to OpenAlex
+        let works = database
+            .lens_works(&[lens_shared.clone(), lens_missing.clone()])
+            .await?;
+        assert_eq!(works, HashMap::from([(lens_shared.clone(), 6)]));
+
+        // From any identifier to every other one
+        let lens_four_id = Identifier::parse_as(IdType::LensId, lens_four.as_ref()).unwrap();
+        let doi_one = Identifier::parse("10.1000/one").unwrap();
+        let crosswalk = database
+            .crosswalk(&[lens_four_id.clone(), doi_one.clone()])
+            .await?;
+        assert_eq!(crosswalk[&lens_four_id].work_id, 4);
+        assert_eq!(
+            crosswalk[&lens_four_id].identifiers,
+            [Identifier::parse("pmid:40000004").unwrap()]
+        );
+        assert_eq!(crosswalk[&doi_one].lens_ids, [lens_one]);
+        Ok(())
+    }
+
+    /// Counts follow `lens::snowball`, like `pubmed::snowball`.
+    #[tokio::test]
+    async fn snowball_counts() -> Result<()> {
+        let database = create_test_database().await;
+
+        let found = snowball(&["10.1000/one"], 2, &SearchFor::Both, &database).await?;
+        assert_eq!(found, counts(&[(2, 1), (3, 1), (5, 1), (1, 3), (4, 1)]));
+
+        let found = snowball(&["W1"], 2, &SearchFor::References, &database).await?;
+        assert_eq!(found, counts(&[(2, 1), (3, 1)]));
+
+        assert!(matches!(
+            snowball(
+                &["10.1000/missing", "arXiv:2101.00001"],
+                2,
+                &SearchFor::Both,
+                &database
+            )
+            .await,
+            Err(OpenAlexError::NoSeedFound)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+//! Tables of the OpenAlex database, created in the BibliZap database by `biblizap-openalex`.
+//!
+//! - `openalex_works`: one row per work, keyed by the number of its `W` ID, with the
+//!   metadata shown in results and exports. Authors are kept as JSON, and the abstract
+//!   as the plain text rebuilt from its inverted index.
+//! - `openalex_sources`, `openalex_authors` and `openalex_topics`: the journals, authors
+//!   and topics that works refer to by ID, from the entity dumps of the snapshot. Works
+//!   keep the IDs of their topics in `topic_ids`, best match first.
+//! - `openalex_work_ids`: the DOI, PMID, PMCID and MAG ID of each work, in the canonical
+//!   form of `biblizap_rs::identifier`. OpenAlex sometimes gives one DOI to several
+//!   works, so the same identifier can map to more than one work.
+//! - `openalex_references`: one row per `referenced_works` edge. The index on
+//!   `cited_work_id` is the reverse index answering citation lookups.
+//! - `openalex_lens_ids`: the Lens articles with the same identifiers as each work,
+//!   linked by `biblizap-openalex crosswalk` from the ID mappings of the Lens cache.
+//!   With `openalex_work_ids`, it is the crosswalk between OpenAlex IDs, Lens IDs, DOIs
+//!   and PMIDs.
+//! - `openalex_imported_files`: the dump files fully imported, so that an interrupted
+//!   import resumes after the last finished file. For entity files, `works_read` and
+//!   `works_inserted` count the sources, authors or topics read and written.
+//! - `openalex_merged_works`: the works merged into another one, or deleted when
+//!   `merged_into_id` is null, from the `merged_ids` files applied by updates.
+//! - `openalex_state`: how far updates went, as named values such as the newest
+//!   partition applied.
+//! - `openalex_staging_works` and `openalex_staged_files`: the works copied by import
+//!   workers and the files they come from, waiting for the merge into the tables above.
+//!   They are unlogged, as they are rebuilt from the dump after a crash anyway.
+
+/// Secondary indexes of the tables filled by imports.
+const IMPORT_INDEXES: [&str; 2] = [
+    "CREATE INDEX IF NOT EXISTS idx_openalex_work_ids_work_id ON openalex_work_ids (work_id)",
+    "CREATE INDEX IF NOT EXISTS idx_openalex_references_cited ON openalex_references (cited_work_id)",
+];
+
+/// Creates the OpenAlex tables and indexes if they do not exist yet.
+pub async fn ensure_schema(pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_works (
+            id BIGINT PRIMARY KEY,
+            doi TEXT,
+            title TEXT,
+            abstract_text TEXT,
+            publication_year INTEGER,
+            publication_date TEXT,
+            work_type TEXT,
+            language TEXT,
+            source_id BIGINT,
+            journal TEXT,
+            volume TEXT,
+            issue TEXT,
+            first_page TEXT,
+            last_page TEXT,
+            authorships JSONB NOT NULL DEFAULT '[]',
+            is_retracted BOOLEAN NOT NULL DEFAULT FALSE,
+            is_oa BOOLEAN NOT NULL DEFAULT FALSE,
+            oa_url TEXT,
+            license TEXT,
+            cited_by_count INTEGER NOT NULL DEFAULT 0,
+            updated_date TEXT,
+            topic_ids BIGINT[] NOT NULL DEFAULT '{}'
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Databases imported before abstracts and topics were kept
+    sqlx::query("ALTER TABLE openalex_works ADD COLUMN IF NOT EXISTS abstract_text TEXT")
+        .execute(pool)
+        .await?;
+    sqlx::query(
+        "ALTER TABLE openalex_works ADD COLUMN IF NOT EXISTS topic_ids BIGINT[] NOT NULL DEFAULT '{}'",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_sources (
+            id BIGINT PRIMARY KEY,
+            display_name TEXT,
+            issn_l TEXT,
+            issns TEXT[] NOT NULL DEFAULT '{}',
+            publisher TEXT,
+            source_type TEXT,
+            works_count INTEGER NOT NULL DEFAULT 0,
+            updated_date TEXT
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_authors (
+            id BIGINT PRIMARY KEY,
+            display_name TEXT,
+            orcid TEXT,
+            institution TEXT,
+            works_count INTEGER NOT NULL DEFAULT 0,
+            updated_date TEXT
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_topics (
+            id BIGINT PRIMARY KEY,
+            display_name TEXT,
+            subfield TEXT,
+            field TEXT,
+            domain TEXT,
+            works_count INTEGER NOT NULL DEFAULT 0,
+            updated_date TEXT
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_work_ids (
+            id_type TEXT NOT NULL,
+            value TEXT NOT NULL,
+            work_id BIGINT NOT NULL,
+            PRIMARY KEY (id_type, value, work_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_references (
+            citing_work_id BIGINT NOT NULL,
+            cited_work_id BIGINT NOT NULL,
+            PRIMARY KEY (citing_work_id, cited_work_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    for index in IMPORT_INDEXES {
+        sqlx::query(index).execute(pool).await?;
+    }
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_lens_ids (
+            work_id BIGINT NOT NULL,
+            lens_id TEXT NOT NULL,
+            PRIMARY KEY (work_id, lens_id)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_openalex_lens_ids_lens_id ON openalex_lens_ids (lens_id)",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_imported_files (
+            file TEXT PRIMARY KEY,
+            partition TEXT,
+            record_count BIGINT,
+            works_read BIGINT NOT NULL,
+            works_inserted BIGINT NOT NULL,
+            imported_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            entity TEXT NOT NULL DEFAULT 'works'
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "ALTER TABLE openalex_imported_files ADD COLUMN IF NOT EXISTS entity TEXT NOT NULL DEFAULT 'works'",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_merged_works (
+            id BIGINT PRIMARY KEY,
+            merged_into_id BIGINT,
+            merge_date TEXT
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS openalex_state (
+            name TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE UNLOGGED TABLE IF NOT EXISTS openalex_staging_works (
+            priority INTEGER NOT NULL,
+            line INTEGER NOT NULL,
+            id BIGINT NOT NULL,
+            doi TEXT,
+            title TEXT,
+            abstract_text TEXT,
+            publication_year INTEGER,
+            publication_date TEXT,
+            work_type TEXT,
+            language TEXT,
+            source_id BIGINT,
+            journal TEXT,
+            volume TEXT,
+            issue TEXT,
+            first_page TEXT,
+            last_page TEXT,
+            authorships TEXT NOT NULL,
+            is_retracted BOOLEAN NOT NULL,
+            is_oa BOOLEAN NOT NULL,
+            oa_url TEXT,
+            license TEXT,
+            cited_by_count INTEGER NOT NULL,
+            updated_date TEXT,
+            id_types TEXT[] NOT NULL,
+            id_values TEXT[] NOT NULL,
+            referenced_works BIGINT[] NOT NULL,
+            topic_ids BIGINT[] NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Works staged before topics were kept, merged with none
+    sqlx::query(
+        "ALTER TABLE openalex_staging_works ADD COLUMN IF NOT EXISTS topic_ids BIGINT[] NOT NULL DEFAULT '{}'",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        CREATE UNLOGGED TABLE IF NOT EXISTS openalex_staged_files (
+            priority INTEGER PRIMARY KEY,
+            file TEXT NOT NULL UNIQUE,
+            partition TEXT,
+            record_count BIGINT,
+            works_read BIGINT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Drops the primary keys and indexes of the tables filled by imports, so that a first
+/// import fills them without maintaining any index. [`create_import_indexes`] builds
+/// them again once the tables are full, which is much faster than row by row.
+pub async fn drop_import_indexes(conn: &mut sqlx::PgConnection) -> Result<(), sqlx::Error> {
+    for statement in [
+        "DROP INDEX IF EXISTS idx_openalex_work_ids_work_id",
+        "DROP INDEX IF EXISTS idx_openalex_references_cited",
+        "ALTER TABLE openalex_works DROP CONSTRAINT IF EXISTS openalex_works_pkey",
+        "ALTER TABLE openalex_work_ids DROP CONSTRAINT IF EXISTS openalex_work_ids_pkey",
+        "ALTER TABLE openalex_references DROP CONSTRAINT IF EXISTS openalex_references_pkey",
+    ] {
+        sqlx::query(statement).execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Builds the primary keys and indexes dropped by [`drop_import_indexes`].
+pub async fn create_import_indexes(conn: &mut sqlx::PgConnection) -> Result<(), sqlx::Error> {
+    for statement in [
+        "ALTER TABLE openalex_works ADD PRIMARY KEY (id)",
+        "ALTER TABLE openalex_work_ids ADD PRIMARY KEY (id_type, value, work_id)",
+        "ALTER TABLE openalex_references ADD PRIMARY KEY (citing_work_id, cited_work_id)",
+    ]
+    .into_iter()
+    .chain(IMPORT_INDEXES)
+    {
+        sqlx::query(statement).execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+//! HTTP transport for the NCBI E-utilities, shared by every PubMed request.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::lens::retry::RetryPolicy;
+
+/// E-utilities base URL used when no other base URL is configured.
+pub const EUTILS_URL: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils";
+
+/// Name sent as the `tool` parameter, so that NCBI can tell where requests come from.
+const TOOL: &str = "biblizap";
+
+/// Requests per second allowed by NCBI without an API key.
+const REQUESTS_PER_SECOND: u32 = 3;
+/// Requests per second allowed by NCBI with an API key.
+const REQUESTS_PER_SECOND_WITH_KEY: u32 = 10;
+
+/// HTTP transport for the NCBI E-utilities (`elink`, `efetch`, ...).
+///
+/// Requests are spaced to stay under the NCBI rate limits: 3 requests per second, or 10
+/// with an API key. Rate-limited (429) and server error (5xx) responses, timeouts and
+/// failed connections are retried with the backoff of the `RetryPolicy`.
+///
+/// Clones share their rate limiter, so one client can be handed to concurrent tasks.
+#[derive(Debug, Clone)]
+pub struct PubmedClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    email: Option<String>,
+    interval: Duration,
+    next_slot: Arc<Mutex<Instant>>,
+    retry_policy: RetryPolicy,
+}
+
+impl PubmedClient {
+    /// Creates a client sending requests to the NCBI E-utilities through `http`.
+    pub fn new(http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url: EUTILS_URL.to_string(),
+            api_key: None,
+            email: None,
+            interval: Duration::from_secs(1) / REQUESTS_PER_SECOND,
+            next_slot: Arc::new(Mutex::new(Instant::now())),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Sends the requests to `base_url` (without trailing slash) instead of the NCBI servers.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Sends `api_key` with every request and raises the rate limit to 10 requests per second.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self.interval = Duration::from_secs(1) / REQUESTS_PER_SECOND_WITH_KEY;
+        self
+    }
+
+    /// Sends `email` with every request, so that NCBI can reach the operator before
+    /// blocking requests that misbehave.
+    pub fn with_email(mut self, email: impl Into<String>) -> Self {
+        self.email = Some(email.into());
+        self
+    }
+
+    /// Spaces the requests to send at most `requests_per_second`, instead of the NCBI limit.
+    pub fn with_requests_per_second(mut self, requests_per_second: u32) -> Self {
+        self.interval = Duration::from_secs(1) / requests_per_second.max(1);
+        self
+    }
+
+    /// Retries failed requests according to `retry_policy` instead of the default policy.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Whether the requests carry an API key.
+    pub fn has_api_key(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    /// Waits until the rate limit allows another request, and books the next slot.
+    async fn wait_for_slot(&self) {
+        let mut next_slot = self.next_slot.lock().await;
+        let now = Instant::now();
+        if *next_slot > now {
+            tokio::time::sleep_until(*next_slot).await;
+        }
+        *next_slot = (*next_slot).max(now) + self.interval;
+    }
+
+    /// POSTs form `params` to an E-utility, e.g. `elink.fcgi`, and returns the response body.
+    ///
+    /// Parameters go in the body rather than the URL, so long ID lists do not hit URL
+    /// length limits. The `tool` name is always added, and the API key and email when the
+    /// client has them.
+    pub async fn post(&self, utility: &str, params: &[(&str, String)]) -> Result<String> {
+        let mut form: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        form.push(("tool", TOOL));
+        if let Some(api_key) = &self.api_key {
+            form.push(("api_key", api_key));
+        }
+        if let Some(email) = &self.email {
+            form.push(("email", email));
+        }
+        let url = format!("{}/{utility}", self.base_url);
+
+        let policy = &self.retry_policy;
+        let mut total_wait = Duration::ZERO;
+        let mut retries = 0;
+
+        loop {
+            self.wait_for_slot().await;
+
+            let mut request = self.http.post(&url).form(&form);
+            if let Some(timeout) = policy.request_timeout {
+                request = request.timeout(timeout);
+            }
+
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    return Ok(response.text().await?);
+                }
+                Ok(response) if response.status() == 429 || response.status().is_server_error() => {
+                    anyhow::anyhow!("{utility} answered {}", response.status())
+                }
+                Ok(response) => bail!("{utility} answered {}", response.status()),
+                Err(e) if e.is_timeout() || e.is_connect() => e.into(),
+                Err(e) => return Err(e.into()),
+            };
+
+            let delay = policy.backoff(retries);
+            if total_wait + delay > policy.max_total_wait {
+                log::warn!(
+                    "Giving up on PubMed request after waiting {total_wait:?} in retries: {error:?}"
+                );
+                return Err(error);
+            }
+            log::warn!("Retrying PubMed request in {delay:?}: {error}");
+            tokio::time::sleep(delay).await;
+            total_wait += delay;
+            retries += 1;
+        }
+    }
+}
+
+impl Default for PubmedClient {
+    fn default() -> Self {
+        Self::new(reqwest::Client::new())
+    }
+}
+
+impl From<reqwest::Client> for PubmedClient {
+    fn from(http: reqwest::Client) -> Self {
+        Self::new(http)
+    }
+}
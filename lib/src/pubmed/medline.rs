@@ -0,0 +1,312 @@
+//! Parser and writer for the MEDLINE text format, as returned by EFetch with
+//! `rettype=medline` and by the PubMed website export.
+//!
+//! Each line starts with a tag of up to four characters, padded to four, followed by
+//! `- ` and the value. Values that do not fit on one line continue on the next lines,
+//! indented by six spaces. Records are separated by blank lines.
+
+use anyhow::{Context, Result, bail};
+
+use super::{Article, clean_text};
+
+/// Width of the tag column, `- ` separator included.
+const TAG_WIDTH: usize = 6;
+/// Maximum line length when writing MEDLINE records.
+const LINE_WIDTH: usize = 80;
+
+/// Splits MEDLINE text into records of `(tag, value)` fields, in order.
+///
+/// Continuation lines are joined to the value they continue with a single space.
+pub fn parse_records(text: &str) -> Result<Vec<Vec<(String, String)>>> {
+    let mut records = Vec::new();
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            if !fields.is_empty() {
+                records.push(std::mem::take(&mut fields));
+            }
+            continue;
+        }
+
+        if let Some((tag, value)) = split_tag(line) {
+            fields.push((tag.to_string(), value.trim().to_string()));
+        } else if line.starts_with(' ') {
+            let Some((_, value)) = fields.last_mut() else {
+                bail!(
+                    "Continuation line without a field on line {}",
+                    line_number + 1
+                );
+            };
+            value.push(' ');
+            value.push_str(line.trim());
+        } else {
+            bail!("Malformed MEDLINE line {}: {line}", line_number + 1);
+        }
+    }
+    if !fields.is_empty() {
+        records.push(fields);
+    }
+
+    Ok(records)
+}
+
+/// Splits a field line into its tag and value, or returns `None` for a continuation line.
+fn split_tag(line: &str) -> Option<(&str, &str)> {
+    let (tag, value) = line.split_at_checked(4)?;
+    let value = value
+        .strip_prefix("- ")
+        .or_else(|| value.strip_prefix('-'))?;
+    let tag = tag.trim_end();
+    let is_tag = !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    is_tag.then_some((tag, value))
+}
+
+/// Strips a `[doi]`-style suffix from a LID or AID value, if it has this type.
+fn typed_id<'a>(value: &'a str, id_type: &str) -> Option<&'a str> {
+    let (id, suffix) = value.rsplit_once(' ')?;
+    (suffix.strip_prefix('[')?.strip_suffix(']')? == id_type).then_some(id)
+}
+
+impl Article {
+    /// Builds an article from the fields of one MEDLINE record.
+    pub fn from_medline_fields(fields: &[(String, String)]) -> Result<Article> {
+        let mut article = Article::default();
+        let mut authors = Vec::new();
+
+        for (tag, value) in fields {
+            let value = clean_text(value);
+            if value.is_empty() {
+                continue;
+            }
+            match tag.as_str() {
+                "PMID" => article.pmid = value,
+                "TI" => article.title = Some(value),
+                "BTI" if article.title.is_none() => article.title = Some(value),
+                "AB" => article.summary = Some(value),
+                "AU" | "CN" => authors.push(value),
+                "AD" if !article.affiliations.contains(&value) => article.affiliations.push(value),
+                "DP" => article.date_published = Some(value),
+                "JT" => article.journal = Some(value),
+                "TA" => article.journal_abbreviation = Some(value),
+                "VI" => article.volume = Some(value),
+                "IP" => article.issue = Some(value),
+                "PG" => article.pages = Some(value),
+                "LID" | "AID" if article.doi.is_none() => {
+                    article.doi = typed_id(&value, "doi").map(str::to_string);
+                }
+                "PMC" => article.pmcid = Some(value),
+                "MH" => article.mesh_terms.push(value),
+                "PT" => article.publication_types.push(value),
+                "LA" => article.languages.push(value),
+                _ => {}
+            }
+        }
+
+        if article.pmid.is_empty() {
+            bail!("No PMID found for this article: {fields:?}");
+        }
+        if !authors.is_empty() {
+            article.authors = Some(authors);
+        }
+
+        Ok(article)
+    }
+
+    /// Parses every record of MEDLINE text.
+    pub fn from_medline(text: &str) -> Result<Vec<Article>> {
+        parse_records(text)?
+            .iter()
+            .map(|fields| Article::from_medline_fields(fields))
+            .collect::<Result<Vec<_>>>()
+            .context("Invalid MEDLINE record")
+    }
+
+    /// Writes the article as a MEDLINE record, without the trailing blank line.
+    ///
+    /// Only the fields kept in `Article` are written, so authors and collective authors
+    /// are all written as `AU`, and affiliations are listed after the authors.
+    pub fn to_medline(&self) -> String {
+        let mut out = String::new();
+        let mut field = |tag: &str, value: &str| write_field(&mut out, tag, value);
+
+        field("PMID", &self.pmid);
+        for (tag, value) in [
+            ("VI", &self.volume),
+            ("IP", &self.issue),
+            ("DP", &self.date_published),
+            ("TI", &self.title),
+            ("PG", &self.pages),
+        ] {
+            if let Some(value) = value {
+                field(tag, value);
+            }
+        }
+        if let Some(doi) = &self.doi {
+            field("LID", &format!("{doi} [doi]"));
+        }
+        if let Some(summary) = &self.summary {
+            field("AB", summary);
+        }
+        for author in self.authors.iter().flatten() {
+            field("AU", author);
+        }
+        for affiliation in &self.affiliations {
+            field("AD", affiliation);
+        }
+        for language in &self.languages {
+            field("LA", language);
+        }
+        for publication_type in &self.publication_types {
+            field("PT", publication_type);
+        }
+        if let Some(journal_abbreviation) = &self.journal_abbreviation {
+            field("TA", journal_abbreviation);
+        }
+        if let Some(journal) = &self.journal {
+            field("JT", journal);
+        }
+        for mesh_term in &self.mesh_terms {
+            field("MH", mesh_term);
+        }
+        if let Some(pmcid) = &self.pmcid {
+            field("PMC", pmcid);
+        }
+
+        out
+    }
+}
+
+/// Appends one field, wrapping its value on word boundaries at `LINE_WIDTH`.
+fn write_field(out: &mut String, tag: &str, value: &str) {
+    let mut line = format!("{tag:<4}- ");
+    let mut line_is_empty = true;
+    for word in value.split_whitespace() {
+        if !line_is_empty && line.len() + 1 + word.len() > LINE_WIDTH {
+            out.push_str(&line);
+            out.push('\n');
+            line = " ".repeat(TAG_WIDTH);
+            line_is_empty = true;
+        }
+        if !line_is_empty {
+            line.push(' ');
+        }
+        line.push_str(word);
+        line_is_empty = false;
+    }
+    out.push_str(&line);
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE_MEDLINE: &str = include_str!("fixtures/medline.txt");
+
+    #[test]
+    fn parse_medline_fixture() {
+        let articles = Article::from_medline(FIXTURE_MEDLINE).unwrap();
+        assert_eq!(articles.len(), 3);
+
+        let first = &articles[0];
+        assert_eq!(first.pmid, "31000001");
+        assert_eq!(
+            first.title.as_deref(),
+            Some(
+                "Serum IL-6 and CRP-based triage of SARS-CoV-2 pneumonia: a retrospective cohort study."
+            )
+        );
+        assert!(first.summary.as_deref().unwrap().starts_with(
+            "BACKGROUND: Early identification of patients at risk of severe COVID-19 is needed. METHODS:"
+        ));
+        assert!(
+            first
+                .summary
+                .as_deref()
+                .unwrap()
+                .ends_with("CONCLUSIONS: IL-6 and CRP may help triage.")
+        );
+        assert_eq!(
+            first.authors,
+            Some(vec!["Martin C".into(), "Nguyen TB".into()])
+        );
+        assert_eq!(
+            first.affiliations.len(),
+            2,
+            "Repeated affiliations are kept once"
+        );
+        assert_eq!(first.date_published.as_deref(), Some("2020 Mar 5"));
+        assert_eq!(first.year_published(), Some(2020));
+        assert_eq!(first.journal.as_deref(), Some("PloS one"));
+        assert_eq!(first.journal_abbreviation.as_deref(), Some("PLoS One"));
+        assert_eq!(first.volume.as_deref(), Some("15"));
+        assert_eq!(first.issue.as_deref(), Some("3"));
+        assert_eq!(first.pages.as_deref(), Some("e0229876"));
+        assert_eq!(first.doi.as_deref(), Some("10.1371/journal.pone.0229876"));
+        assert_eq!(first.pmcid.as_deref(), Some("PMC7050001"));
+        assert_eq!(
+            first.mesh_terms,
+            [
+                "Aged",
+                "*COVID-19/blood/diagnosis",
+                "Humans",
+                "Interleukin-6/*blood",
+                "Triage/*methods"
+            ]
+        );
+        assert_eq!(
+            first.publication_types,
+            ["Journal Article", "Multicenter Study"]
+        );
+        assert_eq!(first.languages, ["eng"]);
+
+        let second = &articles[1];
+        assert_eq!(
+            second.authors,
+            Some(vec!["Dupont AM".into(), "BibliZap Study Group".into()])
+        );
+        assert_eq!(second.languages, ["eng", "fre"]);
+
+        let third = &articles[2];
+        assert_eq!(third.summary, None);
+        assert_eq!(third.doi, None);
+        assert_eq!(third.date_published.as_deref(), Some("2019 Nov-Dec"));
+        assert_eq!(third.year_published(), Some(2019));
+        assert_eq!(third.issue.as_deref(), Some("11 Pt 1"));
+    }
+
+    #[test]
+    fn medline_round_trip() {
+        let articles = Article::from_medline(FIXTURE_MEDLINE).unwrap();
+
+        let written = articles
+            .iter()
+            .map(Article::to_medline)
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(written.lines().all(|line| line.len() <= LINE_WIDTH));
+        assert!(
+            written.contains("\n      "),
+            "Long values should be wrapped"
+        );
+
+        assert_eq!(Article::from_medline(&written).unwrap(), articles);
+    }
+
+    #[test]
+    fn parse_medline_line_endings_and_errors() {
+        let crlf = "PMID- 1\r\nTI  - A title\r\n      continued\r\n\r\nPMID- 2\r\n";
+        let articles = Article::from_medline(crlf).unwrap();
+        assert_eq!(articles.len(), 2);
+        assert_eq!(articles[0].title.as_deref(), Some("A title continued"));
+
+        assert!(Article::from_medline("TI  - No PMID\n").is_err());
+        assert!(Article::from_medline("      orphan continuation\n").is_err());
+        assert!(Article::from_medline("PMID- 1\nnot a field\n").is_err());
+    }
+}
@@ -1,291 +1,625 @@
-//! Handles interactions with the PubMed API for retrieving article data
+//! Handles interactions with the PubMed E-utilities for retrieving article data
 //! and expanding citation networks.
 //!
-//! Note: PubMed primarily provides article metadata and links to related articles
-//! (like PMC articles or articles citing the current one via Europe PMC),
-//! but does not offer a direct citation/reference list API like Lens.org.
-//! The snowballing functionality here is more limited, focusing on retrieving
-//! article details based on PMIDs and finding citing/referenced articles via E-Utilities.
-
-use anyhow::{Context, Result};
-use regex::Regex;
-
-/// Macro to construct regex patterns for extracting specific fields from PubMed's raw format.
-///
-/// The raw PubMed format uses field identifiers (like "PMID", "TI", "AB", "AU")
-/// followed by a hyphen and the field content, ending before the next field identifier.
-macro_rules! pattern {
-    ($feature_identifer:expr) => {{
-        concat!(
-            "(?s)", // Enable dotall mode ('.' matches newline)
-            $feature_identifer,
-            "[[:space:]]*-[[:space:]]*(.*?)[A-Z]+[[:space:]]*-" // Capture content between identifier and next uppercase identifier + hyphen
-        )
-    }};
+//! PubMed links each article to the articles in its reference list
+//! (`pubmed_pubmed_refs`) and to the articles citing it (`pubmed_pubmed_citedin`), both
+//! known through PubMed Central. Coverage is narrower than Lens.org, but needs no
+//! subscription: `snowball` follows these links with batched `elink` requests and
+//! `Article::complete_articles` fetches the records with `efetch`. `search` finds seed
+//! articles for a keyword query with `esearch`.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::common::SearchFor;
+use crate::identifier::{IdType, Identifier};
+use crate::lens::cache::CacheBackend;
+
+mod client;
+mod medline;
+#[cfg(test)]
+pub(crate) mod stub;
+mod xml;
+
+pub use client::PubmedClient;
+pub use medline::parse_records as parse_medline_records;
+
+/// Number of PMIDs per EFetch request.
+const EFETCH_CHUNK_SIZE: usize = 200;
+/// Number of PMIDs per ELink request, each getting its own `LinkSet` in the response.
+const ELINK_CHUNK_SIZE: usize = 100;
+/// Largest number of PMIDs ESearch returns for one query.
+pub const MAX_SEARCH_RESULTS: usize = 10_000;
+
+/// A kind of PubMed-to-PubMed link followed by `elink`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PubmedLink {
+    /// Articles in the reference list of the article
+    References,
+    /// Articles citing the article
+    Citations,
 }
 
-/// Regex pattern string for extracting the PubMed ID (PMID).
-static PMID_PATTERN: &str = pattern!("PMID");
-/// Regex pattern string for extracting the article title (TI).
-static TITLE_PATTERN: &str = pattern!("TI");
-/// Regex pattern string for extracting the abstract (AB).
-static ABSTRACT_PATTERN: &str = pattern!("AB");
-/// Regex pattern string for extracting author names (AU).
-static AUTHOR_PATTERN: &str = pattern!("AU");
-
-/// Base URL for finding articles that cite a given PubMed ID (citedin).
-static ASC_URL_BASE: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/elink.fcgi?dbfrom=pubmed&linkname=pubmed_pubmed_citedin&id=";
-/// Base URL for finding articles referenced by a given PubMed ID (refs).
-static DESC_URL_BASE: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/elink.fcgi?dbfrom=pubmed&linkname=pubmed_pubmed_refs&id=";
-
-// Lazy static initialization for compiled regex patterns.
-lazy_static::lazy_static! {
-    /// Compiled regex for extracting PMID.
-    static ref PMID_REGEX: Regex = Regex::new(PMID_PATTERN).expect("PMID_REGEX failed to compile");
-    /// Compiled regex for extracting Title.
-    static ref TITLE_REGEX: Regex = Regex::new(TITLE_PATTERN).expect("TITLE_REGEX failed to compile");
-    /// Compiled regex for extracting Abstract.
-    static ref ABSTRACT_REGEX: Regex = Regex::new(ABSTRACT_PATTERN).expect("ABSTRACT_REGEX failed to compile");
-    /// Compiled regex for extracting Authors.
-    static ref AUTHOR_REGEX: Regex = Regex::new(AUTHOR_PATTERN).expect("AUTHOR_REGEX failed to compile");
-    /// Compiled regex for extracting <Id> tags from E-Utilities responses.
-    static ref ID_REGEX: Regex = Regex::new("(?s)<Id>(.*?)</Id>").expect("AUTHOR_REGEX failed to compile");
+impl PubmedLink {
+    /// The ELink `linkname` of this kind of link.
+    pub fn link_name(self) -> &'static str {
+        match self {
+            PubmedLink::References => "pubmed_pubmed_refs",
+            PubmedLink::Citations => "pubmed_pubmed_citedin",
+        }
+    }
+
+    /// The links to follow when searching for `search_for`.
+    fn for_search(search_for: &SearchFor) -> &'static [PubmedLink] {
+        match search_for {
+            SearchFor::References => &[PubmedLink::References],
+            SearchFor::Citations => &[PubmedLink::Citations],
+            SearchFor::Both => &[PubmedLink::References, PubmedLink::Citations],
+        }
+    }
 }
 
-/// Represents an article with core bibliographic information retrieved from PubMed.
-#[derive(Debug, Clone, PartialEq)]
+/// Represents an article with the bibliographic information retrieved from PubMed.
+///
+/// Fields hold the values as MEDLINE writes them, whichever format they were read from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Article {
     /// The PubMed ID (PMID) of the article.
     pub pmid: String,
-    /// The title of the article.
+    /// The title of the article (TI).
     pub title: Option<String>,
-    /// The abstract or summary of the article.
+    /// The abstract or summary of the article (AB).
     pub summary: Option<String>,
-    /// The list of authors.
+    /// The list of authors, e.g. `Martin C`, and collective authors (AU, CN).
     pub authors: Option<Vec<String>>,
+    /// Publication date, e.g. `2020 Mar 5` or `2019 Nov-Dec` (DP).
+    pub date_published: Option<String>,
+    /// Full journal title (JT).
+    pub journal: Option<String>,
+    /// Abbreviated journal title (TA).
+    pub journal_abbreviation: Option<String>,
+    /// Volume (VI).
+    pub volume: Option<String>,
+    /// Issue (IP).
+    pub issue: Option<String>,
+    /// Pagination, e.g. `112-9` (PG).
+    pub pages: Option<String>,
+    /// DOI, from the `[doi]` location or article ID (LID, AID).
+    pub doi: Option<String>,
+    /// PubMed Central ID (PMC).
+    pub pmcid: Option<String>,
+    /// MeSH headings, major topics starred, e.g. `Interleukin-6/*blood` (MH).
+    pub mesh_terms: Vec<String>,
+    /// Publication types, e.g. `Journal Article` (PT).
+    pub publication_types: Vec<String>,
+    /// Languages, e.g. `eng` (LA).
+    pub languages: Vec<String>,
+    /// Affiliations of all authors, without repeats (AD).
+    pub affiliations: Vec<String>,
+}
+
+/// Trims a value and collapses its internal whitespace, line breaks included.
+fn clean_text(input: &str) -> String {
+    input.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
 impl Article {
-    /// Parses a single raw article string from PubMed format into an `Article` struct.
+    /// The year of publication, from the start of `date_published`.
+    pub fn year_published(&self) -> Option<i32> {
+        self.date_published.as_deref()?.get(..4)?.parse().ok()
+    }
+
+    /// Parses a single raw article string from MEDLINE format into an `Article` struct.
     ///
     /// # Arguments
     ///
-    /// * `raw_article`: A string slice containing the raw PubMed format for one article.
+    /// * `raw_article`: A string slice containing the MEDLINE format for one article.
     ///
     /// # Returns
     ///
     /// A `Result` containing the parsed `Article`, or an error if parsing fails (e.g., missing PMID).
     pub fn from_raw_article(raw_article: &str) -> Result<Article> {
-        /// Cleans up extracted feature strings by removing newlines and trimming/collapsing whitespace.
-        fn clean_feature(input: &str) -> String {
-            /// Trims leading/trailing whitespace and replaces multiple internal spaces with a single space.
-            pub fn trim_whitespace(s: &str) -> String {
-                let mut new_str = s.trim().to_owned();
-                let mut prev = ' '; // The initial value doesn't really matter
-                new_str.retain(|ch| {
-                    let result = ch != ' ' || prev != ' ';
-                    prev = ch;
-                    result
-                });
-                new_str
-            }
-
-            let ret = input.replace("\r\n", "");
-
-            trim_whitespace(&ret)
-        }
-
-        /// Extracts a single occurrence of a field using a regex.
-        fn extract_single(article: &str, regex: &Regex, group: usize) -> Option<String> {
-            let string = regex.captures(article)?.get(group)?.as_str();
-            Some(clean_feature(string))
+        let mut articles = Article::from_medline(raw_article)?;
+        match articles.len() {
+            1 => Ok(articles.remove(0)),
+            count => anyhow::bail!("Expected one article, found {count}"),
         }
-
-        /// Extracts all occurrences of a repeating field (like authors) using a regex.
-        fn extract_all(article: &str, regex: &Regex, group: usize) -> Option<Vec<String>> {
-            let vec: Option<Vec<String>> = regex
-                .captures_iter(article)
-                .map(|x| Some(clean_feature(x.get(group)?.as_str())))
-                .collect();
-            vec
-        }
-
-        Ok(Article {
-            pmid: extract_single(raw_article, &PMID_REGEX, 1)
-                .with_context(|| format!("No PMID found for this article : \n{raw_article}"))?,
-            title: extract_single(raw_article, &TITLE_REGEX, 1),
-            summary: extract_single(raw_article, &ABSTRACT_REGEX, 1),
-            authors: extract_all(raw_article, &AUTHOR_REGEX, 1),
-        })
     }
 
-    /// Parses a string containing multiple raw articles from PubMed format.
-    ///
-    /// Assumes articles are separated by double newlines (`\r\n\r\n`).
+    /// Parses a string containing multiple raw articles in MEDLINE format.
     ///
     /// # Arguments
     ///
-    /// * `raw_articles`: A string slice containing the raw PubMed format for multiple articles.
+    /// * `raw_articles`: A string slice containing the MEDLINE format for multiple articles,
+    ///   separated by blank lines.
     ///
     /// # Returns
     ///
     /// A `Result` containing an iterator over the parsed `Article` structs.
     pub fn from_raw_articles(raw_articles: &str) -> Result<impl Iterator<Item = Article> + '_> {
-        Ok(raw_articles
-            .split("\r\n\r\n")
-            .map(|x| Article::from_raw_article(x).unwrap())) // Note: Using unwrap here might panic on parsing errors
+        Ok(Article::from_medline(raw_articles)?.into_iter())
     }
 
-    /// Requests raw article data from PubMed for a list of PMIDs.
-    ///
-    /// Uses the E-Utilities API to fetch articles in PubMed format.
-    ///
-    /// # Arguments
-    ///
-    /// * `src_pmid`: A slice of string slices representing the PMIDs to fetch.
-    ///
-    /// # Returns
-    ///
-    /// A `Result` containing the raw response body as a string, or an error if the request fails.
-    async fn request_raw_articles(src_pmid: &[&str]) -> Result<String> {
-        let url: String = format!(
-            "https://pubmed.ncbi.nlm.nih.gov/?term={}&show_snippets=off&format=pubmed&size=200",
-            src_pmid.join(",") // Join PMIDs with commas for the query term
-        );
-
-        let body = reqwest::get(url).await?.text().await?;
-
-        // Regex to extract the <pre> block containing the PubMed format data
-        let body_to_raw_articles: Regex = Regex::new(r"(?s)<pre.*?(PMID.*)</pre>")?;
-
-        Ok(body_to_raw_articles
-            .captures(&body)
-            .context("Capture failed")?
-            .get(1)
-            .context("Get group 1 failed")?
-            .as_str()
-            .to_owned())
+    /// Requests full records from PubMed for a list of PMIDs, as EFetch XML.
+    async fn request_raw_articles(pmids: &[u64], client: &PubmedClient) -> Result<String> {
+        let params = [
+            ("db", "pubmed".to_string()),
+            ("retmode", "xml".to_string()),
+            ("id", join_pmids(pmids)),
+        ];
+        client
+            .post("efetch.fcgi", &params)
+            .await
+            .context("Failed to fetch PubMed records")
     }
 
     /// Completes the information for a list of articles using the PubMed API.
     ///
-    /// This function takes a list of PMIDs and fetches detailed data for them
-    /// from PubMed, returning a vector of `Article` structs.
+    /// Records are read from the cache when possible; the others are fetched from EFetch
+    /// in chunks and stored in the cache. PMIDs unknown to PubMed are left out, and the
+    /// articles are returned in no particular order.
     ///
     /// # Arguments
     ///
-    /// * `src_pmid`: A slice of string slices representing the PMIDs to complete.
-    ///
-    /// # Returns
-    ///
-    /// A `Result` containing a vector of `Article` structs, or an error if fetching or parsing fails.
-    pub async fn complete_articles(src_pmid: &[&str]) -> Result<Vec<Article>> {
-        let raw_articles = Article::request_raw_articles(src_pmid).await?;
-        let ret = Article::from_raw_articles(&raw_articles)?.collect();
-        Ok(ret)
+    /// * `pmids`: The PMIDs to complete.
+    /// * `client`: Optional `PubmedClient`; a new one is created if `None`.
+    /// * `cache`: Optional cache backend for storing and retrieving records.
+    pub async fn complete_articles(
+        pmids: &[u64],
+        client: Option<&PubmedClient>,
+        cache: Option<&dyn CacheBackend>,
+    ) -> Result<Vec<Article>> {
+        let client = match client {
+            Some(c) => c,
+            None => &PubmedClient::default(),
+        };
+
+        let mut articles = match cache {
+            Some(cache) => cache.get_pubmed_articles(pmids).await?,
+            None => Vec::new(),
+        };
+        let cached: HashSet<u64> = articles
+            .iter()
+            .filter_map(|article| article.pmid.parse().ok())
+            .collect();
+        let misses: Vec<u64> = pmids
+            .iter()
+            .filter(|pmid| !cached.contains(pmid))
+            .copied()
+            .collect();
+
+        for chunk in misses.chunks(EFETCH_CHUNK_SIZE) {
+            let raw_articles = Article::request_raw_articles(chunk, client).await?;
+            let fetched = Article::from_efetch_xml(&raw_articles)?;
+            if let Some(cache) = cache {
+                cache.store_pubmed_articles(&fetched).await?;
+            }
+            articles.extend(fetched);
+        }
+
+        Ok(articles)
     }
 }
 
-/// Performs a single step of snowballing using PubMed E-Utilities (unsafe chunk size).
-///
-/// This function finds articles that cite or are referenced by the given PMIDs
-/// by querying the E-Utilities API. It's marked unsafe because it doesn't handle
-/// chunking the input PMIDs, which can lead to URL length limits.
-///
-/// # Arguments
-///
-/// * `src_pmid`: A slice of string slices representing the source PMIDs.
+/// Joins PMIDs with commas, as EFetch expects them.
+fn join_pmids(pmids: &[u64]) -> String {
+    pmids
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Requests the links of up to `ELINK_CHUNK_SIZE` PMIDs in one ELink call.
 ///
-/// # Returns
+/// Each PMID is sent as its own `id` parameter, so that the response keeps the links
+/// of every PMID apart. PMIDs without any link are returned with an empty list.
+async fn request_links(
+    link: PubmedLink,
+    pmids: &[u64],
+    client: &PubmedClient,
+) -> Result<Vec<(u64, Vec<u64>)>> {
+    let mut params = vec![
+        ("dbfrom", "pubmed".to_string()),
+        ("db", "pubmed".to_string()),
+        ("linkname", link.link_name().to_string()),
+    ];
+    params.extend(pmids.iter().map(|pmid| ("id", pmid.to_string())));
+
+    let body = client
+        .post("elink.fcgi", &params)
+        .await
+        .context("Failed to fetch PubMed links")?;
+    let mut links: HashMap<u64, Vec<u64>> = xml::parse_elink_xml(&body, link.link_name())?
+        .into_iter()
+        .collect();
+
+    Ok(pmids
+        .iter()
+        .map(|pmid| (*pmid, links.remove(pmid).unwrap_or_default()))
+        .collect())
+}
+
+/// Gets the links of each PMID, from the cache when possible and from ELink otherwise.
 ///
-/// A `Result` containing a vector of strings representing the PMIDs of related articles,
-/// or an error if the request or parsing fails.
-async fn snowball_onestep_unsafe(src_pmid: &[&str]) -> Result<Vec<String>> {
-    let src_pmid_comma = src_pmid.join(",");
-    let asc_url: String = format!("{ASC_URL_BASE}{src_pmid_comma}");
-    let desc_url: String = format!("{DESC_URL_BASE}{src_pmid_comma}");
-
-    let body_asc: String = reqwest::get(asc_url).await?.text().await?;
-    let body_desc: String = reqwest::get(desc_url).await?.text().await?;
-
-    let body: String = [body_desc, body_asc].join("\n");
-
-    // Extract PMIDs from <Id> tags in the response
-    let dest_pmid: Result<Vec<_>> = ID_REGEX
-        .captures_iter(&body)
-        .map(|x| anyhow::Ok(x.get(1).context("Couldn't get ID")?.as_str().to_owned()))
-        .skip(src_pmid.len()) // Skip n first as pubmed returns input as output before giving citations
+/// Fetched links are stored in the cache, empty lists included.
+pub async fn get_links(
+    link: PubmedLink,
+    pmids: &[u64],
+    client: &PubmedClient,
+    cache: Option<&dyn CacheBackend>,
+) -> Result<HashMap<u64, Vec<u64>>> {
+    let mut links = match cache {
+        Some(cache) => cache.get_pubmed_links(link, pmids).await?,
+        None => HashMap::new(),
+    };
+
+    let misses: Vec<u64> = pmids
+        .iter()
+        .filter(|pmid| !links.contains_key(pmid))
+        .copied()
         .collect();
+    if misses.is_empty() {
+        return Ok(links);
+    }
+
+    // The client spaces the requests, so the chunks can be sent together
+    let fetched: Vec<(u64, Vec<u64>)> = futures::future::try_join_all(
+        misses
+            .chunks(ELINK_CHUNK_SIZE)
+            .map(|chunk| request_links(link, chunk, client)),
+    )
+    .await?
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if let Some(cache) = cache {
+        cache.store_pubmed_links(link, &fetched).await?;
+    }
+    links.extend(fetched);
+
+    Ok(links)
+}
 
-    dest_pmid
+/// Body of an ESearch response in JSON.
+#[derive(Debug, Deserialize)]
+struct ESearchResponse {
+    esearchresult: ESearchResult,
 }
 
-/// Performs a single step of snowballing using PubMed E-Utilities, handling chunking.
+#[derive(Debug, Deserialize)]
+struct ESearchResult {
+    #[serde(default)]
+    idlist: Vec<String>,
+    #[serde(rename = "ERROR")]
+    error: Option<String>,
+}
+
+/// Searches PubMed for a keyword query and returns the PMIDs found, by relevance.
 ///
-/// This function finds articles that cite or are referenced by the given PMIDs
-/// by querying the E-Utilities API. It chunks the input PMIDs to avoid URL length limits.
+/// Queries are compared after collapsing whitespace. Results are read from the cache
+/// when the same query was searched in the last day, and stored in it otherwise.
 ///
 /// # Arguments
 ///
-/// * `src_pmid`: A slice of string slices representing the source PMIDs.
-///
-/// # Returns
-///
-/// A `Result` containing a vector of strings representing the PMIDs of related articles,
-/// or an error if the request or parsing fails.
-pub async fn snowball_onestep(src_pmid: &[&str]) -> Result<Vec<String>> {
-    let dest_pmid = futures::future::join_all(
-        src_pmid
-            .chunks(325) // Chunk size to manage URL length
-            .map(snowball_onestep_unsafe),
-    )
-    .await
-    .into_iter()
-    .collect::<Result<Vec<_>>>()?
-    .into_iter()
-    .flatten()
-    .collect::<Vec<String>>();
+/// * `query`: The query, in PubMed search syntax.
+/// * `max_results`: The maximum number of PMIDs to return, up to `MAX_SEARCH_RESULTS`.
+/// * `client`: The `PubmedClient` to send the request with.
+/// * `cache`: Optional cache backend for storing and retrieving searches.
+pub async fn search(
+    query: &str,
+    max_results: usize,
+    client: &PubmedClient,
+    cache: Option<&dyn CacheBackend>,
+) -> Result<Vec<u64>> {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    if query.is_empty() {
+        bail!("Empty PubMed query");
+    }
+    let max_results = max_results.min(MAX_SEARCH_RESULTS);
+
+    if let Some(cache) = cache
+        && let Some(pmids) = cache.get_pubmed_search(&query, max_results).await?
+    {
+        return Ok(pmids);
+    }
+
+    let params = [
+        ("db", "pubmed".to_string()),
+        ("retmode", "json".to_string()),
+        ("sort", "relevance".to_string()),
+        ("retmax", max_results.to_string()),
+        ("term", query.clone()),
+    ];
+    let body = client
+        .post("esearch.fcgi", &params)
+        .await
+        .context("Failed to search PubMed")?;
+    let response: ESearchResponse =
+        serde_json::from_str(&body).context("Invalid ESearch response")?;
+    if let Some(error) = response.esearchresult.error {
+        bail!("PubMed search failed: {error}");
+    }
+    let pmids = response
+        .esearchresult
+        .idlist
+        .iter()
+        .map(|pmid| pmid.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .context("Invalid PMID in ESearch response")?;
+
+    if let Some(cache) = cache {
+        cache
+            .store_pubmed_search(&query, max_results, &pmids)
+            .await?;
+    }
 
-    Ok(dest_pmid)
+    Ok(pmids)
 }
 
 /// Performs a snowballing expansion of a citation network starting from PubMed IDs.
 ///
-/// This function iteratively finds references and/or citations for the current set
-/// of articles using the PubMed E-Utilities API up to a specified maximum depth.
-/// Note that PubMed's E-Utilities primarily provide citing articles and referenced
-/// articles via links, not comprehensive lists like some other databases.
+/// Scores are computed like `lens::snowball`: within a depth, each linked article
+/// inherits the count of the article linking to it, and counts are added across depths.
+/// Seeds count once each.
 ///
 /// # Arguments
 ///
-/// * `src_pmid`: A slice of string slices representing the starting PMIDs.
-/// * `max_depth`: The maximum depth of the snowballing process. Depth 0 returns only the initial IDs.
+/// * `src_pmid`: The seed PMIDs. Other identifiers are ignored.
+/// * `max_depth`: The maximum depth of the snowballing process.
+/// * `search_for`: Specifies whether to follow references, citations, or both.
+/// * `client`: Optional `PubmedClient`; a new one is created if `None`.
+/// * `cache`: Optional cache backend for storing and retrieving links.
 ///
 /// # Returns
 ///
-/// A `Result` containing a vector of strings representing the unique PMIDs found
-/// during the snowballing, or an error if the process fails.
-pub async fn snowball(src_pmid: &[&str], max_depth: u8) -> Result<Vec<String>> {
-    let mut all_pmid: Vec<String> = Vec::new();
-
-    let mut current_pmid = src_pmid
+/// A `Result` containing the occurrence count of every PMID found, or an error if no
+/// seed is a PMID or a request fails.
+pub async fn snowball<S>(
+    src_pmid: &[S],
+    max_depth: u8,
+    search_for: &SearchFor,
+    client: Option<&PubmedClient>,
+    cache: Option<&dyn CacheBackend>,
+) -> Result<HashMap<u64, usize>>
+where
+    S: AsRef<str>,
+{
+    let client = match client {
+        Some(c) => c,
+        None => &PubmedClient::default(),
+    };
+
+    let mut current_counts: HashMap<u64, usize> = src_pmid
         .iter()
-        .cloned()
-        .map(|x| x.to_owned())
-        .collect::<Vec<String>>();
+        .filter_map(|raw| Identifier::parse_as(IdType::Pmid, raw.as_ref()))
+        .filter_map(|pmid| pmid.value().parse().ok())
+        .map(|pmid| (pmid, 1))
+        .collect();
+    if current_counts.is_empty() {
+        bail!("No valid PMID in the input list");
+    }
 
-    all_pmid.append(&mut current_pmid.clone());
+    let mut all_counts: HashMap<u64, usize> = HashMap::new();
 
     for _ in 0..max_depth {
-        let current_pmid_refs = current_pmid
-            .iter()
-            .map(|x| x.as_str())
-            .collect::<Vec<&str>>();
+        let parents: Vec<u64> = current_counts.keys().copied().collect();
+        let mut next_counts: HashMap<u64, usize> = HashMap::new();
+
+        for &link in PubmedLink::for_search(search_for) {
+            let links = get_links(link, &parents, client, cache).await?;
+
+            // MULTIPLICATION: each child inherits the parent's count
+            for (parent, children) in links {
+                let parent_count = current_counts.get(&parent).copied().unwrap_or_default();
+                for child in children {
+                    *next_counts.entry(child).or_default() += parent_count;
+                }
+            }
+        }
 
-        current_pmid = snowball_onestep(&current_pmid_refs).await?;
+        if next_counts.is_empty() {
+            break;
+        }
+
+        // ADDITION: add this depth's counts to the total
+        for (pmid, count) in &next_counts {
+            *all_counts.entry(*pmid).or_default() += count;
+        }
+        current_counts = next_counts;
+    }
+
+    Ok(all_counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::lens::retry::RetryPolicy;
+    use stub::StubPubmed;
+
+    fn counts(pairs: &[(u64, usize)]) -> HashMap<u64, usize> {
+        pairs.iter().copied().collect()
+    }
+
+    /// Counts follow `lens::snowball`: children inherit the count of their parent
+    /// within a depth, and counts add up across depths.
+    #[tokio::test]
+    async fn snowball_counts_with_parent_multiplicity() {
+        let stub = StubPubmed::start().await;
+        let client = stub.client();
+        let seeds = ["31000001", "PMID: 31000002", "not a pmid"];
+
+        let references = snowball(&seeds, 2, &SearchFor::References, Some(&client), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            references,
+            counts(&[(31000003, 2), (31000010, 2), (31000011, 3), (31000012, 4)])
+        );
+
+        let both = snowball(&seeds, 2, &SearchFor::Both, Some(&client), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            both,
+            counts(&[
+                (31000001, 7),
+                (31000002, 6),
+                (31000003, 3),
+                (31000010, 2),
+                (31000011, 3),
+                (31000012, 4),
+                (31000020, 2),
+            ])
+        );
 
-        all_pmid.append(&mut current_pmid.clone());
+        // One batched request per depth and kind of link
+        assert_eq!(stub.request_count("elink.fcgi"), 2 + 4);
+
+        assert!(
+            snowball(&["not a pmid"], 2, &SearchFor::Both, Some(&client), None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn snowball_and_records_are_cached() {
+        use crate::lens::cache::sqlite::SqliteBackend;
+
+        let stub = StubPubmed::start().await;
+        let client = stub.client();
+        let cache = SqliteBackend::from_url("sqlite::memory:").await.unwrap();
+        let seeds = ["31000001", "31000002"];
+
+        let first = snowball(&seeds, 2, &SearchFor::Both, Some(&client), Some(&cache))
+            .await
+            .unwrap();
+        let elink_requests = stub.request_count("elink.fcgi");
+        let second = snowball(&seeds, 2, &SearchFor::Both, Some(&client), Some(&cache))
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(stub.request_count("elink.fcgi"), elink_requests);
+
+        let pmids = [31000001, 31000003, 31000099];
+        let fetched = Article::complete_articles(&pmids, Some(&client), Some(&cache))
+            .await
+            .unwrap();
+        assert_eq!(fetched.len(), 2, "Unknown PMIDs are left out");
+        assert_eq!(stub.request_count("efetch.fcgi"), 1);
+
+        let mut cached = Article::complete_articles(&pmids[..2], Some(&client), Some(&cache))
+            .await
+            .unwrap();
+        cached.sort_by(|a, b| a.pmid.cmp(&b.pmid));
+        assert_eq!(cached, fetched);
+        assert_eq!(stub.request_count("efetch.fcgi"), 1);
+    }
+
+    /// Requests are spaced by the rate limit, and carry the API key.
+    #[tokio::test]
+    async fn client_respects_rate_limit_and_sends_api_key() {
+        let stub = StubPubmed::start().await;
+        let client = stub
+            .client()
+            .with_api_key("ncbi-key")
+            .with_requests_per_second(20);
+
+        let seeds: Vec<String> = (0..250).map(|pmid| (31000001 + pmid).to_string()).collect();
+        snowball(&seeds, 1, &SearchFor::References, Some(&client), None)
+            .await
+            .unwrap();
+
+        let times = stub.request_times();
+        assert_eq!(times.len(), 3, "250 PMIDs need 3 ELink requests");
+        for pair in times.windows(2) {
+            assert!(pair[1] - pair[0] >= Duration::from_millis(45));
+        }
+        assert!(
+            stub.api_keys()
+                .iter()
+                .all(|key| key.as_deref() == Some("ncbi-key"))
+        );
     }
 
-    Ok(all_pmid)
+    #[cfg(feature = "cache-sqlite")]
+    #[tokio::test]
+    async fn search_is_cached_by_query() {
+        use crate::lens::cache::sqlite::SqliteBackend;
+
+        let stub = StubPubmed::start().await;
+        let client = stub.client().with_email("admin@example.org");
+        let cache = SqliteBackend::from_url("sqlite::memory:").await.unwrap();
+
+        let pmids = search("covid triage", 2, &client, Some(&cache))
+            .await
+            .unwrap();
+        assert_eq!(pmids, [31000001, 31000003]);
+        assert_eq!(
+            stub.param_values("email"),
+            [Some("admin@example.org".to_string())]
+        );
+        assert_eq!(stub.param_values("tool"), [Some("biblizap".to_string())]);
+
+        // Same query and fewer results: served from the cache
+        let pmids = search("  covid\ttriage ", 1, &client, Some(&cache))
+            .await
+            .unwrap();
+        assert_eq!(pmids, [31000001]);
+        assert_eq!(stub.request_count("esearch.fcgi"), 1);
+
+        // More results than cached: searched again, then complete in the cache
+        let pmids = search("covid triage", 50, &client, Some(&cache))
+            .await
+            .unwrap();
+        assert_eq!(pmids, [31000001, 31000003, 31000002]);
+        assert_eq!(stub.request_count("esearch.fcgi"), 2);
+        search("covid triage", 500, &client, Some(&cache))
+            .await
+            .unwrap();
+        assert_eq!(stub.request_count("esearch.fcgi"), 2);
+
+        assert!(
+            search("no such query", 20, &client, None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(search("   ", 20, &client, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn client_retries_server_errors() {
+        let stub = StubPubmed::start().await;
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+            ..Default::default()
+        };
+        let client = stub.client().with_retry_policy(policy);
+
+        stub.server_error_next(2);
+        let articles = Article::complete_articles(&[31000002], Some(&client), None)
+            .await
+            .unwrap();
+        assert_eq!(articles.len(), 1);
+        assert_eq!(stub.request_count("efetch.fcgi"), 3);
+
+        stub.server_error_next(1);
+        let client = client.with_retry_policy(RetryPolicy::none());
+        assert!(
+            Article::complete_articles(&[31000002], Some(&client), None)
+                .await
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,325 @@
+//! Local stand-in for the NCBI E-utilities, for tests.
+//!
+//! `StubPubmed` serves `elink.fcgi` from the link graph in `fixtures/links.json`,
+//! `efetch.fcgi` from the records in `fixtures/efetch.xml` and `esearch.fcgi` from the
+//! queries in `fixtures/esearch.json`, over plain HTTP on a random local port. Like the
+//! real services, it takes form-encoded POST bodies, answers ELink with one `LinkSet` per
+//! `id` parameter, EFetch with the requested records only and ESearch with at most
+//! `retmax` PMIDs. It can be told to answer the next requests with
+//! `503 Service Unavailable`, and records when each request arrived and with which
+//! parameters.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use roxmltree::{Document, ParsingOptions};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::Instant;
+
+use super::client::PubmedClient;
+
+const FIXTURE_LINKS: &str = include_str!("fixtures/links.json");
+const FIXTURE_EFETCH: &str = include_str!("fixtures/efetch.xml");
+const FIXTURE_ESEARCH: &str = include_str!("fixtures/esearch.json");
+
+#[derive(Debug, Default)]
+struct StubState {
+    /// Number of upcoming requests to answer with a 503
+    server_errors: usize,
+    /// Utility and arrival time of every request received
+    requests: Vec<(String, Instant)>,
+    /// Form parameters of every request received
+    params: Vec<Vec<(String, String)>>,
+}
+
+pub(crate) struct StubPubmed {
+    addr: SocketAddr,
+    state: Arc<Mutex<StubState>>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl StubPubmed {
+    /// Starts a stub server on `127.0.0.1` serving the recorded fixtures.
+    pub(crate) async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind stub PubMed server");
+        let addr = listener
+            .local_addr()
+            .expect("Stub PubMed server has no address");
+
+        let links: Arc<Value> =
+            Arc::new(serde_json::from_str(FIXTURE_LINKS).expect("Invalid PubMed link fixtures"));
+        let state = Arc::new(Mutex::new(StubState::default()));
+
+        let server_state = state.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(
+                    stream,
+                    links.clone(),
+                    server_state.clone(),
+                ));
+            }
+        });
+
+        Self {
+            addr,
+            state,
+            server,
+        }
+    }
+
+    /// A client sending its requests to this stub, without any rate limit worth noticing.
+    pub(crate) fn client(&self) -> PubmedClient {
+        PubmedClient::default()
+            .with_base_url(format!("http://{}", self.addr))
+            .with_requests_per_second(1000)
+    }
+
+    /// Answers the next `count` requests with a 503.
+    pub(crate) fn server_error_next(&self, count: usize) {
+        self.state.lock().unwrap().server_errors = count;
+    }
+
+    /// Number of requests received so far for `utility`, e.g. `elink.fcgi`.
+    pub(crate) fn request_count(&self, utility: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|(name, _)| name == utility)
+            .count()
+    }
+
+    /// Arrival time of every request received so far.
+    pub(crate) fn request_times(&self) -> Vec<Instant> {
+        let state = self.state.lock().unwrap();
+        state.requests.iter().map(|(_, time)| *time).collect()
+    }
+
+    /// Value of the `name` parameter of every request received so far, if any.
+    pub(crate) fn param_values(&self, name: &str) -> Vec<Option<String>> {
+        let state = self.state.lock().unwrap();
+        state
+            .params
+            .iter()
+            .map(|params| {
+                params
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+            })
+            .collect()
+    }
+
+    /// API key of every request received so far.
+    pub(crate) fn api_keys(&self) -> Vec<Option<String>> {
+        self.param_values("api_key")
+    }
+}
+
+impl Drop for StubPubmed {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Serves the HTTP/1.1 requests of one keep-alive connection.
+async fn serve_connection(stream: TcpStream, links: Arc<Value>, state: Arc<Mutex<StubState>>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+            return;
+        }
+
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        if reader.read_exact(&mut body).await.is_err() {
+            return;
+        }
+        let params = parse_form(&String::from_utf8_lossy(&body));
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+
+        let utility = request_line
+            .strip_prefix("POST /")
+            .and_then(|rest| rest.split_whitespace().next())
+            .unwrap_or_default()
+            .to_string();
+
+        let server_error = {
+            let mut state = state.lock().unwrap();
+            state.requests.push((utility.clone(), Instant::now()));
+            state.params.push(params.clone());
+            if state.server_errors > 0 {
+                state.server_errors -= 1;
+                true
+            } else {
+                false
+            }
+        };
+
+        let payload = match utility.as_str() {
+            _ if server_error => None,
+            "elink.fcgi" => {
+                let ids: Vec<&str> = params
+                    .iter()
+                    .filter(|(key, _)| key == "id")
+                    .map(|(_, value)| value.as_str())
+                    .collect();
+                Some(elink(&links, param("linkname").unwrap_or_default(), &ids))
+            }
+            "efetch.fcgi" => Some(efetch(param("id").unwrap_or_default())),
+            "esearch.fcgi" => Some(esearch(
+                param("term").unwrap_or_default(),
+                param("retmax")
+                    .and_then(|retmax| retmax.parse().ok())
+                    .unwrap_or(20),
+            )),
+            _ => None,
+        };
+
+        let response = match payload {
+            Some(payload) => format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/xml\r\ncontent-length: {}\r\n\r\n{payload}",
+                payload.len()
+            ),
+            None if server_error => {
+                "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n".to_string()
+            }
+            None => "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n".to_string(),
+        };
+
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Splits a form-encoded body into its parameters, in order.
+fn parse_form(body: &str) -> Vec<(String, String)> {
+    body.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (decode_form_value(key), decode_form_value(value)))
+        .collect()
+}
+
+/// Decodes `+` and `%XX` escapes.
+fn decode_form_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                decoded.push(u8::from_str_radix(&value[i + 1..i + 3], 16).unwrap_or(b'?'));
+                i += 2;
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Answers an ELink request with one `LinkSet` per PMID.
+fn elink(links: &Value, link_name: &str, ids: &[&str]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<eLinkResult>\n");
+    for id in ids {
+        xml.push_str(&format!(
+            "<LinkSet><DbFrom>pubmed</DbFrom><IdList><Id>{id}</Id></IdList>"
+        ));
+        let linked = links[link_name][id].as_array().cloned().unwrap_or_default();
+        if !linked.is_empty() {
+            xml.push_str(&format!(
+                "<LinkSetDb><DbTo>pubmed</DbTo><LinkName>{link_name}</LinkName>"
+            ));
+            for linked_id in linked {
+                xml.push_str(&format!("<Link><Id>{linked_id}</Id></Link>"));
+            }
+            xml.push_str("</LinkSetDb>");
+        }
+        xml.push_str("</LinkSet>\n");
+    }
+    xml.push_str("</eLinkResult>\n");
+    xml
+}
+
+/// Answers an EFetch request with the fixture records of the requested PMIDs.
+fn efetch(ids: &str) -> String {
+    let ids: Vec<&str> = ids.split(',').collect();
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let document =
+        Document::parse_with_options(FIXTURE_EFETCH, options).expect("Invalid EFetch fixture");
+
+    let records: HashMap<&str, &str> = document
+        .root_element()
+        .children()
+        .filter(|n| n.has_tag_name("PubmedArticle"))
+        .filter_map(|article| {
+            let pmid = article
+                .descendants()
+                .find(|n| n.has_tag_name("PMID"))?
+                .text()?;
+            Some((pmid, &FIXTURE_EFETCH[article.range()]))
+        })
+        .collect();
+
+    let mut xml = String::from("<?xml version=\"1.0\" ?>\n<PubmedArticleSet>\n");
+    for id in ids {
+        if let Some(record) = records.get(id) {
+            xml.push_str(record);
+            xml.push('\n');
+        }
+    }
+    xml.push_str("</PubmedArticleSet>\n");
+    xml
+}
+
+/// Answers an ESearch request in JSON, with at most `retmax` of the fixture PMIDs.
+fn esearch(term: &str, retmax: usize) -> String {
+    let searches: Value = serde_json::from_str(FIXTURE_ESEARCH).expect("Invalid ESearch fixture");
+    let pmids = searches[term].as_array().cloned().unwrap_or_default();
+    serde_json::json!({
+        "header": { "type": "esearch", "version": "0.3" },
+        "esearchresult": {
+            "count": pmids.len().to_string(),
+            "retmax": pmids.len().min(retmax).to_string(),
+            "retstart": "0",
+            "idlist": pmids.into_iter().take(retmax).collect::<Vec<_>>(),
+        }
+    })
+    .to_string()
+}
@@ -0,0 +1,332 @@
+//! Parsers for the XML returned by the E-utilities: PubMed records from EFetch with
+//! `retmode=xml`, and links from ELink.
+//!
+//! Fields are read into the same `Article` as the MEDLINE parser, spelled the way
+//! MEDLINE spells them (`Martin C` authors, `2020 Mar 5` dates, `*Descriptor/qualifier`
+//! MeSH headings), so that both formats give the same articles.
+
+use anyhow::{Context, Result, bail};
+use roxmltree::{Document, Node, ParsingOptions};
+
+use super::{Article, clean_text};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// The first child element with this tag name.
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+/// The child elements with this tag name.
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+/// The element at the end of a path of tag names.
+fn path<'a, 'input>(node: Node<'a, 'input>, names: &[&str]) -> Option<Node<'a, 'input>> {
+    names.iter().try_fold(node, |node, name| child(node, name))
+}
+
+/// All the text of an element, inline markup such as `<i>` included, with whitespace
+/// collapsed. Empty text gives `None`.
+fn text(node: Node) -> Option<String> {
+    let text: String = node
+        .descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect();
+    let text = clean_text(&text);
+    (!text.is_empty()).then_some(text)
+}
+
+fn path_text(node: Node, names: &[&str]) -> Option<String> {
+    path(node, names).and_then(text)
+}
+
+/// The publication date as MEDLINE writes it in `DP`, e.g. `2020 Mar 5`.
+fn pub_date(pub_date: Node) -> Option<String> {
+    if let Some(medline_date) = path_text(pub_date, &["MedlineDate"]) {
+        return Some(medline_date);
+    }
+    let mut parts = vec![path_text(pub_date, &["Year"])?];
+    if let Some(month) = path_text(pub_date, &["Month"]) {
+        let month = match month.parse::<usize>() {
+            Ok(number @ 1..=12) => MONTHS[number - 1].to_string(),
+            _ => month,
+        };
+        parts.push(month);
+        if let Some(day) = path_text(pub_date, &["Day"]) {
+            parts.push(day.trim_start_matches('0').to_string());
+        }
+    } else if let Some(season) = path_text(pub_date, &["Season"]) {
+        parts.push(season);
+    }
+    Some(parts.join(" "))
+}
+
+/// An author as MEDLINE writes it in `AU` or `CN`.
+fn author(author: Node) -> Option<String> {
+    if let Some(collective_name) = path_text(author, &["CollectiveName"]) {
+        return Some(collective_name);
+    }
+    let last_name = path_text(author, &["LastName"])?;
+    Some(match path_text(author, &["Initials"]) {
+        Some(initials) => format!("{last_name} {initials}"),
+        None => last_name,
+    })
+}
+
+/// A MeSH heading as MEDLINE writes it in `MH`, major topics starred.
+fn mesh_heading(heading: Node) -> Option<String> {
+    fn starred(node: Node) -> Option<String> {
+        let name = text(node)?;
+        Some(match node.attribute("MajorTopicYN") {
+            Some("Y") => format!("*{name}"),
+            _ => name,
+        })
+    }
+
+    let mut parts = vec![starred(child(heading, "DescriptorName")?)?];
+    parts.extend(children(heading, "QualifierName").filter_map(starred));
+    Some(parts.join("/"))
+}
+
+/// The abstract as MEDLINE writes it in `AB`, with `LABEL: ` before each labelled section.
+fn abstract_text(abstract_node: Node) -> Option<String> {
+    let sections: Vec<String> = children(abstract_node, "AbstractText")
+        .filter_map(|section| {
+            let section_text = text(section)?;
+            Some(match section.attribute("Label") {
+                Some(label) => format!("{label}: {section_text}"),
+                None => section_text,
+            })
+        })
+        .collect();
+    (!sections.is_empty()).then(|| sections.join(" "))
+}
+
+/// An article ID of this type, from the `ELocationID`s or the `PubmedData` ID list.
+fn article_id(pubmed_article: Node, article: Option<Node>, id_type: &str) -> Option<String> {
+    let elocation = article.and_then(|article| {
+        children(article, "ELocationID")
+            .find(|n| n.attribute("EIdType") == Some(id_type))
+            .and_then(text)
+    });
+    elocation.or_else(|| {
+        let ids = path(pubmed_article, &["PubmedData", "ArticleIdList"])?;
+        children(ids, "ArticleId")
+            .find(|n| n.attribute("IdType") == Some(id_type))
+            .and_then(text)
+    })
+}
+
+impl Article {
+    /// Builds an article from a `PubmedArticle` element.
+    fn from_pubmed_article(pubmed_article: Node) -> Result<Article> {
+        let citation =
+            child(pubmed_article, "MedlineCitation").context("No MedlineCitation in article")?;
+        let pmid = path_text(citation, &["PMID"]).context("No PMID found for this article")?;
+        let article = child(citation, "Article");
+        let journal = article.and_then(|article| child(article, "Journal"));
+        let journal_issue = journal.and_then(|journal| child(journal, "JournalIssue"));
+
+        let authors: Vec<String> = article
+            .and_then(|article| child(article, "AuthorList"))
+            .map(|list| children(list, "Author").filter_map(author).collect())
+            .unwrap_or_default();
+
+        let mut affiliations: Vec<String> = Vec::new();
+        if let Some(author_list) = article.and_then(|article| child(article, "AuthorList")) {
+            for affiliation in author_list
+                .descendants()
+                .filter(|n| n.has_tag_name("Affiliation"))
+                .filter_map(text)
+            {
+                if !affiliations.contains(&affiliation) {
+                    affiliations.push(affiliation);
+                }
+            }
+        }
+
+        let list = |list_name: &str, item_name: &'static str| -> Vec<String> {
+            article
+                .and_then(|article| child(article, list_name))
+                .map(|list| children(list, item_name).filter_map(text).collect())
+                .unwrap_or_default()
+        };
+
+        Ok(Article {
+            pmid,
+            title: article.and_then(|article| path_text(article, &["ArticleTitle"])),
+            summary: article
+                .and_then(|article| child(article, "Abstract"))
+                .and_then(abstract_text),
+            authors: (!authors.is_empty()).then_some(authors),
+            date_published: journal_issue
+                .and_then(|issue| child(issue, "PubDate"))
+                .and_then(pub_date),
+            journal: journal.and_then(|journal| path_text(journal, &["Title"])),
+            journal_abbreviation: path_text(citation, &["MedlineJournalInfo", "MedlineTA"]),
+            volume: journal_issue.and_then(|issue| path_text(issue, &["Volume"])),
+            issue: journal_issue.and_then(|issue| path_text(issue, &["Issue"])),
+            pages: article.and_then(|article| path_text(article, &["Pagination", "MedlinePgn"])),
+            doi: article_id(pubmed_article, article, "doi"),
+            pmcid: article_id(pubmed_article, None, "pmc"),
+            mesh_terms: child(citation, "MeshHeadingList")
+                .map(|list| {
+                    children(list, "MeshHeading")
+                        .filter_map(mesh_heading)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            publication_types: list("PublicationTypeList", "PublicationType"),
+            languages: article
+                .map(|article| children(article, "Language").filter_map(text).collect())
+                .unwrap_or_default(),
+            affiliations,
+        })
+    }
+
+    /// Parses every `PubmedArticle` of an EFetch XML response.
+    ///
+    /// Book articles (`PubmedBookArticle`) are skipped.
+    pub fn from_efetch_xml(xml: &str) -> Result<Vec<Article>> {
+        let document = parse_document(xml)?;
+
+        document
+            .root_element()
+            .children()
+            .filter(|n| n.has_tag_name("PubmedArticle"))
+            .map(Article::from_pubmed_article)
+            .collect()
+    }
+}
+
+fn parse_document(xml: &str) -> Result<Document<'_>> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    Document::parse_with_options(xml, options).context("Invalid PubMed XML")
+}
+
+/// Parses an ELink response into the linked PMIDs of each source PMID, for the links
+/// named `link_name`.
+///
+/// ELink answers a request with one `id` parameter per PMID with one `LinkSet` per PMID.
+/// Source PMIDs without any such link have no `LinkSetDb` and get an empty list.
+pub(super) fn parse_elink_xml(xml: &str, link_name: &str) -> Result<Vec<(u64, Vec<u64>)>> {
+    let document = parse_document(xml)?;
+    let root = document.root_element();
+
+    let link_sets: Vec<Node> = children(root, "LinkSet").collect();
+    if link_sets.is_empty()
+        && let Some(error) = child(root, "ERROR").and_then(text)
+    {
+        bail!("ELink error: {error}");
+    }
+
+    let pmid = |node: Node| text(node)?.parse::<u64>().ok();
+
+    Ok(link_sets
+        .into_iter()
+        .filter_map(|link_set| {
+            let source = path(link_set, &["IdList", "Id"]).and_then(pmid)?;
+            let linked = children(link_set, "LinkSetDb")
+                .filter(|db| path_text(*db, &["LinkName"]).as_deref() == Some(link_name))
+                .flat_map(|db| children(db, "Link"))
+                .filter_map(|link| child(link, "Id").and_then(pmid))
+                .collect();
+            Some((source, linked))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE_XML: &str = include_str!("fixtures/efetch.xml");
+    const FIXTURE_MEDLINE: &str = include_str!("fixtures/medline.txt");
+
+    /// The XML and MEDLINE exports of the same records give the same articles.
+    #[test]
+    fn efetch_xml_matches_medline() {
+        let from_xml = Article::from_efetch_xml(FIXTURE_XML).unwrap();
+        let from_medline = Article::from_medline(FIXTURE_MEDLINE).unwrap();
+        assert_eq!(from_xml.len(), 3);
+
+        for (xml, medline) in from_xml.iter().zip(&from_medline) {
+            assert_eq!(xml, medline);
+        }
+    }
+
+    /// Articles read from XML survive a MEDLINE round trip.
+    #[test]
+    fn efetch_xml_to_medline_round_trip() {
+        let from_xml = Article::from_efetch_xml(FIXTURE_XML).unwrap();
+        let written: String = from_xml
+            .iter()
+            .map(|article| article.to_medline() + "\n")
+            .collect();
+        assert_eq!(Article::from_medline(&written).unwrap(), from_xml);
+    }
+
+    #[test]
+    fn efetch_xml_errors() {
+        assert!(Article::from_efetch_xml("<PubmedArticleSet>").is_err());
+        assert!(
+            Article::from_efetch_xml(
+                "<PubmedArticleSet><PubmedArticle><MedlineCitation/></PubmedArticle></PubmedArticleSet>"
+            )
+            .is_err()
+        );
+        assert_eq!(
+            Article::from_efetch_xml("<PubmedArticleSet></PubmedArticleSet>").unwrap(),
+            []
+        );
+    }
+
+    #[test]
+    fn parse_elink_link_sets() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<!DOCTYPE eLinkResult PUBLIC "-//NLM//DTD elink 20101123//EN" "https://eutils.ncbi.nlm.nih.gov/eutils/dtd/20101123/elink.dtd">
+<eLinkResult>
+<LinkSet>
+    <DbFrom>pubmed</DbFrom>
+    <IdList><Id>31000001</Id></IdList>
+    <LinkSetDb>
+        <DbTo>pubmed</DbTo>
+        <LinkName>pubmed_pubmed_refs</LinkName>
+        <Link><Id>31000010</Id></Link>
+        <Link><Id>31000003</Id></Link>
+    </LinkSetDb>
+</LinkSet>
+<LinkSet>
+    <DbFrom>pubmed</DbFrom>
+    <IdList><Id>31000003</Id></IdList>
+</LinkSet>
+</eLinkResult>"#;
+
+        assert_eq!(
+            parse_elink_xml(xml, "pubmed_pubmed_refs").unwrap(),
+            [(31000001, vec![31000010, 31000003]), (31000003, vec![])]
+        );
+        assert_eq!(
+            parse_elink_xml(xml, "pubmed_pubmed_citedin").unwrap(),
+            [(31000001, vec![]), (31000003, vec![])]
+        );
+        assert!(
+            parse_elink_xml(
+                "<eLinkResult><ERROR>Invalid db name</ERROR></eLinkResult>",
+                "pubmed_pubmed_refs"
+            )
+            .is_err()
+        );
+    }
+}
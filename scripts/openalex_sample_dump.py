@@ -0,0 +1,191 @@
+#!/usr/bin/env python3
+"""Write a small OpenAlex works snapshot to benchmark `biblizap-openalex import`.
+
+The output has the layout of a real snapshot (`data/works/updated_date=*/part_*.gz`
+with a `manifest`), so it is imported exactly like one. `sample` copies the first
+works of some files of a real snapshot; `synthetic` generates works with realistic
+sizes, identifiers and reference counts, some of them repeated in several partitions.
+"""
+
+from __future__ import annotations
+
+import argparse
+import gzip
+import json
+import random
+import shutil
+from pathlib import Path
+
+
+FIRST_WORK_ID = 4_000_000_000
+WORDS = (
+    "cohort study patients outcome risk analysis clinical trial randomized effect "
+    "treatment association mortality infection model response protein expression "
+    "cell gene therapy disease population data review systematic meta"
+).split()
+
+
+def write_partition_file(path: Path, lines: list[str]) -> dict:
+    path.parent.mkdir(parents=True, exist_ok=True)
+    with gzip.open(path, "wt", encoding="utf-8", compresslevel=6) as out:
+        for line in lines:
+            out.write(line)
+            out.write("\n")
+    return {
+        "url": f"s3://openalex/data/works/{path.parent.name}/{path.name}",
+        "meta": {"content_length": path.stat().st_size, "record_count": len(lines)},
+    }
+
+
+def write_manifest(works_dir: Path, entries: list[dict]) -> None:
+    manifest = {
+        "entries": entries,
+        "meta": {
+            "content_length": sum(e["meta"]["content_length"] for e in entries),
+            "record_count": sum(e["meta"]["record_count"] for e in entries),
+        },
+    }
+    (works_dir / "manifest").write_text(json.dumps(manifest, indent=2))
+
+
+def synthetic_work(rng: random.Random, number: int, total: int, references: int, date: str) -> str:
+    work_id = FIRST_WORK_ID + number
+    doi = f"https://doi.org/10.5555/sample.{number}"
+    words = [rng.choice(WORDS) for _ in range(rng.randint(120, 250))]
+    inverted: dict[str, list[int]] = {}
+    for position, word in enumerate(words):
+        inverted.setdefault(word, []).append(position)
+    cited = rng.sample(range(total), min(total, max(0, int(rng.gauss(references, references / 3)))))
+    work = {
+        "id": f"https://openalex.org/W{work_id}",
+        "doi": doi,
+        "title": " ".join(rng.choice(WORDS) for _ in range(12)).capitalize(),
+        "publication_year": rng.randint(1990, 2026),
+        "publication_date": f"{rng.randint(1990, 2026)}-0{rng.randint(1, 9)}-1{rng.randint(0, 9)}",
+        "ids": {
+            "openalex": f"https://openalex.org/W{work_id}",
+            "doi": doi,
+            "mag": str(work_id - 1_000_000_000),
+            "pmid": f"https://pubmed.ncbi.nlm.nih.gov/{30_000_000 + number}",
+        },
+        "language": "en",
+        "primary_location": {
+            "is_oa": number % 3 == 0,
+            "source": {
+                "id": f"https://openalex.org/S{200_000_000 + number % 5000}",
+                "display_name": f"Journal of Sample Studies {number % 5000}",
+                "type": "journal",
+            },
+            "license": "cc-by" if number % 3 == 0 else None,
+        },
+        "type": "article",
+        "open_access": {"is_oa": number % 3 == 0, "oa_url": None},
+        "authorships": [
+            {
+                "author_position": "first" if position == 0 else "middle",
+                "author": {
+                    "id": f"https://openalex.org/A{5_000_000_000 + rng.randrange(10**7)}",
+                    "display_name": f"Author {rng.randrange(10**6)}",
+                    "orcid": None,
+                },
+                "institutions": [],
+                "raw_affiliation_strings": [],
+            }
+            for position in range(rng.randint(1, 8))
+        ],
+        "biblio": {"volume": str(rng.randint(1, 80)), "issue": str(rng.randint(1, 12)),
+                   "first_page": str(rng.randint(1, 900)), "last_page": None},
+        "is_retracted": False,
+        "cited_by_count": rng.randint(0, 500),
+        "referenced_works": [f"https://openalex.org/W{FIRST_WORK_ID + c}" for c in cited],
+        "abstract_inverted_index": inverted,
+        "updated_date": f"{date}T00:00:00.000000",
+        "created_date": "2024-01-01",
+    }
+    return json.dumps(work)
+
+
+def synthetic(args: argparse.Namespace) -> None:
+    rng = random.Random(args.seed)
+    works_dir = args.out / "data" / "works"
+    dates = [f"2026-{month:02d}-01" for month in range(1, args.partitions + 1)]
+    files = [(date, part) for date in dates for part in range(args.files_per_partition)]
+    per_file = -(-args.works // len(files))
+
+    entries = []
+    for index, (date, part) in enumerate(files):
+        numbers = list(range(index * per_file, min(args.works, (index + 1) * per_file)))
+        # Copies of works of older files, as works updated since show up again
+        numbers += [rng.randrange(args.works) for _ in range(int(len(numbers) * args.duplicates))]
+        lines = [synthetic_work(rng, n, args.works, args.references, date) for n in numbers]
+        path = works_dir / f"updated_date={date}" / f"part_{part:03d}.gz"
+        entries.append(write_partition_file(path, lines))
+        print(f"{path}: {len(lines)} works")
+    write_manifest(works_dir, entries)
+
+
+def sample(args: argparse.Namespace) -> None:
+    source_dir = args.snapshot / "data" / "works"
+    works_dir = args.out / "data" / "works"
+    partitions = sorted(p for p in source_dir.glob("updated_date=*") if p.is_dir())
+    partitions = partitions[-args.partitions:]
+
+    entries = []
+    for partition in partitions:
+        for source in sorted(partition.glob("*.gz"))[: args.files_per_partition]:
+            lines = []
+            with gzip.open(source, "rt", encoding="utf-8") as lines_in:
+                for line in lines_in:
+                    if len(lines) >= args.works_per_file:
+                        break
+                    if line.strip():
+                        lines.append(line.rstrip("\n"))
+            path = works_dir / partition.name / source.name
+            entries.append(write_partition_file(path, lines))
+            print(f"{path}: {len(lines)} works")
+    write_manifest(works_dir, entries)
+
+
+def parse_args() -> argparse.Namespace:
+    parser = argparse.ArgumentParser(description=__doc__)
+    parser.add_argument("--out", type=Path, required=True, help="snapshot root to write")
+    parser.add_argument("--partitions", type=int, default=4)
+    parser.add_argument("--files-per-partition", type=int, default=4)
+
+    subparsers = parser.add_subparsers(dest="command", required=True)
+
+    synthetic_parser = subparsers.add_parser("synthetic", help="generate works")
+    synthetic_parser.add_argument("--works", type=int, default=200_000)
+    synthetic_parser.add_argument(
+        "--references", type=int, default=40, help="mean number of references per work"
+    )
+    synthetic_parser.add_argument(
+        "--duplicates",
+        type=float,
+        default=0.02,
+        help="share of works repeated from another file",
+    )
+    synthetic_parser.add_argument("--seed", type=int, default=1)
+
+    sample_parser = subparsers.add_parser("sample", help="copy works of a real snapshot")
+    sample_parser.add_argument("--snapshot", type=Path, required=True)
+    sample_parser.add_argument("--works-per-file", type=int, default=50_000)
+
+    return parser.parse_args()
+
+
+def main() -> None:
+    args = parse_args()
+    if args.out.exists():
+        shutil.rmtree(args.out / "data" / "works", ignore_errors=True)
+    if args.command == "synthetic":
+        synthetic(args)
+        return
+    if args.command == "sample":
+        sample(args)
+        return
+    raise AssertionError(f"unhandled command: {args.command}")
+
+
+if __name__ == "__main__":
+    main()
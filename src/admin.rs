@@ -0,0 +1,38 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, http::header, web};
+
+use crate::AppConfig;
+
+/// Checks the `Authorization: Bearer <admin_token>` header of an admin request.
+///
+/// Returns the response to send back if the request must be rejected. Admin
+/// endpoints are disabled altogether when no admin token is configured.
+pub fn authorize(req: &HttpRequest, config: &AppConfig) -> Option<HttpResponse> {
+    let Some(admin_token) = config.admin_token.as_deref() else {
+        return Some(HttpResponse::NotFound().body("Admin endpoints are disabled"));
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), admin_token.as_bytes()) => None,
+        _ => Some(HttpResponse::Unauthorized().body("Invalid admin token")),
+    }
+}
+
+/// Actix-web handler for `GET /api/admin/lens`, reporting Lens request and retry counts.
+pub async fn lens_client_stats(req: HttpRequest, config: web::Data<AppConfig>) -> impl Responder {
+    if let Some(response) = authorize(&req, &config) {
+        return response;
+    }
+
+    HttpResponse::Ok().json(config.lens_client.stats())
+}
+
+/// Compares two byte strings without short-circuiting on the first difference.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,299 @@
+use biblizap_rs::identifier::{IdType, Identifier};
+use biblizap_rs::lens::{
+    cache::{CacheBackend, PostgresBackend, SqliteBackend},
+    error::LensError,
+    lensid::LensId,
+};
+use clap::{Parser, Subcommand};
+use config as conf;
+use serde::Deserialize;
+use std::env;
+use thiserror::Error;
+
+#[derive(Debug, Deserialize, Default)]
+struct FileConfig {
+    cache_backend_url: Option<String>,
+}
+
+#[derive(Debug, Error)]
+enum Error {
+    #[error("missing {0}; set it in biblizap.toml, an environment variable, or the CLI")]
+    MissingConfig(&'static str),
+    #[error("unsupported cache URL {0}; expected a sqlite: or postgres:// URL")]
+    UnsupportedCacheUrl(String),
+    #[error("{0} is not a Lens ID and has no cached mapping to one")]
+    UnknownId(String),
+    #[error(transparent)]
+    Cache(#[from] LensError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Inspect and maintain the BibliZap Lens cache")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Cache backend URL (sqlite: or postgres://)
+    #[arg(long)]
+    cache_backend_url: Option<String>,
+
+    /// Log level
+    #[arg(short = 'L', long, default_value_t = log::LevelFilter::Info)]
+    log_level: log::LevelFilter,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the cached references, citations and metadata of an article
+    Show {
+        /// Lens ID, DOI, PMID or other external ID (which must have been looked up before)
+        id: String,
+    },
+    /// Remove everything cached about some articles, so they are fetched again
+    Purge {
+        /// Lens IDs, DOIs, PMIDs or other external IDs
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Print the number of rows of each cache table
+    Stats,
+    /// Remove pending fetch marks left behind by crashed or timed out requests
+    ClearPending,
+    /// Reclaim free space and refresh the query planner statistics
+    Vacuum,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let args = Args::parse();
+    dotenvy::dotenv().ok();
+
+    let mut logger_builder = env_logger::Builder::from_env(env_logger::Env::default());
+    if env::var_os("RUST_LOG").is_none() {
+        logger_builder.filter_level(args.log_level);
+    }
+    logger_builder.init();
+
+    let file_cfg = load_file_config();
+    let cache_url = resolve_cache_url(args.cache_backend_url, file_cfg.cache_backend_url)?;
+    let cache = open_cache(&cache_url).await?;
+
+    match args.command {
+        Command::Show { id } => {
+            let lens_id = resolve_id(cache.as_ref(), &id).await?;
+            let ids = std::slice::from_ref(&lens_id);
+
+            let references = cache.get_references(ids).await?.remove(&lens_id);
+            let citations = cache.get_citations(ids).await?.remove(&lens_id);
+            let cited_by = cache.get_cited_by(ids).await?.remove(&lens_id);
+            let article_data = cache.get_article_data(ids).await?.pop();
+
+            let output = serde_json::json!({
+                "lens_id": lens_id,
+                "references": references,
+                "citations": citations,
+                "cited_by": cited_by,
+                "article_data": article_data.map(|article| article.article_data),
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        Command::Purge { ids } => {
+            let mut lens_ids = Vec::with_capacity(ids.len());
+            for id in &ids {
+                lens_ids.push(resolve_id(cache.as_ref(), id).await?);
+            }
+
+            let deleted = cache.purge(&lens_ids).await?;
+            log::info!("Purged {} articles ({deleted} rows)", lens_ids.len());
+        }
+        Command::Stats => {
+            let stats = cache.stats().await?;
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+        }
+        Command::ClearPending => {
+            let cleared = cache.clear_stale_pending_fetches().await?;
+            log::info!("Cleared {cleared} stale pending fetches");
+        }
+        Command::Vacuum => {
+            cache.vacuum().await?;
+            log::info!("Vacuum complete");
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens the cache backend matching the URL scheme.
+async fn open_cache(url: &str) -> Result<Box<dyn CacheBackend>, Error> {
+    if url.starts_with("sqlite:") {
+        Ok(Box::new(SqliteBackend::from_url(url).await?))
+    } else if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        Ok(Box::new(PostgresBackend::from_url(url).await?))
+    } else {
+        Err(Error::UnsupportedCacheUrl(url.to_string()))
+    }
+}
+
+/// Turns a Lens ID, DOI, PMID or other external ID into a Lens ID, using the cached ID
+/// mappings.
+async fn resolve_id(cache: &dyn CacheBackend, id: &str) -> Result<LensId, Error> {
+    let id = id.trim();
+    let identifier = Identifier::parse(id);
+    if let Some(lens_id) = identifier
+        .as_ref()
+        .filter(|identifier| identifier.id_type() == IdType::LensId)
+        .and_then(|identifier| LensId::try_from(identifier.value()).ok())
+    {
+        return Ok(lens_id);
+    }
+
+    // Mappings are keyed by the canonical identifier
+    let key = identifier
+        .map(|identifier| identifier.key())
+        .ok_or_else(|| Error::UnknownId(id.to_string()))?;
+    let mut mappings = cache.get_id_mapping(std::slice::from_ref(&key)).await?;
+
+    mappings
+        .remove(&key)
+        .ok_or_else(|| Error::UnknownId(id.to_string()))
+}
+
+fn resolve_cache_url(
+    cli_cache_url: Option<String>,
+    config_cache_url: Option<String>,
+) -> Result<String, Error> {
+    cli_cache_url
+        .or(config_cache_url)
+        .or_else(|| env::var("BIBLIZAP_CACHE_BACKEND_URL").ok())
+        .ok_or(Error::MissingConfig("cache_backend_url"))
+}
+
+fn load_file_config() -> FileConfig {
+    let user_config_dir = env::var("XDG_CONFIG_HOME").ok().unwrap_or_else(|| {
+        let home = env::var("HOME").unwrap_or_default();
+        format!("{}/.config", home)
+    });
+
+    let builder = conf::Config::builder()
+        .add_source(conf::File::with_name("/etc/biblizap/biblizap.toml").required(false))
+        .add_source(
+            conf::File::with_name(&format!("{}/biblizap/biblizap.toml", user_config_dir))
+                .required(false),
+        )
+        .add_source(conf::File::with_name("biblizap.toml").required(false))
+        .add_source(conf::Environment::with_prefix("BIBLIZAP").separator("__"));
+
+    let settings = builder.build().unwrap_or_else(|e| {
+        log::warn!("failed to build config: {}", e);
+        conf::Config::default()
+    });
+
+    settings.try_deserialize().unwrap_or_else(|e| {
+        log::warn!("failed to parse config: {}", e);
+        FileConfig::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENS_ID: &str = "565-646-398-006-312";
+
+    async fn cache_with_mappings() -> SqliteBackend {
+        let cache = SqliteBackend::from_url("sqlite::memory:").await.unwrap();
+        let lens_id = LensId::try_from(LENS_ID).unwrap();
+        cache
+            .store_id_mapping(&[
+                ("10.1000/stub.a".to_string(), lens_id.clone()),
+                ("30000001".to_string(), lens_id.clone()),
+                ("mag:3000000001".to_string(), lens_id.clone()),
+                // Not canonical, as only written by caches older than the key rewrite
+                ("10.1000/STUB.OLD".to_string(), lens_id),
+            ])
+            .await
+            .unwrap();
+        cache
+    }
+
+    #[tokio::test]
+    async fn lens_ids_are_resolved_without_mappings() {
+        let cache = SqliteBackend::from_url("sqlite::memory:").await.unwrap();
+
+        for id in [LENS_ID, " 565-646-398-006-312 ", "lens:565-646-398-006-312"] {
+            let lens_id = resolve_id(&cache, id).await.unwrap();
+            assert_eq!(lens_id.as_ref(), LENS_ID, "{id}");
+        }
+    }
+
+    #[tokio::test]
+    async fn external_ids_are_resolved_by_canonical_key() {
+        let cache = cache_with_mappings().await;
+
+        for id in [
+            "10.1000/stub.a",
+            "https://doi.org/10.1000/STUB.A",
+            "doi:10.1000/stub.a.",
+            "30000001",
+            "pmid:30000001",
+            "mag:3000000001",
+        ] {
+            let lens_id = resolve_id(&cache, id).await.unwrap();
+            assert_eq!(lens_id.as_ref(), LENS_ID, "{id}");
+        }
+    }
+
+    #[tokio::test]
+    async fn legacy_and_unknown_ids_are_not_resolved() {
+        let cache = cache_with_mappings().await;
+
+        // Legacy keys are rewritten when the cache is opened, so only canonical keys are
+        // looked up
+        for id in [
+            "10.1000/STUB.OLD",
+            "10.1000/stub.old",
+            "3000000001",
+            "not an id",
+        ] {
+            let error = resolve_id(&cache, id).await.unwrap_err();
+            assert!(matches!(error, Error::UnknownId(_)), "{id}: {error}");
+        }
+    }
+
+    #[test]
+    fn cache_url_from_cli_then_config_then_environment() {
+        let cli = Some("sqlite:cli.db".to_string());
+        let config = Some("postgres://localhost/config".to_string());
+
+        assert_eq!(
+            resolve_cache_url(cli.clone(), config.clone()).unwrap(),
+            "sqlite:cli.db"
+        );
+        assert_eq!(
+            resolve_cache_url(None, config).unwrap(),
+            "postgres://localhost/config"
+        );
+
+        // SAFETY: no other test of this binary reads or writes the environment
+        unsafe { env::set_var("BIBLIZAP_CACHE_BACKEND_URL", "sqlite:env.db") };
+        assert_eq!(resolve_cache_url(None, None).unwrap(), "sqlite:env.db");
+        assert_eq!(resolve_cache_url(cli, None).unwrap(), "sqlite:cli.db");
+
+        unsafe { env::remove_var("BIBLIZAP_CACHE_BACKEND_URL") };
+        assert!(matches!(
+            resolve_cache_url(None, None),
+            Err(Error::MissingConfig("cache_backend_url"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn only_sqlite_and_postgres_urls_are_opened() {
+        assert!(open_cache("sqlite::memory:").await.is_ok());
+        assert!(matches!(
+            open_cache("mysql://localhost/cache").await,
+            Err(Error::UnsupportedCacheUrl(_))
+        ));
+    }
+}
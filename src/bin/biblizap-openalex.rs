@@ -1,6 +1,10 @@
 #[path = "../openalex/mod.rs"]
 mod openalex;
 
+use biblizap_rs::lens::{
+    cache::{CacheBackend, PostgresBackend, SqliteBackend},
+    error::LensError,
+};
 use clap::{Parser, Subcommand};
 use config as conf;
 use serde::Deserialize;
@@ -10,6 +14,7 @@ use thiserror::Error;
 #[derive(Debug, Deserialize, Default)]
 struct FileConfig {
     openalex_dump_path: Option<PathBuf>,
+    cache_backend_url: Option<String>,
 }
 
 #[derive(Debug, Error)]
@@ -18,8 +23,14 @@ enum Error {
     MissingConfig(&'static str),
     #[error("failed to connect to OpenAlex database: {0}")]
     Database(#[from] sqlx::Error),
+    #[error("unsupported cache URL {0}; expected a sqlite: or postgres:// URL")]
+    UnsupportedCacheUrl(String),
+    #[error(transparent)]
+    Cache(#[from] LensError),
     #[error(transparent)]
     Import(#[from] openalex::OpenAlexImportError),
+    #[error("verification found {0} problems")]
+    Verification(usize),
 }
 
 #[derive(Parser, Debug)]
@@ -35,15 +46,75 @@ struct Args {
 
 #[derive(Subcommand, Debug)]
 enum Command {
-    /// Import an OpenAlex gzipped JSON/JSONL dump into the BibliZap database
+    /// Import an OpenAlex works snapshot or dump into the BibliZap database, with the
+    /// sources, authors and topics of a snapshot, resuming an interrupted import
     Import {
-        /// Path to an OpenAlex gzipped JSON/JSONL dump file or dump directory
+        /// Path to an OpenAlex snapshot root, its data/works directory, or a gzipped
+        /// JSON/JSONL dump file or dump directory
+        #[arg(long)]
+        dump_path: Option<PathBuf>,
+
+        /// PostgreSQL URL for the BibliZap OpenAlex database
+        #[arg(long)]
+        database_url: Option<String>,
+
+        /// Number of dump files read and copied at once [default: number of CPUs]
+        #[arg(long)]
+        workers: Option<usize>,
+
+        /// Number of records per COPY chunk sent by each worker
+        #[arg(long, default_value_t = openalex::ImportOptions::default().batch_size)]
+        batch_size: usize,
+    },
+    /// Apply the partitions, merged works and new entity files of a newer OpenAlex
+    /// snapshot to an imported database
+    Update {
+        /// Path to the OpenAlex snapshot root, holding data/works and data/merged_ids
+        #[arg(long)]
+        dump_path: Option<PathBuf>,
+
+        /// PostgreSQL URL for the BibliZap OpenAlex database
+        #[arg(long)]
+        database_url: Option<String>,
+    },
+    /// Link the imported works to the Lens articles of the Lens cache with the same DOI,
+    /// PMID, PMCID, MAG or OpenAlex ID
+    Crosswalk {
+        /// Cache backend URL (sqlite: or postgres://) holding the Lens ID mappings
+        #[arg(long)]
+        cache_backend_url: Option<String>,
+
+        /// PostgreSQL URL for the BibliZap OpenAlex database
+        #[arg(long)]
+        database_url: Option<String>,
+    },
+    /// List the imported partitions and how far updates went
+    Status {
+        /// PostgreSQL URL for the BibliZap OpenAlex database
+        #[arg(long)]
+        database_url: Option<String>,
+    },
+    /// Check the import against the manifest of the snapshot and spot-check works,
+    /// exiting with an error if anything is off
+    Verify {
+        /// Path to the OpenAlex snapshot that was imported
         #[arg(long)]
         dump_path: Option<PathBuf>,
 
         /// PostgreSQL URL for the BibliZap OpenAlex database
         #[arg(long)]
         database_url: Option<String>,
+
+        /// Number of works read from the dump and compared with the database
+        #[arg(long, default_value_t = 100)]
+        sample: usize,
+    },
+    /// Count the works, references, identifiers and entities of the database, and the
+    /// sizes of reference lists
+    Stats {
+        /// PostgreSQL URL for the BibliZap OpenAlex database
+        #[arg(long)]
+        database_url: Option<String>,
     },
 }
 
@@ -54,7 +125,10 @@ async fn main() -> Result<(), Error> {
 
     let mut logger_builder = env_logger::Builder::from_env(env_logger::Env::default());
     if env::var_os("RUST_LOG").is_none() {
-        logger_builder.filter_level(args.log_level);
+        // `CREATE ... IF NOT EXISTS` notices would drown the reports
+        logger_builder
+            .filter_level(args.log_level)
+            .filter_module("sqlx::postgres::notice", log::LevelFilter::Warn);
     }
     logger_builder.init();
 
@@ -64,7 +138,37 @@ async fn main() -> Result<(), Error> {
         Command::Import {
             dump_path,
             database_url,
-        } => run_import(dump_path, database_url, file_cfg).await?,
+            workers,
+            batch_size,
+        } => {
+            let defaults = openalex::ImportOptions::default();
+            let options = openalex::ImportOptions {
+                workers: workers.unwrap_or(defaults.workers).max(1),
+                batch_size: batch_size.max(1),
+            };
+            run_import(dump_path, database_url, options, file_cfg).await?
+        }
+        Command::Update {
+            dump_path,
+            database_url,
+        } => run_update(dump_path, database_url, file_cfg).await?,
+        Command::Crosswalk {
+            cache_backend_url,
+            database_url,
+        } => run_crosswalk(cache_backend_url, database_url, file_cfg).await?,
+        Command::Status { database_url } => {
+            let pool = connect(database_url, 1).await?;
+            print!("{}", openalex::import_status(&pool).await?);
+        }
+        Command::Verify {
+            dump_path,
+            database_url,
+            sample,
+        } => run_verify(dump_path, database_url, sample, file_cfg).await?,
+        Command::Stats { database_url } => {
+            let pool = connect(database_url, 1).await?;
+            print!("{}", openalex::database_stats(&pool).await?);
+        }
     }
 
     Ok(())
@@ -73,21 +177,78 @@ async fn main() -> Result<(), Error> {
 async fn run_import(
     dump_path: Option<PathBuf>,
     database_url: Option<String>,
+    options: openalex::ImportOptions,
     file_cfg: FileConfig,
 ) -> Result<(), Error> {
     let dump_path = resolve_dump_path(dump_path, file_cfg.openalex_dump_path)?;
-    let database_url = resolve_database_url(database_url)?;
+    // One connection per worker, and one for the merge and bookkeeping
+    let pool = connect(database_url, options.workers as u32 + 1).await?;
 
-    let pool = sqlx::postgres::PgPoolOptions::new()
-        .max_connections(1)
-        .connect(&database_url)
-        .await?;
+    let stats = openalex::import_openalex_dump(&dump_path, &pool, &options).await?;
+    log::info!("Import finished: {stats}");
 
-    openalex::import_openalex_dump(&dump_path, &pool).await?;
+    Ok(())
+}
+
+async fn run_update(
+    dump_path: Option<PathBuf>,
+    database_url: Option<String>,
+    file_cfg: FileConfig,
+) -> Result<(), Error> {
+    let dump_path = resolve_dump_path(dump_path, file_cfg.openalex_dump_path)?;
+    let pool = connect(database_url, 1).await?;
+
+    let stats = openalex::update_openalex(&dump_path, &pool).await?;
+    log::info!("Update finished: {stats}");
+
+    Ok(())
+}
+
+async fn run_crosswalk(
+    cache_backend_url: Option<String>,
+    database_url: Option<String>,
+    file_cfg: FileConfig,
+) -> Result<(), Error> {
+    let cache_url = resolve_cache_url(cache_backend_url, file_cfg.cache_backend_url)?;
+    let cache = open_cache(&cache_url).await?;
+    let pool = connect(database_url, 1).await?;
+
+    let (read, linked) = openalex::link_lens_ids(cache.as_ref(), &pool).await?;
+    log::info!("Crosswalk finished: {read} Lens ID mappings read, {linked} new links");
 
     Ok(())
 }
 
+async fn run_verify(
+    dump_path: Option<PathBuf>,
+    database_url: Option<String>,
+    sample: usize,
+    file_cfg: FileConfig,
+) -> Result<(), Error> {
+    let dump_path = resolve_dump_path(dump_path, file_cfg.openalex_dump_path)?;
+    let pool = connect(database_url, 1).await?;
+
+    let report = openalex::verify_import(&dump_path, &pool, sample).await?;
+    print!("{report}");
+    if !report.problems.is_empty() {
+        return Err(Error::Verification(report.problems.len()));
+    }
+
+    Ok(())
+}
+
+async fn connect(
+    database_url: Option<String>,
+    max_connections: u32,
+) -> Result<sqlx::PgPool, Error> {
+    let database_url = resolve_database_url(database_url)?;
+
+    Ok(sqlx::postgres::PgPoolOptions::new()
+        .max_connections(max_connections)
+        .connect(&database_url)
+        .await?)
+}
+
 fn resolve_dump_path(
     cli_dump_path: Option<PathBuf>,
     config_dump_path: Option<PathBuf>,
@@ -102,6 +263,27 @@ fn resolve_dump_path(
         .ok_or(Error::MissingConfig("openalex_dump_path"))
 }
 
+fn resolve_cache_url(
+    cli_cache_url: Option<String>,
+    config_cache_url: Option<String>,
+) -> Result<String, Error> {
+    cli_cache_url
+        .or(config_cache_url)
+        .or_else(|| env::var("BIBLIZAP_CACHE_BACKEND_URL").ok())
+        .ok_or(Error::MissingConfig("cache_backend_url"))
+}
+
+/// Opens the cache backend matching the URL scheme.
+async fn open_cache(url: &str) -> Result<Box<dyn CacheBackend>, Error> {
+    if url.starts_with("sqlite:") {
+        Ok(Box::new(SqliteBackend::from_url(url).await?))
+    } else if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        Ok(Box::new(PostgresBackend::from_url(url).await?))
+    } else {
+        Err(Error::UnsupportedCacheUrl(url.to_string()))
+    }
+}
+
 fn resolve_database_url(cli_database_url: Option<String>) -> Result<String, Error> {
     cli_database_url
         .or_else(|| env::var("DATABASE_URL").ok())
@@ -2,7 +2,9 @@ use std::collections::BTreeSet;
 
 use actix_web::{HttpResponse, Responder, web};
 
-use crate::AppConfig;
+use biblizap_rs::Article;
+
+use crate::{AppConfig, Error};
 
 pub async fn upload_corpus(req_body: String, config: web::Data<AppConfig>) -> impl Responder {
     let corpus = Corpus::from_flat_string(&req_body);
@@ -17,13 +19,8 @@ pub async fn download_corpus(
     path: web::Path<String>,
     config: web::Data<AppConfig>,
 ) -> impl Responder {
-    let hash_bytes = match hex::decode(path.as_str()) {
-        Ok(bytes) if bytes.len() == 32 => {
-            let mut hash_array = [0u8; 32];
-            hash_array.copy_from_slice(&bytes);
-            hash_array
-        }
-        _ => return HttpResponse::BadRequest().body("Invalid hash format"),
+    let Some(hash_bytes) = parse_hash_hex(path.as_str()) else {
+        return HttpResponse::BadRequest().body("Invalid hash format");
     };
 
     match Corpus::load_from_database(&config.database_pool, &hash_bytes).await {
@@ -39,13 +36,8 @@ pub async fn enrich_corpus(
     path: web::Path<String>,
     config: web::Data<AppConfig>,
 ) -> impl Responder {
-    let hash_bytes = match hex::decode(path.as_str()) {
-        Ok(bytes) if bytes.len() == 32 => {
-            let mut hash_array = [0u8; 32];
-            hash_array.copy_from_slice(&bytes);
-            hash_array
-        }
-        _ => return HttpResponse::BadRequest().body("Invalid hash format"),
+    let Some(hash_bytes) = parse_hash_hex(path.as_str()) else {
+        return HttpResponse::BadRequest().body("Invalid hash format");
     };
 
     let corpus = match Corpus::load_from_database(&config.database_pool, &hash_bytes).await {
@@ -59,21 +51,53 @@ pub async fn enrich_corpus(
     };
 
     let id_strings: Vec<String> = corpus.into();
-    let doi_strs: Vec<&str> = id_strings.iter().map(|s| s.as_str()).collect();
 
-    match biblizap_rs::enrich_by_raw_ids(
-        &doi_strs,
-        &config.lens_api_key,
-        None,
-        Some(&config.cache_backend),
-    )
-    .await
-    {
+    match enrich_ids(&id_strings, &config).await {
         Ok(articles) => HttpResponse::Ok().json(articles),
+        Err(e @ Error::NoLensApiKey) => HttpResponse::ServiceUnavailable().body(format!("{e}")),
         Err(e) => HttpResponse::InternalServerError().body(format!("Failed to enrich corpus: {e}")),
     }
 }
 
+/// Fetches the Lens.org metadata of identifiers in any supported format, leaving out the
+/// ones that cannot be resolved.
+pub async fn enrich_ids(ids: &[String], config: &AppConfig) -> Result<Vec<Article>, Error> {
+    let lens_api_key = config.lens_api_key()?;
+    let raw_ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+
+    Ok(biblizap_rs::enrich_by_raw_ids(
+        &raw_ids,
+        lens_api_key,
+        Some(&config.lens_client),
+        Some(&config.cache_backend),
+    )
+    .await?)
+}
+
+/// Decodes a hex-encoded corpus hash, as returned by the upload endpoint.
+pub fn parse_hash_hex(hash_hex: &str) -> Option<[u8; 32]> {
+    hex::decode(hash_hex).ok()?.try_into().ok()
+}
+
+/// Loads the identifiers of a stored corpus.
+pub async fn load_corpus_ids(
+    pool: &sqlx::PgPool,
+    hash: &[u8; 32],
+) -> Result<Vec<String>, CorpusError> {
+    Ok(Corpus::load_from_database(pool, hash).await?.into())
+}
+
+/// Stores the identifiers of a corpus, skipping the invalid ones.
+/// Returns its hash and its normalised identifiers, deduplicated and sorted.
+pub async fn save_corpus_ids(
+    pool: &sqlx::PgPool,
+    ids: Vec<String>,
+) -> Result<([u8; 32], Vec<String>), CorpusError> {
+    let corpus = Corpus::from(ids);
+    let hash = corpus.save_to_database(pool).await?;
+    Ok((hash, corpus.into()))
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum CorpusError {
     #[error("Database error: {0}")]
@@ -87,8 +111,7 @@ struct Identifier(String);
 
 impl Identifier {
     fn new(s: &str) -> Option<Self> {
-        let normalized = s.trim().to_lowercase();
-        crate::common::is_valid_id(&normalized).then(|| Identifier(normalized))
+        crate::common::parse_seed_id(s).map(|identifier| Identifier(identifier.key()))
     }
 }
 
@@ -109,7 +132,7 @@ impl Corpus {
     pub fn from_flat_string(flat: &str) -> Self {
         let ids = flat
             .lines()
-            .filter_map(|line| Identifier::new(line))
+            .filter_map(Identifier::new)
             .collect::<BTreeSet<Identifier>>();
         Corpus { ids }
     }
@@ -253,6 +276,14 @@ mod tests {
         assert_eq!(clean.sha256(), padded.sha256());
     }
 
+    #[test]
+    fn hash_hex_round_trip() {
+        let hash = make(&["10.1234/abc"]).sha256();
+        assert_eq!(parse_hash_hex(&hex::encode(hash)), Some(hash));
+        assert_eq!(parse_hash_hex("abcd"), None);
+        assert_eq!(parse_hash_hex("not hex"), None);
+    }
+
     #[test]
     fn invalid_ids_dropped() {
         let with_invalid = make(&["10.1234/abc", "not-a-doi", "https://doi.org/10.1234/abc"]);
@@ -260,6 +291,17 @@ mod tests {
         assert_eq!(with_invalid.sha256(), clean.sha256());
     }
 
+    #[test]
+    fn normalization_prefixes_and_dashes() {
+        let clean = make(&["10.1234/abc-def", "PMC7000001"]);
+        let pasted = make(&[
+            "doi:10.1234/ABC\u{2010}def",
+            "https://doi.org/10.1234/abc-def.",
+            "pmc7000001",
+        ]);
+        assert_eq!(clean.sha256(), pasted.sha256());
+    }
+
     #[test]
     fn pmids_accepted() {
         let with_pmid = make(&["10.1234/abc", "29406940"]);
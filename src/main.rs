@@ -1,17 +1,27 @@
 use actix_web::{App, HttpServer, web};
 use actix_web_static_files::ResourceFiles;
 use biblizap_rs::lens::cache::postgres::PostgresBackend;
+use biblizap_rs::lens::keys::KeySelection;
+use biblizap_rs::lens::retry::RetryPolicy;
+use biblizap_rs::openalex::OpenAlexDatabase;
+use biblizap_rs::{LensClient, PubmedClient};
 use config as conf;
 use serde::Deserialize;
 use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
+mod admin;
 mod common;
 mod corpus;
+mod prefetch;
+mod pubmed;
+mod resolve;
 mod snowball;
 mod tracking;
 mod usage;
+mod v1;
 
 use snowball::*;
 
@@ -20,19 +30,45 @@ include!(concat!(env!("OUT_DIR"), "/generated.rs"));
 
 /// Application configuration holding necessary secrets/settings.
 struct AppConfig {
-    lens_api_key: String,
+    /// `None` on deployments without a Lens.org subscription, which snowball with PubMed
+    lens_api_key: Option<String>,
+    lens_client: LensClient,
+    pubmed_client: PubmedClient,
     cache_backend: PostgresBackend,
     database_pool: sqlx::PgPool,
+    /// `None` until `biblizap-openalex` has imported a snapshot into the database
+    openalex_database: Option<OpenAlexDatabase>,
+    /// Source of the snowball requests that do not pick one
+    default_source: Source,
+    admin_token: Option<String>,
+    prefetch_jobs: prefetch::PrefetchJobs,
+}
+
+impl AppConfig {
+    /// The Lens.org API key, for the endpoints that cannot work without one.
+    fn lens_api_key(&self) -> Result<&str, Error> {
+        self.lens_api_key.as_deref().ok_or(Error::NoLensApiKey)
+    }
 }
 
 /// Configuration that can be loaded from `biblizap.toml`.
 #[derive(Debug, Deserialize, Default)]
 struct FileConfig {
     lens_api_key: Option<String>,
+    lens_api_keys: Option<Vec<String>>,
+    lens_key_selection: Option<KeySelection>,
+    lens_api_url: Option<String>,
+    lens_request_timeout_secs: Option<u64>,
+    lens_max_retry_wait_secs: Option<u64>,
+    ncbi_api_key: Option<String>,
+    ncbi_email: Option<String>,
     cache_backend_url: Option<String>,
     openalex_dump_path: Option<PathBuf>,
+    snowball_source: Option<Source>,
     bind_address: Option<String>,
     port: Option<u16>,
+    admin_token: Option<String>,
+    cited_by_fallback: Option<bool>,
 }
 
 /// Custom error type for the server.
@@ -44,12 +80,24 @@ pub enum Error {
     JsonError(#[from] serde_json::Error),
     #[error(transparent)]
     CorpusError(#[from] corpus::CorpusError),
-    #[error("Invalid identifier format: '{0}' is neither a valid DOI nor PMID")]
+    #[error(
+        "Invalid identifier format: '{0}' is not a DOI, PMID, PMCID, arXiv, OpenAlex, MAG or CORE ID"
+    )]
     InvalidIdFormat(String),
+    #[error("Invalid depth: {0}, must be 1 or 2")]
+    InvalidDepth(u8),
     #[error("Too many identifiers: maximum {MAX_IDS} allowed, got {0}")]
     TooManyIds(usize),
     #[error("No valid identifiers provided")]
     NoValidIds,
+    #[error("Invalid corpus hash: '{0}'")]
+    InvalidCorpusHash(String),
+    #[error("PubMed snowballing only accepts PMIDs, got '{0}'")]
+    NotAPmid(String),
+    #[error("No Lens.org API key is configured on this server")]
+    NoLensApiKey,
+    #[error("No OpenAlex database has been imported on this server")]
+    NoOpenAlexDatabase,
 }
 
 /// Main function to start the Actix-web server.
@@ -82,7 +130,13 @@ async fn main() -> std::io::Result<()> {
                 .required(false),
         )
         .add_source(conf::File::with_name("biblizap.toml").required(false))
-        .add_source(conf::Environment::with_prefix("BIBLIZAP").separator("__"));
+        .add_source(
+            conf::Environment::with_prefix("BIBLIZAP")
+                .separator("__")
+                .list_separator(",")
+                .with_list_parse_key("lens_api_keys")
+                .try_parsing(true),
+        );
 
     let settings = builder.build().unwrap_or_else(|e| {
         log::warn!("failed to build config: {}", e);
@@ -104,18 +158,43 @@ async fn main() -> std::io::Result<()> {
 
     let port = args.port.or(file_cfg.port).unwrap_or(DEFAULT_PORT);
 
-    // lens api key: CLI -> config file -> env var -> error
+    // lens api key pool: CLI -> config file (or BIBLIZAP_LENS_API_KEYS env)
+    let lens_api_keys = if args.lens_api_keys.is_empty() {
+        file_cfg.lens_api_keys.unwrap_or_default()
+    } else {
+        args.lens_api_keys.clone()
+    };
+
+    // lens api key: CLI -> config file -> env var -> first key of the pool -> none
     let lens_api_key = args
         .lens_api_key
         .clone()
         .or(file_cfg.lens_api_key)
         .or_else(|| env::var("BIBLIZAP_LENS_API_KEY").ok())
-        .unwrap_or_else(|| {
-            log::error!(
-                "Lens API key is required via CLI, config file, or BIBLIZAP_LENS_API_KEY env"
-            );
-            std::process::exit(1);
-        });
+        .or_else(|| lens_api_keys.first().cloned())
+        .filter(|key| !key.is_empty());
+
+    if lens_api_key.is_none() {
+        log::warn!(
+            "No Lens API key configured (CLI, config file, or BIBLIZAP_LENS_API_KEY env): the Lens-only endpoints are disabled"
+        );
+    }
+
+    // ncbi api key: CLI -> config file -> env var -> none (lower rate limit)
+    let ncbi_api_key = args
+        .ncbi_api_key
+        .clone()
+        .or(file_cfg.ncbi_api_key)
+        .or_else(|| env::var("BIBLIZAP_NCBI_API_KEY").ok())
+        .filter(|key| !key.is_empty());
+
+    // ncbi email: CLI -> config file -> env var -> none
+    let ncbi_email = args
+        .ncbi_email
+        .clone()
+        .or(file_cfg.ncbi_email)
+        .or_else(|| env::var("BIBLIZAP_NCBI_EMAIL").ok())
+        .filter(|email| !email.is_empty());
 
     let cache_backend_url = args
         .cache_backend_url
@@ -143,6 +222,70 @@ async fn main() -> std::io::Result<()> {
         log::info!("Configured OpenAlex dump path: {}", path.display());
     }
 
+    let mut retry_policy = RetryPolicy::default();
+    if let Some(secs) = args
+        .lens_request_timeout_secs
+        .or(file_cfg.lens_request_timeout_secs)
+    {
+        retry_policy.request_timeout = Some(Duration::from_secs(secs));
+    }
+    if let Some(secs) = args
+        .lens_max_retry_wait_secs
+        .or(file_cfg.lens_max_retry_wait_secs)
+    {
+        retry_policy.max_total_wait = Duration::from_secs(secs);
+    }
+
+    let pubmed_client = PubmedClient::default().with_retry_policy(retry_policy.clone());
+    let pubmed_client = match ncbi_api_key {
+        Some(ncbi_api_key) => pubmed_client.with_api_key(ncbi_api_key),
+        None => pubmed_client,
+    };
+    let pubmed_client = match ncbi_email {
+        Some(ncbi_email) => pubmed_client.with_email(ncbi_email),
+        None => pubmed_client,
+    };
+
+    let lens_client = LensClient::default().with_retry_policy(retry_policy);
+    let lens_client = match args
+        .lens_api_url
+        .clone()
+        .or(file_cfg.lens_api_url)
+        .or_else(|| env::var("BIBLIZAP_LENS_API_URL").ok())
+    {
+        Some(lens_api_url) => {
+            log::info!("Sending Lens requests to {lens_api_url}");
+            lens_client.with_base_url(lens_api_url)
+        }
+        None => lens_client,
+    };
+
+    let lens_client = if lens_api_keys.is_empty() {
+        lens_client
+    } else {
+        let selection = args
+            .lens_key_selection
+            .map(KeySelection::from)
+            .or(file_cfg.lens_key_selection)
+            .unwrap_or_default();
+        log::info!(
+            "Using a pool of {} Lens API keys ({selection:?})",
+            lens_api_keys.len()
+        );
+        lens_client.with_api_keys(lens_api_keys, selection)
+    };
+
+    let admin_token = args
+        .admin_token
+        .clone()
+        .or(file_cfg.admin_token)
+        .or_else(|| env::var("BIBLIZAP_ADMIN_TOKEN").ok())
+        .filter(|token| !token.is_empty());
+
+    if admin_token.is_none() {
+        log::info!("No admin token configured, admin endpoints are disabled");
+    }
+
     // For heavy IO workload with 4 workers per CPU
     let worker_count = num_cpus::get() * 4 * 2;
 
@@ -170,6 +313,13 @@ async fn main() -> std::io::Result<()> {
             std::process::exit(1);
         });
 
+    // Answering citation misses from cached references trades completeness for Lens quota
+    let cited_by_fallback = args.cited_by_fallback || file_cfg.cited_by_fallback.unwrap_or(false);
+    if cited_by_fallback {
+        log::info!("Citation cache misses fall back to the cited-by index (incomplete results)");
+    }
+    let cache_backend = cache_backend.with_cited_by_fallback(cited_by_fallback);
+
     let tracking_database_url = env::var("DATABASE_URL").unwrap_or_else(|_| {
         log::error!("Tracking database URL is required via DATABASE_URL env");
         std::process::exit(1);
@@ -187,12 +337,60 @@ async fn main() -> std::io::Result<()> {
 
     log::info!("Connected to tracking database");
 
+    // The OpenAlex tables live in the app database once biblizap-openalex has run
+    let openalex_database = OpenAlexDatabase::new(database_pool.clone());
+    let openalex_database = match openalex_database.is_imported().await {
+        Ok(true) => {
+            log::info!("OpenAlex database found, snowballing from it is available");
+            Some(openalex_database)
+        }
+        Ok(false) => None,
+        Err(e) => {
+            log::warn!("Unable to look for an OpenAlex database: {e}");
+            None
+        }
+    };
+
+    // snowball source: CLI -> config file (or BIBLIZAP_SNOWBALL_SOURCE env) -> Lens if a
+    // key is configured -> OpenAlex if imported -> PubMed
+    let default_source = args.snowball_source.or(file_cfg.snowball_source).unwrap_or(
+        match (&lens_api_key, &openalex_database) {
+            (Some(_), _) => Source::Lens,
+            (None, Some(_)) => Source::OpenAlex,
+            (None, None) => Source::Pubmed,
+        },
+    );
+    match default_source {
+        Source::Lens if lens_api_key.is_none() => {
+            log::error!("The lens snowball source needs a Lens API key");
+            std::process::exit(1);
+        }
+        Source::OpenAlex if openalex_database.is_none() => {
+            log::error!(
+                "The openalex snowball source needs an OpenAlex database, import one with biblizap-openalex"
+            );
+            std::process::exit(1);
+        }
+        _ => log::info!("Snowballing from {default_source:?} unless a request asks otherwise"),
+    }
+
     let config = web::Data::new(AppConfig {
         lens_api_key,
+        lens_client,
+        pubmed_client,
         cache_backend,
         database_pool,
+        openalex_database,
+        default_source,
+        admin_token,
+        prefetch_jobs: prefetch::PrefetchJobs::default(),
     });
 
+    if let Some(Command::Prefetch(prefetch_args)) = args.command {
+        run_prefetch(prefetch_args, &config).await;
+        return Ok(());
+    }
+
     log::info!("Listening on http://{}:{}", bind_address, port);
     log::info!(
         "Running with {} workers (4x {} CPUs) for heavy IO workload",
@@ -205,7 +403,12 @@ async fn main() -> std::io::Result<()> {
 
         App::new()
             .app_data(config.clone())
+            .service(v1::scope())
             .service(web::resource("/api").route(web::post().to(snowball_request)))
+            .service(web::resource("/api/resolve").route(web::post().to(resolve::resolve_request)))
+            .service(
+                web::resource("/api/pubmed_search").route(web::post().to(pubmed::pubmed_search)),
+            )
             .service(
                 web::resource("/api/corpus/download/{hash_hex}")
                     .route(web::get().to(corpus::download_corpus)),
@@ -220,6 +423,18 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::resource("api/usage_info/").route(web::post().to(usage::usage_info_request)),
             )
+            .service(
+                web::resource("/api/admin/prefetch")
+                    .route(web::post().to(prefetch::start_prefetch))
+                    .route(web::get().to(prefetch::list_prefetch_jobs)),
+            )
+            .service(
+                web::resource("/api/admin/prefetch/{job_id}")
+                    .route(web::get().to(prefetch::prefetch_status)),
+            )
+            .service(
+                web::resource("/api/admin/lens").route(web::get().to(admin::lens_client_stats)),
+            )
             // Catch all route to serve frontend static files, with fallback to index.html for SPA routing
             .default_service(ResourceFiles::new("/", generated).resolve_not_found_to_root())
             .wrap(actix_web::middleware::Compress::default())
@@ -230,7 +445,53 @@ async fn main() -> std::io::Result<()> {
     .await
 }
 
-use clap::Parser;
+/// Runs the `prefetch` subcommand against the configured cache, then exits.
+async fn run_prefetch(prefetch_args: PrefetchArgs, config: &AppConfig) {
+    let lens_api_key = config.lens_api_key().unwrap_or_else(|e| {
+        log::error!("Prefetching needs Lens: {}", e);
+        std::process::exit(1);
+    });
+
+    let parameters = prefetch::PrefetchParameters {
+        input_id_list: prefetch_args.ids,
+        corpus_hash: prefetch_args.corpus,
+        depth: prefetch_args.depth,
+        search_for: prefetch_args.search_for.into(),
+        with_metadata: !prefetch_args.no_metadata,
+    };
+
+    let seeds = prefetch::resolve_seeds(&parameters, &config.database_pool)
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Unable to start prefetch: {}", e);
+            std::process::exit(1);
+        });
+
+    log::info!(
+        "Prefetching {} seeds at depth {}",
+        seeds.len(),
+        parameters.depth
+    );
+
+    let result = biblizap_rs::prefetch(
+        &seeds,
+        parameters.depth,
+        &parameters.search_for,
+        parameters.with_metadata,
+        lens_api_key,
+        Some(&config.lens_client),
+        &config.cache_backend,
+        |progress| log::info!("{}", prefetch::describe_progress(progress)),
+    )
+    .await;
+
+    if let Err(e) = result {
+        log::error!("Prefetch failed (re-run the same command to resume): {}", e);
+        std::process::exit(1);
+    }
+}
+
+use clap::{Parser, Subcommand, ValueEnum};
 
 /// Run an instance of BibliZap
 #[derive(Parser, Debug, Clone)]
@@ -251,18 +512,61 @@ Values available in the config:
     - bind_address
     - port
     - lens_api_key
+    - lens_api_keys
+    - lens_key_selection
+    - lens_api_url
+    - lens_request_timeout_secs
+    - lens_max_retry_wait_secs
+    - ncbi_api_key
+    - ncbi_email
     - cache_backend_url
     - openalex_dump_path
+    - snowball_source
+    - admin_token
+    - cited_by_fallback
+
+Without a Lens API key, snowballing uses the imported OpenAlex database if there is one, PubMed (PMID seeds only) otherwise, and the Lens-only endpoints are disabled.
 
-Secrets (Lens API key and Cache URL): prefer keeping `biblizap.toml` file mode 600, or set BIBLIZAP_LENS_API_KEY.
+Secrets (Lens and NCBI API keys, Cache URL and admin token): prefer keeping `biblizap.toml` file mode 600, or set BIBLIZAP_LENS_API_KEY.
 
 CLI flags override config and env."#),
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Your Lens.org API key (optional; can come from config or env)
     #[arg(short, long)]
     lens_api_key: Option<String>,
 
+    /// Several Lens.org API keys to spread the requests over, comma separated (overrides lens_api_key)
+    #[arg(long, value_delimiter = ',')]
+    lens_api_keys: Vec<String>,
+
+    /// How the key of each Lens request is picked when several keys are configured
+    #[arg(long, value_enum)]
+    lens_key_selection: Option<KeySelectionArg>,
+
+    /// Lens.org scholarly search endpoint (optional; defaults to the public API)
+    #[arg(long)]
+    lens_api_url: Option<String>,
+
+    /// Timeout of each Lens request attempt, in seconds (default 120)
+    #[arg(long)]
+    lens_request_timeout_secs: Option<u64>,
+
+    /// Longest time a Lens request may spend waiting between retries, in seconds (default 300)
+    #[arg(long)]
+    lens_max_retry_wait_secs: Option<u64>,
+
+    /// Your NCBI API key, raising the PubMed rate limit from 3 to 10 requests per second (optional)
+    #[arg(long)]
+    ncbi_api_key: Option<String>,
+
+    /// Contact email sent to NCBI with every PubMed request (optional)
+    #[arg(long)]
+    ncbi_email: Option<String>,
+
     /// An URL to a working postgresql cache database (optional; can come from config or env)
     #[arg(short, long)]
     cache_backend_url: Option<String>,
@@ -271,6 +575,11 @@ struct Args {
     #[arg(long)]
     openalex_dump_path: Option<PathBuf>,
 
+    /// Where snowball requests take the citation network from, unless they pick a source
+    /// (default: lens with a Lens API key, openalex once imported, pubmed otherwise)
+    #[arg(long, value_enum)]
+    snowball_source: Option<Source>,
+
     /// Address to bind the server (optional; overrides config)
     #[arg(short, long)]
     bind_address: Option<String>,
@@ -279,7 +588,77 @@ struct Args {
     #[arg(short, long)]
     port: Option<u16>,
 
+    /// Answer citation cache misses from cached references (incomplete, but saves Lens quota)
+    #[arg(long)]
+    cited_by_fallback: bool,
+
+    /// Token required by the /api/admin endpoints (optional; they are disabled without it)
+    #[arg(long)]
+    admin_token: Option<String>,
+
     /// Log level for the application
     #[arg(short = 'L', long, default_value_t = log::LevelFilter::Info)]
     log_level: log::LevelFilter,
 }
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Fill the cache for a set of seed articles without scoring, then exit.
+    /// Re-running an interrupted prefetch resumes where it stopped.
+    Prefetch(PrefetchArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct PrefetchArgs {
+    /// Seed identifiers (DOI, PMID or Lens ID); can be repeated
+    #[arg(long = "id", value_name = "ID")]
+    ids: Vec<String>,
+
+    /// Hash of an uploaded corpus whose identifiers are used as seeds
+    #[arg(long)]
+    corpus: Option<String>,
+
+    /// Number of levels to expand
+    #[arg(long, default_value_t = 2)]
+    depth: u8,
+
+    /// Which edges to fetch
+    #[arg(long, value_enum, default_value_t = SearchDirection::Both)]
+    search_for: SearchDirection,
+
+    /// Only fetch references/citations, not article metadata
+    #[arg(long)]
+    no_metadata: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum SearchDirection {
+    References,
+    Citations,
+    Both,
+}
+
+impl From<SearchDirection> for biblizap_rs::SearchFor {
+    fn from(direction: SearchDirection) -> Self {
+        match direction {
+            SearchDirection::References => Self::References,
+            SearchDirection::Citations => Self::Citations,
+            SearchDirection::Both => Self::Both,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum KeySelectionArg {
+    RoundRobin,
+    MostRemaining,
+}
+
+impl From<KeySelectionArg> for KeySelection {
+    fn from(selection: KeySelectionArg) -> Self {
+        match selection {
+            KeySelectionArg::RoundRobin => Self::RoundRobin,
+            KeySelectionArg::MostRemaining => Self::MostRemaining,
+        }
+    }
+}
@@ -0,0 +1,542 @@
+//! Parallel import of snapshot files with binary `COPY`.
+//!
+//! Each worker thread takes the next file from a shared queue, decompresses and parses
+//! it, and encodes its records as binary COPY rows. A writer task per worker copies them
+//! in one transaction per file, which also writes them where they belong (see
+//! [`CopiedRecords`]): a file is either fully copied or not at all, and copied files are
+//! skipped when an interrupted import is resumed.
+//!
+//! Works are copied into `openalex_staging_works`, each file being recorded in
+//! `openalex_staged_files`. Once every file is staged, one transaction merges the
+//! staging table into the works, identifiers and references tables, keeping one copy of
+//! each work: the one of the newest partition, then the first one read. Into an empty
+//! database the merge drops the primary keys and indexes first and builds them once at
+//! the end.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use biblizap_rs::openalex::schema::{create_import_indexes, drop_import_indexes};
+use sqlx::PgConnection;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use super::copy::{self, CopyRows};
+use super::snapshot::DumpFile;
+use super::work::WorkRow;
+use super::{
+    CHANNEL_CAPACITY, ImportOptions, ImportStats, OpenAlexImportError, PROGRESS_EVERY,
+    open_dump_file, parse_work,
+};
+
+const STAGING_COLUMNS: i16 = 27;
+
+/// Files left to copy, with their rank in the whole snapshot, newest partition first.
+type FileQueue = Mutex<VecDeque<(i32, DumpFile)>>;
+
+/// A kind of record read from snapshot files: how it is encoded, and what is done with
+/// the rows of a file once copied.
+pub(super) trait CopiedRecords: 'static {
+    /// Plural name used in logs, e.g. `works`
+    const NAME: &'static str;
+    /// Statements run in the transaction of each file before its rows are copied
+    const PREPARE: &'static [&'static str];
+    /// `COPY ... FROM STDIN (FORMAT binary)` statement receiving the rows
+    const COPY: &'static str;
+
+    /// Encodes the record of a non-empty line, returning `false` when it is not valid.
+    fn encode(
+        rows: &mut CopyRows,
+        priority: i32,
+        line_number: usize,
+        line: &str,
+        path: &Path,
+    ) -> Result<bool, OpenAlexImportError>;
+
+    /// Runs in the transaction of `file` once its rows are copied, and returns the
+    /// number of records written.
+    fn file_copied(
+        conn: &mut PgConnection,
+        file: &CopiedFile,
+    ) -> impl Future<Output = Result<u64, OpenAlexImportError>> + Send;
+}
+
+/// What a worker hands over to its writer.
+enum CopyMessage {
+    /// Encoded rows of `records` records of the current file
+    Rows { rows: Vec<u8>, records: u64 },
+    /// Every record of the current file has been sent
+    FileDone(CopiedFile),
+}
+
+pub(super) struct CopiedFile {
+    pub priority: i32,
+    pub file: DumpFile,
+    /// Non-empty lines
+    pub records: u64,
+    /// Valid records among them
+    pub records_read: u64,
+}
+
+/// What [`copy_files`] did.
+#[derive(Debug, Default)]
+pub(super) struct CopyTotals {
+    pub records_read: u64,
+    pub written: u64,
+    pub invalid_lines: u64,
+}
+
+/// Copies `files` with `options.workers` workers.
+pub(super) async fn copy_files<R: CopiedRecords>(
+    pool: &sqlx::PgPool,
+    files: Vec<(i32, DumpFile)>,
+    options: &ImportOptions,
+) -> Result<CopyTotals, OpenAlexImportError> {
+    let started = Instant::now();
+    let queue = Arc::new(FileQueue::new(files.into_iter().collect()));
+    let copied = Arc::new(AtomicU64::new(0));
+    let mut workers = Vec::new();
+    let mut writers = JoinSet::new();
+    for _ in 0..options.workers.max(1) {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let queue = queue.clone();
+        let batch_size = options.batch_size.max(1);
+        workers.push(tokio::task::spawn_blocking(move || {
+            read_files::<R>(&queue, batch_size, sender)
+        }));
+        writers.spawn(write_files::<R>(
+            pool.clone(),
+            receiver,
+            copied.clone(),
+            started,
+        ));
+    }
+
+    let mut totals = CopyTotals::default();
+    // Dropping the set on the first error aborts the other writers, and their workers
+    // stop as soon as they find the channel closed
+    while let Some(result) = writers.join_next().await {
+        totals.written += result??;
+    }
+    for worker in workers {
+        totals.invalid_lines += worker.await??;
+    }
+    totals.records_read = copied.load(Ordering::Relaxed);
+    log::info!(
+        "{} {} copied in {:.1?}",
+        totals.records_read,
+        R::NAME,
+        started.elapsed()
+    );
+
+    Ok(totals)
+}
+
+/// Stages the works of `files`, then merges everything staged.
+pub(super) async fn import_works(
+    pool: &sqlx::PgPool,
+    files: Vec<(i32, DumpFile)>,
+    options: &ImportOptions,
+    stats: &mut ImportStats,
+) -> Result<(), OpenAlexImportError> {
+    stats.files += files.len() as u64;
+
+    let totals = copy_files::<StagedWorks>(pool, files, options).await?;
+    stats.invalid_lines += totals.invalid_lines;
+
+    merge_staged(pool, stats).await
+}
+
+/// Reads the files of `queue` until it is empty, sending each file's rows in chunks of
+/// `batch_size` records followed by a [`CopyMessage::FileDone`]. Returns the number of
+/// invalid lines, and stops early if the writer is gone.
+fn read_files<R: CopiedRecords>(
+    queue: &FileQueue,
+    batch_size: usize,
+    sender: mpsc::Sender<CopyMessage>,
+) -> Result<u64, OpenAlexImportError> {
+    let mut invalid_lines = 0;
+    loop {
+        let Some((priority, file)) = queue.lock().expect("file queue poisoned").pop_front() else {
+            return Ok(invalid_lines);
+        };
+        let path = &file.path;
+        let io_error = |source| OpenAlexImportError::Io {
+            path: path.clone(),
+            source,
+        };
+        log::debug!("Copying {}", path.display());
+
+        let mut rows = CopyRows::default();
+        let mut in_chunk = 0;
+        let mut records = 0;
+        let mut records_read = 0;
+        let lines = match open_dump_file(path) {
+            Ok(reader) => reader.lines(),
+            Err(e) => return Err(stop_workers(queue, e)),
+        };
+        for (line_number, line) in lines.enumerate() {
+            let line = line.map_err(|e| stop_workers(queue, io_error(e)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records += 1;
+
+            if !R::encode(&mut rows, priority, line_number, &line, path)
+                .map_err(|e| stop_workers(queue, e))?
+            {
+                invalid_lines += 1;
+                continue;
+            }
+            in_chunk += 1;
+
+            if in_chunk >= batch_size as u64 {
+                records_read += in_chunk;
+                let full = std::mem::take(&mut rows).into_bytes();
+                let message = CopyMessage::Rows {
+                    rows: full,
+                    records: in_chunk,
+                };
+                if sender.blocking_send(message).is_err() {
+                    return Ok(invalid_lines);
+                }
+                in_chunk = 0;
+            }
+        }
+        records_read += in_chunk;
+        if in_chunk > 0 {
+            let message = CopyMessage::Rows {
+                rows: rows.into_bytes(),
+                records: in_chunk,
+            };
+            if sender.blocking_send(message).is_err() {
+                return Ok(invalid_lines);
+            }
+        }
+        let done = CopyMessage::FileDone(CopiedFile {
+            priority,
+            file,
+            records,
+            records_read,
+        });
+        if sender.blocking_send(done).is_err() {
+            return Ok(invalid_lines);
+        }
+    }
+}
+
+/// Empties the queue so that the other workers stop after their current file.
+fn stop_workers(queue: &FileQueue, error: OpenAlexImportError) -> OpenAlexImportError {
+    queue.lock().expect("file queue poisoned").clear();
+    error
+}
+
+/// Copies the rows of each file received, in one transaction per file ending with
+/// [`CopiedRecords::file_copied`]. Returns the number of records written once the worker
+/// is done, dropping the current file if the worker stopped in the middle of it.
+async fn write_files<R: CopiedRecords>(
+    pool: sqlx::PgPool,
+    mut receiver: mpsc::Receiver<CopyMessage>,
+    copied: Arc<AtomicU64>,
+    started: Instant,
+) -> Result<u64, OpenAlexImportError> {
+    let mut written = 0;
+    while let Some(mut message) = receiver.recv().await {
+        let mut tx = pool.begin().await?;
+        for statement in R::PREPARE {
+            sqlx::query(*statement).execute(&mut *tx).await?;
+        }
+        let mut copy = tx.copy_in_raw(R::COPY).await?;
+        copy.send(copy::HEADER).await?;
+
+        let done = loop {
+            match message {
+                CopyMessage::Rows { rows, records } => {
+                    copy.send(rows).await?;
+                    let before = copied.fetch_add(records, Ordering::Relaxed);
+                    let after = before + records;
+                    if before / PROGRESS_EVERY != after / PROGRESS_EVERY {
+                        log::info!(
+                            "{after} {} copied ({:.0}/s)",
+                            R::NAME,
+                            after as f64 / started.elapsed().as_secs_f64().max(0.001)
+                        );
+                    }
+                }
+                CopyMessage::FileDone(done) => break done,
+            }
+            message = match receiver.recv().await {
+                Some(message) => message,
+                None => {
+                    copy.abort("dump reader stopped").await?;
+                    return Ok(written);
+                }
+            };
+        };
+        copy.send(copy::TRAILER).await?;
+        copy.finish().await?;
+
+        let file = &done.file;
+        if file
+            .record_count
+            .is_some_and(|expected| expected != done.records)
+        {
+            log::warn!(
+                "{} has {} records, the manifest announced {}",
+                file.path.display(),
+                done.records,
+                file.record_count.unwrap_or_default()
+            );
+        }
+        written += R::file_copied(&mut tx, &done).await?;
+        tx.commit().await?;
+    }
+
+    Ok(written)
+}
+
+/// Works, copied into the staging table.
+struct StagedWorks;
+
+impl CopiedRecords for StagedWorks {
+    const NAME: &'static str = "works";
+    const PREPARE: &'static [&'static str] = &[];
+    const COPY: &'static str = r#"
+        COPY openalex_staging_works (
+            priority, line, id, doi, title, abstract_text, publication_year,
+            publication_date, work_type, language, source_id, journal, volume, issue,
+            first_page, last_page, authorships, is_retracted, is_oa, oa_url, license,
+            cited_by_count, updated_date, id_types, id_values, referenced_works, topic_ids
+        ) FROM STDIN (FORMAT binary)
+    "#;
+
+    fn encode(
+        rows: &mut CopyRows,
+        priority: i32,
+        line_number: usize,
+        line: &str,
+        path: &Path,
+    ) -> Result<bool, OpenAlexImportError> {
+        let Some(row) = parse_work(line, line_number, path) else {
+            return Ok(false);
+        };
+        encode_row(rows, priority, line_number as i32, &row)?;
+        Ok(true)
+    }
+
+    async fn file_copied(
+        conn: &mut PgConnection,
+        copied: &CopiedFile,
+    ) -> Result<u64, OpenAlexImportError> {
+        let file = &copied.file;
+        sqlx::query(
+            r#"
+            INSERT INTO openalex_staged_files (priority, file, partition, record_count, works_read)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(copied.priority)
+        .bind(&file.key)
+        .bind(&file.partition)
+        .bind(file.record_count.map(|count| count as i64))
+        .bind(copied.records_read as i64)
+        .execute(conn)
+        .await?;
+
+        Ok(copied.records_read)
+    }
+}
+
+fn encode_row(
+    rows: &mut CopyRows,
+    priority: i32,
+    line: i32,
+    row: &WorkRow,
+) -> Result<(), OpenAlexImportError> {
+    let authorships = serde_json::to_string(&row.authors)?;
+    rows.row(STAGING_COLUMNS)
+        .int4(Some(priority))
+        .int4(Some(line))
+        .int8(Some(row.id))
+        .text(row.doi.as_deref())
+        .text(row.title.as_deref())
+        .text(row.abstract_text.as_deref())
+        .int4(row.publication_year)
+        .text(row.publication_date.as_deref())
+        .text(row.work_type.as_deref())
+        .text(row.language.as_deref())
+        .int8(row.source_id)
+        .text(row.journal.as_deref())
+        .text(row.volume.as_deref())
+        .text(row.issue.as_deref())
+        .text(row.first_page.as_deref())
+        .text(row.last_page.as_deref())
+        .text(Some(&authorships))
+        .bool(row.is_retracted)
+        .bool(row.is_oa)
+        .text(row.oa_url.as_deref())
+        .text(row.license.as_deref())
+        .int4(Some(row.cited_by_count))
+        .text(row.updated_date.as_deref())
+        .text_array(row.ids.iter().map(|(id_type, _)| id_type.lens_field()))
+        .text_array(row.ids.iter().map(|(_, value)| value.as_str()))
+        .int8_array(&row.references)
+        .int8_array(&row.topic_ids);
+
+    Ok(())
+}
+
+/// Moves the staged works that are not in the database yet into the works, identifiers
+/// and references tables, records their files as imported and empties the staging
+/// tables, all in one transaction. Does nothing when no file is staged.
+async fn merge_staged(
+    pool: &sqlx::PgPool,
+    stats: &mut ImportStats,
+) -> Result<(), OpenAlexImportError> {
+    let started = Instant::now();
+    let mut tx = pool.begin().await?;
+
+    let (staged_read,): (Option<i64>,) =
+        sqlx::query_as("SELECT SUM(works_read)::BIGINT FROM openalex_staged_files")
+            .fetch_one(&mut *tx)
+            .await?;
+    let Some(staged_read) = staged_read else {
+        return Ok(());
+    };
+    let (first_import, merged_works): (bool, bool) = sqlx::query_as(
+        r#"
+        SELECT NOT EXISTS (SELECT 1 FROM openalex_works),
+            EXISTS (SELECT 1 FROM openalex_merged_works)
+        "#,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    log::info!(
+        "Merging {staged_read} staged works{}",
+        if first_import {
+            " into an empty database, indexes are built afterwards"
+        } else {
+            ""
+        }
+    );
+
+    // The copy kept of each new work: newest partition first, then first read
+    sqlx::query(
+        r#"
+        CREATE TEMPORARY TABLE openalex_import_winners ON COMMIT DROP AS
+        SELECT DISTINCT ON (id) id, priority, line
+        FROM openalex_staging_works staged
+        WHERE NOT EXISTS (SELECT 1 FROM openalex_works work WHERE work.id = staged.id)
+        ORDER BY id, priority, line
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if first_import {
+        drop_import_indexes(&mut tx).await?;
+    }
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO openalex_works (
+            id, doi, title, publication_year, publication_date, work_type, language,
+            source_id, journal, volume, issue, first_page, last_page, authorships,
+            is_retracted, is_oa, oa_url, license, cited_by_count, updated_date,
+            abstract_text, topic_ids
+        )
+        SELECT
+            id, doi, title, publication_year, publication_date, work_type, language,
+            source_id, journal, volume, issue, first_page, last_page, authorships::JSONB,
+            is_retracted, is_oa, oa_url, license, cited_by_count, updated_date,
+            abstract_text, topic_ids
+        FROM openalex_staging_works
+        JOIN openalex_import_winners USING (id, priority, line)
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let identifiers = sqlx::query(
+        r#"
+        INSERT INTO openalex_work_ids (id_type, value, work_id)
+        SELECT id_type, value, staged.id
+        FROM openalex_staging_works staged
+        JOIN openalex_import_winners USING (id, priority, line)
+        CROSS JOIN UNNEST(staged.id_types, staged.id_values) AS ids (id_type, value)
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    // Redirecting references to merged works can make two of them the same edge
+    let references = if merged_works {
+        r#"
+        INSERT INTO openalex_references (citing_work_id, cited_work_id)
+        SELECT DISTINCT staged.id, COALESCE(merged.merged_into_id, edge.cited)
+        FROM openalex_staging_works staged
+        JOIN openalex_import_winners USING (id, priority, line)
+        CROSS JOIN UNNEST(staged.referenced_works) AS edge (cited)
+        LEFT JOIN openalex_merged_works merged ON merged.id = edge.cited
+        WHERE merged.id IS NULL
+            OR (merged.merged_into_id IS NOT NULL AND merged.merged_into_id <> staged.id)
+        "#
+    } else {
+        r#"
+        INSERT INTO openalex_references (citing_work_id, cited_work_id)
+        SELECT staged.id, edge.cited
+        FROM openalex_staging_works staged
+        JOIN openalex_import_winners USING (id, priority, line)
+        CROSS JOIN UNNEST(staged.referenced_works) AS edge (cited)
+        "#
+    };
+    let references = sqlx::query(references)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    if first_import {
+        log::info!("Building the indexes of the OpenAlex tables");
+        create_import_indexes(&mut tx).await?;
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_imported_files (file, partition, record_count, works_read, works_inserted)
+        SELECT file, partition, record_count, works_read, COALESCE(winners.works, 0)
+        FROM openalex_staged_files
+        LEFT JOIN (
+            SELECT priority, COUNT(*) AS works FROM openalex_import_winners GROUP BY priority
+        ) winners USING (priority)
+        ON CONFLICT (file) DO NOTHING
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("TRUNCATE openalex_staging_works, openalex_staged_files")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    sqlx::query("ANALYZE openalex_works, openalex_work_ids, openalex_references")
+        .execute(pool)
+        .await?;
+
+    let staged_read = staged_read as u64;
+    stats.works_read += staged_read;
+    stats.works_inserted += inserted;
+    stats.works_existing += staged_read.saturating_sub(inserted);
+    stats.identifiers += identifiers;
+    stats.references += references;
+    log::info!("Merged in {:.1?}", started.elapsed());
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+//! Rows in the binary format of `COPY ... FROM STDIN (FORMAT binary)`.
+//!
+//! The binary format spares the server parsing every value from text, which matters when
+//! staging hundreds of millions of rows. A stream is [`HEADER`], then rows, then
+//! [`TRAILER`]; each row is its number of fields and each field its length (`-1` for
+//! NULL) and value, in network byte order.
+
+/// Signature, flags and header extension length of a binary COPY stream.
+pub const HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+/// Field count marking the end of a binary COPY stream.
+pub const TRAILER: &[u8] = &(-1i16).to_be_bytes();
+
+const INT8_OID: i32 = 20;
+const TEXT_OID: i32 = 25;
+
+/// A buffer of encoded rows, sent to the server in one piece.
+#[derive(Debug, Default)]
+pub struct CopyRows {
+    buffer: Vec<u8>,
+}
+
+impl CopyRows {
+    /// Starts a row of `fields` fields, which must then be written in column order.
+    pub fn row(&mut self, fields: i16) -> &mut Self {
+        self.buffer.extend_from_slice(&fields.to_be_bytes());
+        self
+    }
+
+    pub fn int4(&mut self, value: Option<i32>) -> &mut Self {
+        match value {
+            Some(value) => self.field(&value.to_be_bytes()),
+            None => self.null(),
+        }
+    }
+
+    pub fn int8(&mut self, value: Option<i64>) -> &mut Self {
+        match value {
+            Some(value) => self.field(&value.to_be_bytes()),
+            None => self.null(),
+        }
+    }
+
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        self.field(&[value as u8])
+    }
+
+    pub fn text(&mut self, value: Option<&str>) -> &mut Self {
+        match value {
+            Some(value) => self.field(value.as_bytes()),
+            None => self.null(),
+        }
+    }
+
+    /// A one-dimensional `BIGINT[]` without NULLs.
+    pub fn int8_array(&mut self, values: &[i64]) -> &mut Self {
+        let start = self.start_array(INT8_OID, values.len());
+        for value in values {
+            self.field(&value.to_be_bytes());
+        }
+        self.end_field(start)
+    }
+
+    /// A one-dimensional `TEXT[]` without NULLs.
+    pub fn text_array<'a>(&mut self, values: impl ExactSizeIterator<Item = &'a str>) -> &mut Self {
+        let start = self.start_array(TEXT_OID, values.len());
+        for value in values {
+            self.field(value.as_bytes());
+        }
+        self.end_field(start)
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    fn null(&mut self) -> &mut Self {
+        self.buffer.extend_from_slice(&(-1i32).to_be_bytes());
+        self
+    }
+
+    fn field(&mut self, value: &[u8]) -> &mut Self {
+        self.buffer
+            .extend_from_slice(&(value.len() as i32).to_be_bytes());
+        self.buffer.extend_from_slice(value);
+        self
+    }
+
+    /// Writes the length placeholder and array header, returning where the field starts.
+    fn start_array(&mut self, element_oid: i32, len: usize) -> usize {
+        let start = self.buffer.len();
+        self.buffer.extend_from_slice(&0i32.to_be_bytes());
+        // Dimensions (none for an empty array), no NULL, element type
+        let dimensions = if len == 0 { 0i32 } else { 1 };
+        self.buffer.extend_from_slice(&dimensions.to_be_bytes());
+        self.buffer.extend_from_slice(&0i32.to_be_bytes());
+        self.buffer.extend_from_slice(&element_oid.to_be_bytes());
+        if len > 0 {
+            // Length and lower bound of the dimension
+            self.buffer.extend_from_slice(&(len as i32).to_be_bytes());
+            self.buffer.extend_from_slice(&1i32.to_be_bytes());
+        }
+        start
+    }
+
+    /// Fills in the length of the field started at `start`.
+    fn end_field(&mut self, start: usize) -> &mut Self {
+        let len = (self.buffer.len() - start - 4) as i32;
+        self.buffer[start..start + 4].copy_from_slice(&len.to_be_bytes());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn copy_binary_rows(pool: sqlx::PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE copied (n INTEGER, id BIGINT, flag BOOLEAN, name TEXT, ids BIGINT[], names TEXT[])",
+        )
+        .execute(&pool)
+        .await?;
+
+        let mut rows = CopyRows::default();
+        rows.row(6)
+            .int4(Some(-3))
+            .int8(Some(3000000001))
+            .bool(true)
+            .text(Some("Hôpital"))
+            .int8_array(&[1, 2])
+            .text_array(["doi", "pmid"].into_iter());
+        rows.row(6)
+            .int4(None)
+            .int8(None)
+            .bool(false)
+            .text(None)
+            .int8_array(&[])
+            .text_array(std::iter::empty());
+
+        let mut connection = pool.acquire().await?;
+        let mut copy = connection
+            .copy_in_raw("COPY copied FROM STDIN (FORMAT binary)")
+            .await?;
+        copy.send(HEADER).await?;
+        copy.send(rows.into_bytes()).await?;
+        copy.send(TRAILER).await?;
+        assert_eq!(copy.finish().await?, 2);
+
+        type Copied = (
+            Option<i32>,
+            Option<i64>,
+            bool,
+            Option<String>,
+            Vec<i64>,
+            Vec<String>,
+        );
+        let copied: Vec<Copied> = sqlx::query_as("SELECT * FROM copied ORDER BY n NULLS LAST")
+            .fetch_all(&mut *connection)
+            .await?;
+        assert_eq!(
+            copied,
+            [
+                (
+                    Some(-3),
+                    Some(3000000001),
+                    true,
+                    Some("Hôpital".to_string()),
+                    vec![1, 2],
+                    vec!["doi".to_string(), "pmid".to_string()]
+                ),
+                (None, None, false, None, Vec::new(), Vec::new()),
+            ]
+        );
+        Ok(())
+    }
+}
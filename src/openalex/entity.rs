@@ -0,0 +1,509 @@
+//! Import of the sources, authors and topics that works refer to by ID.
+//!
+//! Works only name their journal, authors and topics as they were when the work was last
+//! updated, and some not at all. The entity dumps of a snapshot (`data/sources/`,
+//! `data/authors/` and `data/topics/`) hold the current names, with the ISSNs and
+//! publisher of each source, the ORCID of each author and the subfield, field and domain
+//! of each topic, which article metadata joins in.
+//!
+//! Entity files are copied with the parallel workers of [`bulk`](super::bulk), each file
+//! in one transaction that upserts its records and records the file as imported. Entities
+//! are few next to works, so they skip the staging and merge of works: a record replaces
+//! the one in the database unless it is older, whatever the order the files are read in.
+
+use std::path::Path;
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use sqlx::PgConnection;
+
+use super::bulk::{CopiedFile, CopiedRecords, copy_files};
+use super::copy::CopyRows;
+use super::finished_files;
+use super::snapshot::dump_files;
+use super::work::parse_entity_id;
+use super::{ImportOptions, ImportStats, OpenAlexImportError};
+
+/// Imports the entity dumps found in the snapshot at `snapshot_root`, skipping the files
+/// already imported. Snapshots without an entity directory are left as they are.
+pub(super) async fn import_entities(
+    snapshot_root: &Path,
+    pool: &sqlx::PgPool,
+    options: &ImportOptions,
+    stats: &mut ImportStats,
+) -> Result<(), OpenAlexImportError> {
+    let sources = import_entity::<Sources>(snapshot_root, pool, options, stats).await?;
+    let authors = import_entity::<Authors>(snapshot_root, pool, options, stats).await?;
+    let topics = import_entity::<Topics>(snapshot_root, pool, options, stats).await?;
+    stats.sources += sources;
+    stats.authors += authors;
+    stats.topics += topics;
+
+    Ok(())
+}
+
+/// Imports the files of `data/<R::NAME>/`, returning the number of records written.
+async fn import_entity<R: CopiedRecords>(
+    snapshot_root: &Path,
+    pool: &sqlx::PgPool,
+    options: &ImportOptions,
+    stats: &mut ImportStats,
+) -> Result<u64, OpenAlexImportError> {
+    if !snapshot_root.join("data").join(R::NAME).is_dir() {
+        return Ok(0);
+    }
+
+    let finished = finished_files(pool).await?;
+    let (done, files): (Vec<_>, Vec<_>) = dump_files(snapshot_root, R::NAME)?
+        .into_iter()
+        .map(|mut file| {
+            // Partition keys are the same in every entity directory
+            if file.partition.is_some() {
+                file.key = format!("{}/{}", R::NAME, file.key);
+            }
+            file
+        })
+        .enumerate()
+        .map(|(rank, file)| (rank as i32, file))
+        .partition(|(_, file)| finished.contains(&file.key));
+    log::info!(
+        "Importing {} OpenAlex {} files ({} already imported)",
+        files.len(),
+        R::NAME,
+        done.len()
+    );
+    stats.files += files.len() as u64;
+    stats.files_skipped += done.len() as u64;
+
+    let totals = copy_files::<R>(pool, files, options).await?;
+    stats.invalid_lines += totals.invalid_lines;
+
+    Ok(totals.written)
+}
+
+/// A source as found in the sources dump.
+#[derive(Debug, Deserialize)]
+struct Source {
+    id: String,
+    display_name: Option<String>,
+    issn_l: Option<String>,
+    issn: Option<Vec<String>>,
+    host_organization_name: Option<String>,
+    #[serde(rename = "type")]
+    source_type: Option<String>,
+    works_count: Option<i32>,
+    updated_date: Option<String>,
+}
+
+/// An author as found in the authors dump.
+#[derive(Debug, Deserialize)]
+struct Author {
+    id: String,
+    display_name: Option<String>,
+    orcid: Option<String>,
+    #[serde(default)]
+    last_known_institutions: Vec<Named>,
+    /// Replaced by `last_known_institutions` in 2023 snapshots
+    last_known_institution: Option<Named>,
+    works_count: Option<i32>,
+    updated_date: Option<String>,
+}
+
+/// A topic as found in the topics dump.
+#[derive(Debug, Deserialize)]
+struct Topic {
+    id: String,
+    display_name: Option<String>,
+    subfield: Option<Named>,
+    field: Option<Named>,
+    domain: Option<Named>,
+    works_count: Option<i32>,
+    updated_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    display_name: Option<String>,
+}
+
+fn name(named: Option<&Named>) -> Option<&str> {
+    named.and_then(|named| named.display_name.as_deref())
+}
+
+/// Parses a non-empty line of an entity file, logging it if it is not a valid record
+/// with an ID of `kind`.
+fn parse_entity<T: DeserializeOwned>(
+    line: &str,
+    line_number: usize,
+    path: &Path,
+    kind: char,
+    id: impl Fn(&T) -> &str,
+) -> Option<(i64, T)> {
+    let record: T = match serde_json::from_str(line) {
+        Ok(record) => record,
+        Err(e) => {
+            log::warn!(
+                "Invalid record on line {} of {}: {e}",
+                line_number + 1,
+                path.display()
+            );
+            return None;
+        }
+    };
+    match parse_entity_id(id(&record), kind) {
+        Some(number) => Some((number, record)),
+        None => {
+            log::warn!(
+                "No {kind} ID on line {} of {}",
+                line_number + 1,
+                path.display()
+            );
+            None
+        }
+    }
+}
+
+/// Runs the `upsert` of the records copied from `file`, which keeps the newest copy of
+/// each and leaves newer ones in the database, then records the file as imported.
+/// Returns the number of records written.
+async fn upsert_copied(
+    conn: &mut PgConnection,
+    file: &CopiedFile,
+    entity: &str,
+    upsert: &'static str,
+) -> Result<u64, OpenAlexImportError> {
+    let written = sqlx::query(upsert)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_imported_files (
+            file, partition, record_count, works_read, works_inserted, entity
+        )
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (file) DO NOTHING
+        "#,
+    )
+    .bind(&file.file.key)
+    .bind(&file.file.partition)
+    .bind(file.file.record_count.map(|count| count as i64))
+    .bind(file.records_read as i64)
+    .bind(written as i64)
+    .bind(entity)
+    .execute(conn)
+    .await?;
+
+    Ok(written)
+}
+
+struct Sources;
+
+impl CopiedRecords for Sources {
+    const NAME: &'static str = "sources";
+    const PREPARE: &'static [&'static str] =
+        &["CREATE TEMPORARY TABLE openalex_copied_sources (LIKE openalex_sources) ON COMMIT DROP"];
+    const COPY: &'static str = r#"
+        COPY openalex_copied_sources (
+            id, display_name, issn_l, issns, publisher, source_type, works_count,
+            updated_date
+        ) FROM STDIN (FORMAT binary)
+    "#;
+
+    fn encode(
+        rows: &mut CopyRows,
+        _priority: i32,
+        line_number: usize,
+        line: &str,
+        path: &Path,
+    ) -> Result<bool, OpenAlexImportError> {
+        let Some((id, source)) =
+            parse_entity(line, line_number, path, 'S', |source: &Source| &source.id)
+        else {
+            return Ok(false);
+        };
+        let issns = source.issn.unwrap_or_default();
+        rows.row(8)
+            .int8(Some(id))
+            .text(source.display_name.as_deref())
+            .text(source.issn_l.as_deref())
+            .text_array(issns.iter().map(String::as_str))
+            .text(source.host_organization_name.as_deref())
+            .text(source.source_type.as_deref())
+            .int4(Some(source.works_count.unwrap_or_default()))
+            .text(source.updated_date.as_deref());
+        Ok(true)
+    }
+
+    async fn file_copied(
+        conn: &mut PgConnection,
+        file: &CopiedFile,
+    ) -> Result<u64, OpenAlexImportError> {
+        upsert_copied(
+            conn,
+            file,
+            Self::NAME,
+            r#"
+            INSERT INTO openalex_sources
+            SELECT DISTINCT ON (id) * FROM openalex_copied_sources
+            ORDER BY id, updated_date DESC NULLS LAST
+            ON CONFLICT (id) DO UPDATE SET
+                display_name = EXCLUDED.display_name,
+                issn_l = EXCLUDED.issn_l,
+                issns = EXCLUDED.issns,
+                publisher = EXCLUDED.publisher,
+                source_type = EXCLUDED.source_type,
+                works_count = EXCLUDED.works_count,
+                updated_date = EXCLUDED.updated_date
+            WHERE openalex_sources.updated_date IS NULL
+                OR EXCLUDED.updated_date >= openalex_sources.updated_date
+            "#,
+        )
+        .await
+    }
+}
+
+struct Authors;
+
+impl CopiedRecords for Authors {
+    const NAME: &'static str = "authors";
+    const PREPARE: &'static [&'static str] =
+        &["CREATE TEMPORARY TABLE openalex_copied_authors (LIKE openalex_authors) ON COMMIT DROP"];
+    const COPY: &'static str = r#"
+        COPY openalex_copied_authors (
+            id, display_name, orcid, institution, works_count, updated_date
+        ) FROM STDIN (FORMAT binary)
+    "#;
+
+    fn encode(
+        rows: &mut CopyRows,
+        _priority: i32,
+        line_number: usize,
+        line: &str,
+        path: &Path,
+    ) -> Result<bool, OpenAlexImportError> {
+        let Some((id, author)) =
+            parse_entity(line, line_number, path, 'A', |author: &Author| &author.id)
+        else {
+            return Ok(false);
+        };
+        let institution = name(author.last_known_institutions.first())
+            .or_else(|| name(author.last_known_institution.as_ref()));
+        rows.row(6)
+            .int8(Some(id))
+            .text(author.display_name.as_deref())
+            .text(author.orcid.as_deref())
+            .text(institution)
+            .int4(Some(author.works_count.unwrap_or_default()))
+            .text(author.updated_date.as_deref());
+        Ok(true)
+    }
+
+    async fn file_copied(
+        conn: &mut PgConnection,
+        file: &CopiedFile,
+    ) -> Result<u64, OpenAlexImportError> {
+        upsert_copied(
+            conn,
+            file,
+            Self::NAME,
+            r#"
+            INSERT INTO openalex_authors
+            SELECT DISTINCT ON (id) * FROM openalex_copied_authors
+            ORDER BY id, updated_date DESC NULLS LAST
+            ON CONFLICT (id) DO UPDATE SET
+                display_name = EXCLUDED.display_name,
+                orcid = EXCLUDED.orcid,
+                institution = EXCLUDED.institution,
+                works_count = EXCLUDED.works_count,
+                updated_date = EXCLUDED.updated_date
+            WHERE openalex_authors.updated_date IS NULL
+                OR EXCLUDED.updated_date >= openalex_authors.updated_date
+            "#,
+        )
+        .await
+    }
+}
+
+struct Topics;
+
+impl CopiedRecords for Topics {
+    const NAME: &'static str = "topics";
+    const PREPARE: &'static [&'static str] =
+        &["CREATE TEMPORARY TABLE openalex_copied_topics (LIKE openalex_topics) ON COMMIT DROP"];
+    const COPY: &'static str = r#"
+        COPY openalex_copied_topics (
+            id, display_name, subfield, field, domain, works_count, updated_date
+        ) FROM STDIN (FORMAT binary)
+    "#;
+
+    fn encode(
+        rows: &mut CopyRows,
+        _priority: i32,
+        line_number: usize,
+        line: &str,
+        path: &Path,
+    ) -> Result<bool, OpenAlexImportError> {
+        let Some((id, topic)) =
+            parse_entity(line, line_number, path, 'T', |topic: &Topic| &topic.id)
+        else {
+            return Ok(false);
+        };
+        rows.row(7)
+            .int8(Some(id))
+            .text(topic.display_name.as_deref())
+            .text(name(topic.subfield.as_ref()))
+            .text(name(topic.field.as_ref()))
+            .text(name(topic.domain.as_ref()))
+            .int4(Some(topic.works_count.unwrap_or_default()))
+            .text(topic.updated_date.as_deref());
+        Ok(true)
+    }
+
+    async fn file_copied(
+        conn: &mut PgConnection,
+        file: &CopiedFile,
+    ) -> Result<u64, OpenAlexImportError> {
+        upsert_copied(
+            conn,
+            file,
+            Self::NAME,
+            r#"
+            INSERT INTO openalex_topics
+            SELECT DISTINCT ON (id) * FROM openalex_copied_topics
+            ORDER BY id, updated_date DESC NULLS LAST
+            ON CONFLICT (id) DO UPDATE SET
+                display_name = EXCLUDED.display_name,
+                subfield = EXCLUDED.subfield,
+                field = EXCLUDED.field,
+                domain = EXCLUDED.domain,
+                works_count = EXCLUDED.works_count,
+                updated_date = EXCLUDED.updated_date
+            WHERE openalex_topics.updated_date IS NULL
+                OR EXCLUDED.updated_date >= openalex_topics.updated_date
+            "#,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{FIXTURE_WORKS, temp_directory, write_gzip};
+    use super::super::{import_openalex_dump, import_status};
+    use super::*;
+
+    const FIXTURE_SOURCES: &str = include_str!("fixtures/sources.jsonl");
+    const FIXTURE_AUTHORS: &str = include_str!("fixtures/authors.jsonl");
+    const FIXTURE_TOPICS: &str = include_str!("fixtures/topics.jsonl");
+
+    #[sqlx::test]
+    async fn import_entity_dumps(pool: sqlx::PgPool) -> Result<(), OpenAlexImportError> {
+        let root = temp_directory();
+        let data = root.join("data");
+        write_gzip(
+            &data.join("works/updated_date=2026-06-01/part_000.gz"),
+            FIXTURE_WORKS,
+        );
+        write_gzip(
+            &data.join("sources/updated_date=2026-06-01/part_000.gz"),
+            FIXTURE_SOURCES,
+        );
+        // An older copy of PLOS ONE, read after the newer one
+        write_gzip(
+            &data.join("sources/updated_date=2026-05-01/part_000.gz"),
+            &FIXTURE_SOURCES
+                .lines()
+                .next()
+                .unwrap()
+                .replace("PLOS ONE", "PLoS ONE")
+                .replace("2024-03-01", "2023-03-01"),
+        );
+        write_gzip(
+            &data.join("authors/updated_date=2026-06-01/part_000.gz"),
+            FIXTURE_AUTHORS,
+        );
+        write_gzip(
+            &data.join("topics/updated_date=2026-06-01/part_000.gz"),
+            FIXTURE_TOPICS,
+        );
+        let options = ImportOptions {
+            workers: 1,
+            batch_size: 2,
+        };
+
+        let stats = import_openalex_dump(&root, &pool, &options).await?;
+        assert_eq!(stats.files, 5);
+        assert_eq!(stats.works_inserted, 4);
+        assert_eq!((stats.sources, stats.authors, stats.topics), (3, 3, 1));
+        assert_eq!(stats.invalid_lines, 1);
+
+        let sources: Vec<(i64, String, Vec<String>, Option<String>)> = sqlx::query_as(
+            "SELECT id, display_name, issns, publisher FROM openalex_sources ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(
+            sources,
+            [
+                (
+                    202381698,
+                    "PLOS ONE".to_string(),
+                    vec!["1932-6203".to_string()],
+                    Some("Public Library of Science".to_string())
+                ),
+                (
+                    4210184765,
+                    "Journal of Clinical Medicine".to_string(),
+                    vec!["2077-0383".to_string()],
+                    Some("MDPI".to_string())
+                ),
+                (4306400000, "medRxiv".to_string(), Vec::new(), None),
+            ]
+        );
+
+        let authors: Vec<(i64, Option<String>, Option<String>)> =
+            sqlx::query_as("SELECT id, orcid, institution FROM openalex_authors ORDER BY id")
+                .fetch_all(&pool)
+                .await?;
+        let pompidou = Some("Hôpital Européen Georges Pompidou".to_string());
+        assert_eq!(
+            authors,
+            [
+                (
+                    5000000001,
+                    Some("https://orcid.org/0000-0002-1825-0097".to_string()),
+                    pompidou.clone()
+                ),
+                (5000000002, None, None),
+                (5000000003, None, pompidou),
+            ]
+        );
+
+        let topic: (String, String, String, String) = sqlx::query_as(
+            "SELECT display_name, subfield, field, domain FROM openalex_topics WHERE id = 10003",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(topic.1, "Infectious Diseases");
+        assert_eq!(topic.3, "Health Sciences");
+
+        let status = import_status(&pool).await?;
+        let entities: Vec<&str> = status
+            .partitions
+            .iter()
+            .map(|partition| partition.entity.as_str())
+            .collect();
+        assert_eq!(
+            entities,
+            ["works", "authors", "sources", "sources", "topics"]
+        );
+
+        // Every file is recorded, under a key of its own entity
+        let stats = import_openalex_dump(&root, &pool, &options).await?;
+        assert_eq!(stats.files, 0);
+        assert_eq!(stats.files_skipped, 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,656 @@
+//! Reports on an imported OpenAlex database, for operators.
+//!
+//! [`import_status`] tells which partitions of each entity are loaded and how far
+//! updates went,
+//! [`verify_import`] checks the import against the manifest of the snapshot and against
+//! a sample of its works, and [`database_stats`] sums up the works, references,
+//! identifiers and entities held.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::BufRead;
+use std::path::Path;
+
+use biblizap_rs::openalex::schema::ensure_schema;
+
+use super::snapshot::{DumpFile, dump_files};
+use super::update::{MERGED_WORKS_DATE, WORKS_PARTITION, state};
+use super::work::WorkRow;
+use super::{OpenAlexImportError, open_dump_file, parse_work};
+
+/// Lower bounds of the reference-list size buckets of [`DatabaseStats`], besides 0.
+const REFERENCE_BUCKETS: [i64; 7] = [1, 10, 25, 50, 100, 250, 1000];
+
+/// The files of one entity imported from one partition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionStatus {
+    /// `works`, `sources`, `authors` or `topics`
+    pub entity: String,
+    /// `None` for files outside of any `updated_date=` partition
+    pub partition: Option<String>,
+    pub files: i64,
+    pub works_read: i64,
+    pub works_inserted: i64,
+    /// When the last file of the partition was imported, as `YYYY-MM-DD HH:MM`
+    pub imported_at: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportStatus {
+    /// Works first, then the other entities by name, oldest partition first
+    pub partitions: Vec<PartitionStatus>,
+    /// Newest partition imported or applied by an update
+    pub works_partition: Option<String>,
+    /// Date of the newest `merged_ids` file applied by an update
+    pub merged_works_date: Option<String>,
+    /// Files staged by an import yet to be merged
+    pub staged_files: i64,
+}
+
+impl fmt::Display for ImportStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.partitions.is_empty() && self.staged_files == 0 {
+            return writeln!(f, "No OpenAlex dump imported");
+        }
+        writeln!(
+            f,
+            "Snapshot: works up to updated_date={}, merged_ids applied up to {}",
+            self.works_partition
+                .as_deref()
+                .unwrap_or("(unfinished import)"),
+            self.merged_works_date.as_deref().unwrap_or("(none)")
+        )?;
+        for partition in &self.partitions {
+            writeln!(
+                f,
+                "  {:<8} {:<26} {:>6} files {:>12} read {:>12} written   imported {}",
+                partition.entity,
+                partition
+                    .partition
+                    .as_ref()
+                    .map_or("(no partition)".to_string(), |date| format!(
+                        "updated_date={date}"
+                    )),
+                partition.files,
+                partition.works_read,
+                partition.works_inserted,
+                partition.imported_at
+            )?;
+        }
+        if self.staged_files > 0 {
+            writeln!(
+                f,
+                "{} files staged but not merged yet, run `biblizap-openalex import` again to finish the import",
+                self.staged_files
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the partitions of each entity imported and how far updates went.
+pub async fn import_status(pool: &sqlx::PgPool) -> Result<ImportStatus, OpenAlexImportError> {
+    ensure_schema(pool).await?;
+
+    let partitions: Vec<(String, Option<String>, i64, i64, i64, String)> = sqlx::query_as(
+        r#"
+        SELECT entity, partition, COUNT(*), SUM(works_read)::BIGINT,
+            SUM(works_inserted)::BIGINT, to_char(MAX(imported_at), 'YYYY-MM-DD HH24:MI')
+        FROM openalex_imported_files
+        GROUP BY entity, partition
+        ORDER BY entity <> 'works', entity, partition NULLS FIRST
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    let (staged_files,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM openalex_staged_files")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(ImportStatus {
+        partitions: partitions
+            .into_iter()
+            .map(
+                |(entity, partition, files, works_read, works_inserted, imported_at)| {
+                    PartitionStatus {
+                        entity,
+                        partition,
+                        files,
+                        works_read,
+                        works_inserted,
+                        imported_at,
+                    }
+                },
+            )
+            .collect(),
+        works_partition: state(pool, WORKS_PARTITION).await?,
+        merged_works_date: state(pool, MERGED_WORKS_DATE).await?,
+        staged_files,
+    })
+}
+
+/// What [`verify_import`] found. The import is sound when `problems` is empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub files_checked: u64,
+    pub works_sampled: u64,
+    /// Imported files no longer in the snapshot, which is not a problem in itself
+    pub files_not_in_snapshot: u64,
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} files checked against the manifest, {} works spot-checked, {} imported files no longer in the snapshot",
+            self.files_checked, self.works_sampled, self.files_not_in_snapshot
+        )?;
+        for problem in &self.problems {
+            writeln!(f, "  {problem}")?;
+        }
+        match self.problems.len() {
+            0 => writeln!(f, "No problem found"),
+            1 => writeln!(f, "1 problem found"),
+            problems => writeln!(f, "{problems} problems found"),
+        }
+    }
+}
+
+/// Checks the import of the snapshot at `dump_path`: every file of its manifest must be
+/// imported with as many works as records announced, the tables must not hold rows of
+/// missing works, and `sample` works read from the files must be in the database as
+/// they are in the dump, unless a newer copy replaced them or they were merged since.
+pub async fn verify_import(
+    dump_path: &Path,
+    pool: &sqlx::PgPool,
+    sample: usize,
+) -> Result<VerifyReport, OpenAlexImportError> {
+    let files = dump_files(dump_path, "works")?;
+    ensure_schema(pool).await?;
+
+    let mut report = VerifyReport::default();
+    let imported: HashMap<String, (i64, Option<i64>)> = sqlx::query_as(
+        "SELECT file, works_read, record_count FROM openalex_imported_files WHERE entity = 'works'",
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(
+        |(file, works_read, record_count): (String, i64, Option<i64>)| {
+            (file, (works_read, record_count))
+        },
+    )
+    .collect();
+
+    let in_snapshot: HashSet<&str> = files.iter().map(|file| file.key.as_str()).collect();
+    report.files_not_in_snapshot = imported
+        .keys()
+        .filter(|file| !in_snapshot.contains(file.as_str()))
+        .count() as u64;
+
+    let mut imported_files = Vec::new();
+    for file in &files {
+        report.files_checked += 1;
+        let Some(&(works_read, recorded_count)) = imported.get(&file.key) else {
+            report
+                .problems
+                .push(format!("{} is not imported", file.key));
+            continue;
+        };
+        if let Some(expected) = file.record_count.map(|count| count as i64) {
+            if recorded_count.is_some_and(|recorded| recorded != expected) {
+                report.problems.push(format!(
+                    "{} has {expected} records in the manifest, {} when it was imported",
+                    file.key,
+                    recorded_count.unwrap_or_default()
+                ));
+            } else if works_read != expected {
+                report.problems.push(format!(
+                    "{}: {works_read} works imported out of {expected} records",
+                    file.key
+                ));
+            }
+        }
+        imported_files.push(file.clone());
+    }
+
+    let (staged_files,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM openalex_staged_files")
+        .fetch_one(pool)
+        .await?;
+    if staged_files > 0 {
+        report.problems.push(format!(
+            "{staged_files} files are staged but not merged, the last import did not finish"
+        ));
+    }
+    for (table, query) in [
+        (
+            "openalex_references",
+            "SELECT COUNT(*) FROM openalex_references reference WHERE NOT EXISTS (SELECT 1 FROM openalex_works work WHERE work.id = reference.citing_work_id)",
+        ),
+        (
+            "openalex_work_ids",
+            "SELECT COUNT(*) FROM openalex_work_ids ids WHERE NOT EXISTS (SELECT 1 FROM openalex_works work WHERE work.id = ids.work_id)",
+        ),
+    ] {
+        let (orphans,): (i64,) = sqlx::query_as(query).fetch_one(pool).await?;
+        if orphans > 0 {
+            report
+                .problems
+                .push(format!("{orphans} rows of {table} belong to missing works"));
+        }
+    }
+
+    let sampled =
+        tokio::task::spawn_blocking(move || sample_works(&imported_files, sample)).await??;
+    for row in &sampled {
+        report.works_sampled += 1;
+        if let Some(problem) = check_work(pool, row).await? {
+            report.problems.push(problem);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Reads about `sample` works from files spread over `files`, a few from the start of
+/// each, so that the whole files do not need to be decompressed.
+fn sample_works(files: &[DumpFile], sample: usize) -> Result<Vec<WorkRow>, OpenAlexImportError> {
+    if files.is_empty() || sample == 0 {
+        return Ok(Vec::new());
+    }
+    let step = (files.len() / sample).max(1);
+    let per_file = sample.div_ceil(files.len()).max(1);
+
+    let mut rows = Vec::with_capacity(sample);
+    for file in files.iter().step_by(step) {
+        let reader = open_dump_file(&file.path)?;
+        let mut from_file = 0;
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line.map_err(|source| OpenAlexImportError::Io {
+                path: file.path.clone(),
+                source,
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(row) = parse_work(&line, line_number, &file.path) {
+                rows.push(row);
+                from_file += 1;
+            }
+            if from_file >= per_file || rows.len() >= sample {
+                break;
+            }
+        }
+        if rows.len() >= sample {
+            break;
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Compares a work read from the dump with the database, returning what differs.
+async fn check_work(
+    pool: &sqlx::PgPool,
+    row: &WorkRow,
+) -> Result<Option<String>, OpenAlexImportError> {
+    let work = format!("W{}", row.id);
+    let stored: Option<(Option<String>, Option<String>, Option<String>)> =
+        sqlx::query_as("SELECT updated_date, title, doi FROM openalex_works WHERE id = $1")
+            .bind(row.id)
+            .fetch_optional(pool)
+            .await?;
+    let Some((updated_date, title, doi)) = stored else {
+        let (merged,): (bool,) =
+            sqlx::query_as("SELECT EXISTS (SELECT 1 FROM openalex_merged_works WHERE id = $1)")
+                .bind(row.id)
+                .fetch_one(pool)
+                .await?;
+        return Ok((!merged).then(|| format!("{work} is missing")));
+    };
+
+    // A newer copy from a later partition is not expected to match
+    if updated_date > row.updated_date {
+        return Ok(None);
+    }
+    if updated_date < row.updated_date {
+        return Ok(Some(format!(
+            "{work} is older in the database ({}) than in the dump ({})",
+            updated_date.unwrap_or_default(),
+            row.updated_date.clone().unwrap_or_default()
+        )));
+    }
+    if title != row.title || doi != row.doi {
+        return Ok(Some(format!(
+            "{work} has another title or DOI than in the dump"
+        )));
+    }
+
+    let (id_types, values): (Vec<&str>, Vec<&str>) = row
+        .ids
+        .iter()
+        .map(|(id_type, value)| (id_type.lens_field(), value.as_str()))
+        .unzip();
+    let (missing_ids,): (i64,) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*) FROM UNNEST($1::TEXT[], $2::TEXT[]) AS ids (id_type, value)
+        WHERE NOT EXISTS (
+            SELECT 1 FROM openalex_work_ids stored
+            WHERE stored.id_type = ids.id_type AND stored.value = ids.value AND stored.work_id = $3
+        )
+        "#,
+    )
+    .bind(id_types)
+    .bind(values)
+    .bind(row.id)
+    .fetch_one(pool)
+    .await?;
+    if missing_ids > 0 {
+        return Ok(Some(format!(
+            "{work} lacks {missing_ids} of its identifiers"
+        )));
+    }
+
+    // References to works merged since are redirected or dropped
+    let (missing_references,): (i64,) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*) FROM UNNEST($1::BIGINT[]) AS edge (cited)
+        WHERE NOT EXISTS (
+            SELECT 1 FROM openalex_references stored
+            WHERE stored.citing_work_id = $2 AND stored.cited_work_id = edge.cited
+        )
+        AND NOT EXISTS (SELECT 1 FROM openalex_merged_works merged WHERE merged.id = edge.cited)
+        "#,
+    )
+    .bind(&row.references)
+    .bind(row.id)
+    .fetch_one(pool)
+    .await?;
+    if missing_references > 0 {
+        return Ok(Some(format!(
+            "{work} lacks {missing_references} of its {} references",
+            row.references.len()
+        )));
+    }
+
+    Ok(None)
+}
+
+/// Counts of the works, references, identifiers and entities of the database.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DatabaseStats {
+    pub works: i64,
+    pub references: i64,
+    /// Works with at least one reference
+    pub citing_works: i64,
+    /// Works cited at least once, whether in the database or not
+    pub cited_works: i64,
+    pub abstracts: i64,
+    /// Works with at least one topic
+    pub works_with_topics: i64,
+    pub lens_linked_works: i64,
+    pub sources: i64,
+    pub authors: i64,
+    pub topics: i64,
+    /// Number of works with an identifier of each type, e.g. `doi`
+    pub identifiers: Vec<(String, i64)>,
+    /// Number of works by size of their reference list, e.g. `("10-24", 42)`
+    pub reference_sizes: Vec<(String, i64)>,
+    /// Median, 90th and 99th percentiles and maximum of the reference-list sizes of the
+    /// citing works
+    pub reference_percentiles: [i64; 4],
+}
+
+impl fmt::Display for DatabaseStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let share = |count: i64| 100.0 * count as f64 / self.works.max(1) as f64;
+        writeln!(f, "{} works", self.works)?;
+        writeln!(
+            f,
+            "{} references from {} citing works ({:.1}%) to {} cited works",
+            self.references,
+            self.citing_works,
+            share(self.citing_works),
+            self.cited_works
+        )?;
+        writeln!(
+            f,
+            "{} abstracts ({:.1}%), {} works with topics ({:.1}%)",
+            self.abstracts,
+            share(self.abstracts),
+            self.works_with_topics,
+            share(self.works_with_topics)
+        )?;
+        writeln!(
+            f,
+            "{} sources, {} authors, {} topics",
+            self.sources, self.authors, self.topics
+        )?;
+        writeln!(f, "Identifier coverage:")?;
+        for (id_type, works) in &self.identifiers {
+            writeln!(f, "  {id_type:<8} {works:>12} ({:.1}%)", share(*works))?;
+        }
+        writeln!(
+            f,
+            "  {:<8} {:>12} ({:.1}%)",
+            "lens",
+            self.lens_linked_works,
+            share(self.lens_linked_works)
+        )?;
+        writeln!(f, "Reference-list sizes:")?;
+        for (bucket, works) in &self.reference_sizes {
+            writeln!(f, "  {bucket:<8} {works:>12} ({:.1}%)", share(*works))?;
+        }
+        let [median, p90, p99, max] = self.reference_percentiles;
+        writeln!(
+            f,
+            "  citing works: median {median}, 90th percentile {p90}, 99th percentile {p99}, max {max}"
+        )
+    }
+}
+
+/// Counts the works, references, identifiers and entities of the database. It scans the tables,
+/// which takes minutes on a full snapshot.
+pub async fn database_stats(pool: &sqlx::PgPool) -> Result<DatabaseStats, OpenAlexImportError> {
+    ensure_schema(pool).await?;
+
+    let (works, abstracts, works_with_topics): (i64, i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), COUNT(abstract_text), COUNT(*) FILTER (WHERE topic_ids <> '{}') FROM openalex_works",
+    )
+    .fetch_one(pool)
+    .await?;
+    let (sources, authors, topics): (i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT (SELECT COUNT(*) FROM openalex_sources),
+            (SELECT COUNT(*) FROM openalex_authors),
+            (SELECT COUNT(*) FROM openalex_topics)
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+    let (references, cited_works): (i64, i64) =
+        sqlx::query_as("SELECT COUNT(*), COUNT(DISTINCT cited_work_id) FROM openalex_references")
+            .fetch_one(pool)
+            .await?;
+    let (lens_linked_works,): (i64,) =
+        sqlx::query_as("SELECT COUNT(DISTINCT work_id) FROM openalex_lens_ids")
+            .fetch_one(pool)
+            .await?;
+    let identifiers: Vec<(String, i64)> = sqlx::query_as(
+        r#"
+        SELECT id_type, COUNT(DISTINCT work_id)
+        FROM openalex_work_ids
+        GROUP BY id_type
+        ORDER BY id_type
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let sizes: Vec<(i32, i64)> = sqlx::query_as(
+        r#"
+        WITH sizes AS (
+            SELECT COUNT(*) AS size FROM openalex_references GROUP BY citing_work_id
+        )
+        SELECT width_bucket(size, $1::BIGINT[]), COUNT(*) FROM sizes GROUP BY 1 ORDER BY 1
+        "#,
+    )
+    .bind(REFERENCE_BUCKETS)
+    .fetch_all(pool)
+    .await?;
+    let citing_works = sizes.iter().map(|(_, works)| works).sum::<i64>();
+    let sizes: HashMap<i32, i64> = sizes.into_iter().collect();
+    let mut reference_sizes = vec![("0".to_string(), works - citing_works)];
+    for (index, lower) in REFERENCE_BUCKETS.iter().enumerate() {
+        let label = match REFERENCE_BUCKETS.get(index + 1) {
+            Some(next) => format!("{lower}-{}", next - 1),
+            None => format!("{lower}+"),
+        };
+        let works = sizes.get(&(index as i32 + 1)).copied().unwrap_or_default();
+        reference_sizes.push((label, works));
+    }
+
+    let (percentiles, max): (Option<Vec<i64>>, Option<i64>) = sqlx::query_as(
+        r#"
+        WITH sizes AS (
+            SELECT COUNT(*) AS size FROM openalex_references GROUP BY citing_work_id
+        )
+        SELECT percentile_disc(ARRAY[0.5, 0.9, 0.99]) WITHIN GROUP (ORDER BY size), MAX(size)
+        FROM sizes
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+    let percentiles = percentiles.unwrap_or_default();
+    let percentile = |index: usize| percentiles.get(index).copied().unwrap_or_default();
+
+    Ok(DatabaseStats {
+        works,
+        references,
+        citing_works,
+        cited_works,
+        abstracts,
+        works_with_topics,
+        lens_linked_works,
+        sources,
+        authors,
+        topics,
+        identifiers,
+        reference_sizes,
+        reference_percentiles: [
+            percentile(0),
+            percentile(1),
+            percentile(2),
+            max.unwrap_or_default(),
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{FIXTURE_WORKS, temp_directory, write_gzip};
+    use super::super::{ImportOptions, import_openalex_dump};
+    use super::*;
+
+    fn write_manifest(works: &Path, first_partition_records: u64) {
+        std::fs::write(
+            works.join("manifest"),
+            format!(
+                r#"{{"entries": [
+                    {{"url": "s3://openalex/data/works/updated_date=2026-05-01/part_000.gz", "meta": {{"record_count": {first_partition_records}}}}},
+                    {{"url": "s3://openalex/data/works/updated_date=2026-06-01/part_000.gz", "meta": {{"record_count": 2}}}}
+                ]}}"#
+            ),
+        )
+        .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn report_on_import(pool: sqlx::PgPool) -> Result<(), OpenAlexImportError> {
+        assert_eq!(import_status(&pool).await?, ImportStatus::default());
+
+        let lines: Vec<&str> = FIXTURE_WORKS.lines().collect();
+        let root = temp_directory();
+        let works = root.join("data/works");
+        write_gzip(
+            &works.join("updated_date=2026-05-01/part_000.gz"),
+            &format!("{}\n{}\n", lines[2], lines[3]),
+        );
+        write_gzip(
+            &works.join("updated_date=2026-06-01/part_000.gz"),
+            &format!("{}\n{}\n", lines[0], lines[1]),
+        );
+        write_manifest(&works, 2);
+        import_openalex_dump(&root, &pool, &ImportOptions::default()).await?;
+
+        let status = import_status(&pool).await?;
+        assert_eq!(status.works_partition.as_deref(), Some("2026-06-01"));
+        assert_eq!(status.merged_works_date, None);
+        assert_eq!(status.staged_files, 0);
+        let partitions: Vec<(&str, Option<&str>, i64, i64, i64)> = status
+            .partitions
+            .iter()
+            .map(|p| {
+                (
+                    p.entity.as_str(),
+                    p.partition.as_deref(),
+                    p.files,
+                    p.works_read,
+                    p.works_inserted,
+                )
+            })
+            .collect();
+        assert_eq!(
+            partitions,
+            [
+                ("works", Some("2026-05-01"), 1, 2, 2),
+                ("works", Some("2026-06-01"), 1, 2, 2)
+            ]
+        );
+
+        let report = verify_import(&root, &pool, 10).await?;
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.works_sampled, 4);
+        assert_eq!(report.problems, Vec::<String>::new());
+
+        let stats = database_stats(&pool).await?;
+        assert_eq!(stats.works, 4);
+        assert_eq!(stats.references, 6);
+        assert_eq!(stats.citing_works, 3);
+        assert_eq!(stats.identifiers.len(), 4);
+        assert_eq!(
+            stats
+                .reference_sizes
+                .iter()
+                .map(|(_, works)| works)
+                .sum::<i64>(),
+            4
+        );
+        assert_eq!(stats.reference_sizes[0], ("0".to_string(), 1));
+        assert_eq!(stats.reference_sizes[7].0, "1000+");
+
+        // A manifest announcing another count, an edited work and a lost identifier
+        write_manifest(&works, 3);
+        sqlx::query("UPDATE openalex_works SET title = 'Edited' WHERE id = 3000000001")
+            .execute(&pool)
+            .await?;
+        sqlx::query(
+            "DELETE FROM openalex_work_ids WHERE id_type = 'pmcid' AND work_id = 3000000002",
+        )
+        .execute(&pool)
+        .await?;
+        let report = verify_import(&root, &pool, 10).await?;
+        assert_eq!(
+            report.problems,
+            [
+                "updated_date=2026-05-01/part_000.gz has 3 records in the manifest, 2 when it was imported",
+                "W3000000001 has another title or DOI than in the dump",
+                "W3000000002 lacks 1 of its identifiers",
+            ]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+        Ok(())
+    }
+}
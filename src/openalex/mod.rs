@@ -1,20 +1,900 @@
-use std::path::Path;
+//! Import of OpenAlex works dumps into the BibliZap database.
+//!
+//! A dump is a set of gzipped JSON Lines files holding one work per line, usually the
+//! `updated_date=` partitions of a snapshot (see [`snapshot`]). Imports read the files
+//! with parallel workers and copy them into staging tables with binary `COPY`, then
+//! merge them into the OpenAlex tables at once (see [`bulk`]). Every channel between
+//! readers and writers is bounded, so only a few batches are in memory at once, whatever
+//! the size of the dump.
+//!
+//! Works already in the database are left untouched (insert-only), and so are their
+//! identifiers and references. Partitions are ranked newest first, so the copy kept of a
+//! work found in several partitions is the newest one. Each file is recorded once
+//! imported, and skipped by later imports. Later snapshots are applied on top of an
+//! import by [`update_openalex`], which upserts batches of works in transactions.
+//!
+//! The sources, authors and topics of a snapshot are imported along with its works when
+//! its root holds their directories (see [`entity`]), so that journals, authors and
+//! topics are known by name.
 
+mod bulk;
+mod copy;
+mod entity;
+mod inspect;
+mod snapshot;
+mod update;
+mod work;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use biblizap_rs::lens::cache::CacheBackend;
+use biblizap_rs::openalex::OpenAlexDatabase;
+use biblizap_rs::openalex::schema::ensure_schema;
+use flate2::read::MultiGzDecoder;
 use thiserror::Error;
+use tokio::sync::mpsc;
+
+use snapshot::{DumpFile, dump_files};
+use work::{Work, WorkRow};
+
+pub use inspect::{database_stats, import_status, verify_import};
+pub use update::update_openalex;
+
+/// Number of works written per transaction by updates, and per COPY chunk by default.
+const BATCH_SIZE: usize = 1000;
+/// Number of parsed batches waiting for each writer, bounding memory use.
+const CHANNEL_CAPACITY: usize = 4;
+/// Progress is logged every time this many more works have been read.
+const PROGRESS_EVERY: u64 = 100_000;
 
 #[derive(Debug, Error)]
 pub enum OpenAlexImportError {
-    #[error("OpenAlex importer is not implemented yet")]
-    NotImplemented,
+    #[error("failed to read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("no gzipped dump file found in {}", .0.display())]
+    NoDumpFiles(PathBuf),
+    #[error("invalid manifest {}: {reason}", path.display())]
+    Manifest { path: PathBuf, reason: String },
+    #[error("{} is listed in the manifest but missing", .0.display())]
+    MissingFile(PathBuf),
+    #[error("{} is not an OpenAlex snapshot root, it has no data/works directory", .0.display())]
+    NotASnapshot(PathBuf),
+    #[error("no finished snapshot import to update, run `biblizap-openalex import` first")]
+    NoImport,
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("failed to serialize authors: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("dump reader stopped unexpectedly: {0}")]
+    Reader(#[from] tokio::task::JoinError),
+    #[error(transparent)]
+    Crosswalk(#[from] biblizap_rs::openalex::OpenAlexError),
+}
+
+/// Counters of an import, logged as it goes and returned at the end.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    pub files: u64,
+    /// Files skipped because an earlier import finished them
+    pub files_skipped: u64,
+    pub works_read: u64,
+    pub works_inserted: u64,
+    /// Works skipped because they were already in the database
+    pub works_existing: u64,
+    /// Works replaced by a newer copy, by updates only
+    pub works_updated: u64,
+    /// Works merged into another one, by updates only
+    pub works_merged: u64,
+    /// Works removed from OpenAlex, by updates only
+    pub works_deleted: u64,
+    pub references: u64,
+    pub identifiers: u64,
+    /// Sources, authors and topics written
+    pub sources: u64,
+    pub authors: u64,
+    pub topics: u64,
+    /// Lines that are not a valid record, logged and skipped
+    pub invalid_lines: u64,
+    pub elapsed: Duration,
+}
+
+impl ImportStats {
+    fn per_second(&self, count: u64) -> f64 {
+        count as f64 / self.elapsed.as_secs_f64().max(0.001)
+    }
+}
+
+impl fmt::Display for ImportStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files ({} skipped as already imported), {} works read ({:.0}/s), {} inserted, {} already present, {} references ({:.0}/s), {} identifiers, {} invalid lines in {:.1?}",
+            self.files,
+            self.files_skipped,
+            self.works_read,
+            self.per_second(self.works_read),
+            self.works_inserted,
+            self.works_existing,
+            self.references,
+            self.per_second(self.references),
+            self.identifiers,
+            self.invalid_lines,
+            self.elapsed,
+        )?;
+        if self.works_updated + self.works_merged + self.works_deleted > 0 {
+            write!(
+                f,
+                ", {} works updated, {} merged, {} deleted",
+                self.works_updated, self.works_merged, self.works_deleted
+            )?;
+        }
+        if self.sources + self.authors + self.topics > 0 {
+            write!(
+                f,
+                ", {} sources, {} authors, {} topics",
+                self.sources, self.authors, self.topics
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Tuning of imports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// Number of files read at once, each by a worker thread with its own connection
+    pub workers: usize,
+    /// Number of works encoded per COPY chunk
+    pub batch_size: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            workers: std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            batch_size: BATCH_SIZE,
+        }
+    }
 }
 
+/// What the reader hands over to the writer.
+enum ReaderMessage {
+    Batch(Vec<WorkRow>),
+    /// Every work of `file` has been sent, out of `records` non-empty lines
+    FileDone {
+        file: DumpFile,
+        records: u64,
+        works_read: u64,
+    },
+}
+
+/// Imports an OpenAlex works dump: a snapshot root, a works directory or a single file.
+///
+/// The sources, authors and topics of a snapshot root are imported too. Files imported
+/// or staged by an earlier run are skipped, so an interrupted import picks up where it
+/// stopped. Once every partition file is imported, the newest
+/// partition is recorded as the starting point of [`update_openalex`]. The pool needs
+/// `options.workers` connections to keep every worker busy.
 pub async fn import_openalex_dump(
     dump_path: &Path,
-    _pool: &sqlx::PgPool,
-) -> Result<(), OpenAlexImportError> {
+    pool: &sqlx::PgPool,
+    options: &ImportOptions,
+) -> Result<ImportStats, OpenAlexImportError> {
+    let started = Instant::now();
+    let files = dump_files(dump_path, "works")?;
+    let newest_partition = files.iter().filter_map(|file| file.partition.clone()).max();
+
+    ensure_schema(pool).await?;
+
+    // Ranks are taken before skipping, so that they match those of earlier runs
+    let finished = finished_files(pool).await?;
+    let (done, files): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .enumerate()
+        .map(|(rank, file)| (rank as i32, file))
+        .partition(|(_, file)| finished.contains(&file.key));
     log::info!(
-        "OpenAlex import stub reached for dump path: {}",
-        dump_path.display()
+        "Importing {} OpenAlex dump files from {} with {} workers ({} already imported)",
+        files.len(),
+        dump_path.display(),
+        options.workers.max(1),
+        done.len()
     );
-    Err(OpenAlexImportError::NotImplemented)
+
+    let mut stats = ImportStats {
+        files_skipped: done.len() as u64,
+        ..Default::default()
+    };
+    bulk::import_works(pool, files, options, &mut stats).await?;
+    entity::import_entities(dump_path, pool, options, &mut stats).await?;
+    stats.elapsed = started.elapsed();
+
+    if let Some(partition) = newest_partition {
+        update::advance_state(pool, update::WORKS_PARTITION, &partition).await?;
+    }
+
+    Ok(stats)
+}
+
+/// Links the imported works to the Lens articles of the Lens cache with the same
+/// identifiers, from its ID mappings. Returns the number of mappings read and of new
+/// links.
+pub async fn link_lens_ids(
+    cache: &dyn CacheBackend,
+    pool: &sqlx::PgPool,
+) -> Result<(u64, u64), OpenAlexImportError> {
+    ensure_schema(pool).await?;
+
+    Ok(OpenAlexDatabase::new(pool.clone())
+        .link_cached_lens_ids(cache)
+        .await?)
+}
+
+/// The files recorded as imported, or staged by an import yet to be merged.
+async fn finished_files(pool: &sqlx::PgPool) -> Result<HashSet<String>, OpenAlexImportError> {
+    let files: Vec<(String,)> = sqlx::query_as(
+        "SELECT file FROM openalex_imported_files UNION SELECT file FROM openalex_staged_files",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(files.into_iter().map(|(file,)| file).collect())
+}
+
+/// Leaves out the files recorded as imported, returning how many they were.
+async fn skip_imported_files(
+    pool: &sqlx::PgPool,
+    files: Vec<DumpFile>,
+) -> Result<(u64, Vec<DumpFile>), OpenAlexImportError> {
+    let finished = finished_files(pool).await?;
+    let (done, files): (Vec<DumpFile>, Vec<DumpFile>) = files
+        .into_iter()
+        .partition(|file| finished.contains(&file.key));
+
+    Ok((done.len() as u64, files))
+}
+
+/// Reads `files` in order and upserts their works, recording each file once done.
+async fn write_files(
+    pool: &sqlx::PgPool,
+    files: Vec<DumpFile>,
+    stats: &mut ImportStats,
+) -> Result<(), OpenAlexImportError> {
+    let started = Instant::now();
+    stats.files += files.len() as u64;
+
+    let (sender, mut receiver) = mpsc::channel::<ReaderMessage>(CHANNEL_CAPACITY);
+    let reader = tokio::task::spawn_blocking(move || read_files(files, sender));
+
+    let mut next_progress = stats.works_read + PROGRESS_EVERY;
+    let mut inserted_before_file = stats.works_inserted;
+    while let Some(message) = receiver.recv().await {
+        let batch = match message {
+            ReaderMessage::Batch(batch) => batch,
+            ReaderMessage::FileDone {
+                file,
+                records,
+                works_read,
+            } => {
+                if file
+                    .record_count
+                    .is_some_and(|expected| expected != records)
+                {
+                    log::warn!(
+                        "{} has {records} records, the manifest announced {}",
+                        file.path.display(),
+                        file.record_count.unwrap_or_default()
+                    );
+                }
+                let works_inserted = stats.works_inserted - inserted_before_file;
+                mark_file_imported(pool, &file, works_read, works_inserted).await?;
+                inserted_before_file = stats.works_inserted;
+                continue;
+            }
+        };
+
+        stats.works_read += batch.len() as u64;
+        write_batch(pool, batch, stats).await?;
+
+        if stats.works_read >= next_progress {
+            stats.elapsed = started.elapsed();
+            log::info!(
+                "{} works read ({:.0}/s), {} inserted, {} references ({:.0}/s)",
+                stats.works_read,
+                stats.per_second(stats.works_read),
+                stats.works_inserted,
+                stats.references,
+                stats.per_second(stats.references),
+            );
+            next_progress += PROGRESS_EVERY;
+        }
+    }
+    stats.invalid_lines += reader.await??;
+    stats.elapsed = started.elapsed();
+
+    Ok(())
+}
+
+/// Records that every work of `file` is in the database.
+async fn mark_file_imported(
+    pool: &sqlx::PgPool,
+    file: &DumpFile,
+    works_read: u64,
+    works_inserted: u64,
+) -> Result<(), OpenAlexImportError> {
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_imported_files (file, partition, record_count, works_read, works_inserted)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (file) DO NOTHING
+        "#,
+    )
+    .bind(&file.key)
+    .bind(&file.partition)
+    .bind(file.record_count.map(|count| count as i64))
+    .bind(works_read as i64)
+    .bind(works_inserted as i64)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Reads the works of `files` and sends them in batches, each file ending with a
+/// [`ReaderMessage::FileDone`]. Returns the number of invalid lines, and stops early if
+/// the receiving side is gone.
+fn read_files(
+    files: Vec<DumpFile>,
+    sender: mpsc::Sender<ReaderMessage>,
+) -> Result<u64, OpenAlexImportError> {
+    let mut invalid_lines = 0;
+    let mut partition = None;
+
+    for file in files {
+        let path = &file.path;
+        let io_error = |source| OpenAlexImportError::Io {
+            path: path.clone(),
+            source,
+        };
+        if file.partition.is_some() && file.partition != partition {
+            partition = file.partition.clone();
+            log::info!(
+                "Reading partition updated_date={}",
+                partition.as_deref().unwrap_or_default()
+            );
+        }
+        let reader = open_dump_file(path)?;
+        log::debug!("Reading {}", path.display());
+
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut records = 0;
+        let mut works_read = 0;
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line.map_err(io_error)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records += 1;
+
+            match parse_work(&line, line_number, path) {
+                Some(row) => batch.push(row),
+                None => invalid_lines += 1,
+            }
+
+            if batch.len() >= BATCH_SIZE {
+                works_read += batch.len() as u64;
+                let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+                if sender.blocking_send(ReaderMessage::Batch(full)).is_err() {
+                    return Ok(invalid_lines);
+                }
+            }
+        }
+        works_read += batch.len() as u64;
+        if !batch.is_empty() && sender.blocking_send(ReaderMessage::Batch(batch)).is_err() {
+            return Ok(invalid_lines);
+        }
+        let done = ReaderMessage::FileDone {
+            file,
+            records,
+            works_read,
+        };
+        if sender.blocking_send(done).is_err() {
+            return Ok(invalid_lines);
+        }
+    }
+
+    Ok(invalid_lines)
+}
+
+/// Opens a gzipped dump file, to be read line by line.
+fn open_dump_file(path: &Path) -> Result<impl BufRead + use<>, OpenAlexImportError> {
+    let file = File::open(path).map_err(|source| OpenAlexImportError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    Ok(BufReader::new(MultiGzDecoder::new(BufReader::new(file))))
+}
+
+/// Parses a non-empty line of a dump file, logging it if it is not a valid work.
+fn parse_work(line: &str, line_number: usize, path: &Path) -> Option<WorkRow> {
+    match serde_json::from_str::<Work>(line).map(Work::into_row) {
+        Ok(Some(row)) => Some(row),
+        Ok(None) => {
+            log::warn!(
+                "No work ID on line {} of {}",
+                line_number + 1,
+                path.display()
+            );
+            None
+        }
+        Err(e) => {
+            log::warn!(
+                "Invalid work on line {} of {}: {e}",
+                line_number + 1,
+                path.display()
+            );
+            None
+        }
+    }
+}
+
+/// Writes a batch of works, then the identifiers and references of those that were
+/// inserted or updated, in one transaction.
+///
+/// Works already in the database are replaced with their references, unless the
+/// database holds a copy updated later. Their identifiers are only added to, as they
+/// include those of the works merged into them. References to merged works are
+/// redirected to the work they were merged into, and references to deleted works
+/// dropped.
+async fn write_batch(
+    pool: &sqlx::PgPool,
+    mut batch: Vec<WorkRow>,
+    stats: &mut ImportStats,
+) -> Result<(), OpenAlexImportError> {
+    // A work listed twice in a batch is kept as first seen, like across batches
+    let read = batch.len();
+    let mut seen = HashSet::with_capacity(read);
+    batch.retain(|row| seen.insert(row.id));
+    stats.works_existing += (read - batch.len()) as u64;
+
+    let mut tx = pool.begin().await?;
+
+    let authorships = batch
+        .iter()
+        .map(|row| serde_json::to_string(&row.authors))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let written: Vec<(i64, bool)> = sqlx::query_as(
+        r#"
+        INSERT INTO openalex_works (
+            id, doi, title, publication_year, publication_date, work_type, language,
+            source_id, journal, volume, issue, first_page, last_page, authorships,
+            is_retracted, is_oa, oa_url, license, cited_by_count, updated_date,
+            abstract_text, topic_ids
+        )
+        SELECT
+            id, doi, title, publication_year, publication_date, work_type, language,
+            source_id, journal, volume, issue, first_page, last_page, authorships::JSONB,
+            is_retracted, is_oa, oa_url, license, cited_by_count, updated_date,
+            abstract_text, topic_ids::BIGINT[]
+        FROM UNNEST(
+            $1::BIGINT[], $2::TEXT[], $3::TEXT[], $4::INTEGER[], $5::TEXT[], $6::TEXT[],
+            $7::TEXT[], $8::BIGINT[], $9::TEXT[], $10::TEXT[], $11::TEXT[], $12::TEXT[],
+            $13::TEXT[], $14::TEXT[], $15::BOOLEAN[], $16::BOOLEAN[], $17::TEXT[],
+            $18::TEXT[], $19::INTEGER[], $20::TEXT[], $21::TEXT[], $22::TEXT[]
+        ) AS batch (
+            id, doi, title, publication_year, publication_date, work_type, language,
+            source_id, journal, volume, issue, first_page, last_page, authorships,
+            is_retracted, is_oa, oa_url, license, cited_by_count, updated_date,
+            abstract_text, topic_ids
+        )
+        ON CONFLICT (id) DO UPDATE SET
+            doi = EXCLUDED.doi,
+            title = EXCLUDED.title,
+            publication_year = EXCLUDED.publication_year,
+            publication_date = EXCLUDED.publication_date,
+            work_type = EXCLUDED.work_type,
+            language = EXCLUDED.language,
+            source_id = EXCLUDED.source_id,
+            journal = EXCLUDED.journal,
+            volume = EXCLUDED.volume,
+            issue = EXCLUDED.issue,
+            first_page = EXCLUDED.first_page,
+            last_page = EXCLUDED.last_page,
+            authorships = EXCLUDED.authorships,
+            is_retracted = EXCLUDED.is_retracted,
+            is_oa = EXCLUDED.is_oa,
+            oa_url = EXCLUDED.oa_url,
+            license = EXCLUDED.license,
+            cited_by_count = EXCLUDED.cited_by_count,
+            updated_date = EXCLUDED.updated_date,
+            abstract_text = EXCLUDED.abstract_text,
+            topic_ids = EXCLUDED.topic_ids
+        WHERE openalex_works.updated_date IS NULL
+            OR EXCLUDED.updated_date >= openalex_works.updated_date
+        RETURNING id, xmax::TEXT = '0' AS inserted
+        "#,
+    )
+    .bind(batch.iter().map(|row| row.id).collect::<Vec<_>>())
+    .bind(batch.iter().map(|row| row.doi.clone()).collect::<Vec<_>>())
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.title.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.publication_year)
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.publication_date.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.work_type.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.language.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(batch.iter().map(|row| row.source_id).collect::<Vec<_>>())
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.journal.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.volume.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.issue.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.first_page.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.last_page.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(authorships)
+    .bind(batch.iter().map(|row| row.is_retracted).collect::<Vec<_>>())
+    .bind(batch.iter().map(|row| row.is_oa).collect::<Vec<_>>())
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.oa_url.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.license.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.cited_by_count)
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.updated_date.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        batch
+            .iter()
+            .map(|row| row.abstract_text.clone())
+            .collect::<Vec<_>>(),
+    )
+    // Arrays of arrays must be rectangular, so each list of topics is an array literal
+    .bind(
+        batch
+            .iter()
+            .map(|row| {
+                let ids: Vec<String> = row.topic_ids.iter().map(i64::to_string).collect();
+                format!("{{{}}}", ids.join(","))
+            })
+            .collect::<Vec<_>>(),
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let updated: Vec<i64> = written
+        .iter()
+        .filter(|(_, inserted)| !inserted)
+        .map(|(id, _)| *id)
+        .collect();
+    let written: HashSet<i64> = written.into_iter().map(|(id, _)| id).collect();
+    stats.works_updated += updated.len() as u64;
+    stats.works_inserted += (written.len() - updated.len()) as u64;
+    stats.works_existing += (batch.len() - written.len()) as u64;
+
+    // The references of updated works are replaced
+    if !updated.is_empty() {
+        sqlx::query("DELETE FROM openalex_references WHERE citing_work_id = ANY($1)")
+            .bind(&updated)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let new_rows = || batch.iter().filter(|row| written.contains(&row.id));
+
+    let mut id_types = Vec::new();
+    let mut values = Vec::new();
+    let mut id_work_ids = Vec::new();
+    for row in new_rows() {
+        for (id_type, value) in &row.ids {
+            id_types.push(id_type.lens_field());
+            values.push(value.as_str());
+            id_work_ids.push(row.id);
+        }
+    }
+    stats.identifiers += values.len() as u64;
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_work_ids (id_type, value, work_id)
+        SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[], $3::BIGINT[])
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(id_types)
+    .bind(values)
+    .bind(id_work_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    let (citing, cited): (Vec<i64>, Vec<i64>) = new_rows()
+        .flat_map(|row| row.references.iter().map(|&cited| (row.id, cited)))
+        .unzip();
+    stats.references += citing.len() as u64;
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_references (citing_work_id, cited_work_id)
+        SELECT edge.citing, COALESCE(merged.merged_into_id, edge.cited)
+        FROM UNNEST($1::BIGINT[], $2::BIGINT[]) AS edge (citing, cited)
+        LEFT JOIN openalex_merged_works merged ON merged.id = edge.cited
+        WHERE merged.id IS NULL
+            OR (merged.merged_into_id IS NOT NULL AND merged.merged_into_id <> edge.citing)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(citing)
+    .bind(cited)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+
+    pub(super) const FIXTURE_WORKS: &str = include_str!("fixtures/works.jsonl");
+
+    /// A new, empty temporary directory.
+    pub(super) fn temp_directory() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "biblizap-openalex-{}-{}",
+            std::process::id(),
+            uuid::Uuid::new_v4()
+        ))
+    }
+
+    /// Writes `content` gzipped to `path`, creating its directories.
+    pub(super) fn write_gzip(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::fast());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    /// Writes `content` gzipped into a new temporary directory, as `name`.
+    pub(super) fn gzip_fixture(name: &str, content: &str) -> PathBuf {
+        let path = temp_directory().join(name);
+        write_gzip(&path, content);
+        path
+    }
+
+    #[sqlx::test]
+    async fn import_works_dump(pool: sqlx::PgPool) -> Result<(), OpenAlexImportError> {
+        let content = format!("{FIXTURE_WORKS}not json\n{{\"id\": \"https://openalex.org/A1\"}}\n");
+        let path = gzip_fixture("part_000.gz", &content);
+
+        let stats = import_openalex_dump(&path, &pool, &ImportOptions::default()).await?;
+        assert_eq!(stats.works_read, 4);
+        assert_eq!(stats.works_inserted, 4);
+        assert_eq!(stats.invalid_lines, 2);
+        assert_eq!(stats.references, 6, "Duplicate references are kept once");
+        assert_eq!(stats.identifiers, 9);
+
+        let (title, journal, authors): (String, String, serde_json::Value) = sqlx::query_as(
+            "SELECT title, journal, authorships FROM openalex_works WHERE id = 3000000001",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(title.starts_with("Serum IL-6"));
+        assert_eq!(journal, "PLoS ONE");
+        assert_eq!(authors[1]["name"], "Thi Binh Nguyen");
+
+        let (work_id,): (i64,) = sqlx::query_as(
+            "SELECT work_id FROM openalex_work_ids WHERE id_type = 'pmcid' AND value = 'PMC7050002'",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(work_id, 3000000002);
+
+        let abstracts: Vec<(Option<String>,)> =
+            sqlx::query_as("SELECT abstract_text FROM openalex_works ORDER BY id")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(
+            abstracts[1].0.as_deref(),
+            Some("IL-6 predicts the outcome of pneumonia; IL-6 levels & CRP too.")
+        );
+        assert_eq!(abstracts[2].0, None);
+
+        let topics: Vec<(Vec<i64>,)> =
+            sqlx::query_as("SELECT topic_ids FROM openalex_works ORDER BY id")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(topics[0].0, [10003]);
+        assert_eq!(topics[2].0, Vec::<i64>::new());
+
+        let citing: Vec<(i64,)> = sqlx::query_as(
+            "SELECT citing_work_id FROM openalex_references WHERE cited_work_id = 3000000002 ORDER BY 1",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(citing, [(3000000001,), (3000000004,)]);
+
+        // The first import built the indexes it dropped, and emptied the staging tables
+        let (indexes,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) FROM pg_indexes
+            WHERE tablename IN ('openalex_works', 'openalex_work_ids', 'openalex_references')
+            "#,
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(indexes, 5);
+        let (staged,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM openalex_staging_works")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(staged, 0);
+
+        // Importing again skips the finished file
+        let again =
+            import_openalex_dump(path.parent().unwrap(), &pool, &ImportOptions::default()).await?;
+        assert_eq!(again.files_skipped, 1);
+        assert_eq!(again.works_read, 0);
+
+        // And reading it again leaves everything in place
+        sqlx::query("DELETE FROM openalex_imported_files")
+            .execute(&pool)
+            .await?;
+        let again =
+            import_openalex_dump(path.parent().unwrap(), &pool, &ImportOptions::default()).await?;
+        assert_eq!(again.works_inserted, 0);
+        assert_eq!(again.works_existing, 4);
+        assert_eq!(again.references, 0);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn import_snapshot_newest_first(pool: sqlx::PgPool) -> Result<(), OpenAlexImportError> {
+        let lines: Vec<&str> = FIXTURE_WORKS.lines().collect();
+        let outdated = lines[0].replace("Serum IL-6", "Outdated title");
+        let root = temp_directory();
+        let works = root.join("data/works");
+        write_gzip(
+            &works.join("updated_date=2026-05-01/part_000.gz"),
+            &format!("{outdated}\n{}\n", lines[2]),
+        );
+        write_gzip(
+            &works.join("updated_date=2026-06-01/part_000.gz"),
+            &format!("{}\n{}\n", lines[0], lines[1]),
+        );
+        std::fs::write(
+            works.join("manifest"),
+            r#"{"entries": [
+                {"url": "s3://openalex/data/works/updated_date=2026-05-01/part_000.gz", "meta": {"record_count": 2}},
+                {"url": "s3://openalex/data/works/updated_date=2026-06-01/part_000.gz", "meta": {"record_count": 2}}
+            ]}"#,
+        )
+        .unwrap();
+
+        let stats = import_openalex_dump(&root, &pool, &ImportOptions::default()).await?;
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.works_read, 4);
+        assert_eq!(stats.works_inserted, 3);
+        assert_eq!(stats.works_existing, 1);
+
+        let (title,): (String,) =
+            sqlx::query_as("SELECT title FROM openalex_works WHERE id = 3000000001")
+                .fetch_one(&pool)
+                .await?;
+        assert!(title.starts_with("Serum IL-6"), "The newest copy is kept");
+
+        let files: Vec<(String, Option<String>, i64, i64)> = sqlx::query_as(
+            "SELECT file, partition, works_read, works_inserted FROM openalex_imported_files ORDER BY file",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(
+            files,
+            [
+                (
+                    "updated_date=2026-05-01/part_000.gz".to_string(),
+                    Some("2026-05-01".to_string()),
+                    2,
+                    1
+                ),
+                (
+                    "updated_date=2026-06-01/part_000.gz".to_string(),
+                    Some("2026-06-01".to_string()),
+                    2,
+                    2
+                ),
+            ]
+        );
+
+        // An import interrupted before the older partition resumes with it alone
+        sqlx::query("DELETE FROM openalex_imported_files WHERE partition = '2026-05-01'")
+            .execute(&pool)
+            .await?;
+        let resumed = import_openalex_dump(&root, &pool, &ImportOptions::default()).await?;
+        assert_eq!(resumed.files_skipped, 1);
+        assert_eq!(resumed.files, 1);
+        assert_eq!(resumed.works_read, 2);
+        assert_eq!(resumed.works_inserted, 0);
+
+        std::fs::remove_dir_all(&root).unwrap();
+        Ok(())
+    }
 }
@@ -0,0 +1,239 @@
+//! Layout of an OpenAlex snapshot on disk.
+//!
+//! A snapshot keeps each entity under `data/<entity>/`, split into
+//! `updated_date=YYYY-MM-DD` partitions of gzipped JSON Lines files, with a `manifest`
+//! listing every file and its record count. A work updated several times appears in
+//! several partitions, so files are listed newest partition first: with insert-only
+//! writes, the first copy seen, the newest, is the one kept.
+
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::OpenAlexImportError;
+
+/// Prefix of the partition directories of a snapshot.
+const PARTITION_PREFIX: &str = "updated_date=";
+
+/// A gzipped file of a dump, in import order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpFile {
+    pub path: PathBuf,
+    /// Name under which the file is recorded once imported: `updated_date=…/part_…gz`
+    /// for partition files, so that the same snapshot mounted elsewhere is recognised,
+    /// and the canonical path otherwise
+    pub key: String,
+    /// Date of the `updated_date=` partition holding the file, if any
+    pub partition: Option<String>,
+    /// Number of records announced by the manifest
+    pub record_count: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestEntry {
+    url: String,
+    #[serde(default)]
+    meta: ManifestMeta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ManifestMeta {
+    record_count: Option<u64>,
+}
+
+/// Lists the files of a dump of `entity`, e.g. `works`, newest partition first.
+///
+/// `dump_path` is a snapshot root (holding `data/<entity>/`), an entity directory, any
+/// directory of `.gz` files, or a single file. When the entity directory has a
+/// `manifest`, the files it lists are imported and must all be present; otherwise every
+/// `.gz` file below the directory is.
+pub fn dump_files(dump_path: &Path, entity: &str) -> Result<Vec<DumpFile>, OpenAlexImportError> {
+    let io_error = |source| OpenAlexImportError::Io {
+        path: dump_path.to_path_buf(),
+        source,
+    };
+    if !dump_path.metadata().map_err(io_error)?.is_dir() {
+        return Ok(vec![dump_file(dump_path.to_path_buf(), None)?]);
+    }
+
+    let entity_dir = dump_path.join("data").join(entity);
+    let entity_dir = if entity_dir.is_dir() {
+        entity_dir
+    } else {
+        dump_path.to_path_buf()
+    };
+    let manifest_path = entity_dir.join("manifest");
+    let mut files = if manifest_path.is_file() {
+        manifest_files(&manifest_path, &entity_dir, entity)?
+    } else {
+        walk_gz_files(&entity_dir)?
+            .into_iter()
+            .map(|path| dump_file(path, None))
+            .collect::<Result<_, _>>()?
+    };
+    if files.is_empty() {
+        return Err(OpenAlexImportError::NoDumpFiles(dump_path.to_path_buf()));
+    }
+    files.sort_by(|a, b| (Reverse(&a.partition), &a.key).cmp(&(Reverse(&b.partition), &b.key)));
+
+    Ok(files)
+}
+
+/// Lists the files of a manifest, which name them by their S3 URL.
+fn manifest_files(
+    manifest_path: &Path,
+    entity_dir: &Path,
+    entity: &str,
+) -> Result<Vec<DumpFile>, OpenAlexImportError> {
+    let invalid = |reason: String| OpenAlexImportError::Manifest {
+        path: manifest_path.to_path_buf(),
+        reason,
+    };
+    let content =
+        std::fs::read_to_string(manifest_path).map_err(|source| OpenAlexImportError::Io {
+            path: manifest_path.to_path_buf(),
+            source,
+        })?;
+    let manifest: Manifest = serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+
+    let marker = format!("data/{entity}/");
+    manifest
+        .entries
+        .into_iter()
+        .map(|entry| {
+            let (_, relative) = entry
+                .url
+                .split_once(&marker)
+                .ok_or_else(|| invalid(format!("unexpected file URL {}", entry.url)))?;
+            let path = entity_dir.join(relative);
+            if !path.is_file() {
+                return Err(OpenAlexImportError::MissingFile(path));
+            }
+            dump_file(path, entry.meta.record_count)
+        })
+        .collect()
+}
+
+/// Every `.gz` file below `directory`.
+fn walk_gz_files(directory: &Path) -> Result<Vec<PathBuf>, OpenAlexImportError> {
+    let mut files = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let io_error = |source| OpenAlexImportError::Io {
+            path: directory.clone(),
+            source,
+        };
+        for entry in std::fs::read_dir(&directory).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "gz") {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+fn dump_file(path: PathBuf, record_count: Option<u64>) -> Result<DumpFile, OpenAlexImportError> {
+    let partition_dir = path
+        .parent()
+        .and_then(|parent| parent.file_name())
+        .and_then(|name| name.to_str())
+        .filter(|name| name.starts_with(PARTITION_PREFIX));
+    let file_name = path.file_name().and_then(|name| name.to_str());
+
+    let (key, partition) = match (partition_dir, file_name) {
+        (Some(partition_dir), Some(file_name)) => (
+            format!("{partition_dir}/{file_name}"),
+            Some(partition_dir[PARTITION_PREFIX.len()..].to_string()),
+        ),
+        _ => {
+            let canonical = path
+                .canonicalize()
+                .map_err(|source| OpenAlexImportError::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+            (canonical.display().to_string(), None)
+        }
+    };
+
+    Ok(DumpFile {
+        path,
+        key,
+        partition,
+        record_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty file, and its directories.
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"").unwrap();
+    }
+
+    #[test]
+    fn snapshot_files_from_manifest() {
+        let root = std::env::temp_dir().join(format!("biblizap-snapshot-{}", uuid::Uuid::new_v4()));
+        let works = root.join("data/works");
+        for file in [
+            "updated_date=2026-05-01/part_000.gz",
+            "updated_date=2026-06-01/part_000.gz",
+            "updated_date=2026-06-01/part_001.gz",
+            // Not listed in the manifest, so not imported
+            "updated_date=2026-06-02/part_000.gz",
+        ] {
+            touch(&works.join(file));
+        }
+        std::fs::write(
+            works.join("manifest"),
+            r#"{"entries": [
+                {"url": "s3://openalex/data/works/updated_date=2026-05-01/part_000.gz", "meta": {"content_length": 10, "record_count": 3}},
+                {"url": "s3://openalex/data/works/updated_date=2026-06-01/part_001.gz", "meta": {"content_length": 10, "record_count": 1}},
+                {"url": "s3://openalex/data/works/updated_date=2026-06-01/part_000.gz", "meta": {"content_length": 10, "record_count": 2}}
+            ], "meta": {"content_length": 30, "record_count": 6}}"#,
+        )
+        .unwrap();
+
+        for dump_path in [&root, &works] {
+            let files = dump_files(dump_path, "works").unwrap();
+            let keys: Vec<&str> = files.iter().map(|file| file.key.as_str()).collect();
+            assert_eq!(
+                keys,
+                [
+                    "updated_date=2026-06-01/part_000.gz",
+                    "updated_date=2026-06-01/part_001.gz",
+                    "updated_date=2026-05-01/part_000.gz",
+                ]
+            );
+            assert_eq!(files[0].partition.as_deref(), Some("2026-06-01"));
+            assert_eq!(files[0].record_count, Some(2));
+        }
+
+        // A single partition file keeps its partition key
+        let single =
+            dump_files(&works.join("updated_date=2026-06-02/part_000.gz"), "works").unwrap();
+        assert_eq!(single[0].key, "updated_date=2026-06-02/part_000.gz");
+        assert_eq!(single[0].record_count, None);
+
+        std::fs::remove_file(works.join("updated_date=2026-06-01/part_001.gz")).unwrap();
+        assert!(matches!(
+            dump_files(&root, "works"),
+            Err(OpenAlexImportError::MissingFile(_))
+        ));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,529 @@
+//! Incremental updates of an imported OpenAlex snapshot.
+//!
+//! OpenAlex publishes a new `updated_date=` partition holding the works changed since
+//! the previous one, and lists the works merged into another one, or deleted, in
+//! `data/merged_ids/works/<merge date>.csv.gz`. An update applies the `merged_ids` files
+//! newer than the last one applied, then the partitions newer than the last one
+//! imported or applied, oldest first, replacing the works they hold. How far it went is
+//! kept in `openalex_state`, so running it again only applies what is new. The sources,
+//! authors and topics files not imported yet are applied last.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use biblizap_rs::openalex::schema::ensure_schema;
+use flate2::read::MultiGzDecoder;
+
+use super::snapshot::{DumpFile, dump_files};
+use super::work::parse_entity_id;
+use super::{
+    BATCH_SIZE, ImportOptions, ImportStats, OpenAlexImportError, entity, skip_imported_files,
+    write_files,
+};
+
+/// State holding the newest works partition imported or applied.
+pub(super) const WORKS_PARTITION: &str = "works_partition";
+/// State holding the date of the newest `merged_ids` file applied.
+pub(super) const MERGED_WORKS_DATE: &str = "merged_works_date";
+/// Longest chain of merges followed, in case OpenAlex ever merges works in a loop.
+const MAX_MERGE_HOPS: usize = 16;
+
+/// A work merged into another one, or deleted when `None`.
+type Merge = (i64, Option<i64>);
+
+/// A value of `openalex_state`, if set.
+pub(super) async fn state(pool: &sqlx::PgPool, name: &str) -> Result<Option<String>, sqlx::Error> {
+    let value: Option<(String,)> =
+        sqlx::query_as("SELECT value FROM openalex_state WHERE name = $1")
+            .bind(name)
+            .fetch_optional(pool)
+            .await?;
+    Ok(value.map(|(value,)| value))
+}
+
+/// Sets a value of `openalex_state`, unless it already holds a later one. Values are
+/// dates, which sort as text.
+pub(super) async fn advance_state(
+    pool: &sqlx::PgPool,
+    name: &str,
+    value: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_state (name, value)
+        VALUES ($1, $2)
+        ON CONFLICT (name) DO UPDATE SET
+            value = GREATEST(openalex_state.value, EXCLUDED.value),
+            updated_at = NOW()
+        "#,
+    )
+    .bind(name)
+    .bind(value)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Applies the partitions and `merged_ids` files of a snapshot that are newer than the
+/// database, which must hold a finished import. `snapshot_root` holds `data/works/`.
+pub async fn update_openalex(
+    snapshot_root: &Path,
+    pool: &sqlx::PgPool,
+) -> Result<ImportStats, OpenAlexImportError> {
+    if !snapshot_root.join("data").join("works").is_dir() {
+        return Err(OpenAlexImportError::NotASnapshot(
+            snapshot_root.to_path_buf(),
+        ));
+    }
+
+    ensure_schema(pool).await?;
+
+    let last_partition = state(pool, WORKS_PARTITION)
+        .await?
+        .ok_or(OpenAlexImportError::NoImport)?;
+    let started = Instant::now();
+    let mut stats = ImportStats::default();
+
+    let last_merge = state(pool, MERGED_WORKS_DATE).await?;
+    let merged_files: Vec<(String, PathBuf)> =
+        merged_id_files(&snapshot_root.join("data").join("merged_ids").join("works"))?
+            .into_iter()
+            .filter(|(date, _)| last_merge.as_ref().is_none_or(|last| date > last))
+            .collect();
+    log::info!(
+        "Applying {} merged_ids files newer than {}",
+        merged_files.len(),
+        last_merge.as_deref().unwrap_or("the import")
+    );
+    for (date, path) in merged_files {
+        apply_merged_file(pool, &path, &date, &mut stats).await?;
+        advance_state(pool, MERGED_WORKS_DATE, &date).await?;
+    }
+
+    // Oldest first, so that the newest copy of a work is written last
+    let mut files: Vec<DumpFile> = dump_files(snapshot_root, "works")?
+        .into_iter()
+        .filter(|file| {
+            file.partition
+                .as_ref()
+                .is_some_and(|partition| *partition > last_partition)
+        })
+        .collect();
+    files.sort_by(|a, b| (&a.partition, &a.key).cmp(&(&b.partition, &b.key)));
+    let newest_partition = files.iter().filter_map(|file| file.partition.clone()).max();
+
+    let (done, files) = skip_imported_files(pool, files).await?;
+    log::info!(
+        "Applying {} files of the partitions newer than updated_date={last_partition} ({} already applied)",
+        files.len(),
+        done
+    );
+    stats.files_skipped = done;
+    write_files(pool, files, &mut stats).await?;
+
+    if let Some(partition) = newest_partition {
+        advance_state(pool, WORKS_PARTITION, &partition).await?;
+    }
+
+    // Updates get by with one connection
+    let options = ImportOptions {
+        workers: 1,
+        batch_size: BATCH_SIZE,
+    };
+    entity::import_entities(snapshot_root, pool, &options, &mut stats).await?;
+    stats.elapsed = started.elapsed();
+
+    Ok(stats)
+}
+
+/// The `merged_ids` files of works, by date, oldest first. None when the snapshot has no
+/// `merged_ids` directory.
+fn merged_id_files(directory: &Path) -> Result<Vec<(String, PathBuf)>, OpenAlexImportError> {
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let io_error = |source| OpenAlexImportError::Io {
+        path: directory.to_path_buf(),
+        source,
+    };
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(directory).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        let date = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".csv.gz"));
+        if let Some(date) = date {
+            files.push((date.to_string(), path.clone()));
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Reads a `merged_ids` file: `merge_date,id,merge_into_id` lines, with an empty
+/// `merge_into_id` for deleted works.
+fn read_merged_ids(path: &Path) -> Result<(Vec<Merge>, u64), OpenAlexImportError> {
+    let io_error = |source| OpenAlexImportError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let reader = BufReader::new(MultiGzDecoder::new(BufReader::new(
+        File::open(path).map_err(io_error)?,
+    )));
+
+    let mut merges = Vec::new();
+    let mut invalid_lines = 0;
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line.map_err(io_error)?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("merge_date") {
+            continue;
+        }
+
+        let mut fields = line.split(',').skip(1);
+        let id = fields.next().and_then(|id| parse_entity_id(id.trim(), 'W'));
+        let merged_into = fields.next().map(str::trim).unwrap_or_default();
+        let merged_into = match merged_into {
+            "" => Some(None),
+            raw => parse_entity_id(raw, 'W').map(Some),
+        };
+        match (id, merged_into) {
+            (Some(id), Some(merged_into)) => merges.push((id, merged_into)),
+            _ => {
+                log::warn!(
+                    "Invalid merge on line {} of {}",
+                    line_number + 1,
+                    path.display()
+                );
+                invalid_lines += 1;
+            }
+        }
+    }
+
+    Ok((merges, invalid_lines))
+}
+
+async fn apply_merged_file(
+    pool: &sqlx::PgPool,
+    path: &Path,
+    date: &str,
+    stats: &mut ImportStats,
+) -> Result<(), OpenAlexImportError> {
+    let reader_path = path.to_path_buf();
+    let (merges, invalid_lines) =
+        tokio::task::spawn_blocking(move || read_merged_ids(&reader_path)).await??;
+    log::info!("Applying {} merges of {}", merges.len(), path.display());
+
+    stats.invalid_lines += invalid_lines;
+    for batch in merges.chunks(BATCH_SIZE) {
+        apply_merges(pool, batch, date, stats).await?;
+    }
+
+    Ok(())
+}
+
+/// Applies a batch of merges in one transaction. References to a merged work are
+/// redirected to the work it was merged into, and its identifiers and Lens links are
+/// given to that work. A merged or deleted work is removed, with its references; references to a
+/// deleted work are dropped.
+async fn apply_merges(
+    pool: &sqlx::PgPool,
+    merges: &[Merge],
+    date: &str,
+    stats: &mut ImportStats,
+) -> Result<(), OpenAlexImportError> {
+    // A work may have been merged into one merged in turn, in this batch or before
+    let in_batch: HashMap<i64, Option<i64>> = merges.iter().copied().collect();
+    let mut targets = in_batch.clone();
+    for _ in 0..MAX_MERGE_HOPS {
+        let next: Vec<i64> = targets
+            .values()
+            .flatten()
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let mut redirects: HashMap<i64, Option<i64>> = sqlx::query_as(
+            "SELECT id, merged_into_id FROM openalex_merged_works WHERE id = ANY($1)",
+        )
+        .bind(&next)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+        redirects.extend(
+            next.iter()
+                .filter_map(|target| in_batch.get(target).map(|&into| (*target, into))),
+        );
+
+        let mut changed = false;
+        for target in targets.values_mut() {
+            if let Some(id) = *target
+                && let Some(&into) = redirects.get(&id)
+                && into != Some(id)
+            {
+                *target = into;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let (ids, merged_into): (Vec<i64>, Vec<Option<i64>>) = targets
+        .into_iter()
+        .filter(|(id, into)| *into != Some(*id))
+        .unzip();
+    let deleted = merged_into.iter().filter(|into| into.is_none()).count() as u64;
+    stats.works_deleted += deleted;
+    stats.works_merged += ids.len() as u64 - deleted;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_merged_works (id, merged_into_id, merge_date)
+        SELECT id, merged_into_id, $3 FROM UNNEST($1::BIGINT[], $2::BIGINT[])
+            AS merge (id, merged_into_id)
+        ON CONFLICT (id) DO UPDATE SET
+            merged_into_id = EXCLUDED.merged_into_id,
+            merge_date = EXCLUDED.merge_date
+        "#,
+    )
+    .bind(&ids)
+    .bind(&merged_into)
+    .bind(date)
+    .execute(&mut *tx)
+    .await?;
+
+    // Earlier merges into these works now lead where they do
+    sqlx::query(
+        r#"
+        UPDATE openalex_merged_works
+        SET merged_into_id = merge.merged_into_id
+        FROM UNNEST($1::BIGINT[], $2::BIGINT[]) AS merge (id, merged_into_id)
+        WHERE openalex_merged_works.merged_into_id = merge.id
+        "#,
+    )
+    .bind(&ids)
+    .bind(&merged_into)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_references (citing_work_id, cited_work_id)
+        SELECT r.citing_work_id, merge.merged_into_id
+        FROM openalex_references r
+        JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS merge (id, merged_into_id)
+            ON r.cited_work_id = merge.id
+        WHERE merge.merged_into_id IS NOT NULL AND r.citing_work_id <> merge.merged_into_id
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&ids)
+    .bind(&merged_into)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_work_ids (id_type, value, work_id)
+        SELECT i.id_type, i.value, merge.merged_into_id
+        FROM openalex_work_ids i
+        JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS merge (id, merged_into_id)
+            ON i.work_id = merge.id
+        WHERE merge.merged_into_id IS NOT NULL
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&ids)
+    .bind(&merged_into)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO openalex_lens_ids (work_id, lens_id)
+        SELECT merge.merged_into_id, l.lens_id
+        FROM openalex_lens_ids l
+        JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS merge (id, merged_into_id)
+            ON l.work_id = merge.id
+        WHERE merge.merged_into_id IS NOT NULL
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&ids)
+    .bind(&merged_into)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "DELETE FROM openalex_references WHERE cited_work_id = ANY($1) OR citing_work_id = ANY($1)",
+    )
+    .bind(&ids)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM openalex_work_ids WHERE work_id = ANY($1)")
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM openalex_lens_ids WHERE work_id = ANY($1)")
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM openalex_works WHERE id = ANY($1)")
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::super::tests::{FIXTURE_WORKS, temp_directory, write_gzip};
+    use super::super::{ImportOptions, import_openalex_dump};
+    use super::*;
+
+    /// The fixture work on line `index`, modified by `change`, as a JSON line.
+    fn fixture_work(index: usize, change: impl FnOnce(&mut Value)) -> String {
+        let mut work: Value =
+            serde_json::from_str(FIXTURE_WORKS.lines().nth(index).unwrap()).unwrap();
+        change(&mut work);
+        work.to_string()
+    }
+
+    async fn references(pool: &sqlx::PgPool) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT citing_work_id, cited_work_id FROM openalex_references ORDER BY 1, 2",
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    #[sqlx::test]
+    async fn update_snapshot(pool: sqlx::PgPool) -> Result<(), OpenAlexImportError> {
+        let root = temp_directory();
+        let works = root.join("data/works");
+        write_gzip(
+            &works.join("updated_date=2026-05-01/part_000.gz"),
+            FIXTURE_WORKS,
+        );
+
+        assert!(matches!(
+            update_openalex(&root, &pool).await,
+            Err(OpenAlexImportError::NoImport)
+        ));
+        import_openalex_dump(&root, &pool, &ImportOptions::default()).await?;
+        assert_eq!(references(&pool).await?.len(), 6);
+        sqlx::query("INSERT INTO openalex_lens_ids VALUES (3000000003, '010-000-000-000-003')")
+            .execute(&pool)
+            .await?;
+
+        // W3 is merged into W2, W4 is deleted and W9, never imported, merged into W1
+        write_gzip(
+            &root.join("data/merged_ids/works/2026-06-01.csv.gz"),
+            "merge_date,id,merge_into_id\n\
+             2026-06-01,W3000000003,W3000000002\n\
+             2026-06-01,W3000000004,\n\
+             2026-06-01,W3000000009,W3000000001\n",
+        );
+        // W2 is updated to cite W1, and W5 is new, citing W9 and W2
+        let updated = fixture_work(1, |work| {
+            work["title"] = json!("Updated title");
+            work["referenced_works"] = json!(["https://openalex.org/W3000000001"]);
+            work["topics"] = json!([
+                {"id": "https://openalex.org/T10004", "score": 0.9},
+                {"id": "https://openalex.org/T10003", "score": 0.8}
+            ]);
+            work["updated_date"] = json!("2026-06-01T00:00:00");
+        });
+        let new = fixture_work(3, |work| {
+            work["id"] = json!("https://openalex.org/W3000000005");
+            work["ids"] = json!({});
+            work["doi"] = Value::Null;
+            work["referenced_works"] = json!([
+                "https://openalex.org/W3000000009",
+                "https://openalex.org/W3000000002"
+            ]);
+        });
+        write_gzip(
+            &works.join("updated_date=2026-06-01/part_000.gz"),
+            &format!("{updated}\n{new}\n"),
+        );
+        // Entity files not imported yet are applied too
+        write_gzip(
+            &root.join("data/topics/updated_date=2026-06-01/part_000.gz"),
+            r#"{"id": "https://openalex.org/T10004", "display_name": "Sepsis", "updated_date": "2026-06-01T00:00:00"}"#,
+        );
+
+        let stats = update_openalex(&root, &pool).await?;
+        // The works file and the topics file
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.works_read, 2);
+        assert_eq!(stats.works_inserted, 1);
+        assert_eq!(stats.works_updated, 1);
+        assert_eq!(stats.works_merged, 2);
+        assert_eq!(stats.works_deleted, 1);
+        assert_eq!(stats.topics, 1);
+
+        let ids: Vec<(i64,)> = sqlx::query_as("SELECT id FROM openalex_works ORDER BY id")
+            .fetch_all(&pool)
+            .await?;
+        assert_eq!(ids, [(3000000001,), (3000000002,), (3000000005,)]);
+        let (title, topic_ids): (String, Vec<i64>) =
+            sqlx::query_as("SELECT title, topic_ids FROM openalex_works WHERE id = 3000000002")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(title, "Updated title");
+        assert_eq!(topic_ids, [10004, 10003]);
+
+        assert_eq!(
+            references(&pool).await?,
+            [
+                (3000000001, 3000000002),
+                (3000000002, 3000000001),
+                (3000000005, 3000000001),
+                (3000000005, 3000000002),
+            ]
+        );
+
+        // The DOI of the merged W3 now leads to W2
+        let (work_id,): (i64,) = sqlx::query_as(
+            "SELECT work_id FROM openalex_work_ids WHERE id_type = 'doi' AND value = '10.1000/crp.2018.001'",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(work_id, 3000000002);
+        // And so does its Lens article
+        let links: Vec<(i64,)> = sqlx::query_as("SELECT work_id FROM openalex_lens_ids")
+            .fetch_all(&pool)
+            .await?;
+        assert_eq!(links, [(3000000002,)]);
+
+        // Nothing is left to apply
+        let again = update_openalex(&root, &pool).await?;
+        assert_eq!(again.files, 0);
+        assert_eq!(again.works_merged + again.works_deleted, 0);
+        assert_eq!(
+            state(&pool, WORKS_PARTITION).await?.as_deref(),
+            Some("2026-06-01")
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+        Ok(())
+    }
+}
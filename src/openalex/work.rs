@@ -0,0 +1,470 @@
+//! The parts of an OpenAlex work record kept in the BibliZap database.
+
+use std::collections::BTreeMap;
+
+use biblizap_rs::identifier::{IdType, Identifier};
+use serde::{Deserialize, Serialize};
+
+/// Prefix of every OpenAlex entity ID, e.g. `https://openalex.org/W2741809807`.
+const OPENALEX_URL: &str = "https://openalex.org/";
+
+/// Parses an OpenAlex entity ID, full URL or bare, into its number.
+///
+/// `kind` is the letter of the entity type: `W` for works, `S` for sources, `A` for
+/// authors, `T` for topics.
+pub fn parse_entity_id(raw: &str, kind: char) -> Option<i64> {
+    let id = raw.strip_prefix(OPENALEX_URL).unwrap_or(raw);
+    let digits = id.strip_prefix(kind)?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// A work as found in the OpenAlex works dump, one JSON object per line.
+///
+/// Only the fields BibliZap uses are read; the others are skipped by serde.
+#[derive(Debug, Deserialize)]
+pub struct Work {
+    pub id: String,
+    pub doi: Option<String>,
+    pub title: Option<String>,
+    pub display_name: Option<String>,
+    pub publication_year: Option<i32>,
+    pub publication_date: Option<String>,
+    #[serde(default)]
+    pub ids: WorkIds,
+    pub language: Option<String>,
+    pub primary_location: Option<Location>,
+    #[serde(rename = "type")]
+    pub work_type: Option<String>,
+    pub open_access: Option<OpenAccess>,
+    #[serde(default)]
+    pub authorships: Vec<Authorship>,
+    #[serde(default)]
+    pub biblio: Biblio,
+    #[serde(default)]
+    pub is_retracted: bool,
+    pub cited_by_count: Option<i32>,
+    #[serde(default)]
+    pub referenced_works: Vec<String>,
+    /// Best match first
+    #[serde(default)]
+    pub topics: Vec<TopicRef>,
+    /// Each word of the abstract and its positions, the only form OpenAlex gives it in
+    pub abstract_inverted_index: Option<BTreeMap<String, Vec<i64>>>,
+    pub updated_date: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct WorkIds {
+    pub doi: Option<String>,
+    pub pmid: Option<String>,
+    pub pmcid: Option<String>,
+    pub mag: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Location {
+    pub source: Option<Source>,
+    pub license: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Source {
+    pub id: Option<String>,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenAccess {
+    #[serde(default)]
+    pub is_oa: bool,
+    pub oa_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Authorship {
+    pub author: Option<AuthorRef>,
+    #[serde(default)]
+    pub institutions: Vec<InstitutionRef>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorRef {
+    pub id: Option<String>,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstitutionRef {
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TopicRef {
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Biblio {
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    pub first_page: Option<String>,
+    pub last_page: Option<String>,
+}
+
+/// An author of a work, as stored in the `authorships` JSON column.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredAuthor {
+    /// OpenAlex author number, without the `A`
+    pub id: Option<i64>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub institutions: Vec<String>,
+}
+
+/// A work ready to be written: one row of `openalex_works`, plus its identifiers and
+/// the works it references.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkRow {
+    pub id: i64,
+    pub doi: Option<String>,
+    pub title: Option<String>,
+    /// Plain text rebuilt from `abstract_inverted_index`
+    pub abstract_text: Option<String>,
+    pub publication_year: Option<i32>,
+    pub publication_date: Option<String>,
+    pub work_type: Option<String>,
+    pub language: Option<String>,
+    pub source_id: Option<i64>,
+    pub journal: Option<String>,
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    pub first_page: Option<String>,
+    pub last_page: Option<String>,
+    pub authors: Vec<StoredAuthor>,
+    pub is_retracted: bool,
+    pub is_oa: bool,
+    pub oa_url: Option<String>,
+    pub license: Option<String>,
+    pub cited_by_count: i32,
+    pub updated_date: Option<String>,
+    /// `(id_type, value)` pairs, in the canonical form of `biblizap_rs::identifier`
+    pub ids: Vec<(IdType, String)>,
+    /// Numbers of the referenced works, without duplicates
+    pub references: Vec<i64>,
+    /// Numbers of the topics of the work, best match first
+    pub topic_ids: Vec<i64>,
+}
+
+impl Work {
+    /// Converts the record into a row, or returns `None` if its ID is not a work ID.
+    ///
+    /// Identifiers that do not parse are dropped, as are references to malformed IDs.
+    pub fn into_row(self) -> Option<WorkRow> {
+        let id = parse_entity_id(&self.id, 'W')?;
+
+        let doi = self
+            .ids
+            .doi
+            .as_deref()
+            .or(self.doi.as_deref())
+            .and_then(|raw| Identifier::parse_as(IdType::Doi, raw));
+        let pmid = self
+            .ids
+            .pmid
+            .as_deref()
+            .and_then(|raw| Identifier::parse_as(IdType::Pmid, raw));
+        // PMC URLs in the dump sometimes lack the `PMC` of the PMCID
+        let pmcid = self.ids.pmcid.as_deref().and_then(|raw| {
+            Identifier::parse_as(IdType::Pmcid, raw).or_else(|| {
+                let last_segment = raw.trim_end_matches('/').rsplit('/').next()?;
+                Identifier::parse_as(IdType::Pmcid, &format!("PMC{last_segment}"))
+            })
+        });
+        let mag = self.ids.mag.as_ref().and_then(|raw| match raw {
+            serde_json::Value::String(s) => Identifier::parse_as(IdType::Mag, s),
+            serde_json::Value::Number(n) => Identifier::parse_as(IdType::Mag, &n.to_string()),
+            _ => None,
+        });
+        let ids = [doi.clone(), pmid, pmcid, mag]
+            .into_iter()
+            .flatten()
+            .map(|identifier| (identifier.id_type(), identifier.value().to_string()))
+            .collect();
+
+        let mut references: Vec<i64> = self
+            .referenced_works
+            .iter()
+            .filter_map(|raw| parse_entity_id(raw, 'W'))
+            .collect();
+        references.sort_unstable();
+        references.dedup();
+
+        let mut topic_ids: Vec<i64> = Vec::new();
+        for topic in &self.topics {
+            if let Some(id) = topic.id.as_deref().and_then(|id| parse_entity_id(id, 'T'))
+                && !topic_ids.contains(&id)
+            {
+                topic_ids.push(id);
+            }
+        }
+
+        let authors = self
+            .authorships
+            .into_iter()
+            .filter_map(|authorship| {
+                let author = authorship.author?;
+                Some(StoredAuthor {
+                    id: author.id.as_deref().and_then(|id| parse_entity_id(id, 'A')),
+                    name: author.display_name?,
+                    institutions: authorship
+                        .institutions
+                        .into_iter()
+                        .filter_map(|institution| institution.display_name)
+                        .collect(),
+                })
+            })
+            .collect();
+
+        let (source, license) = match self.primary_location {
+            Some(location) => (location.source, location.license),
+            None => (None, None),
+        };
+        let (source_id, journal) = match source {
+            Some(source) => (
+                source.id.as_deref().and_then(|id| parse_entity_id(id, 'S')),
+                source.display_name,
+            ),
+            None => (None, None),
+        };
+        let (is_oa, oa_url) = match self.open_access {
+            Some(open_access) => (open_access.is_oa, open_access.oa_url),
+            None => (false, None),
+        };
+
+        Some(WorkRow {
+            id,
+            doi: doi.map(|doi| doi.value().to_string()),
+            title: self.title.or(self.display_name),
+            abstract_text: self
+                .abstract_inverted_index
+                .as_ref()
+                .and_then(rebuild_abstract),
+            publication_year: self.publication_year,
+            publication_date: self.publication_date,
+            work_type: self.work_type,
+            language: self.language,
+            source_id,
+            journal,
+            volume: self.biblio.volume,
+            issue: self.biblio.issue,
+            first_page: self.biblio.first_page,
+            last_page: self.biblio.last_page,
+            authors,
+            is_retracted: self.is_retracted,
+            is_oa,
+            oa_url,
+            license,
+            cited_by_count: self.cited_by_count.unwrap_or_default(),
+            updated_date: self.updated_date,
+            ids,
+            references,
+            topic_ids,
+        })
+    }
+}
+
+/// Rebuilds the plain text of an abstract from its inverted index.
+///
+/// Positions missing from the index are skipped, and a position claimed by several
+/// words keeps the first of them in alphabetical order. Some abstracts were deposited as
+/// HTML, so tags are removed and character entities decoded. Returns `None` when no word
+/// is left.
+pub fn rebuild_abstract(index: &BTreeMap<String, Vec<i64>>) -> Option<String> {
+    let mut words: BTreeMap<i64, &str> = BTreeMap::new();
+    for (word, positions) in index {
+        for &position in positions.iter().filter(|&&position| position >= 0) {
+            words.entry(position).or_insert(word);
+        }
+    }
+
+    let text = words.into_values().collect::<Vec<_>>().join(" ");
+    let text = decode_html_entities(&strip_html_tags(&text));
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Removes HTML and JATS tags, such as `<p>`, `</jats:title>` or `<sup>`. A `<` that
+/// does not open a tag, as in `p<0.05`, is kept.
+fn strip_html_tags(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        stripped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let name = rest[1..].strip_prefix('/').unwrap_or(&rest[1..]);
+        let tag_len = rest
+            .find('>')
+            .filter(|&end| {
+                name.starts_with(|c: char| c.is_ascii_alphabetic()) && !rest[1..end].contains('<')
+            })
+            .map(|end| end + 1);
+        match tag_len {
+            Some(tag_len) => rest = &rest[tag_len..],
+            None => {
+                stripped.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Decodes numeric character references and the named entities found in abstracts. Any
+/// other `&` is kept as is.
+fn decode_html_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..1 + end])?, end + 2)));
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "plusmn" => '±',
+        "times" => '×',
+        "deg" => '°',
+        "micro" => 'µ',
+        "le" => '≤',
+        "ge" => '≥',
+        _ => {
+            let code = name.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            return char::from_u32(code);
+        }
+    };
+    Some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE_WORKS: &str = include_str!("fixtures/works.jsonl");
+
+    #[test]
+    fn parse_entity_ids() {
+        assert_eq!(
+            parse_entity_id("https://openalex.org/W2741809807", 'W'),
+            Some(2741809807)
+        );
+        assert_eq!(parse_entity_id("S1983995261", 'S'), Some(1983995261));
+        assert_eq!(
+            parse_entity_id("https://openalex.org/A5023888391", 'W'),
+            None
+        );
+        assert_eq!(parse_entity_id("https://openalex.org/W", 'W'), None);
+        assert_eq!(parse_entity_id("W12x", 'W'), None);
+    }
+
+    #[test]
+    fn work_into_row() {
+        let line = FIXTURE_WORKS.lines().next().unwrap();
+        let work: Work = serde_json::from_str(line).unwrap();
+        let row = work.into_row().unwrap();
+
+        assert_eq!(row.id, 3000000001);
+        assert_eq!(row.doi.as_deref(), Some("10.1371/journal.pone.0229876"));
+        assert_eq!(
+            row.ids,
+            [
+                (IdType::Doi, "10.1371/journal.pone.0229876".to_string()),
+                (IdType::Pmid, "31000001".to_string()),
+                (IdType::Pmcid, "PMC7050001".to_string()),
+                (IdType::Mag, "3008000001".to_string()),
+            ]
+        );
+        assert_eq!(row.references, [3000000002, 3000000003]);
+        assert_eq!(row.topic_ids, [10003]);
+        assert_eq!(row.source_id, Some(202381698));
+        assert_eq!(row.journal.as_deref(), Some("PLoS ONE"));
+        assert_eq!(row.authors.len(), 2);
+        assert_eq!(row.authors[0].id, Some(5000000001));
+        assert_eq!(
+            row.authors[0].institutions,
+            ["Hôpital Européen Georges Pompidou"]
+        );
+        assert!(row.is_oa);
+        assert_eq!(row.first_page.as_deref(), Some("e0229876"));
+        assert_eq!(
+            row.abstract_text.as_deref(),
+            Some("Early identification of severe COVID-19 is needed.")
+        );
+    }
+
+    #[test]
+    fn rebuild_abstracts() {
+        let rebuild = |json: &str| rebuild_abstract(&serde_json::from_str(json).unwrap());
+
+        // Repeated words, and position 3 missing
+        assert_eq!(
+            rebuild(r#"{"IL-6": [0, 4], "rises": [1], "when": [2], "falls.": [5]}"#).as_deref(),
+            Some("IL-6 rises when IL-6 falls.")
+        );
+        // Entities, tags and a `<` that is not a tag
+        assert_eq!(
+            rebuild(
+                r#"{"<jats:p>Mortality": [0], "fell": [1], "(p&lt;0.01,": [2], "p<0.05)": [3], "with": [4], "&#946;-": [5], "&amp;": [6], "&#x3B1;&#8201;blockers</jats:p>": [7], "&nbsp;": [8]}"#
+            )
+            .as_deref(),
+            Some("Mortality fell (p<0.01, p<0.05) with β- & α blockers")
+        );
+        // Unknown entities and stray ampersands are kept
+        assert_eq!(
+            rebuild(r#"{"R&D": [0], "&unknown;": [1]}"#).as_deref(),
+            Some("R&D &unknown;")
+        );
+        // A position claimed twice keeps one word
+        assert_eq!(
+            rebuild(r#"{"b": [0], "a": [0], "c": [1]}"#).as_deref(),
+            Some("a c")
+        );
+        assert_eq!(rebuild(r#"{"<p></p>": [0]}"#), None);
+        assert_eq!(rebuild("{}"), None);
+    }
+}
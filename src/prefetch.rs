@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use biblizap_rs::{SearchFor, identifier::Identifier, lens::prefetch::PrefetchProgress};
+use serde::{Deserialize, Serialize};
+
+use super::{AppConfig, Error};
+use crate::common::*;
+use crate::corpus;
+
+/// Live searches are clamped to depth 2, so deeper prefetches would only burn quota.
+pub const MAX_PREFETCH_DEPTH: u8 = 2;
+
+/// Number of finished jobs kept for the admin endpoint; older ones are forgotten.
+const MAX_FINISHED_PREFETCH_JOBS: usize = 20;
+
+/// Parameters for a prefetch job, from the admin endpoint or the CLI.
+#[derive(Debug, Deserialize)]
+pub struct PrefetchParameters {
+    #[serde(default)]
+    pub input_id_list: Vec<String>,
+    /// Hex-encoded hash of a corpus previously uploaded to `/api/corpus/upload`
+    pub corpus_hash: Option<String>,
+    pub depth: u8,
+    pub search_for: SearchFor,
+    #[serde(default = "default_with_metadata")]
+    pub with_metadata: bool,
+}
+
+fn default_with_metadata() -> bool {
+    true
+}
+
+/// State of a prefetch job started through the admin endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrefetchJob {
+    pub id: u64,
+    pub seed_count: usize,
+    pub progress: PrefetchProgress,
+    pub error: Option<String>,
+    pub started_ms: i64,
+    pub finished_ms: Option<i64>,
+}
+
+/// Prefetch jobs started since the server came up, by job id.
+///
+/// Jobs only live in memory: a restart forgets them, and only the finished jobs
+/// are dropped, oldest first, past [`MAX_FINISHED_PREFETCH_JOBS`].
+/// What a job fetched is persisted by the cache, so running it again resumes it.
+#[derive(Debug, Default)]
+pub struct PrefetchJobs(Mutex<BTreeMap<u64, PrefetchJob>>);
+
+impl PrefetchJobs {
+    fn start(&self, seed_count: usize) -> u64 {
+        let mut jobs = self.0.lock().unwrap();
+        let id = jobs.keys().next_back().map_or(1, |last| last + 1);
+
+        let finished: Vec<u64> = jobs
+            .values()
+            .filter(|job| job.finished_ms.is_some())
+            .map(|job| job.id)
+            .collect();
+        let evicted = finished
+            .len()
+            .saturating_sub(MAX_FINISHED_PREFETCH_JOBS - 1);
+        for id in &finished[..evicted] {
+            jobs.remove(id);
+        }
+
+        jobs.insert(
+            id,
+            PrefetchJob {
+                id,
+                seed_count,
+                progress: PrefetchProgress::default(),
+                error: None,
+                started_ms: epoch_ms(),
+                finished_ms: None,
+            },
+        );
+        id
+    }
+
+    fn update(&self, id: u64, update: impl FnOnce(&mut PrefetchJob)) {
+        if let Some(job) = self.0.lock().unwrap().get_mut(&id) {
+            update(job);
+        }
+    }
+
+    fn get(&self, id: u64) -> Option<PrefetchJob> {
+        self.0.lock().unwrap().get(&id).cloned()
+    }
+
+    fn list(&self) -> Vec<PrefetchJob> {
+        self.0.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// Validates the depth of a prefetch job and collects its seed identifiers.
+///
+/// Explicit identifiers and the content of the referenced corpus are merged.
+/// Identifiers are normalised, and unlike live searches, Lens IDs are accepted alongside
+/// DOIs, PMIDs and the other external IDs.
+pub async fn resolve_seeds(
+    parameters: &PrefetchParameters,
+    pool: &sqlx::PgPool,
+) -> Result<Vec<String>, Error> {
+    if !(1..=MAX_PREFETCH_DEPTH).contains(&parameters.depth) {
+        return Err(Error::InvalidDepth(parameters.depth));
+    }
+
+    let mut seeds = Vec::new();
+
+    for id in &parameters.input_id_list {
+        let identifier =
+            Identifier::parse(id).ok_or_else(|| Error::InvalidIdFormat(id.trim().to_string()))?;
+        seeds.push(identifier.key());
+    }
+
+    if let Some(hash_hex) = &parameters.corpus_hash {
+        let hash = corpus::parse_hash_hex(hash_hex)
+            .ok_or_else(|| Error::InvalidCorpusHash(hash_hex.clone()))?;
+        seeds.extend(corpus::load_corpus_ids(pool, &hash).await?);
+    }
+
+    seeds.sort();
+    seeds.dedup();
+
+    if seeds.is_empty() {
+        return Err(Error::NoValidIds);
+    }
+
+    Ok(seeds)
+}
+
+/// One-line summary of a progress report, for logs and the CLI.
+pub fn describe_progress(progress: &PrefetchProgress) -> String {
+    use biblizap_rs::lens::prefetch::PrefetchPhase;
+
+    match progress.phase {
+        PrefetchPhase::Edges => format!(
+            "depth {}/{}: expanded {}/{} articles, {} discovered",
+            progress.depth,
+            progress.max_depth,
+            progress.expanded,
+            progress.to_expand,
+            progress.discovered
+        ),
+        PrefetchPhase::Metadata => format!(
+            "metadata: {}/{} articles",
+            progress.completed, progress.discovered
+        ),
+        PrefetchPhase::Done => format!(
+            "done: {} articles discovered, {} with metadata",
+            progress.discovered, progress.completed
+        ),
+    }
+}
+
+/// Actix-web handler for `POST /api/admin/prefetch`.
+/// Validates the parameters, starts the prefetch in the background and returns the job.
+pub async fn start_prefetch(
+    req: HttpRequest,
+    req_body: String,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    if let Some(response) = crate::admin::authorize(&req, &config) {
+        return response;
+    }
+
+    if let Err(e) = config.lens_api_key() {
+        return HttpResponse::ServiceUnavailable().body(format!("{e}"));
+    }
+
+    let parameters = match serde_json::from_str::<PrefetchParameters>(&req_body) {
+        Ok(parameters) => parameters,
+        Err(e) => return HttpResponse::BadRequest().body(format!("{e}")),
+    };
+
+    let seeds = match resolve_seeds(&parameters, &config.database_pool).await {
+        Ok(seeds) => seeds,
+        Err(Error::CorpusError(corpus::CorpusError::DatabaseError(sqlx::Error::RowNotFound))) => {
+            return HttpResponse::NotFound().body("Corpus not found");
+        }
+        Err(
+            error @ (Error::InvalidDepth(_)
+            | Error::InvalidIdFormat(_)
+            | Error::InvalidCorpusHash(_)
+            | Error::NoValidIds),
+        ) => return HttpResponse::BadRequest().body(format!("{error}")),
+        Err(error) => return HttpResponse::InternalServerError().body(format!("{error}")),
+    };
+
+    let job_id = config.prefetch_jobs.start(seeds.len());
+    log::info!(
+        "Starting prefetch job {job_id} for {} seeds: {:?}",
+        seeds.len(),
+        parameters
+    );
+
+    let job_config = config.clone();
+    actix_web::rt::spawn(async move {
+        let result = biblizap_rs::prefetch(
+            &seeds,
+            parameters.depth,
+            &parameters.search_for,
+            parameters.with_metadata,
+            job_config.lens_api_key.as_deref().unwrap_or_default(),
+            Some(&job_config.lens_client),
+            &job_config.cache_backend,
+            |progress| {
+                log::debug!("Prefetch job {job_id}: {}", describe_progress(progress));
+                job_config
+                    .prefetch_jobs
+                    .update(job_id, |job| job.progress = progress.clone());
+            },
+        )
+        .await;
+
+        if let Err(e) = &result {
+            log::error!("Prefetch job {job_id} failed: {e}");
+        } else {
+            log::info!("Prefetch job {job_id} completed");
+        }
+
+        job_config.prefetch_jobs.update(job_id, |job| {
+            job.error = result.err().map(|e| e.to_string());
+            job.finished_ms = Some(epoch_ms());
+        });
+    });
+
+    match config.prefetch_jobs.get(job_id) {
+        Some(job) => HttpResponse::Accepted().json(job),
+        None => HttpResponse::InternalServerError().body("Prefetch job vanished"),
+    }
+}
+
+/// Actix-web handler for `GET /api/admin/prefetch`, listing all jobs.
+pub async fn list_prefetch_jobs(req: HttpRequest, config: web::Data<AppConfig>) -> impl Responder {
+    if let Some(response) = crate::admin::authorize(&req, &config) {
+        return response;
+    }
+
+    HttpResponse::Ok().json(config.prefetch_jobs.list())
+}
+
+/// Actix-web handler for `GET /api/admin/prefetch/{job_id}`.
+pub async fn prefetch_status(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
+    if let Some(response) = crate::admin::authorize(&req, &config) {
+        return response;
+    }
+
+    match config.prefetch_jobs.get(path.into_inner()) {
+        Some(job) => HttpResponse::Ok().json(job),
+        None => HttpResponse::NotFound().body("Prefetch job not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_ids_increase() {
+        let jobs = PrefetchJobs::default();
+        assert_eq!(jobs.start(3), 1);
+        assert_eq!(jobs.start(1), 2);
+
+        jobs.update(1, |job| job.error = Some("boom".to_string()));
+        assert_eq!(jobs.get(1).unwrap().error.as_deref(), Some("boom"));
+        assert_eq!(jobs.get(2).unwrap().seed_count, 1);
+        assert!(jobs.get(3).is_none());
+        assert_eq!(jobs.list().len(), 2);
+    }
+
+    #[test]
+    fn only_recent_finished_jobs_are_kept() {
+        let jobs = PrefetchJobs::default();
+        let running = jobs.start(1);
+        for _ in 0..MAX_FINISHED_PREFETCH_JOBS + 5 {
+            let id = jobs.start(1);
+            jobs.update(id, |job| job.finished_ms = Some(epoch_ms()));
+        }
+        let last = jobs.start(1);
+
+        let list = jobs.list();
+        assert_eq!(list.len(), MAX_FINISHED_PREFETCH_JOBS + 1);
+        assert!(jobs.get(running).is_some());
+        assert!(jobs.get(2).is_none());
+        assert_eq!(list.last().unwrap().id, last);
+        assert_eq!(last, MAX_FINISHED_PREFETCH_JOBS as u64 + 7);
+    }
+
+    #[tokio::test]
+    async fn out_of_range_depths_are_rejected() {
+        let pool = sqlx::PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        for depth in [0, MAX_PREFETCH_DEPTH + 1] {
+            let parameters = PrefetchParameters {
+                input_id_list: vec!["10.1234/abc".to_string()],
+                corpus_hash: None,
+                depth,
+                search_for: SearchFor::Both,
+                with_metadata: true,
+            };
+            assert!(matches!(
+                resolve_seeds(&parameters, &pool).await,
+                Err(Error::InvalidDepth(d)) if d == depth
+            ));
+        }
+    }
+
+    #[test]
+    fn parameters_default_to_metadata() {
+        let parameters: PrefetchParameters =
+            serde_json::from_str(r#"{"depth": 2, "search_for": "Both", "corpus_hash": "ab"}"#)
+                .unwrap();
+        assert!(parameters.with_metadata);
+        assert!(parameters.input_id_list.is_empty());
+    }
+}
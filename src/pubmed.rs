@@ -1,16 +1,25 @@
+use std::collections::HashMap;
+
 use super::AppConfig;
 use actix_web::{HttpResponse, Responder, web};
+use biblizap_rs::pubmed;
 use serde::{Deserialize, Serialize};
 
+/// Maximum number of results of one search, enough to seed a corpus.
+const MAX_RESULTS: usize = 500;
+
 /// Parameters received for a PubMed keyword search.
 #[derive(Debug, Deserialize)]
 struct PubmedSearchParams {
     query: String,
     max_results: Option<usize>,
+    /// Return the PMIDs only, without fetching the records
+    #[serde(default)]
+    pmids_only: bool,
 }
 
 /// A single article result from a PubMed keyword search.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 struct PubmedSearchResult {
     pmid: String,
     title: Option<String>,
@@ -20,124 +29,86 @@ struct PubmedSearchResult {
     doi: Option<String>,
 }
 
+impl From<pubmed::Article> for PubmedSearchResult {
+    fn from(article: pubmed::Article) -> Self {
+        PubmedSearchResult {
+            year: article.year_published().map(|year| year.to_string()),
+            authors: article.authors.map(|authors| authors.join(", ")),
+            pmid: article.pmid,
+            title: article.title,
+            journal: article.journal,
+            doi: article.doi,
+        }
+    }
+}
+
 /// Actix-web handler for the `/api/pubmed_search` endpoint.
-/// Receives a keyword query, searches PubMed via ESearch+ESummary,
-/// and returns matching articles for the user to select from.
+/// Receives a keyword query, searches PubMed via ESearch and completes the records
+/// via EFetch, and returns matching articles for the user to select from.
+/// Searches and records go through the shared PubMed client and the cache.
 pub async fn pubmed_search(req_body: String, config: web::Data<AppConfig>) -> impl Responder {
     let params: PubmedSearchParams = match serde_json::from_str(&req_body) {
         Ok(p) => p,
         Err(e) => return HttpResponse::BadRequest().body(format!("Invalid request: {e}")),
     };
-
-    let max_results = params.max_results.unwrap_or(20).clamp(1, 100);
-    let query_encoded = urlencoding::encode(&params.query);
-
-    // Build ESearch URL
-    let mut esearch_url = format!(
-        "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/esearch.fcgi?db=pubmed&retmode=json&term={}&retmax={}&sort=relevance",
-        query_encoded, max_results
-    );
-    if let Some(ref api_key) = config.pubmed_api_key {
-        esearch_url.push_str(&format!("&api_key={}", api_key));
+    if params.query.trim().is_empty() {
+        return HttpResponse::BadRequest().body("Empty query");
     }
 
+    let max_results = params.max_results.unwrap_or(20).clamp(1, MAX_RESULTS);
+
     log::info!("PubMed ESearch for: {}", params.query);
 
-    // Call ESearch to get PMIDs
-    let esearch_response = match reqwest::get(&esearch_url).await {
-        Ok(r) => r,
+    let pmids = match pubmed::search(
+        &params.query,
+        max_results,
+        &config.pubmed_client,
+        Some(&config.cache_backend),
+    )
+    .await
+    {
+        Ok(pmids) => pmids,
         Err(e) => {
             return HttpResponse::InternalServerError()
-                .body(format!("ESearch request failed: {e}"));
-        }
-    };
-    let esearch_text = match esearch_response.text().await {
-        Ok(t) => t,
-        Err(e) => {
-            return HttpResponse::InternalServerError().body(format!("ESearch read failed: {e}"));
-        }
-    };
-    let esearch_json: serde_json::Value = match serde_json::from_str(&esearch_text) {
-        Ok(v) => v,
-        Err(e) => {
-            return HttpResponse::InternalServerError().body(format!("ESearch parse failed: {e}"));
+                .body(format!("PubMed search failed: {e:#}"));
         }
     };
 
-    let pmids: Vec<String> = match esearch_json["esearchresult"]["idlist"].as_array() {
-        Some(arr) => arr
+    if params.pmids_only {
+        let results: Vec<PubmedSearchResult> = pmids
             .iter()
-            .filter_map(|v| v.as_str().map(String::from))
-            .collect(),
-        None => return HttpResponse::Ok().json(Vec::<PubmedSearchResult>::new()),
-    };
-
-    if pmids.is_empty() {
-        return HttpResponse::Ok().json(Vec::<PubmedSearchResult>::new());
-    }
-
-    // Build ESummary URL
-    let pmid_list = pmids.join(",");
-    let mut esummary_url = format!(
-        "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/esummary.fcgi?db=pubmed&retmode=json&id={}",
-        pmid_list
-    );
-    if let Some(ref api_key) = config.pubmed_api_key {
-        esummary_url.push_str(&format!("&api_key={}", api_key));
+            .map(|pmid| PubmedSearchResult {
+                pmid: pmid.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        return HttpResponse::Ok().json(results);
     }
 
-    // Call ESummary to get article metadata
-    let esummary_response = match reqwest::get(&esummary_url).await {
-        Ok(r) => r,
+    let articles = match pubmed::Article::complete_articles(
+        &pmids,
+        Some(&config.pubmed_client),
+        Some(&config.cache_backend),
+    )
+    .await
+    {
+        Ok(articles) => articles,
         Err(e) => {
             return HttpResponse::InternalServerError()
-                .body(format!("ESummary request failed: {e}"));
-        }
-    };
-    let esummary_text = match esummary_response.text().await {
-        Ok(t) => t,
-        Err(e) => {
-            return HttpResponse::InternalServerError().body(format!("ESummary read failed: {e}"));
+                .body(format!("PubMed records request failed: {e:#}"));
         }
     };
-    let esummary_json: serde_json::Value = match serde_json::from_str(&esummary_text) {
-        Ok(v) => v,
-        Err(e) => {
-            return HttpResponse::InternalServerError().body(format!("ESummary parse failed: {e}"));
-        }
-    };
-
-    // Parse results preserving ESearch order
-    let result_obj = &esummary_json["result"];
-    let mut results: Vec<PubmedSearchResult> = Vec::new();
-    for pmid in &pmids {
-        if let Some(article) = result_obj.get(pmid) {
-            let authors = article["authors"].as_array().map(|authors| {
-                authors
-                    .iter()
-                    .filter_map(|a| a["name"].as_str())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            });
 
-            let doi = article["articleids"].as_array().and_then(|ids| {
-                ids.iter()
-                    .find(|id| id["idtype"].as_str() == Some("doi"))
-                    .and_then(|id| id["value"].as_str().map(String::from))
-            });
-
-            results.push(PubmedSearchResult {
-                pmid: pmid.clone(),
-                title: article["title"].as_str().map(String::from),
-                authors,
-                journal: article["fulljournalname"].as_str().map(String::from),
-                year: article["pubdate"]
-                    .as_str()
-                    .map(|d| d.chars().take(4).collect()),
-                doi,
-            });
-        }
-    }
+    // Records come back in no particular order, results keep the ESearch order
+    let mut articles: HashMap<String, pubmed::Article> = articles
+        .into_iter()
+        .map(|article| (article.pmid.clone(), article))
+        .collect();
+    let results: Vec<PubmedSearchResult> = pmids
+        .iter()
+        .filter_map(|pmid| articles.remove(&pmid.to_string()))
+        .map(PubmedSearchResult::from)
+        .collect();
 
     log::info!("PubMed search returned {} results", results.len());
     HttpResponse::Ok().json(results)
@@ -0,0 +1,63 @@
+use super::{AppConfig, Error};
+use crate::snowball::MAX_IDS;
+use actix_web::{HttpResponse, Responder, web};
+use biblizap_rs::IdResolution;
+use serde::Deserialize;
+
+/// Parameters received from the frontend to check seed identifiers.
+#[derive(Debug, Deserialize)]
+struct ResolveParameters {
+    input_id_list: Vec<String>,
+}
+
+/// Resolves every identifier of the request, invalid ones included.
+/// Only the length of the list is validated, since the point is to report what is wrong
+/// with each identifier.
+async fn handle_request(req_body: &str, config: &AppConfig) -> Result<Vec<IdResolution>, Error> {
+    let parameters = serde_json::from_str::<ResolveParameters>(req_body)?;
+    log::info!("Received resolve request: {:?}", parameters);
+
+    if parameters.input_id_list.len() > MAX_IDS {
+        return Err(Error::TooManyIds(parameters.input_id_list.len()));
+    }
+
+    if parameters.input_id_list.is_empty() {
+        return Err(Error::NoValidIds);
+    }
+
+    let raw_ids: Vec<&str> = parameters
+        .input_id_list
+        .iter()
+        .map(String::as_str)
+        .collect();
+
+    Ok(biblizap_rs::resolve_ids(
+        &raw_ids,
+        config.lens_api_key()?,
+        Some(&config.lens_client),
+        Some(&config.cache_backend),
+    )
+    .await?)
+}
+
+/// Actix-web handler for the `/api/resolve` endpoint.
+/// Returns one resolution per input identifier, in order, with the matching Lens ID and
+/// article or the reason it could not be resolved.
+pub async fn resolve_request(req_body: String, config: web::Data<AppConfig>) -> impl Responder {
+    let resolutions = handle_request(&req_body, &config).await;
+
+    match resolutions {
+        Ok(resolutions) => HttpResponse::Ok().json(resolutions),
+        Err(error) => {
+            log::error!("Resolve request failed: {error:?}");
+
+            match error {
+                Error::JsonError(_) | Error::TooManyIds(_) | Error::NoValidIds => {
+                    HttpResponse::BadRequest().body(format!("{error}"))
+                }
+                Error::NoLensApiKey => HttpResponse::ServiceUnavailable().body(format!("{error}")),
+                _ => HttpResponse::InternalServerError().body(format!("{error}")),
+            }
+        }
+    }
+}
@@ -3,11 +3,32 @@ use crate::tracking;
 
 use super::{AppConfig, Error};
 use actix_web::{HttpResponse, Responder, web};
-use biblizap_rs::{SearchFor, lens::cache::postgres::PostgresBackend};
-use serde::Deserialize;
+use biblizap_rs::identifier::IdType;
+use biblizap_rs::openalex::OpenAlexError;
+use biblizap_rs::{SearchFor, Snowball};
+use serde::{Deserialize, Serialize};
 
 pub const MAX_IDS: usize = 100;
 
+/// Set to `true` on `/api` responses of which some citations were rebuilt from the
+/// cited-by index, see [`Snowball::incomplete_citations`].
+pub const INCOMPLETE_CITATIONS_HEADER: &str = "X-Biblizap-Incomplete-Citations";
+
+/// Where the citation network is taken from: Lens.org, PubMed links (PMID seeds only) or the
+/// OpenAlex snapshot imported on the server. Defaults to the source configured on the server.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum, utoipa::ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Lens,
+    /// PubMed links, for deployments without a Lens.org subscription (PMIDs only)
+    Pubmed,
+    /// The OpenAlex snapshot imported by `biblizap-openalex`, offline
+    #[value(name = "openalex")]
+    OpenAlex,
+}
+
 /// Parameters received from the frontend for the snowball search.
 #[derive(Debug, Deserialize)]
 struct SnowballParameters {
@@ -15,68 +36,161 @@ struct SnowballParameters {
     depth: u8,
     input_id_list: Vec<String>,
     search_for: SearchFor,
+    /// Drop retracted articles from the results
+    #[serde(default)]
+    exclude_retracted: bool,
+    /// The default source of the server when missing
+    #[serde(default)]
+    source: Option<Source>,
 }
 
-/// Handles the core logic of performing the snowball search using biblizap-rs.
-/// Takes the request body (JSON string) and the Lens API key.
-/// Returns a JSON string representing the search results or an error.
-async fn handle_request(
-    req_body: &str,
-    lens_api_key: &str,
-    cache_backend: &PostgresBackend,
-) -> Result<String, Error> {
-    let parameters = serde_json::from_str::<SnowballParameters>(req_body)?;
-    log::info!("Received request: {:?}", parameters);
+/// A snowball search with validated bounds, as run by the `/api` and `/api/v1/snowball`
+/// endpoints. The identifiers are checked by [`run_snowball`].
+#[derive(Debug)]
+pub struct SnowballSearch {
+    pub ids: Vec<String>,
+    /// 1 or 2
+    pub depth: u8,
+    pub max_results: usize,
+    pub search_for: SearchFor,
+    pub exclude_retracted: bool,
+    /// The default source of the server when `None`
+    pub source: Option<Source>,
+}
+
+impl From<SnowballParameters> for SnowballSearch {
+    /// The frontend parameters are read leniently: the depth is clamped and an output size
+    /// that is not a number means no limit.
+    fn from(parameters: SnowballParameters) -> Self {
+        SnowballSearch {
+            ids: parameters.input_id_list,
+            depth: parameters.depth.clamp(1, 2),
+            max_results: parameters
+                .output_max_size
+                .parse::<usize>()
+                .unwrap_or(usize::MAX)
+                .clamp(1, usize::MAX),
+            search_for: parameters.search_for,
+            exclude_retracted: parameters.exclude_retracted,
+            source: parameters.source,
+        }
+    }
+}
 
-    // Server-side validation: check max 7 IDs
-    if parameters.input_id_list.len() > MAX_IDS {
-        return Err(Error::TooManyIds(parameters.input_id_list.len()));
+/// Performs a snowball search using biblizap-rs, with the clients, keys and cache of the
+/// source picked by the search or the server.
+/// Returns the source used and the articles found, best scored first.
+pub async fn run_snowball(
+    search: &SnowballSearch,
+    config: &AppConfig,
+) -> Result<(Source, Snowball), Error> {
+    // Server-side validation: check max number of IDs
+    if search.ids.len() > MAX_IDS {
+        return Err(Error::TooManyIds(search.ids.len()));
     }
 
     // Server-side validation: ensure at least one ID
-    if parameters.input_id_list.is_empty() {
+    if search.ids.is_empty() {
         return Err(Error::NoValidIds);
     }
 
-    // Server-side validation: check each ID is valid DOI or PMID
-    for id in &parameters.input_id_list {
-        if !is_valid_id(id) {
-            return Err(Error::InvalidIdFormat(id.clone()));
+    // Server-side validation: check each ID is a supported identifier, and normalise it
+    let seeds = search
+        .ids
+        .iter()
+        .map(|id| {
+            parse_seed_id(id)
+                .map(|identifier| identifier.key())
+                .ok_or_else(|| Error::InvalidIdFormat(id.clone()))
+        })
+        .collect::<Result<Vec<String>, Error>>()?;
+
+    let source = search.source.unwrap_or(config.default_source);
+
+    let snowball = match source {
+        Source::Lens => {
+            biblizap_rs::snowball(
+                &seeds,
+                search.depth,
+                search.max_results,
+                &search.search_for,
+                search.exclude_retracted,
+                config.lens_api_key()?,
+                Some(&config.lens_client),
+                Some(&config.cache_backend),
+            )
+            .await?
         }
-    }
-    let snowball = biblizap_rs::snowball(
-        &parameters.input_id_list,
-        parameters.depth.clamp(1, 2),
-        parameters
-            .output_max_size
-            .parse::<usize>()
-            .unwrap_or(usize::MAX)
-            .clamp(1, usize::MAX),
-        &parameters.search_for,
-        lens_api_key,
-        None,
-        Some(cache_backend),
-    )
-    .await?;
-
-    let json_str = serde_json::to_string(&snowball)?;
-    log::debug!(
-        "Sending {} articles, {} characters response",
-        snowball.len(),
-        json_str.len()
-    );
-
-    Ok(json_str)
+        Source::Pubmed => {
+            if let Some(id) = search.ids.iter().find(|id| {
+                parse_seed_id(id).map(|identifier| identifier.id_type()) != Some(IdType::Pmid)
+            }) {
+                return Err(Error::NotAPmid(id.clone()));
+            }
+
+            let articles = biblizap_rs::pubmed_snowball(
+                &seeds,
+                search.depth,
+                search.max_results,
+                &search.search_for,
+                search.exclude_retracted,
+                Some(&config.pubmed_client),
+                Some(&config.cache_backend),
+            )
+            .await?;
+
+            Snowball {
+                articles,
+                incomplete_citations: false,
+            }
+        }
+        Source::OpenAlex => {
+            let database = config
+                .openalex_database
+                .as_ref()
+                .ok_or(Error::NoOpenAlexDatabase)?;
+
+            let articles = biblizap_rs::openalex_snowball(
+                &seeds,
+                search.depth,
+                search.max_results,
+                &search.search_for,
+                search.exclude_retracted,
+                database,
+            )
+            .await?;
+
+            Snowball {
+                articles,
+                incomplete_citations: false,
+            }
+        }
+    };
+
+    log::debug!("Found {} articles from {source:?}", snowball.articles.len());
+
+    Ok((source, snowball))
+}
+
+/// Parses the `/api` request body (JSON string) and runs the snowball search.
+async fn handle_request(req_body: &str, config: &AppConfig) -> Result<Snowball, Error> {
+    let parameters = serde_json::from_str::<SnowballParameters>(req_body)?;
+    log::info!("Received request: {:?}", parameters);
+
+    let (_, snowball) = run_snowball(&parameters.into(), config).await?;
+    Ok(snowball)
 }
 
 /// Actix-web handler for the `/api` endpoint.
 /// Receives the request body, extracts parameters, performs the snowball search,
-/// and returns the results as JSON or an error response.
+/// and returns the results as JSON or an error response. Results built from incomplete
+/// citations are flagged by the [`INCOMPLETE_CITATIONS_HEADER`].
+/// Kept for the frontend and existing scripts, `/api/v1/snowball` checks its parameters
+/// strictly instead.
 pub async fn snowball_request(req_body: String, config: web::Data<AppConfig>) -> impl Responder {
     let request_started_ms = epoch_ms();
     let request_inputs = serde_json::from_str::<serde_json::Value>(&req_body).ok();
-    let snowball: Result<String, Error> =
-        handle_request(&req_body, &config.lens_api_key, &config.cache_backend).await;
+    let snowball = handle_request(&req_body, &config).await;
     let request_completed_ms = epoch_ms();
 
     let pool = config.database_pool.clone();
@@ -85,22 +199,27 @@ pub async fn snowball_request(req_body: String, config: web::Data<AppConfig>) ->
         Ok(snowball) => {
             log::info!("Request completed successfully");
 
-            let article_count = snowball.matches("\"doi\":").count();
             tracking::log_search_success(
-                article_count,
+                "/api",
+                snowball.articles.len(),
                 request_started_ms,
                 request_completed_ms,
                 request_inputs.clone(),
                 pool,
             );
 
-            HttpResponse::Ok().body(snowball)
+            let mut response = HttpResponse::Ok();
+            if snowball.incomplete_citations {
+                response.insert_header((INCOMPLETE_CITATIONS_HEADER, "true"));
+            }
+            response.json(snowball.articles)
         }
         Err(error) => {
             log::error!("Request failed: {error:?}");
 
             let error_msg = error.to_string();
             tracking::log_search_error(
+                "/api",
                 error_msg,
                 request_started_ms,
                 request_completed_ms,
@@ -108,13 +227,53 @@ pub async fn snowball_request(req_body: String, config: web::Data<AppConfig>) ->
                 pool,
             );
 
-            // Return 400 Bad Request for validation errors, 500 for others
+            // Return 400 Bad Request for validation errors, 503 for a source the server is
+            // not configured for, 500 for others
             match error {
-                Error::InvalidIdFormat(_) | Error::TooManyIds(_) | Error::NoValidIds => {
+                Error::InvalidIdFormat(_)
+                | Error::TooManyIds(_)
+                | Error::NoValidIds
+                | Error::NotAPmid(_)
+                | Error::Biblizap(biblizap_rs::Error::OpenAlexError(OpenAlexError::NoSeedFound)) => {
                     HttpResponse::BadRequest().body(format!("{error}"))
                 }
+                Error::NoLensApiKey | Error::NoOpenAlexDatabase => {
+                    HttpResponse::ServiceUnavailable().body(format!("{error}"))
+                }
                 _ => HttpResponse::InternalServerError().body(format!("{error}")),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(body: &str) -> SnowballSearch {
+        serde_json::from_str::<SnowballParameters>(body)
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn legacy_parameters_are_lenient() {
+        let parsed = search(
+            r#"{"output_max_size": "50", "depth": 2, "input_id_list": ["32109876"], "search_for": "Both"}"#,
+        );
+        assert_eq!(parsed.max_results, 50);
+        assert_eq!(parsed.depth, 2);
+
+        let lenient = search(
+            r#"{"output_max_size": "all", "depth": 5, "input_id_list": ["32109876"], "search_for": "Both"}"#,
+        );
+        assert_eq!(lenient.max_results, usize::MAX);
+        assert_eq!(lenient.depth, 2);
+
+        let zero = search(
+            r#"{"output_max_size": "0", "depth": 0, "input_id_list": ["32109876"], "search_for": "Both"}"#,
+        );
+        assert_eq!(zero.max_results, 1);
+        assert_eq!(zero.depth, 1);
+    }
+}
@@ -2,6 +2,7 @@ use serde_json::Value;
 
 /// Logs a successful search event asynchronously.
 pub fn log_search_success(
+    endpoint: &'static str,
     article_count: usize,
     request_started_ms: i64,
     request_completed_ms: i64,
@@ -26,7 +27,7 @@ pub fn log_search_success(
             VALUES ($1, $2, $3, $4, $5)
             "#,
             "search_success",
-            "/api",
+            endpoint,
             request_started_ms,
             request_completed_ms,
             metadata
@@ -42,6 +43,7 @@ pub fn log_search_success(
 
 /// Logs a search error event asynchronously.
 pub fn log_search_error(
+    endpoint: &'static str,
     error_msg: String,
     request_started_ms: i64,
     request_completed_ms: i64,
@@ -66,7 +68,7 @@ pub fn log_search_error(
             VALUES ($1, $2, $3, $4, $5)
             "#,
             "search_error",
-            "/api",
+            endpoint,
             request_started_ms,
             request_completed_ms,
             metadata
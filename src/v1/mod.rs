@@ -0,0 +1,689 @@
+//! Version 1 of the public REST API, under `/api/v1`.
+//!
+//! Unlike the endpoints of the frontend, requests are read strictly: unknown fields, values
+//! out of range and identifiers that cannot be parsed are rejected with a JSON error instead
+//! of being ignored, clamped or defaulted. Every endpoint is described by an OpenAPI document
+//! served at `/api/v1/openapi.json`, generated from the handlers and types of this module.
+
+use std::num::NonZeroUsize;
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError, Scope, web};
+use biblizap_rs::identifier::Identifier;
+use biblizap_rs::openalex::OpenAlexError;
+use biblizap_rs::{Article, SearchFor};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::common::{epoch_ms, parse_seed_id};
+use crate::corpus::{self, CorpusError};
+use crate::snowball::{MAX_IDS, SnowballSearch, Source, run_snowball};
+use crate::{AppConfig, Error, tracking};
+
+/// The OpenAPI description of the endpoints.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "BibliZap API",
+        version = "1",
+        description = "Snowball searches over the citation network of seed articles, corpora of \
+            identifiers and article metadata. Requests are read strictly: unknown fields, values out \
+            of range and identifiers that cannot be parsed are rejected with an Error object. The \
+            unversioned /api endpoint used by the web interface is kept for compatibility but is \
+            not described here.",
+        license(name = "MIT", identifier = "MIT")
+    ),
+    servers((url = "/api/v1")),
+    paths(
+        snowball,
+        create_corpus,
+        get_corpus,
+        corpus_articles,
+        enrich,
+        openapi
+    )
+)]
+struct ApiDoc;
+
+/// The routes of the API, to be registered before the static files.
+pub fn scope() -> Scope {
+    web::scope("/api/v1")
+        .service(web::resource("/snowball").route(web::post().to(snowball)))
+        .service(web::resource("/corpus").route(web::post().to(create_corpus)))
+        .service(web::resource("/corpus/{hash}").route(web::get().to(get_corpus)))
+        .service(web::resource("/corpus/{hash}/articles").route(web::get().to(corpus_articles)))
+        .service(web::resource("/enrich").route(web::post().to(enrich)))
+        .service(web::resource("/openapi.json").route(web::get().to(openapi)))
+        .default_service(web::to(not_found))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+#[schema(examples(json!({
+    "ids": ["10.1016/j.cell.2020.01.040", "32109876"],
+    "depth": 2,
+    "max_results": 100,
+    "search_for": "Both",
+    "exclude_retracted": false,
+    "source": "lens"
+})))]
+struct SnowballRequest {
+    /// Seed identifiers: DOIs, PMIDs, PMCIDs, arXiv, OpenAlex work IDs, or MAG and CORE IDs
+    /// prefixed with `mag:` and `core:`
+    #[schema(min_items = 1, max_items = 100)]
+    ids: Vec<String>,
+    /// Number of levels to expand
+    #[schema(minimum = 1, maximum = 2)]
+    depth: u8,
+    /// Number of articles to return, all of them when missing
+    #[serde(default)]
+    #[schema(value_type = Option<usize>, minimum = 1)]
+    max_results: Option<NonZeroUsize>,
+    search_for: SearchFor,
+    /// Drop retracted articles from the results
+    #[serde(default)]
+    exclude_retracted: bool,
+    #[serde(default)]
+    source: Option<Source>,
+}
+
+impl TryFrom<SnowballRequest> for SnowballSearch {
+    type Error = Error;
+
+    fn try_from(request: SnowballRequest) -> Result<Self, Error> {
+        if !(1..=2).contains(&request.depth) {
+            return Err(Error::InvalidDepth(request.depth));
+        }
+
+        Ok(SnowballSearch {
+            ids: request.ids,
+            depth: request.depth,
+            max_results: request.max_results.map_or(usize::MAX, NonZeroUsize::get),
+            search_for: request.search_for,
+            exclude_retracted: request.exclude_retracted,
+            source: request.source,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct SnowballResponse {
+    source: Source,
+    articles: Vec<Article>,
+    /// Some citations were rebuilt from the references cached by earlier searches instead of
+    /// being fetched from Lens.org, because the server runs with `cited_by_fallback`. Articles
+    /// citing the ones visited may be missing or scored too low.
+    incomplete_citations: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+#[schema(examples(json!({"ids": ["10.1016/j.cell.2020.01.040", "PMC7102548"]})))]
+struct IdsRequest {
+    /// Article identifiers
+    #[schema(min_items = 1)]
+    ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct CorpusResponse {
+    /// Hex-encoded SHA-256 hash of the corpus
+    hash: String,
+    /// Normalised identifiers, deduplicated and sorted
+    ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+struct CorpusHash {
+    /// Hex-encoded SHA-256 hash of the corpus
+    #[param(pattern = "^[0-9a-fA-F]{64}$")]
+    hash: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ArticlesResponse {
+    articles: Vec<Article>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = Error)]
+struct ErrorResponse {
+    /// Human-readable message
+    error: String,
+    code: ErrorCode,
+}
+
+/// Stable code of an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum ErrorCode {
+    InvalidRequest,
+    InvalidIdentifier,
+    InvalidDepth,
+    TooManyIdentifiers,
+    NoIdentifiers,
+    InvalidCorpusHash,
+    NotAPmid,
+    NoSeedFound,
+    CorpusNotFound,
+    LensUnavailable,
+    OpenalexUnavailable,
+    InternalError,
+    NotFound,
+}
+
+/// A server error answered as an [`ErrorResponse`].
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+struct ApiError(#[from] Error);
+
+impl From<CorpusError> for ApiError {
+    fn from(error: CorpusError) -> Self {
+        ApiError(error.into())
+    }
+}
+
+impl ApiError {
+    /// The HTTP status and the stable code telling the errors apart.
+    fn status_and_code(&self) -> (StatusCode, ErrorCode) {
+        match &self.0 {
+            Error::JsonError(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest),
+            Error::InvalidIdFormat(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidIdentifier),
+            Error::InvalidDepth(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidDepth),
+            Error::TooManyIds(_) => (StatusCode::BAD_REQUEST, ErrorCode::TooManyIdentifiers),
+            Error::NoValidIds => (StatusCode::BAD_REQUEST, ErrorCode::NoIdentifiers),
+            Error::InvalidCorpusHash(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidCorpusHash),
+            Error::NotAPmid(_) => (StatusCode::BAD_REQUEST, ErrorCode::NotAPmid),
+            Error::Biblizap(biblizap_rs::Error::OpenAlexError(OpenAlexError::NoSeedFound)) => {
+                (StatusCode::BAD_REQUEST, ErrorCode::NoSeedFound)
+            }
+            Error::CorpusError(CorpusError::DatabaseError(sqlx::Error::RowNotFound)) => {
+                (StatusCode::NOT_FOUND, ErrorCode::CorpusNotFound)
+            }
+            Error::NoLensApiKey => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::LensUnavailable),
+            Error::NoOpenAlexDatabase => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ErrorCode::OpenalexUnavailable,
+            ),
+            Error::Biblizap(_) | Error::CorpusError(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::InternalError)
+            }
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status_and_code().0
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let (status, code) = self.status_and_code();
+        if status.is_server_error() {
+            log::error!("API request failed: {:?}", self.0);
+        }
+
+        HttpResponse::build(status).json(ErrorResponse {
+            error: self.to_string(),
+            code,
+        })
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(req_body: &str) -> Result<T, Error> {
+    Ok(serde_json::from_str(req_body)?)
+}
+
+fn parse_hash(hash_hex: &str) -> Result<[u8; 32], Error> {
+    corpus::parse_hash_hex(hash_hex).ok_or_else(|| Error::InvalidCorpusHash(hash_hex.to_string()))
+}
+
+/// Checks that there are identifiers and that `parse` accepts every one of them.
+fn check_ids(ids: &[String], parse: impl Fn(&str) -> Option<Identifier>) -> Result<(), Error> {
+    if ids.is_empty() {
+        return Err(Error::NoValidIds);
+    }
+
+    match ids.iter().find(|id| parse(id).is_none()) {
+        Some(id) => Err(Error::InvalidIdFormat(id.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Checks the identifiers given to enrich: Lens IDs are accepted besides the seed types.
+fn check_enrich_ids(ids: &[String]) -> Result<(), Error> {
+    if ids.len() > MAX_IDS {
+        return Err(Error::TooManyIds(ids.len()));
+    }
+
+    check_ids(ids, Identifier::parse)
+}
+
+async fn handle_snowball(req_body: &str, config: &AppConfig) -> Result<SnowballResponse, Error> {
+    let search: SnowballSearch = parse_body::<SnowballRequest>(req_body)?.try_into()?;
+    log::info!("Received API request: {:?}", search);
+
+    let (source, snowball) = run_snowball(&search, config).await?;
+    Ok(SnowballResponse {
+        source,
+        articles: snowball.articles,
+        incomplete_citations: snowball.incomplete_citations,
+    })
+}
+
+/// Run a snowball search
+///
+/// Expands the seed articles along their references and citations, then scores the articles
+/// found by how often they were reached. Requests are counted in the usage statistics of the
+/// server, like those of `/api`.
+#[utoipa::path(
+    post,
+    path = "/snowball",
+    request_body = SnowballRequest,
+    responses(
+        (status = 200, description = "The articles found, best scored first", body = SnowballResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 500, description = "The request could not be completed", body = ErrorResponse),
+        (status = 503, description = "The source is not configured on this server", body = ErrorResponse)
+    )
+)]
+async fn snowball(
+    req_body: String,
+    config: web::Data<AppConfig>,
+) -> Result<web::Json<SnowballResponse>, ApiError> {
+    let request_started_ms = epoch_ms();
+    let request_inputs = serde_json::from_str::<serde_json::Value>(&req_body).ok();
+    let snowball = handle_snowball(&req_body, &config).await;
+    let request_completed_ms = epoch_ms();
+
+    let pool = config.database_pool.clone();
+
+    match snowball {
+        Ok(snowball) => {
+            tracking::log_search_success(
+                "/api/v1/snowball",
+                snowball.articles.len(),
+                request_started_ms,
+                request_completed_ms,
+                request_inputs,
+                pool,
+            );
+
+            Ok(web::Json(snowball))
+        }
+        Err(error) => {
+            tracking::log_search_error(
+                "/api/v1/snowball",
+                error.to_string(),
+                request_started_ms,
+                request_completed_ms,
+                request_inputs,
+                pool,
+            );
+
+            Err(error.into())
+        }
+    }
+}
+
+/// Store a corpus of identifiers
+///
+/// Takes DOIs, PMIDs, PMCIDs and the other seed identifiers, and rejects the request if any of
+/// them cannot be parsed. Corpora are addressed by the SHA-256 hash of their normalised
+/// identifiers, so storing the same identifiers twice gives the same hash.
+#[utoipa::path(
+    post,
+    path = "/corpus",
+    operation_id = "createCorpus",
+    request_body = IdsRequest,
+    responses(
+        (status = 200, description = "The stored corpus", body = CorpusResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 500, description = "The request could not be completed", body = ErrorResponse)
+    )
+)]
+async fn create_corpus(
+    req_body: String,
+    config: web::Data<AppConfig>,
+) -> Result<web::Json<CorpusResponse>, ApiError> {
+    let request = parse_body::<IdsRequest>(&req_body)?;
+    check_ids(&request.ids, parse_seed_id)?;
+
+    let (hash, ids) = corpus::save_corpus_ids(&config.database_pool, request.ids).await?;
+
+    Ok(web::Json(CorpusResponse {
+        hash: hex::encode(hash),
+        ids,
+    }))
+}
+
+/// Get the identifiers of a corpus
+#[utoipa::path(
+    get,
+    path = "/corpus/{hash}",
+    operation_id = "getCorpus",
+    params(CorpusHash),
+    responses(
+        (status = 200, description = "The corpus", body = CorpusResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "No corpus with this hash", body = ErrorResponse),
+        (status = 500, description = "The request could not be completed", body = ErrorResponse)
+    )
+)]
+async fn get_corpus(
+    path: web::Path<CorpusHash>,
+    config: web::Data<AppConfig>,
+) -> Result<web::Json<CorpusResponse>, ApiError> {
+    let hash = parse_hash(&path.hash)?;
+    let ids = corpus::load_corpus_ids(&config.database_pool, &hash).await?;
+
+    Ok(web::Json(CorpusResponse {
+        hash: hex::encode(hash),
+        ids,
+    }))
+}
+
+/// Get the metadata of the articles of a corpus
+///
+/// Needs a Lens.org API key on the server. Identifiers that Lens.org cannot resolve are left
+/// out.
+#[utoipa::path(
+    get,
+    path = "/corpus/{hash}/articles",
+    operation_id = "getCorpusArticles",
+    params(CorpusHash),
+    responses(
+        (status = 200, description = "The articles of the corpus", body = ArticlesResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "No corpus with this hash", body = ErrorResponse),
+        (status = 500, description = "The request could not be completed", body = ErrorResponse),
+        (status = 503, description = "The source is not configured on this server", body = ErrorResponse)
+    )
+)]
+async fn corpus_articles(
+    path: web::Path<CorpusHash>,
+    config: web::Data<AppConfig>,
+) -> Result<web::Json<ArticlesResponse>, ApiError> {
+    let hash = parse_hash(&path.hash)?;
+    let ids = corpus::load_corpus_ids(&config.database_pool, &hash).await?;
+    let articles = corpus::enrich_ids(&ids, &config).await?;
+
+    Ok(web::Json(ArticlesResponse { articles }))
+}
+
+/// Get the metadata of articles
+///
+/// Needs a Lens.org API key on the server. Takes up to 100 identifiers, Lens IDs included.
+/// Identifiers that Lens.org cannot resolve are left out.
+#[utoipa::path(
+    post,
+    path = "/enrich",
+    request_body = IdsRequest,
+    responses(
+        (status = 200, description = "The articles found", body = ArticlesResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 500, description = "The request could not be completed", body = ErrorResponse),
+        (status = 503, description = "The source is not configured on this server", body = ErrorResponse)
+    )
+)]
+async fn enrich(
+    req_body: String,
+    config: web::Data<AppConfig>,
+) -> Result<web::Json<ArticlesResponse>, ApiError> {
+    let request = parse_body::<IdsRequest>(&req_body)?;
+    check_enrich_ids(&request.ids)?;
+    let articles = corpus::enrich_ids(&request.ids, &config).await?;
+
+    Ok(web::Json(ArticlesResponse { articles }))
+}
+
+/// This document
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    responses((status = 200, description = "The OpenAPI description of the API", content_type = "application/json"))
+)]
+async fn openapi() -> web::Json<utoipa::openapi::OpenApi> {
+    web::Json(ApiDoc::openapi())
+}
+
+async fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(ErrorResponse {
+        error: "No such API endpoint, see /api/v1/openapi.json".to_string(),
+        code: ErrorCode::NotFound,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use biblizap_rs::ArticleAuthor;
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    fn openapi_document() -> Value {
+        serde_json::to_value(ApiDoc::openapi()).unwrap()
+    }
+
+    fn schema(name: &str) -> Value {
+        openapi_document()["components"]["schemas"][name].clone()
+    }
+
+    fn keys(object: &Value) -> BTreeSet<String> {
+        object.as_object().unwrap().keys().cloned().collect()
+    }
+
+    /// Collects a string array, a missing one (e.g. `required`) being empty.
+    fn strings(array: &Value) -> BTreeSet<String> {
+        array
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|value| value.as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn article() -> Article {
+        serde_json::from_str("{}").unwrap()
+    }
+
+    #[test]
+    fn openapi_lists_every_route() {
+        let document = openapi_document();
+        assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+        assert_eq!(
+            keys(&document["paths"]),
+            BTreeSet::from(
+                [
+                    "/snowball",
+                    "/corpus",
+                    "/corpus/{hash}",
+                    "/corpus/{hash}/articles",
+                    "/enrich",
+                    "/openapi.json",
+                ]
+                .map(String::from)
+            )
+        );
+    }
+
+    #[test]
+    fn response_schemas_match_types() {
+        let responses = [
+            (
+                "SnowballResponse",
+                serde_json::to_value(SnowballResponse {
+                    source: Source::Lens,
+                    articles: vec![],
+                    incomplete_citations: false,
+                }),
+            ),
+            (
+                "CorpusResponse",
+                serde_json::to_value(CorpusResponse {
+                    hash: String::new(),
+                    ids: vec![],
+                }),
+            ),
+            (
+                "ArticlesResponse",
+                serde_json::to_value(ArticlesResponse { articles: vec![] }),
+            ),
+            ("Article", serde_json::to_value(article())),
+            (
+                "ArticleAuthor",
+                serde_json::to_value(ArticleAuthor::default()),
+            ),
+            (
+                "Error",
+                serde_json::to_value(ErrorResponse {
+                    error: String::new(),
+                    code: ErrorCode::NotFound,
+                }),
+            ),
+        ];
+
+        for (name, value) in responses {
+            let schema = schema(name);
+            let value = value.unwrap();
+            assert_eq!(keys(&schema["properties"]), keys(&value), "{name}");
+            assert!(
+                strings(&schema["required"]).is_subset(&keys(&value)),
+                "{name}"
+            );
+        }
+
+        let sources = [Source::Lens, Source::Pubmed, Source::OpenAlex]
+            .map(|source| serde_json::to_value(source).unwrap())
+            .to_vec();
+        assert_eq!(schema("Source")["enum"], Value::Array(sources));
+    }
+
+    /// Checks the example and the required properties of a request schema against `T`.
+    fn check_request_schema<T: DeserializeOwned>(name: &str) {
+        let schema = schema(name);
+        let example = schema["examples"][0].as_object().unwrap().clone();
+        let required = strings(&schema["required"]);
+        assert_eq!(keys(&schema["properties"]), keys(&schema["examples"][0]));
+        assert_eq!(schema["additionalProperties"], json!(false));
+
+        assert!(serde_json::from_value::<T>(Value::Object(example.clone())).is_ok());
+        for property in example.keys() {
+            let mut request = example.clone();
+            request.remove(property);
+            assert_eq!(
+                serde_json::from_value::<T>(Value::Object(request)).is_ok(),
+                !required.contains(property),
+                "{name}.{property}"
+            );
+        }
+
+        let mut request = example;
+        request.insert("unknown".to_string(), json!(true));
+        assert!(serde_json::from_value::<T>(Value::Object(request)).is_err());
+    }
+
+    #[test]
+    fn request_schemas_match_types() {
+        check_request_schema::<SnowballRequest>("SnowballRequest");
+        check_request_schema::<IdsRequest>("IdsRequest");
+    }
+
+    #[test]
+    fn snowball_request_is_strict() {
+        let request = |body: Value| -> Result<SnowballSearch, Error> {
+            serde_json::from_value::<SnowballRequest>(body)?.try_into()
+        };
+
+        let search =
+            request(json!({"ids": ["32109876"], "depth": 1, "search_for": "Both"})).unwrap();
+        assert_eq!(search.max_results, usize::MAX);
+        assert_eq!(search.source, None);
+        assert!(!search.exclude_retracted);
+
+        let invalid = [
+            json!({"ids": ["32109876"], "depth": 3, "search_for": "Both"}),
+            json!({"ids": ["32109876"], "depth": 0, "search_for": "Both"}),
+            json!({"ids": ["32109876"], "depth": 2, "search_for": "Both", "max_results": 0}),
+            json!({"ids": ["32109876"], "depth": 2, "search_for": "Both", "max_results": "100"}),
+            json!({"ids": ["32109876"], "depth": 2, "search_for": "both"}),
+            json!({"ids": ["32109876"], "depth": 2, "search_for": "Both", "source": "scopus"}),
+            json!({"input_id_list": ["32109876"], "depth": 2, "search_for": "Both"}),
+        ];
+        for body in invalid {
+            assert!(request(body.clone()).is_err(), "{body}");
+        }
+
+        let schema = schema("SnowballRequest");
+        assert_eq!(schema["properties"]["ids"]["maxItems"], json!(MAX_IDS));
+    }
+
+    #[test]
+    fn corpus_ids_are_checked() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        assert!(check_ids(&ids(&["10.1234/abc", "32109876"]), parse_seed_id).is_ok());
+        assert!(matches!(
+            check_ids(&[], parse_seed_id),
+            Err(Error::NoValidIds)
+        ));
+        assert!(matches!(
+            check_ids(&ids(&["10.1234/abc", "not an id"]), parse_seed_id),
+            Err(Error::InvalidIdFormat(id)) if id == "not an id"
+        ));
+    }
+
+    #[test]
+    fn enrich_ids_are_checked() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        assert!(check_enrich_ids(&ids(&["10.1234/abc", "PMC7102548"])).is_ok());
+        assert!(matches!(check_enrich_ids(&[]), Err(Error::NoValidIds)));
+        assert!(matches!(
+            check_enrich_ids(&ids(&["10.1234/abc", "not an id"])),
+            Err(Error::InvalidIdFormat(id)) if id == "not an id"
+        ));
+        assert!(matches!(
+            check_enrich_ids(&vec!["32109876".to_string(); MAX_IDS + 1]),
+            Err(Error::TooManyIds(_))
+        ));
+    }
+
+    #[test]
+    fn error_codes_are_documented() {
+        let documented = strings(&schema("ErrorCode")["enum"]);
+        let errors = [
+            Error::from(serde_json::from_str::<IdsRequest>("{}").unwrap_err()),
+            Error::InvalidIdFormat(String::new()),
+            Error::InvalidDepth(3),
+            Error::TooManyIds(MAX_IDS + 1),
+            Error::NoValidIds,
+            Error::InvalidCorpusHash(String::new()),
+            Error::NotAPmid(String::new()),
+            Error::Biblizap(OpenAlexError::NoSeedFound.into()),
+            Error::CorpusError(CorpusError::DatabaseError(sqlx::Error::RowNotFound)),
+            Error::NoLensApiKey,
+            Error::NoOpenAlexDatabase,
+            Error::CorpusError(CorpusError::DatabaseError(sqlx::Error::PoolClosed)),
+        ];
+
+        for error in errors {
+            let (status, code) = ApiError(error).status_and_code();
+            let code = serde_json::to_value(code).unwrap();
+            let code = code.as_str().unwrap();
+            assert!(documented.contains(code), "{code}");
+            match code {
+                "corpus_not_found" => assert_eq!(status, StatusCode::NOT_FOUND),
+                "lens_unavailable" | "openalex_unavailable" => {
+                    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE)
+                }
+                "internal_error" => assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR),
+                _ => assert_eq!(status, StatusCode::BAD_REQUEST),
+            }
+        }
+    }
+}